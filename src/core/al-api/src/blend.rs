@@ -1,4 +1,4 @@
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
 
 use wasm_bindgen::prelude::wasm_bindgen;
 
@@ -7,7 +7,7 @@ pub type WebGlRenderingCtx = web_sys::WebGl2RenderingContext;
 #[cfg(feature = "webgl1")]
 pub type WebGlRenderingCtx = web_sys::WebGlRenderingContext;
 
-#[derive(Deserialize, Debug, Clone, Copy)]
+#[derive(Deserialize, Serialize, Debug, Clone, Copy)]
 #[serde(rename_all = "camelCase")]
 #[wasm_bindgen]
 pub struct BlendCfg {
@@ -26,7 +26,7 @@ impl Default for BlendCfg {
     }
 }
 
-#[derive(Deserialize, Debug, Clone, Copy, PartialEq)]
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, PartialEq)]
 #[wasm_bindgen]
 pub enum BlendFactor {
     Zero,
@@ -50,7 +50,7 @@ pub enum BlendFactor {
     OneMinusConstantAlpha,
 }
 
-#[derive(Deserialize, Debug, Clone, Copy, PartialEq)]
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, PartialEq)]
 #[wasm_bindgen]
 pub enum BlendFunc {
     FuncAdd,
@@ -0,0 +1,27 @@
+use serde::Serialize;
+use std::collections::HashMap;
+
+/// Memory statistics of the cached resources and the HiPS textures
+#[derive(Serialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct CacheStats {
+    /// Memory budget shared by all the HiPS layers in bytes
+    pub budget: usize,
+    /// Memory used in bytes
+    pub num_bytes: usize,
+    /// Number of fetches served by the resource cache
+    pub num_hits: u32,
+    /// Number of fetches sent to the servers
+    pub num_misses: u32,
+    /// Memory used by each HiPS layer
+    pub layers: HashMap<String, LayerCacheStats>,
+}
+
+#[derive(Serialize, Debug, Clone, Copy)]
+#[serde(rename_all = "camelCase")]
+pub struct LayerCacheStats {
+    /// Memory taken by the resources cached in bytes
+    pub cpu_num_bytes: usize,
+    /// Memory taken by the textures in bytes
+    pub gpu_num_bytes: usize,
+}
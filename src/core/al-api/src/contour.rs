@@ -0,0 +1,24 @@
+use serde::{Deserialize, Serialize};
+
+use super::color::ColorRGBA;
+
+#[derive(Deserialize, Serialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct ContourCfg {
+    /// Identifier of the contours
+    pub id: String,
+    /// Name of the layer whose data is contoured. It must be a FITS image
+    /// or a FITS HiPS
+    pub layer: String,
+    /// Levels of the contours given in physical values
+    pub levels: Vec<f32>,
+    /// Color of the lines, components being between 0 and 1
+    pub color: ColorRGBA,
+    /// Thickness of the lines in pixels
+    #[serde(default = "default_thickness")]
+    pub thickness: f32,
+}
+
+fn default_thickness() -> f32 {
+    1.0
+}
@@ -0,0 +1,21 @@
+use serde::Deserialize;
+
+#[derive(Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct CutoutCfg {
+    /// Name of the layer whose values are extracted. It must be a FITS image
+    /// or a FITS HiPS
+    pub layer: String,
+    /// Region of the view to extract. The whole view is extracted if not given
+    #[serde(default)]
+    pub rect: Option<ScreenRect>,
+}
+
+/// A rectangle of the view given in pixels from its top-left corner
+#[derive(Deserialize, Debug, Clone, Copy)]
+pub struct ScreenRect {
+    pub x: f64,
+    pub y: f64,
+    pub width: f64,
+    pub height: f64,
+}
@@ -0,0 +1,79 @@
+use serde::Deserialize;
+
+use super::color::ColorRGBA;
+
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum ExportFormat {
+    /// A FITS RGB cube
+    #[default]
+    Fits,
+    /// A PNG image with a WCS header as a sidecar file
+    Png,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct ExportCfg {
+    /// Width of the image in pixels. The width of the view is taken if not given
+    #[serde(default)]
+    pub width: Option<u32>,
+    /// Height of the image in pixels. If not given, the aspect ratio of the view is kept
+    #[serde(default)]
+    pub height: Option<u32>,
+    #[serde(default)]
+    pub format: ExportFormat,
+}
+
+/// Shape of the markers drawn at the position of catalog sources
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum MarkerShape {
+    #[default]
+    Square,
+    Circle,
+    Plus,
+    Cross,
+    Rhomb,
+    Triangle,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct SnapshotCatalog {
+    /// Positions of the sources as `[ra, dec]` in degrees (ICRS)
+    pub sources: Vec<[f64; 2]>,
+    /// Color of the markers, components being between 0 and 1
+    pub color: ColorRGBA,
+    /// Size of the markers in pixels of the view. It is scaled with the snapshot
+    #[serde(default = "default_marker_size")]
+    pub size: f32,
+    #[serde(default)]
+    pub shape: MarkerShape,
+}
+
+fn default_marker_size() -> f32 {
+    8.0
+}
+
+#[derive(Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct SnapshotCfg {
+    /// Width of the image in pixels. The width of the view is taken if not given
+    #[serde(default)]
+    pub width: Option<u32>,
+    /// Height of the image in pixels. If not given, the aspect ratio of the view is kept
+    #[serde(default)]
+    pub height: Option<u32>,
+    /// Catalogs whose sources are drawn over the layers
+    #[serde(default)]
+    pub catalogs: Vec<SnapshotCatalog>,
+    /// Time in seconds waited for the tiles of each part of the image. What has been
+    /// received is rendered once it is elapsed
+    #[serde(default = "default_timeout")]
+    pub timeout: f32,
+}
+
+fn default_timeout() -> f32 {
+    30.0
+}
@@ -4,7 +4,7 @@ use serde::{Deserialize, Serialize};
 
 use super::color::ColorRGB;
 #[wasm_bindgen]
-#[derive(Deserialize, Serialize)]
+#[derive(Deserialize, Serialize, Clone, Debug)]
 #[serde(rename_all = "camelCase")]
 pub struct GridCfg {
     #[serde(default = "default_color")]
@@ -1,7 +1,7 @@
 use wasm_bindgen::JsValue;
 
 use super::blend::BlendCfg;
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
 
 #[derive(Deserialize, Debug, Clone)]
 #[serde(rename_all = "camelCase")]
@@ -13,6 +13,9 @@ pub struct HiPSCfg {
     pub properties: HiPSProperties,
     /// Its color
     pub meta: ImageMetadata,
+    /// Base urls of the mirrors of the HiPS service
+    #[serde(default)]
+    pub mirrors: Vec<String>,
 }
 
 impl HiPSCfg {
@@ -35,7 +38,87 @@ pub struct FITSCfg {
     pub meta: ImageMetadata,
 }
 
+#[derive(Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct WCSImageCfg {
+    /// Layer name
+    pub layer: String,
+    /// Identifier of the image (its url or any unique name)
+    pub url: String,
+    /// The WCS keywords given as a JSON object string
+    /// or as a FITS header string
+    pub wcs: String,
+    /// Its color
+    pub meta: ImageMetadata,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct RGBCompositeCfg {
+    /// Layer name
+    pub layer: String,
+    /// Names of the layers giving the red, green and blue channels.
+    /// Each of them is rendered with its own cuts and transfer function
+    pub red: String,
+    pub green: String,
+    pub blue: String,
+    /// Optional Lupton et al. (2004) asinh scaling of the composite
+    #[serde(default)]
+    pub lupton: Option<LuptonCfg>,
+    /// Its color
+    pub meta: ImageMetadata,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, Copy)]
+#[serde(rename_all = "camelCase")]
+pub struct LuptonCfg {
+    /// Softening parameter of the asinh
+    pub q: f32,
+    /// Linear stretch of the intensity
+    pub stretch: f32,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct LayerArithmeticCfg {
+    /// Layer name
+    pub layer: String,
+    /// Names of the two operand layers. They must store FITS values
+    pub a: String,
+    pub b: String,
+    /// The operation computed between the values of `a` and `b`
+    pub op: LayerOp,
+    /// Its color. The result is colormapped with it
+    pub meta: ImageMetadata,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub enum LayerOp {
+    /// A - B
+    Difference,
+    /// A / B
+    Ratio,
+    /// (A - B) / (A + B)
+    NormalizedDifference,
+}
+
 use crate::coo_system::CooSystem;
+use crate::properties::{HiPSPropertiesFile, PropertiesError};
+
+/// Download statistics of the tiles of a HiPS
+#[derive(Serialize, Debug, Clone, Copy, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct HiPSTileStats {
+    /// Tiles received
+    pub num_found: u32,
+    /// Tiles not existing on the server
+    pub num_missing: u32,
+    /// Tiles that could not be retrieved after all the retries
+    pub num_failed: u32,
+    /// Number of retries performed
+    pub num_retries: u32,
+}
 
 #[derive(Deserialize, Debug, Clone)]
 #[serde(rename_all = "camelCase")]
@@ -47,7 +130,6 @@ pub struct HiPSProperties {
     tile_size: i32,
     formats: Vec<ImageExt>,
     dataproduct_subtype: Option<Vec<String>>,
-    is_planetary_body: Option<bool>,
 
     bitpix: Option<i32>,
     sky_fraction: Option<f32>,
@@ -57,12 +139,95 @@ pub struct HiPSProperties {
     hips_initial_ra: Option<f64>,
     hips_initial_dec: Option<f64>,
 
+    // HiPS cubes
+    hips_cube_depth: Option<u32>,
+    hips_cube_firstframe: Option<u32>,
+    // Spectral WCS of the frames
+    hips_cube_crpix3: Option<f64>,
+    hips_cube_crval3: Option<f64>,
+    hips_cube_cdelt3: Option<f64>,
+    hips_cube_bunit3: Option<String>,
+    #[serde(default)]
+    hips_tile_depth: Option<u32>,
+    #[serde(default)]
+    hips_order_freq: Option<u8>,
+
+    // Description of the HiPS
+    #[serde(default)]
+    hips_creator: Option<String>,
+    #[serde(default)]
+    hips_copyright: Option<String>,
+    #[serde(default)]
+    obs_regime: Option<String>,
+    // Sky background subtraction method
+    #[serde(default)]
+    hips_skyval: Option<String>,
+
     // Parametrable by the user
     min_cutout: Option<f32>,
     max_cutout: Option<f32>,
+    // Range of the pixel values
+    #[serde(default)]
+    min_data_range: Option<f32>,
+    #[serde(default)]
+    max_data_range: Option<f32>,
 }
 
 impl HiPSProperties {
+    /// Build the properties of a HiPS from its `properties` file
+    ///
+    /// # Arguments
+    ///
+    /// * `url` - The root url the tiles are fetched from
+    /// * `file` - The parsed `properties` file
+    pub fn from_file(url: String, file: &HiPSPropertiesFile) -> Result<Self, PropertiesError> {
+        let dataproduct_subtype = if file.dataproduct_subtype.is_empty() {
+            vec!["color".to_string()]
+        } else {
+            file.dataproduct_subtype.clone()
+        };
+
+        // A too small initial fov means the whole sky is viewed
+        let hips_initial_fov = file.hips_initial_fov
+            .map(|fov| if fov < 1.0 / 36000.0 { 360.0 } else { fov });
+
+        Ok(Self {
+            url,
+            max_order: file.hips_order,
+            frame: file.get_frame()?,
+            tile_size: file.hips_tile_width.unwrap_or(512),
+            formats: file.get_formats()?,
+            dataproduct_subtype: Some(dataproduct_subtype),
+
+            bitpix: file.hips_pixel_bitpix,
+            sky_fraction: Some(file.moc_sky_fraction.unwrap_or(0.0)),
+            min_order: Some(file.hips_order_min.unwrap_or(0)),
+
+            hips_initial_fov,
+            hips_initial_ra: file.hips_initial_ra,
+            hips_initial_dec: file.hips_initial_dec,
+
+            hips_cube_depth: file.hips_cube_depth,
+            hips_cube_firstframe: file.hips_cube_firstframe,
+            hips_cube_crpix3: file.hips_cube_crpix3,
+            hips_cube_crval3: file.hips_cube_crval3,
+            hips_cube_cdelt3: file.hips_cube_cdelt3,
+            hips_cube_bunit3: file.hips_cube_bunit3.clone(),
+            hips_tile_depth: file.hips_tile_depth,
+            hips_order_freq: file.hips_order_freq,
+
+            hips_creator: file.hips_creator.clone(),
+            hips_copyright: file.hips_copyright.clone(),
+            obs_regime: file.obs_regime.clone(),
+            hips_skyval: file.hips_skyval.clone(),
+
+            min_cutout: file.hips_pixel_cut.map(|cut| cut[0]),
+            max_cutout: file.hips_pixel_cut.map(|cut| cut[1]),
+            min_data_range: file.hips_data_range.map(|range| range[0]),
+            max_data_range: file.hips_data_range.map(|range| range[1]),
+        })
+    }
+
     #[inline]
     pub fn get_url(&self) -> &str {
         &self.url
@@ -122,9 +287,81 @@ impl HiPSProperties {
     pub fn get_dataproduct_subtype(&self) -> &Option<Vec<String>> {
         &self.dataproduct_subtype
     }
+
+    #[inline]
+    pub fn get_cube_depth(&self) -> Option<u32> {
+        self.hips_cube_depth
+    }
+
+    #[inline]
+    pub fn get_cube_firstframe(&self) -> Option<u32> {
+        self.hips_cube_firstframe
+    }
+
+    /// Spectral WCS of the cube frames, i.e. (CRPIX3, CRVAL3, CDELT3)
+    #[inline]
+    pub fn get_cube_spectral_wcs(&self) -> Option<(f64, f64, f64)> {
+        match (self.hips_cube_crpix3, self.hips_cube_crval3, self.hips_cube_cdelt3) {
+            (Some(crpix3), Some(crval3), Some(cdelt3)) => Some((crpix3, crval3, cdelt3)),
+            _ => None,
+        }
+    }
+
+    #[inline]
+    pub fn get_cube_bunit3(&self) -> Option<&String> {
+        self.hips_cube_bunit3.as_ref()
+    }
+
+    #[inline]
+    pub fn get_tile_depth(&self) -> Option<u32> {
+        self.hips_tile_depth
+    }
+
+    #[inline]
+    pub fn get_order_freq(&self) -> Option<u8> {
+        self.hips_order_freq
+    }
+
+    #[inline]
+    pub fn get_creator(&self) -> Option<&String> {
+        self.hips_creator.as_ref()
+    }
+
+    #[inline]
+    pub fn get_copyright(&self) -> Option<&String> {
+        self.hips_copyright.as_ref()
+    }
+
+    #[inline]
+    pub fn get_obs_regime(&self) -> Option<&String> {
+        self.obs_regime.as_ref()
+    }
+
+    #[inline]
+    pub fn get_skyval(&self) -> Option<&String> {
+        self.hips_skyval.as_ref()
+    }
+
+    /// The cuts given by `hips_pixel_cut`
+    #[inline]
+    pub fn get_cutouts(&self) -> Option<[f32; 2]> {
+        match (self.min_cutout, self.max_cutout) {
+            (Some(min), Some(max)) if !min.is_nan() && !max.is_nan() => Some([min, max]),
+            _ => None,
+        }
+    }
+
+    /// The range of the pixel values given by `hips_data_range`
+    #[inline]
+    pub fn get_data_range(&self) -> Option<[f32; 2]> {
+        match (self.min_data_range, self.max_data_range) {
+            (Some(min), Some(max)) if !min.is_nan() && !max.is_nan() => Some([min, max]),
+            _ => None,
+        }
+    }
 }
 
-#[derive(Deserialize, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, PartialEq, Eq, Hash)]
 #[wasm_bindgen]
 #[serde(rename_all = "camelCase")]
 pub enum ImageExt {
@@ -145,7 +382,6 @@ impl std::fmt::Display for ImageExt {
     }
 }
 
-use serde::Serialize;
 use wasm_bindgen::prelude::*;
 #[wasm_bindgen]
 #[derive(Clone, Copy, PartialEq, Debug, Deserialize, Serialize)]
@@ -187,7 +423,7 @@ impl From<String> for TransferFunction {
 }
 
 use crate::colormap::CmapLabel;
-#[derive(Deserialize, Debug, Clone)]
+#[derive(Deserialize, Serialize, Debug, Clone)]
 #[serde(rename_all = "camelCase")]
 pub struct HiPSColor {
     // transfer function called before evaluating the colormap
@@ -207,7 +443,7 @@ pub struct HiPSColor {
     pub k_brightness: f32,
 }
 
-#[derive(Deserialize, Debug)]
+#[derive(Deserialize, Serialize, Debug)]
 #[serde(rename_all = "camelCase")]
 #[derive(Clone)]
 #[wasm_bindgen]
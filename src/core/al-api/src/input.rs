@@ -0,0 +1,40 @@
+use serde::{Deserialize, Serialize};
+
+/// Gestures handled by the core and their sensitivity
+#[derive(Deserialize, Serialize, Clone, Debug, PartialEq)]
+#[serde(rename_all = "camelCase", default)]
+pub struct InputCfg {
+    /// Move the view by dragging it with one pointer
+    pub drag: bool,
+    /// Zoom by pinching with two pointers
+    pub pinch_zoom: bool,
+    /// Rotate the view around its center with two pointers
+    pub rotation: bool,
+    /// Zoom with the mouse wheel or the touchpad
+    pub wheel_zoom: bool,
+    /// Move with the arrow keys and zoom with the + and - keys
+    pub keyboard: bool,
+
+    /// The field of view is divided by `1 + zoomSensitivity` for each wheel notch or key press
+    pub zoom_sensitivity: f64,
+    /// Move done by an arrow key press as a fraction of the view size
+    pub pan_step: f64,
+    /// Factor applied to the angle the two pointers have turned by
+    pub rotation_sensitivity: f64,
+}
+
+impl Default for InputCfg {
+    fn default() -> Self {
+        Self {
+            drag: true,
+            pinch_zoom: true,
+            rotation: true,
+            wheel_zoom: true,
+            keyboard: true,
+
+            zoom_sensitivity: 0.2,
+            pan_step: 0.1,
+            rotation_sensitivity: 1.0,
+        }
+    }
+}
@@ -8,11 +8,20 @@ pub mod colormap;
 pub mod coo_system;
 pub mod grid;
 pub mod hips;
+pub mod properties;
 pub mod moc;
 pub mod resources;
 pub mod cell;
 pub mod fov;
 pub mod image;
+pub mod contour;
+pub mod cutout;
+pub mod cache;
+pub mod tour;
+pub mod input;
+pub mod state;
+pub mod export;
+pub mod projection;
 
 pub trait Abort {
     type Item;
@@ -43,3 +52,4 @@ impl<T, E> Abort for Result<T, E> {
         }
     }
 }
+pub mod view;
@@ -0,0 +1,33 @@
+use serde::{Deserialize, Serialize};
+
+/// The FITS WCS parameters of a projection, the angles being given in degrees
+///
+/// Only the conic and cylindrical projections take parameters, the ones not given
+/// keep their default value
+#[derive(Deserialize, Serialize, Clone, Copy, Debug, Default, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct ProjectionParams {
+    /// COD: latitude of the mean standard parallel (PV2_1, 45 by default)
+    #[serde(default, alias = "theta_a", skip_serializing_if = "Option::is_none")]
+    pub theta_a: Option<f64>,
+    /// COD: half the angle between the two standard parallels (PV2_2, 0 by default)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub eta: Option<f64>,
+    /// CYP: distance of the point of projection from the center of the sphere (PV2_1, 1 by default)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub mu: Option<f64>,
+    /// CYP: radius of the cylinder (PV2_2, sqrt(2)/2 by default)
+    /// CEA: scaling of the latitudes (PV2_1, 1 by default)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub lambda: Option<f64>,
+}
+
+/// A projection given by its name and its parameters, e.g. `{name: "COD", theta_a: 45, eta: 25}`
+#[derive(Deserialize, Serialize, Clone, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct ProjectionCfg {
+    /// Name of the projection, e.g. "TAN" or "COD"
+    pub name: String,
+    #[serde(flatten)]
+    pub params: ProjectionParams,
+}
@@ -0,0 +1,452 @@
+//! Parser of the HiPS `properties` file
+//!
+//! The file is made of `key = value` lines, the keywords being defined by the
+//! HiPS 1.0 standard (https://www.ivoa.net/documents/HiPS/).
+use std::collections::HashMap;
+use std::str::FromStr;
+
+use serde::Serialize;
+
+use crate::coo_system::CooSystem;
+use crate::hips::ImageExt;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum PropertiesError {
+    /// A line is not of the form `key = value`
+    Syntax { line: usize, content: String },
+    /// A mandatory keyword is not given
+    Missing(&'static str),
+    /// The value of a keyword cannot be interpreted
+    Invalid { key: &'static str, value: String, reason: &'static str },
+}
+
+impl std::fmt::Display for PropertiesError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            PropertiesError::Syntax { line, content } => {
+                write!(f, "properties line {}: '{}' is not of the form 'key = value'", line, content)
+            }
+            PropertiesError::Missing(key) => write!(f, "properties: the mandatory keyword '{}' is missing", key),
+            PropertiesError::Invalid { key, value, reason } => {
+                write!(f, "properties: invalid value '{}' for '{}': {}", value, key, reason)
+            }
+        }
+    }
+}
+
+impl std::error::Error for PropertiesError {}
+
+impl From<PropertiesError> for wasm_bindgen::JsValue {
+    fn from(err: PropertiesError) -> Self {
+        wasm_bindgen::JsValue::from_str(&err.to_string())
+    }
+}
+
+/// The content of a HiPS `properties` file
+///
+/// The keywords used by the viewer are parsed and validated, all of them stay
+/// readable as text with [`HiPSPropertiesFile::get`].
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct HiPSPropertiesFile {
+    // Values of all the keywords, some of them (e.g. `prov_progenitor`) can be repeated
+    keywords: HashMap<String, Vec<String>>,
+
+    /* HiPS description */
+    pub creator_did: Option<String>,
+    pub obs_title: Option<String>,
+    pub obs_description: Option<String>,
+    pub dataproduct_type: Option<String>,
+    pub dataproduct_subtype: Vec<String>,
+    pub hips_version: Option<String>,
+    pub hips_release_date: Option<String>,
+    pub hips_status: Vec<String>,
+    pub hips_service_url: Option<String>,
+    // hips_service_url_1, hips_service_url_2, ...
+    pub hips_mirror_urls: Vec<String>,
+    pub hips_body: Option<String>,
+    pub hips_creator: Option<String>,
+    pub hips_copyright: Option<String>,
+    pub obs_regime: Option<String>,
+
+    /* Spectral and time coverage */
+    pub em_min: Option<f64>,
+    pub em_max: Option<f64>,
+    pub t_min: Option<f64>,
+    pub t_max: Option<f64>,
+
+    /* Structure */
+    pub hips_frame: Option<String>,
+    pub hips_order: u8,
+    pub hips_order_min: Option<u8>,
+    // Order of the frequency axis of the HiPS cubes
+    pub hips_order_freq: Option<u8>,
+    pub hips_tile_width: Option<i32>,
+    // Number of cube frames stored in a tile
+    pub hips_tile_depth: Option<u32>,
+    pub hips_tile_format: Vec<String>,
+    pub hips_pixel_bitpix: Option<i32>,
+    pub data_pixel_bitpix: Option<i32>,
+    pub hips_pixel_cut: Option<[f32; 2]>,
+    pub hips_data_range: Option<[f32; 2]>,
+    pub hips_pixel_scale: Option<f64>,
+    pub s_pixel_scale: Option<f64>,
+    pub hips_estsize: Option<u64>,
+    // Sky background subtraction method
+    pub hips_skyval: Option<String>,
+
+    /* Initial view */
+    pub hips_initial_fov: Option<f64>,
+    pub hips_initial_ra: Option<f64>,
+    pub hips_initial_dec: Option<f64>,
+
+    /* Coverage */
+    pub moc_sky_fraction: Option<f32>,
+
+    /* HiPS cubes */
+    pub hips_cube_depth: Option<u32>,
+    pub hips_cube_firstframe: Option<u32>,
+    pub hips_cube_crpix3: Option<f64>,
+    pub hips_cube_crval3: Option<f64>,
+    pub hips_cube_cdelt3: Option<f64>,
+    pub hips_cube_bunit3: Option<String>,
+}
+
+impl FromStr for HiPSPropertiesFile {
+    type Err = PropertiesError;
+
+    fn from_str(content: &str) -> Result<Self, Self::Err> {
+        let keywords = parse_keywords(content)?;
+        let get = |key: &str| keywords.get(key).and_then(|values| values.last()).map(|v| v.as_str());
+
+        let hips_order = parse_value("hips_order", get("hips_order"))?
+            .ok_or(PropertiesError::Missing("hips_order"))?;
+        let hips_order_min = parse_value::<u8>("hips_order_min", get("hips_order_min"))?;
+        if let Some(order_min) = hips_order_min {
+            if order_min > hips_order {
+                return Err(PropertiesError::Invalid {
+                    key: "hips_order_min",
+                    value: order_min.to_string(),
+                    reason: "greater than hips_order",
+                });
+            }
+        }
+
+        let hips_tile_width = parse_value::<i32>("hips_tile_width", get("hips_tile_width"))?;
+        if let Some(width) = hips_tile_width {
+            if width <= 0 || width & (width - 1) != 0 {
+                return Err(PropertiesError::Invalid {
+                    key: "hips_tile_width",
+                    value: width.to_string(),
+                    reason: "not a power of two",
+                });
+            }
+        }
+
+        let hips_initial_dec = parse_value::<f64>("hips_initial_dec", get("hips_initial_dec"))?;
+        if let Some(dec) = hips_initial_dec {
+            if !(-90.0..=90.0).contains(&dec) {
+                return Err(PropertiesError::Invalid {
+                    key: "hips_initial_dec",
+                    value: dec.to_string(),
+                    reason: "not in [-90, 90]",
+                });
+            }
+        }
+
+        let moc_sky_fraction = parse_value::<f32>("moc_sky_fraction", get("moc_sky_fraction"))?;
+        if let Some(fraction) = moc_sky_fraction {
+            if !(0.0..=1.0).contains(&fraction) {
+                return Err(PropertiesError::Invalid {
+                    key: "moc_sky_fraction",
+                    value: fraction.to_string(),
+                    reason: "not in [0, 1]",
+                });
+            }
+        }
+
+        let mut mirrors = keywords
+            .iter()
+            .filter_map(|(key, values)| {
+                let idx = key.strip_prefix("hips_service_url_")?.parse::<u32>().ok()?;
+                Some((idx, values.last()?.clone()))
+            })
+            .collect::<Vec<_>>();
+        mirrors.sort_by_key(|(idx, _)| *idx);
+
+        let list = |key: &str| {
+            get(key)
+                .map(|v| v.split_whitespace().map(|s| s.to_lowercase()).collect())
+                .unwrap_or_default()
+        };
+        let text = |key: &str| get(key).map(String::from);
+
+        Ok(Self {
+            creator_did: text("creator_did").or_else(|| text("publisher_did")),
+            obs_title: text("obs_title"),
+            obs_description: text("obs_description"),
+            dataproduct_type: text("dataproduct_type"),
+            dataproduct_subtype: list("dataproduct_subtype"),
+            hips_version: text("hips_version"),
+            hips_release_date: text("hips_release_date"),
+            hips_status: list("hips_status"),
+            hips_service_url: text("hips_service_url"),
+            hips_mirror_urls: mirrors.into_iter().map(|(_, url)| url).collect(),
+            hips_body: text("hips_body"),
+            hips_creator: text("hips_creator"),
+            hips_copyright: text("hips_copyright"),
+            obs_regime: text("obs_regime"),
+
+            em_min: parse_value("em_min", get("em_min"))?,
+            em_max: parse_value("em_max", get("em_max"))?,
+            t_min: parse_value("t_min", get("t_min"))?,
+            t_max: parse_value("t_max", get("t_max"))?,
+
+            hips_frame: text("hips_frame"),
+            hips_order,
+            hips_order_min,
+            hips_order_freq: parse_value("hips_order_freq", get("hips_order_freq"))?,
+            hips_tile_width,
+            hips_tile_depth: parse_value("hips_tile_depth", get("hips_tile_depth"))?,
+            hips_tile_format: list("hips_tile_format"),
+            hips_pixel_bitpix: parse_value("hips_pixel_bitpix", get("hips_pixel_bitpix"))?,
+            data_pixel_bitpix: parse_value("data_pixel_bitpix", get("data_pixel_bitpix"))?,
+            hips_pixel_cut: parse_range("hips_pixel_cut", get("hips_pixel_cut"))?,
+            hips_data_range: parse_range("hips_data_range", get("hips_data_range"))?,
+            hips_pixel_scale: parse_value("hips_pixel_scale", get("hips_pixel_scale"))?,
+            s_pixel_scale: parse_value("s_pixel_scale", get("s_pixel_scale"))?,
+            hips_estsize: parse_value("hips_estsize", get("hips_estsize"))?,
+            hips_skyval: text("hips_skyval"),
+
+            hips_initial_fov: parse_value("hips_initial_fov", get("hips_initial_fov"))?,
+            hips_initial_ra: parse_value("hips_initial_ra", get("hips_initial_ra"))?,
+            hips_initial_dec,
+
+            moc_sky_fraction,
+
+            hips_cube_depth: parse_value("hips_cube_depth", get("hips_cube_depth"))?,
+            hips_cube_firstframe: parse_value("hips_cube_firstframe", get("hips_cube_firstframe"))?,
+            hips_cube_crpix3: parse_value("hips_cube_crpix3", get("hips_cube_crpix3"))?,
+            hips_cube_crval3: parse_value("hips_cube_crval3", get("hips_cube_crval3"))?,
+            hips_cube_cdelt3: parse_value("hips_cube_cdelt3", get("hips_cube_cdelt3"))?,
+            hips_cube_bunit3: text("hips_cube_bunit3"),
+
+            keywords,
+        })
+    }
+}
+
+impl HiPSPropertiesFile {
+    /// The value of a keyword as written in the file
+    ///
+    /// The last one is returned for the repeated keywords
+    pub fn get(&self, key: &str) -> Option<&str> {
+        self.keywords.get(key)?.last().map(|v| v.as_str())
+    }
+
+    /// All the values of a repeated keyword
+    pub fn get_all(&self, key: &str) -> &[String] {
+        self.keywords.get(key).map(|values| &values[..]).unwrap_or(&[])
+    }
+
+    /// The keywords with their value as written in the file
+    pub fn keywords(&self) -> impl Iterator<Item = (&String, &str)> {
+        self.keywords
+            .iter()
+            .filter_map(|(key, values)| Some((key, values.last()?.as_str())))
+    }
+
+    /// The coordinate frame of the HiPS
+    ///
+    /// Planets and other bodies are displayed in the equatorial frame
+    pub fn get_frame(&self) -> Result<CooSystem, PropertiesError> {
+        if self.hips_body.is_some() {
+            return Ok(CooSystem::ICRSJ2000);
+        }
+
+        match self.hips_frame.as_deref() {
+            None | Some("equatorial") | Some("ICRS") | Some("ICRSd") | Some("j2000") => Ok(CooSystem::ICRSJ2000),
+            Some("galactic") => Ok(CooSystem::GAL),
+            Some(frame) => Err(PropertiesError::Invalid {
+                key: "hips_frame",
+                value: frame.to_string(),
+                reason: "only the equatorial and galactic frames are supported",
+            }),
+        }
+    }
+
+    /// The tile formats, jpeg if not given
+    pub fn get_formats(&self) -> Result<Vec<ImageExt>, PropertiesError> {
+        if self.hips_tile_format.is_empty() {
+            return Ok(vec![ImageExt::Jpeg]);
+        }
+
+        self.hips_tile_format
+            .iter()
+            .map(|format| match format.as_str() {
+                "jpeg" | "jpg" => Ok(ImageExt::Jpeg),
+                "png" => Ok(ImageExt::Png),
+                "fits" => Ok(ImageExt::Fits),
+                "webp" => Ok(ImageExt::Webp),
+                _ => Err(PropertiesError::Invalid {
+                    key: "hips_tile_format",
+                    value: format.clone(),
+                    reason: "the formats supported are jpeg, png, webp and fits",
+                }),
+            })
+            .collect()
+    }
+}
+
+/// The keywords of a properties file, to be given to javascript
+#[derive(Serialize)]
+#[serde(transparent)]
+pub struct PropertiesKeywords<'a>(HashMap<&'a String, &'a str>);
+
+impl<'a> From<&'a HiPSPropertiesFile> for PropertiesKeywords<'a> {
+    fn from(file: &'a HiPSPropertiesFile) -> Self {
+        PropertiesKeywords(file.keywords().collect())
+    }
+}
+
+fn parse_keywords(content: &str) -> Result<HashMap<String, Vec<String>>, PropertiesError> {
+    let mut keywords: HashMap<String, Vec<String>> = HashMap::new();
+
+    let mut lines = content.lines().enumerate();
+    while let Some((idx, line)) = lines.next() {
+        let mut line = line.trim().to_string();
+        // Comments and empty lines
+        if line.is_empty() || line.starts_with('#') || line.starts_with('!') {
+            continue;
+        }
+
+        // A backslash at the end of the line continues the value on the next one
+        while line.ends_with('\\') {
+            line.pop();
+            match lines.next() {
+                Some((_, next)) => line.push_str(next.trim()),
+                None => break,
+            }
+        }
+
+        let (key, value) = line
+            .split_once('=')
+            .ok_or_else(|| PropertiesError::Syntax { line: idx + 1, content: line.clone() })?;
+        let key = key.trim();
+        if key.is_empty() {
+            return Err(PropertiesError::Syntax { line: idx + 1, content: line.clone() });
+        }
+
+        keywords
+            .entry(key.to_string())
+            .or_default()
+            .push(value.trim().to_string());
+    }
+
+    Ok(keywords)
+}
+
+fn parse_value<T: FromStr>(key: &'static str, value: Option<&str>) -> Result<Option<T>, PropertiesError> {
+    value
+        .map(|value| {
+            value.parse::<T>().map_err(|_| PropertiesError::Invalid {
+                key,
+                value: value.to_string(),
+                reason: "not a number",
+            })
+        })
+        .transpose()
+}
+
+// Parse a `min max` couple of values
+fn parse_range(key: &'static str, value: Option<&str>) -> Result<Option<[f32; 2]>, PropertiesError> {
+    value
+        .map(|value| {
+            let invalid = |reason| PropertiesError::Invalid { key, value: value.to_string(), reason };
+
+            let values = value
+                .split_whitespace()
+                .map(|v| v.parse::<f32>().map_err(|_| invalid("not a number")))
+                .collect::<Result<Vec<_>, _>>()?;
+
+            match values[..] {
+                [min, max] => Ok([min, max]),
+                _ => Err(invalid("two values are expected")),
+            }
+        })
+        .transpose()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{HiPSPropertiesFile, PropertiesError};
+    use crate::coo_system::CooSystem;
+    use crate::hips::ImageExt;
+
+    const PROPERTIES: &str = "
+# DSS colored
+creator_did          = ivo://CDS/P/DSS2/color
+obs_title            = DSS colored
+obs_regime           = Optical
+hips_creator         = CDS (A.Oberto)
+hips_copyright       = CNRS/Unistra
+hips_order           = 9
+hips_order_min       = 0
+hips_tile_width      = 512
+hips_tile_format     = jpeg png
+hips_frame           = equatorial
+hips_pixel_cut       = -10 1000
+hips_skyval          = none
+hips_tile_depth      = 1
+hips_order_freq      = 0
+hips_initial_fov     = 60
+hips_initial_ra      = 85.25
+hips_initial_dec     = -2.46
+moc_sky_fraction     = 1
+prov_progenitor      = POSS II
+prov_progenitor      = SERC
+hips_service_url     = https://alasky.cds.unistra.fr/DSS/DSSColor
+hips_service_url_2   = https://skies.esac.esa.int/DSSColor
+hips_service_url_1   = https://alaskybis.cds.unistra.fr/DSS/DSSColor
+";
+
+    #[test]
+    fn test_parse_properties() {
+        let properties: HiPSPropertiesFile = PROPERTIES.parse().unwrap();
+
+        assert_eq!(properties.hips_order, 9);
+        assert_eq!(properties.hips_order_min, Some(0));
+        assert_eq!(properties.hips_tile_width, Some(512));
+        assert_eq!(properties.get_formats().unwrap(), vec![ImageExt::Jpeg, ImageExt::Png]);
+        assert_eq!(properties.get_frame().unwrap(), CooSystem::ICRSJ2000);
+        assert_eq!(properties.hips_pixel_cut, Some([-10.0, 1000.0]));
+        assert_eq!(properties.hips_initial_dec, Some(-2.46));
+        assert_eq!(properties.moc_sky_fraction, Some(1.0));
+        assert_eq!(properties.hips_creator.as_deref(), Some("CDS (A.Oberto)"));
+        assert_eq!(properties.hips_copyright.as_deref(), Some("CNRS/Unistra"));
+        assert_eq!(properties.obs_regime.as_deref(), Some("Optical"));
+        assert_eq!(properties.hips_skyval.as_deref(), Some("none"));
+        assert_eq!(properties.hips_tile_depth, Some(1));
+        assert_eq!(properties.hips_order_freq, Some(0));
+        assert_eq!(properties.get("obs_title"), Some("DSS colored"));
+        assert_eq!(properties.get_all("prov_progenitor"), ["POSS II", "SERC"]);
+        assert_eq!(
+            properties.hips_mirror_urls,
+            ["https://alaskybis.cds.unistra.fr/DSS/DSSColor", "https://skies.esac.esa.int/DSSColor"]
+        );
+    }
+
+    #[test]
+    fn test_invalid_properties() {
+        let err = "obs_title = DSS".parse::<HiPSPropertiesFile>().unwrap_err();
+        assert_eq!(err, PropertiesError::Missing("hips_order"));
+
+        let err = "hips_order = 3\nhips_tile_width 512".parse::<HiPSPropertiesFile>().unwrap_err();
+        assert!(matches!(err, PropertiesError::Syntax { line: 2, .. }));
+
+        let err = "hips_order = 3\nhips_order_min = 4".parse::<HiPSPropertiesFile>().unwrap_err();
+        assert!(matches!(err, PropertiesError::Invalid { key: "hips_order_min", .. }));
+
+        let err = "hips_order = 3\nhips_pixel_cut = 10".parse::<HiPSPropertiesFile>().unwrap_err();
+        assert!(matches!(err, PropertiesError::Invalid { key: "hips_pixel_cut", .. }));
+    }
+}
@@ -0,0 +1,50 @@
+use serde::{Deserialize, Serialize};
+
+use super::contour::ContourCfg;
+use super::coo_system::CooSystem;
+use super::grid::GridCfg;
+use super::hips::{LayerArithmeticCfg, RGBCompositeCfg};
+use super::projection::ProjectionParams;
+
+/// A colormap created from a list of colors
+#[derive(Deserialize, Serialize, Clone, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct CustomColormap {
+    pub label: String,
+    /// Hex colors, e.g. "#ff0000ff"
+    pub colors: Vec<String>,
+}
+
+/// What the core shows: the camera, the projection, the frame, the grid, the colormaps created
+/// and what is computed from the image layers
+///
+/// The image layers, catalogs and MOCs are loaded from their sources by the javascript side
+#[derive(Deserialize, Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct ViewState {
+    /// Center of the view in degrees (ICRS)
+    pub ra: f64,
+    pub dec: f64,
+    /// Field of view in degrees
+    pub fov: f64,
+    /// Rotation of the view around its center in degrees
+    #[serde(default)]
+    pub rotation: f64,
+    /// Name of the projection, e.g. "TAN" or "AIT"
+    pub projection: String,
+    /// Parameters of the conic and cylindrical projections
+    #[serde(default)]
+    pub projection_params: ProjectionParams,
+    pub frame: CooSystem,
+    pub grid: GridCfg,
+    #[serde(default)]
+    pub colormaps: Vec<CustomColormap>,
+    /// The RGB composites, arithmetic layers and contours, added back once
+    /// the image layers they are computed from are
+    #[serde(default)]
+    pub composites: Vec<RGBCompositeCfg>,
+    #[serde(default)]
+    pub arithmetic_layers: Vec<LayerArithmeticCfg>,
+    #[serde(default)]
+    pub contours: Vec<ContourCfg>,
+}
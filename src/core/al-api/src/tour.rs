@@ -0,0 +1,126 @@
+use serde::{Deserialize, Deserializer, Serialize};
+use std::collections::HashMap;
+
+use crate::projection::{ProjectionCfg, ProjectionParams};
+
+/// A view of a tour reached at a given time
+#[derive(Deserialize, Serialize, Clone, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct Keyframe {
+    /// Time in seconds from the start of the tour
+    pub time: f64,
+    /// Center of the view in degrees (ICRS)
+    pub ra: f64,
+    pub dec: f64,
+    /// Field of view in degrees
+    pub fov: f64,
+    /// Rotation of the view around its center in degrees
+    #[serde(default)]
+    pub rotation: f64,
+    /// Projection given by its name, e.g. "TAN" or "AIT", or by its name and its parameters.
+    /// The current one is kept if not given
+    #[serde(default, deserialize_with = "deserialize_projection")]
+    pub projection: Option<ProjectionCfg>,
+    /// Opacity of the layers indexed by their name
+    #[serde(default)]
+    pub opacities: HashMap<String, f32>,
+}
+
+// A projection is given by its name or by an object with its name and its parameters
+fn deserialize_projection<'de, D>(deserializer: D) -> Result<Option<ProjectionCfg>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum Projection {
+        Name(String),
+        Cfg(ProjectionCfg),
+    }
+
+    Ok(Option::<Projection>::deserialize(deserializer)?.map(|projection| match projection {
+        Projection::Name(name) => ProjectionCfg {
+            name,
+            params: ProjectionParams::default(),
+        },
+        Projection::Cfg(cfg) => cfg,
+    }))
+}
+
+/// A sequence of views played one after the other
+#[derive(Deserialize, Serialize, Clone, Debug, Default, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct Tour {
+    keyframes: Vec<Keyframe>,
+}
+
+impl Tour {
+    pub fn new(mut keyframes: Vec<Keyframe>) -> Self {
+        keyframes.sort_by(|k1, k2| k1.time.total_cmp(&k2.time));
+
+        Self { keyframes }
+    }
+
+    /// Add a keyframe, keeping them sorted by time
+    pub fn push(&mut self, keyframe: Keyframe) {
+        let idx = self.keyframes.partition_point(|k| k.time <= keyframe.time);
+        self.keyframes.insert(idx, keyframe);
+    }
+
+    pub fn keyframes(&self) -> &[Keyframe] {
+        &self.keyframes
+    }
+
+    /// Duration of the tour in seconds
+    pub fn duration(&self) -> f64 {
+        self.keyframes.last().map(|k| k.time).unwrap_or(0.0)
+    }
+
+    /// The keyframes surrounding a time with the progression from the first one to the second one
+    ///
+    /// Before the first keyframe and after the last one, the nearest keyframe is given twice
+    pub fn segment(&self, time: f64) -> Option<(&Keyframe, &Keyframe, f64)> {
+        let idx = self.keyframes.partition_point(|k| k.time <= time);
+
+        match (idx.checked_sub(1).map(|i| &self.keyframes[i]), self.keyframes.get(idx)) {
+            (Some(k0), Some(k1)) => {
+                let alpha = (time - k0.time) / (k1.time - k0.time);
+                Some((k0, k1, alpha.clamp(0.0, 1.0)))
+            }
+            (Some(k), None) | (None, Some(k)) => Some((k, k, 0.0)),
+            (None, None) => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Keyframe, Tour};
+
+    fn keyframe(time: f64, fov: f64) -> Keyframe {
+        Keyframe {
+            time,
+            ra: 0.0,
+            dec: 0.0,
+            fov,
+            rotation: 0.0,
+            projection: None,
+            opacities: Default::default(),
+        }
+    }
+
+    #[test]
+    fn test_tour_segment() {
+        let mut tour = Tour::new(vec![keyframe(4.0, 10.0), keyframe(0.0, 180.0)]);
+        tour.push(keyframe(2.0, 60.0));
+        assert_eq!(tour.duration(), 4.0);
+
+        let (k0, k1, alpha) = tour.segment(3.0).unwrap();
+        assert_eq!((k0.fov, k1.fov, alpha), (60.0, 10.0, 0.5));
+
+        let (k0, k1, _) = tour.segment(10.0).unwrap();
+        assert_eq!((k0.fov, k1.fov), (10.0, 10.0));
+
+        assert!(Tour::default().segment(0.0).is_none());
+    }
+}
@@ -0,0 +1,75 @@
+use serde::{Deserialize, Serialize};
+
+/// Rectangle of the canvas a view is rendered in
+///
+/// It is given in fractions of the canvas size from its top-left corner
+#[derive(Deserialize, Serialize, Clone, Copy, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct ViewRect {
+    pub x: f32,
+    pub y: f32,
+    pub width: f32,
+    pub height: f32,
+}
+
+impl Default for ViewRect {
+    fn default() -> Self {
+        Self {
+            x: 0.0,
+            y: 0.0,
+            width: 1.0,
+            height: 1.0,
+        }
+    }
+}
+
+impl ViewRect {
+    /// The rectangle in pixels of a canvas given as `(x, y, width, height)`, `y` being counted from the bottom
+    pub fn to_pixels(&self, canvas_width: f32, canvas_height: f32) -> (f32, f32, f32, f32) {
+        let x = (self.x * canvas_width).round();
+        let y = ((1.0 - self.y - self.height) * canvas_height).round();
+        let width = ((self.x + self.width) * canvas_width).round() - x;
+        let height = ((1.0 - self.y) * canvas_height).round() - y;
+
+        (x, y, width.max(1.0), height.max(1.0))
+    }
+
+    /// Whether a position of the canvas, given in fractions of its size from its top-left corner, is in the rectangle
+    pub fn contains(&self, x: f32, y: f32) -> bool {
+        x >= self.x && x < self.x + self.width && y >= self.y && y < self.y + self.height
+    }
+}
+
+/// The properties of the active view the other views follow
+#[derive(Deserialize, Serialize, Clone, Copy, Debug, Default, PartialEq)]
+#[serde(rename_all = "camelCase", default)]
+pub struct ViewLock {
+    pub center: bool,
+    pub fov: bool,
+    pub rotation: bool,
+}
+
+impl ViewLock {
+    pub fn any(&self) -> bool {
+        self.center || self.fov || self.rotation
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::ViewRect;
+
+    #[test]
+    fn test_view_rect_to_pixels() {
+        let left = ViewRect { x: 0.0, y: 0.0, width: 0.5, height: 1.0 };
+        let right = ViewRect { x: 0.5, y: 0.0, width: 0.5, height: 1.0 };
+        assert_eq!(left.to_pixels(801.0, 600.0), (0.0, 0.0, 401.0, 600.0));
+        assert_eq!(right.to_pixels(801.0, 600.0), (401.0, 0.0, 400.0, 600.0));
+
+        // The top half of the canvas is at the top of the framebuffer
+        let top = ViewRect { x: 0.0, y: 0.0, width: 1.0, height: 0.5 };
+        assert_eq!(top.to_pixels(800.0, 600.0), (0.0, 300.0, 800.0, 300.0));
+        assert!(top.contains(0.2, 0.4));
+        assert!(!top.contains(0.2, 0.5));
+    }
+}
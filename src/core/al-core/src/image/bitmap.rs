@@ -18,6 +18,10 @@ where
             format: std::marker::PhantomData,
         }
     }
+
+    pub fn num_bytes(&self) -> usize {
+        crate::image::format::num_bytes::<F>(self.image.width(), self.image.height())
+    }
 }
 use wasm_bindgen::JsValue;
 use crate::texture::Texture2DArray;
@@ -15,6 +15,10 @@ where
             format: std::marker::PhantomData,
         }
     }
+
+    pub fn num_bytes(&self) -> usize {
+        crate::image::format::num_bytes::<F>(self.canvas.width(), self.canvas.height())
+    }
 }
 
 use cgmath::Vector3;
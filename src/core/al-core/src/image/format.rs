@@ -1,6 +1,11 @@
 use crate::texture::pixel::Pixel;
 use al_api::hips::ImageExt;
 
+/// Size in bytes of an image of format `F`
+pub fn num_bytes<F: ImageFormat>(width: u32, height: u32) -> usize {
+    (width as usize) * (height as usize) * F::NUM_CHANNELS * std::mem::size_of::<<F::P as Pixel>::Item>()
+}
+
 pub enum Bytes<'a> {
     Borrowed(&'a [u8]),
     Owned(Vec<u8>),
@@ -316,6 +321,27 @@ pub struct ImageFormatType {
     pub channel: ChannelType,
 }
 
+impl ChannelType {
+    /// Size of a pixel stored in a texture
+    pub fn num_bytes_per_pixel(&self) -> usize {
+        match self {
+            ChannelType::RGBA32F => 16,
+            ChannelType::RGB32F => 12,
+            ChannelType::RGBA8U => 4,
+            ChannelType::RGB8U => 3,
+            ChannelType::R32F => 4,
+            #[cfg(feature = "webgl2")]
+            ChannelType::R64F => 8,
+            #[cfg(feature = "webgl2")]
+            ChannelType::R8UI => 1,
+            #[cfg(feature = "webgl2")]
+            ChannelType::R16I => 2,
+            #[cfg(feature = "webgl2")]
+            ChannelType::R32I => 4,
+        }
+    }
+}
+
 impl ImageFormatType {
     pub fn get_ext_file(&self) -> &ImageExt {
         &self.ext
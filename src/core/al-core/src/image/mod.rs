@@ -288,6 +288,43 @@ pub enum ImageType {
     RawR32f { image: ImageBuffer<R32F> },
 }
 
+impl ImageType {
+    /// Size in bytes of the decoded image
+    #[cfg(feature = "webgl2")]
+    pub fn num_bytes(&self) -> usize {
+        match self {
+            ImageType::FitsImage { raw_bytes } => raw_bytes.length() as usize,
+            ImageType::Canvas { canvas } => canvas.num_bytes(),
+            ImageType::ImageRgba8u { image } => image.num_bytes(),
+            ImageType::ImageRgb8u { image } => image.num_bytes(),
+            ImageType::HTMLImageRgba8u { image } => image.num_bytes(),
+            ImageType::HTMLImageRgb8u { image } => image.num_bytes(),
+            ImageType::RawRgb8u { image } => image.num_bytes(),
+            ImageType::RawRgba8u { image } => image.num_bytes(),
+            ImageType::RawR32f { image } => image.num_bytes(),
+            ImageType::RawR32i { image } => image.num_bytes(),
+            ImageType::RawR16i { image } => image.num_bytes(),
+            ImageType::RawR8ui { image } => image.num_bytes(),
+        }
+    }
+
+    /// Size in bytes of the decoded image
+    #[cfg(feature = "webgl1")]
+    pub fn num_bytes(&self) -> usize {
+        match self {
+            ImageType::FitsImage { raw_bytes } => raw_bytes.length() as usize,
+            ImageType::Canvas { canvas } => canvas.num_bytes(),
+            ImageType::PngHTMLImageRgba8u { image } => image.num_bytes(),
+            ImageType::JpgHTMLImageRgb8u { image } => image.num_bytes(),
+            ImageType::PngImageRgba8u { image } => image.num_bytes(),
+            ImageType::JpgImageRgb8u { image } => image.num_bytes(),
+            ImageType::RawRgb8u { image } => image.num_bytes(),
+            ImageType::RawRgba8u { image } => image.num_bytes(),
+            ImageType::RawR32f { image } => image.num_bytes(),
+        }
+    }
+}
+
 use cgmath::Vector3;
 impl Image for ImageType {
     fn tex_sub_image_3d(
@@ -31,6 +31,10 @@ where
         Self { data, size }
     }
 
+    pub fn num_bytes(&self) -> usize {
+        self.data.len() * std::mem::size_of::<<<T as ImageFormat>::P as Pixel>::Item>()
+    }
+
     pub fn from_encoded_raw_bytes(raw_bytes: &[u8], width: i32, height: i32) -> Result<Self, JsValue> {
         let mut decoded_bytes = match T::decode(raw_bytes).map_err(|e| JsValue::from_str(e))? {
             Bytes::Borrowed(bytes) => bytes.to_vec(),
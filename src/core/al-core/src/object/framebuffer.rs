@@ -9,18 +9,14 @@ pub struct FrameBufferObject {
 }
 use crate::webgl_ctx::WebGlContext;
 use crate::texture::Texture2D;
+use crate::image::format::ImageFormat;
 
 impl FrameBufferObject {
     pub fn new(gl: &WebGlContext, width: usize, height: usize) -> Result<Self, JsValue> {
-        let fbo = gl
-            .create_framebuffer()
-            .ok_or("failed to create framebuffer")?;
-        gl.bind_framebuffer(WebGlRenderingCtx::FRAMEBUFFER, Some(&fbo));
-
-        let texture = Texture2D::create_empty_with_format::<crate::image::format::RGBA8U>(
+        Self::new_with_format::<crate::image::format::RGBA8U>(
             gl,
-            width as i32,
-            height as i32,
+            width,
+            height,
             &[
                 (
                     WebGlRenderingCtx::TEXTURE_MIN_FILTER,
@@ -41,6 +37,28 @@ impl FrameBufferObject {
                     WebGlRenderingCtx::CLAMP_TO_EDGE,
                 ),
             ],
+        )
+    }
+
+    /// Create a fbo rendering into a texture of format `F`
+    ///
+    /// Float formats need the `EXT_color_buffer_float` extension to be renderable
+    pub fn new_with_format<F: ImageFormat>(
+        gl: &WebGlContext,
+        width: usize,
+        height: usize,
+        tex_params: &'static [(u32, u32)],
+    ) -> Result<Self, JsValue> {
+        let fbo = gl
+            .create_framebuffer()
+            .ok_or("failed to create framebuffer")?;
+        gl.bind_framebuffer(WebGlRenderingCtx::FRAMEBUFFER, Some(&fbo));
+
+        let texture = Texture2D::create_empty_with_format::<F>(
+            gl,
+            width as i32,
+            height as i32,
+            tex_params,
         )?;
         texture.attach_to_framebuffer();
 
@@ -60,27 +78,15 @@ impl FrameBufferObject {
                 self.texture.height() as usize,
             )
         {
-            //let pixels = [0, 0, 0, 0].iter().cloned().cycle().take(4*height*width).collect::<Vec<_>>();
-            #[cfg(feature = "webgl2")]
-            self.texture
-                .bind_mut()
-                .tex_image_2d_with_i32_and_i32_and_i32_and_format_and_type_and_opt_u8_array(
-                    width as i32,
-                    height as i32,
-                    WebGlRenderingCtx::SRGB8_ALPHA8 as i32,
-                    WebGlRenderingCtx::RGBA,
-                    WebGlRenderingCtx::UNSIGNED_BYTE,
-                    None,
-                );
-            #[cfg(feature = "webgl1")]
+            let (internal_format, format, type_) = self.texture.get_format();
             self.texture
                 .bind_mut()
                 .tex_image_2d_with_i32_and_i32_and_i32_and_format_and_type_and_opt_u8_array(
                     width as i32,
                     height as i32,
-                    WebGlRenderingCtx::RGBA as i32,
-                    WebGlRenderingCtx::RGBA,
-                    WebGlRenderingCtx::UNSIGNED_BYTE,
+                    internal_format,
+                    format,
+                    type_,
                     None,
                 );
         }
@@ -122,6 +128,54 @@ impl FrameBufferObject {
 
         Ok(())
     }
+
+    /// Read a rectangle of a float fbo
+    ///
+    /// The RGBA values are returned row by row starting from the bottom row
+    pub fn read_pixels_f32(&self, x: i32, y: i32, width: i32, height: i32) -> Result<Vec<f32>, JsValue> {
+        let pixels = js_sys::Float32Array::new_with_length((4 * width * height) as u32);
+
+        self.gl
+            .bind_framebuffer(WebGlRenderingCtx::FRAMEBUFFER, Some(&self.fbo));
+        let res = self.gl.read_pixels_with_opt_array_buffer_view(
+            x,
+            y,
+            width,
+            height,
+            WebGlRenderingCtx::RGBA,
+            WebGlRenderingCtx::FLOAT,
+            Some(&pixels),
+        );
+        self.gl
+            .bind_framebuffer(WebGlRenderingCtx::FRAMEBUFFER, None);
+        res?;
+
+        Ok(pixels.to_vec())
+    }
+
+    /// Read a rectangle of a RGBA8 fbo
+    ///
+    /// The RGBA values are returned row by row starting from the bottom row
+    pub fn read_pixels_u8(&self, x: i32, y: i32, width: i32, height: i32) -> Result<Vec<u8>, JsValue> {
+        let mut pixels = vec![0_u8; (4 * width * height) as usize];
+
+        self.gl
+            .bind_framebuffer(WebGlRenderingCtx::FRAMEBUFFER, Some(&self.fbo));
+        let res = self.gl.read_pixels_with_opt_u8_array(
+            x,
+            y,
+            width,
+            height,
+            WebGlRenderingCtx::RGBA,
+            WebGlRenderingCtx::UNSIGNED_BYTE,
+            Some(&mut pixels),
+        );
+        self.gl
+            .bind_framebuffer(WebGlRenderingCtx::FRAMEBUFFER, None);
+        res?;
+
+        Ok(pixels)
+    }
 }
 
 impl Drop for FrameBufferObject {
@@ -281,6 +281,12 @@ impl Texture2D {
         self.metadata.as_ref().unwrap_abort().borrow().height
     }
 
+    /// Internal format, format and type of the texture
+    pub fn get_format(&self) -> (i32, u32, u32) {
+        let metadata = self.metadata.as_ref().unwrap_abort().borrow();
+        (metadata.internal_format, metadata.format, metadata.type_)
+    }
+
     pub fn active_texture(&self, idx_tex_unit: u8) -> &Self {
         self.gl
             .active_texture(WebGlRenderingCtx::TEXTURE0 + idx_tex_unit as u32);
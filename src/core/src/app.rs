@@ -1,7 +1,7 @@
 use crate::{
     async_task::{BuildCatalogIndex, ParseTableTask, TaskExecutor, TaskResult, TaskType},
     camera::CameraViewPort,
-    downloader::Downloader,
+    downloader::{source::{HttpSource, TileSource}, Downloader},
     math::{
         self,
         angle::{Angle, ArcDeg},
@@ -11,12 +11,15 @@ use crate::{
         catalog::{Manager, Source},
         grid::ProjetedGrid,
         moc::MOC,
+        contour::Contours,
+        hips::HiPS,
         ImageCfg,
     },
     healpix::coverage::HEALPixCoverage,
     shader::ShaderManager,
     renderable::Layers,
-    tile_fetcher::TileFetcherQueue,
+    survey::budget,
+    tile_fetcher::{TileFetcherQueue, TilePriority},
     time::DeltaTime,
 };
 
@@ -27,8 +30,13 @@ use al_core::colormap::{Colormap, Colormaps};
 
 use al_api::{
     coo_system::CooSystem,
+    contour::ContourCfg,
+    cache::{CacheStats, LayerCacheStats},
+    cutout::{CutoutCfg, ScreenRect},
     grid::GridCfg,
-    hips::{ImageMetadata, HiPSCfg, FITSCfg},
+    state::{CustomColormap, ViewState},
+    hips::{ImageMetadata, HiPSCfg, HiPSProperties, HiPSTileStats, FITSCfg, WCSImageCfg, RGBCompositeCfg, LayerArithmeticCfg},
+    properties::{HiPSPropertiesFile, PropertiesKeywords},
 };
 use wasm_bindgen_futures::JsFuture;
 use fitsrs::{fits::AsyncFits, hdu::{extension::AsyncXtensionHDU}};
@@ -39,14 +47,23 @@ use cgmath::Vector4;
 use web_sys::WebGl2RenderingContext;
 
 use std::{cell::RefCell};
+use std::ops::Deref;
 use std::rc::Rc;
 
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
 
 use crate::renderable::final_pass::RenderPass;
 use al_core::FrameBufferObject;
+use al_core::image::format::RGBA32F;
+
+use crate::export::fits::Card;
+use crate::export::ExportedView;
+use crate::export::snapshot::TileRect;
+use crate::renderable::lines::RasterizedLinesRenderManager;
+use al_api::export::{ExportCfg, ExportFormat, SnapshotCfg};
 
 use al_api::image::ImageParams;
+use al_api::view::{ViewLock, ViewRect};
 
 pub struct App {
     pub gl: WebGlContext,
@@ -57,7 +74,15 @@ pub struct App {
 
     downloader: Downloader,
     tile_fetcher: TileFetcherQueue,
+    // Fetch the tiles around the view and of the next deeper order when the network is idle
+    prefetch_tiles: bool,
+    // View whose tiles are fetched ahead of time, given by its center and its field of view
+    prefetch_target: Option<(LonLatT<f64>, Angle<f64>)>,
     layers: Layers,
+    // Memory in bytes shared by the cached resources and the textures of all the HiPS
+    memory_budget: usize,
+    // Sources of the HiPS not served over HTTP, indexed by their root url
+    tile_sources: HashMap<String, Rc<dyn TileSource>>,
 
     time_start_blending: Time,
     request_redraw: bool,
@@ -67,14 +92,26 @@ pub struct App {
     grid: ProjetedGrid,
     // The moc renderable
     moc: MOC,
+    // The contour overlays
+    contours: Contours,
     // Catalog manager
     manager: Manager,
 
     // Task executor
     exec: Rc<RefCell<TaskExecutor>>,
 
-    //move_animation: Option<MoveAnimation>,
-    //zoom_animation: Option<ZoomAnimation>,
+    move_animation: Option<MoveAnimation>,
+    // The transition of the active view to another projection
+    projection_animation: Option<ProjectionAnimation>,
+    // The tour played back and the one being recorded with the time its recording started
+    tour_player: Option<TourPlayer>,
+    tour_recording: Option<(Tour, Time)>,
+    // Turns the pointer, wheel and key events into moves of the view
+    input: InputController,
+    // The fov a smooth zoom is going to
+    zoom_target: Option<Angle<f64>>,
+    // The high resolution image being rendered offscreen
+    snapshot: Option<Snapshot>,
     inertial_move_animation: Option<InertiaAnimation>,
     disable_inertia: Rc<RefCell<bool>>,
     prev_cam_position: Vector3<f64>,
@@ -91,10 +128,31 @@ pub struct App {
     _fbo_ui: FrameBufferObject,
 
     colormaps: Colormaps,
+    // The colormaps created from a list of colors
+    custom_colormaps: Vec<CustomColormap>,
 
     projection: ProjectionType,
 
+    // The views sharing the canvas. The slot of the active view is empty,
+    // its camera, projection and renderables being the ones of the app
+    views: Vec<Option<SkyView>>,
+    active_view: usize,
+    // Area of the canvas covered by the active view
+    view_rect: ViewRect,
+    // What the other views follow from the active one
+    view_lock: ViewLock,
+    // Resources given back at the next frame to the views that delayed them
+    delayed_rscs: Vec<DelayedResource>,
+    // Size of the canvas in CSS pixels
+    canvas_size: Vector2<f32>,
+    // Kept to build the grid of the new views
+    resources: Resources,
+
     // Async data receivers
+    // HiPS whose properties have been fetched, ready to be added
+    // The HiPS whose properties have been fetched, with where to send whether they could be added
+    hips_send: async_channel::Sender<(HiPSCfg, async_channel::Sender<Result<(), JsValue>>)>,
+    hips_recv: async_channel::Receiver<(HiPSCfg, async_channel::Sender<Result<(), JsValue>>)>,
     fits_send: async_channel::Sender<ImageCfg>,
     fits_recv: async_channel::Receiver<ImageCfg>,
 
@@ -103,8 +161,107 @@ pub struct App {
 }
 
 use cgmath::{Vector2, Vector3};
+use crate::math::rotation::Rotation;
+use crate::tour::TourPlayer;
+use crate::input::{InputAction, InputController};
+use al_api::input::InputCfg;
+use al_api::tour::{Keyframe, Tour};
+use al_api::projection::ProjectionCfg;
 use futures::{stream::StreamExt, io::BufReader}; // for `next`
 
+/// State of a high resolution image rendered offscreen part by part
+struct Snapshot {
+    // Size of the image in pixels
+    width: usize,
+    height: usize,
+    // The parts of the image, a part is rendered once its tiles have been received
+    tiles: Vec<TileRect>,
+    idx_tile: usize,
+    // Time when the tiles of the current part started to be waited
+    time_start_tile: Time,
+    // Time waited for the tiles of a part in ms
+    timeout: f32,
+    // RGBA pixels given row by row from the bottom row
+    pixels: Vec<u8>,
+    // The markers of the catalog sources
+    markers: RasterizedLinesRenderManager,
+    // Size and position of the view in the canvas to go back to
+    screen_size: (f32, f32),
+    screen_origin: Vector2<i32>,
+    // Resolves the promise with the PNG bytes, it is dropped if the snapshot is interrupted
+    done: async_channel::Sender<Result<Vec<u8>, JsValue>>,
+}
+
+/// A view of the canvas that is not the active one
+///
+/// The views share the downloader, the shaders and the colormaps of the app
+struct SkyView {
+    camera: CameraViewPort,
+    projection: ProjectionType,
+    layers: Layers,
+    grid: ProjetedGrid,
+    moc: MOC,
+    contours: Contours,
+    rect: ViewRect,
+}
+
+// The layers of all the views, the ones of the active view first
+fn get_all_layers<'a>(layers: &'a Layers, views: &'a [Option<SkyView>]) -> Vec<&'a Layers> {
+    std::iter::once(layers)
+        .chain(views.iter().flatten().map(|view| &view.layers))
+        .collect()
+}
+
+// Mutable access to the layers of all the views
+fn get_all_layers_mut<'a>(layers: &'a mut Layers, views: &'a mut [Option<SkyView>]) -> Vec<&'a mut Layers> {
+    std::iter::once(layers)
+        .chain(views.iter_mut().flatten().map(|view| &mut view.layers))
+        .collect()
+}
+
+// The HiPS of the views, the ones sharing their textures with a previous one being left out
+fn get_distinct_hips<H: Deref<Target = HiPS>>(hipses: impl Iterator<Item = H>) -> Vec<H> {
+    let mut distinct: Vec<H> = vec![];
+    for hips in hipses {
+        if !distinct.iter().any(|other| other.shares_textures_with(&hips)) {
+            distinct.push(hips);
+        }
+    }
+
+    distinct
+}
+
+// The keywords of a cutout: its WCS followed by the ones giving its physical values
+fn get_cutout_cards(wcs: Vec<Card>, scale: f32, offset: f32, bunit: Option<String>) -> Vec<Card> {
+    let mut cards = wcs;
+    cards.push(Card::new("BSCALE", scale as f64));
+    cards.push(Card::new("BZERO", offset as f64));
+    if let Some(bunit) = bunit {
+        cards.push(Card::new("BUNIT", bunit.as_str()));
+    }
+
+    cards
+}
+
+/// What a view has done with a received resource
+#[derive(Clone, Copy, PartialEq)]
+enum Reception {
+    Done,
+    // The resource is kept in the cache of the downloader for later
+    Cached,
+    // The resource is given back next frame not to slow down a moving view
+    Delayed,
+}
+
+/// A received resource some views have not taken yet
+struct DelayedResource {
+    rsc: Resource,
+    // Index of the views the resource is given back to
+    views: Vec<usize>,
+    // A view has asked to keep it in the cache of the downloader
+    cached: bool,
+}
+
 /// State for inertia
 struct InertiaAnimation {
     // Initial angular distance
@@ -114,16 +271,71 @@ struct InertiaAnimation {
     // The time when the inertia begins
     time_start_anim: Time,
 }
-/*
-struct ZoomAnimation {
+
+/// State of an animated camera transition
+struct MoveAnimation {
     time_start_anim: Time,
+    // Duration in ms
+    duration: f32,
+    start_anim_rot: Rotation<f64>,
+    goal_anim_rot: Rotation<f64>,
     start_fov: Angle<f64>,
     goal_fov: Angle<f64>,
-    w0: f64,
+    // The fov reached at the middle of the transition, the view zooms out to travel far
+    max_fov: Angle<f64>,
+    // Resolves the promise of the transition, it is dropped if the transition is interrupted
+    done: async_channel::Sender<bool>,
+}
+
+/// State of a transition between two projections
+struct ProjectionAnimation {
+    time_start_anim: Time,
+    // Duration in ms
+    duration: f32,
+    // Resolves the promise of the transition, it is dropped if the transition is interrupted
+    done: async_channel::Sender<bool>,
+}
+
+// Interpolate between two fovs, logarithmically so that the zoom speed looks constant
+fn interpolate_fov(f0: Angle<f64>, f1: Angle<f64>, alpha: f64) -> Angle<f64> {
+    Angle(math::utils::log_interpolate(f0.0, f1.0, alpha))
+}
+
+impl MoveAnimation {
+    // The rotation and the fov of the camera at a time, `None` if the transition is finished
+    fn interpolate(&self, time: Time) -> Option<(Rotation<f64>, Angle<f64>)> {
+        let t = ((time - self.time_start_anim).as_millis() / self.duration) as f64;
+        if t >= 1.0 {
+            return None;
+        }
+
+        let alpha = math::utils::ease_in_out_cubic(t.max(0.0));
+        let rot = self.start_anim_rot.slerp(&self.goal_anim_rot, alpha);
+
+        let fov = if self.max_fov.0 > self.start_fov.0.max(self.goal_fov.0) {
+            // Zoom out during the first half, and in during the second one
+            if alpha < 0.5 {
+                interpolate_fov(self.start_fov, self.max_fov, 2.0 * alpha)
+            } else {
+                interpolate_fov(self.max_fov, self.goal_fov, 2.0 * alpha - 1.0)
+            }
+        } else {
+            interpolate_fov(self.start_fov, self.goal_fov, alpha)
+        };
+
+        Some((rot, fov))
+    }
 }
-*/
 use crate::math::projection::*;
 pub const BLENDING_ANIM_DURATION: f32 = 500.0; // in ms
+// Time constant of the smooth zoom in ms
+const SMOOTH_ZOOM_DURATION: f64 = 80.0;
+// The smallest fov reachable by zooming, in degrees
+const MIN_FOV: f64 = 0.00002777777;
+// Memory shared by the cached resources and the HiPS textures, 1GB by default
+const DEFAULT_MEMORY_BUDGET: usize = 1024 * 1024 * 1024;
+// Largest size in pixels of the parts a snapshot is rendered by
+const SNAPSHOT_TILE_SIZE: usize = 2048;
                                                //use crate::buffer::Tile;
 use crate::time::Time;
 use cgmath::InnerSpace;
@@ -178,6 +390,7 @@ impl App {
         let grid = ProjetedGrid::new(&gl, &camera, &resources, &projection)?;
 
         // Variable storing the location to move to
+        let move_animation = None;
         let inertial_move_animation = None;
         let disable_inertia = Rc::new(RefCell::new(false));
 
@@ -201,12 +414,17 @@ impl App {
         let request_for_new_tiles = true;
 
         let moc = MOC::new(&gl);
+        let contours = Contours::new(&gl)?;
 
         gl.clear_color(0.15, 0.15, 0.15, 1.0);
 
+        let (hips_send, hips_recv) = async_channel::unbounded();
         let (fits_send, fits_recv) = async_channel::unbounded::<ImageCfg>();
         let (ack_send, ack_recv) = async_channel::unbounded::<ImageParams>();
 
+        let dpi = camera.get_dpi();
+        let canvas_size = Vector2::new(camera.get_width() / dpi, camera.get_height() / dpi);
+
         Ok(App {
             gl,
             start_time_frame,
@@ -227,6 +445,8 @@ impl App {
             grid,
             // MOCs renderable
             moc,
+            // Contours renderable
+            contours,
             // The catalog renderable
             manager,
             exec,
@@ -236,6 +456,13 @@ impl App {
             _fbo_ui,
             _final_rendering_pass,
 
+            move_animation,
+            projection_animation: None,
+            tour_player: None,
+            tour_recording: None,
+            input: InputController::new(),
+            zoom_target: None,
+            snapshot: None,
             inertial_move_animation,
             disable_inertia,
             prev_cam_position,
@@ -245,10 +472,25 @@ impl App {
             catalog_loaded,
 
             tile_fetcher,
+            prefetch_tiles: true,
+            prefetch_target: None,
+            memory_budget: DEFAULT_MEMORY_BUDGET,
+            tile_sources: HashMap::new(),
 
             colormaps,
+            custom_colormaps: vec![],
             projection,
 
+            views: vec![None],
+            active_view: 0,
+            view_rect: ViewRect::default(),
+            view_lock: ViewLock::default(),
+            delayed_rscs: vec![],
+            canvas_size,
+            resources,
+
+            hips_send,
+            hips_recv,
             fits_send,
             fits_recv,
             ack_send,
@@ -259,8 +501,29 @@ impl App {
     fn look_for_new_tiles(&mut self) -> Result<(), JsValue> {
         // Move the views of the different active surveys
         self.tile_fetcher.clear();
+        // The tiles of all the views are fetched together
+        self.for_each_view(|app| {
+            app.append_view_tile_queries();
+            Ok(())
+        })?;
+        self.append_target_tile_queries();
+
+        self.tile_fetcher.fetch(&mut self.downloader);
+
+        Ok(())
+    }
+
+    // Queue the tiles needed by the surveys of the active view
+    fn append_view_tile_queries(&mut self) {
         // Loop over the surveys
         for survey in self.layers.values_mut_hips() {
+            // The tiles closest to the center of the view are fetched first
+            let view_center = crate::coosys::apply_coo_system(
+                self.camera.get_system(),
+                survey.get_view().get_frame(),
+                self.camera.get_center(),
+            );
+
             // do not add tiles if the view is already at depth 0
             let view = survey.get_view();
             let depth_tile = view.get_depth();
@@ -305,21 +568,31 @@ impl App {
                         .collect();
                 }
 
+                if self.prefetch_tiles {
+                    let cfg = survey.get_config();
+                    for tile_cell in survey.get_prefetch_tile_cells() {
+                        let priority = TilePriority::new(&tile_cell, &view_center);
+                        self.tile_fetcher
+                            .append_prefetch(query::Tile::new(&tile_cell, &cfg), priority, &self.downloader);
+                    }
+                }
+
+                let url = survey.get_config().get_root_url().clone();
                 for tile_cell in tile_cells {
                     let tile_found = survey.update_priority_tile(&tile_cell);
-                    if !tile_found {
+                    // Tiles already in the textures may still be needed to compute contours
+                    if !tile_found || self.contours.needs_tile(&url, &tile_cell) {
                         // Submit the request to the buffer
                         let cfg = survey.get_config();
                         // Launch the new tile requests
                         //self.downloader.fetch(query::Tile::new(&tile_cell, cfg));
+                        let priority = TilePriority::new(&tile_cell, &view_center);
                         self.tile_fetcher
-                            .append(query::Tile::new(&tile_cell, cfg), &mut self.downloader);
+                            .append(query::Tile::new(&tile_cell, &cfg), priority);
                     }
                 }
             }
         }
-
-        Ok(())
     }
 
     // Run async tasks:
@@ -401,7 +674,8 @@ use al_api::cell::HEALPixCellProjeted;
 use crate::downloader::request::Resource;
 
 use crate::healpix::cell::HEALPixCell;
-use al_api::color::ColorRGB;
+use al_api::color::{Color, ColorRGB, ColorRGBA};
+use std::convert::TryInto;
 use crate::downloader::request::tile::Tile;
 
 impl App {
@@ -471,8 +745,275 @@ impl App {
         Ok(())
     }
 
-    pub(crate) fn update(&mut self, _dt: DeltaTime) -> Result<(), JsValue> {
+    pub(crate) fn add_contours(&mut self, cfg: ContourCfg) -> Result<(), JsValue> {
+        self.contours.add(cfg, &self.layers)?;
+        // Tiles of a HiPS already received must be requested again for being contoured
+        self.request_for_new_tiles = true;
+        self.request_redraw = true;
+
+        Ok(())
+    }
+
+    pub(crate) fn remove_contours(&mut self, id: &str) -> Result<(), JsValue> {
+        self.contours.remove(id)?;
+        self.request_redraw = true;
+
+        Ok(())
+    }
+
+    pub(crate) fn get_cutout(&mut self, cfg: CutoutCfg) -> Result<Vec<u8>, JsValue> {
+        let CutoutCfg { layer, rect } = cfg;
+
+        // 1. The rectangle in physical pixels, clamped to the screen
+        let dpi = self.camera.get_dpi() as f64;
+        let (w_screen, h_screen) = (self.camera.get_width() as f64, self.camera.get_height() as f64);
+        let rect = rect
+            .map(|r| ScreenRect {
+                x: r.x * dpi,
+                y: r.y * dpi,
+                width: r.width * dpi,
+                height: r.height * dpi,
+            })
+            .unwrap_or(ScreenRect {
+                x: 0.0,
+                y: 0.0,
+                width: w_screen,
+                height: h_screen,
+            });
+        let x0 = rect.x.max(0.0).floor();
+        let y0 = rect.y.max(0.0).floor();
+        let x1 = (rect.x + rect.width).min(w_screen).ceil();
+        let y1 = (rect.y + rect.height).min(h_screen).ceil();
+        if x1 <= x0 || y1 <= y0 {
+            return Err(JsValue::from_str("The cutout rectangle does not intersect the view"));
+        }
+        let rect = ScreenRect {
+            x: x0,
+            y: y0,
+            width: x1 - x0,
+            height: y1 - y0,
+        };
+        let (width, height) = (rect.width as usize, rect.height as usize);
+
+        // 2. The values of an image layer are copied from its original pixels
+        if let Some(image) = self.layers.get_image_from_layer(&layer) {
+            let (scale, offset, bunit) = image.get_physical_params();
+            let (values, width, height, wcs) = image.get_cutout(&rect, &self.camera, &self.projection)?;
+
+            let data = values.iter()
+                .map(|v| (v - offset) / scale)
+                .collect::<Vec<_>>();
+            let cards = get_cutout_cards(wcs, scale, offset, bunit.cloned());
+
+            return Ok(crate::export::fits::write_f32_image(width, height, &data, &cards));
+        }
+
+        // 3. The physical parameters of the HiPS layer
+        let (scale, offset, bunit) = if let Some(hips) = self.layers.get_hips_from_layer(&layer) {
+            let cfg = hips.get_config();
+            (cfg.scale, cfg.offset, cfg.bunit.clone())
+        } else {
+            return Err(JsValue::from_str(&format!("Layer {:?} not found.", layer)));
+        };
+
+        // The HiPS is resampled in the projection of the view, that must be a FITS one
+        if self.projection.get_fits_code().is_none() {
+            return Err(JsValue::from_str("No cutout can be made in the current projection"));
+        }
+
+        let wcs = crate::export::wcs::get_view_wcs(&self.camera, &self.projection, &rect, width, height)?;
+
+        // 4. Render the physical values of the layer and read the rectangle
+        let fbo = FrameBufferObject::new_with_format::<RGBA32F>(
+            &self.gl,
+            w_screen as usize,
+            h_screen as usize,
+            &[
+                (
+                    WebGl2RenderingContext::TEXTURE_MIN_FILTER,
+                    WebGl2RenderingContext::NEAREST,
+                ),
+                (
+                    WebGl2RenderingContext::TEXTURE_MAG_FILTER,
+                    WebGl2RenderingContext::NEAREST,
+                ),
+                (
+                    WebGl2RenderingContext::TEXTURE_WRAP_S,
+                    WebGl2RenderingContext::CLAMP_TO_EDGE,
+                ),
+                (
+                    WebGl2RenderingContext::TEXTURE_WRAP_T,
+                    WebGl2RenderingContext::CLAMP_TO_EDGE,
+                ),
+            ],
+        )?;
+        self.layers.draw_raw_values(&layer, &fbo, &self.camera, &mut self.shaders, &self.colormaps, &self.projection)?;
+
+        // The GL rows are given from the bottom of the screen like the FITS ones
+        let y_gl = h_screen - (rect.y + rect.height);
+        let pixels = fbo.read_pixels_f32(rect.x as i32, y_gl as i32, width as i32, height as i32)?;
+
+        // 5. Store the values as they are in the original data.
+        // The values are blended with the transparent background so they are weighted by alpha
+        let data = pixels
+            .chunks(4)
+            .map(|rgba| {
+                if rgba[3] == 0.0 {
+                    f32::NAN
+                } else {
+                    (rgba[0] / rgba[3] - offset) / scale
+                }
+            })
+            .collect::<Vec<_>>();
+
+        let cards = get_cutout_cards(wcs, scale, offset, bunit);
+
+        Ok(crate::export::fits::write_f32_image(width, height, &data, &cards))
+    }
+
+    // The size of an image of the view, keeping the aspect ratio of the view if only one dimension is given
+    fn get_image_size(&self, width: Option<u32>, height: Option<u32>) -> (f32, f32) {
+        let (w_screen, h_screen) = (self.camera.get_width(), self.camera.get_height());
+        let aspect = w_screen / h_screen;
+
+        match (width, height) {
+            (Some(w), Some(h)) => (w as f32, h as f32),
+            (Some(w), None) => (w as f32, (w as f32 / aspect).round()),
+            (None, Some(h)) => ((h as f32 * aspect).round(), h as f32),
+            (None, None) => (w_screen, h_screen),
+        }
+    }
+
+    fn get_max_texture_size(&self) -> Result<f32, JsValue> {
+        let max_size = self.gl.get_parameter(WebGl2RenderingContext::MAX_TEXTURE_SIZE)?
+            .as_f64()
+            .unwrap_or(4096.0) as f32;
+
+        Ok(max_size)
+    }
+
+    /// Render the layers offscreen at a given resolution and export them with the WCS of the view
+    pub(crate) fn export_view(&mut self, cfg: ExportCfg) -> Result<ExportedView, JsValue> {
+        let ExportCfg { width, height, format } = cfg;
+
+        // 1. The size of the image, keeping the aspect ratio of the view by default
+        let (w_screen, h_screen) = (self.camera.get_width(), self.camera.get_height());
+        let (width, height) = self.get_image_size(width, height);
+
+        let max_size = self.get_max_texture_size()?;
+        if width < 1.0 || height < 1.0 || width > max_size || height > max_size {
+            return Err(JsValue::from_str(&format!("The size of the image must be between 1 and {} pixels", max_size)));
+        }
+
+        // 2. Render the layers at that size
+        let fbo = FrameBufferObject::new(&self.gl, width as usize, height as usize)?;
+
+        let origin = self.camera.get_viewport_origin();
+        self.camera.set_viewport_origin(Vector2::new(0, 0));
+        self.camera.set_render_size(width, height, &self.projection);
+        let wcs = crate::export::wcs::get_view_wcs(
+            &self.camera,
+            &self.projection,
+            &ScreenRect { x: 0.0, y: 0.0, width: width as f64, height: height as f64 },
+            width as usize,
+            height as usize,
+        );
+
+        // The HiPS cells and the image vertices must cover the image
+        self.layers.refresh_views(&mut self.camera);
+        let res = self.layers.recompute_vertices(&self.camera, &self.projection)
+            .and_then(|_| {
+                let layers = &mut self.layers;
+                let shaders = &mut self.shaders;
+                let (camera, colormaps, projection) = (&self.camera, &self.colormaps, &self.projection);
+                fbo.draw_onto(|| layers.draw(camera, shaders, colormaps, projection), None)
+            });
+
+        // Back to the screen
+        self.camera.set_viewport_origin(origin);
+        self.camera.set_render_size(w_screen, h_screen, &self.projection);
+        self.layers.refresh_views(&mut self.camera);
+        let restored = self.layers.recompute_vertices(&self.camera, &self.projection);
+        self.request_redraw = true;
+
+        res?;
+        restored?;
+        let cards = wcs?;
+        let pixels = fbo.read_pixels_u8(0, 0, width as i32, height as i32)?;
+
+        // 3. Write the file
+        let (width, height) = (width as usize, height as usize);
+        match format {
+            ExportFormat::Fits => Ok(ExportedView {
+                image: crate::export::fits::write_rgb_image(width, height, &pixels, &cards),
+                wcs: None,
+            }),
+            ExportFormat::Png => {
+                let mut cards = cards;
+                cards.push(Card::new("IMAGEW", width as i64));
+                cards.push(Card::new("IMAGEH", height as i64));
+
+                Ok(ExportedView {
+                    image: crate::export::encode_png(width, height, &pixels)?,
+                    wcs: Some(crate::export::fits::write_header_only(&cards)),
+                })
+            }
+        }
+    }
+
+    pub(crate) fn update(&mut self, dt: DeltaTime) -> Result<(), JsValue> {
         //let available_tiles = self.run_tasks(dt)?;
+        let tour_view = self.tour_player.as_mut()
+            .and_then(|player| player.update())
+            .map(|(k0, k1, alpha)| (k0.clone(), k1.clone(), alpha));
+        if let Some((k0, k1, alpha)) = tour_view {
+            self.apply_tour_view(&k0, &k1, alpha)?;
+        }
+
+        if let Some(target) = self.zoom_target {
+            // Go exponentially to the target fov
+            let fov = self.camera.get_aperture();
+            let alpha = 1.0 - (-(dt.as_millis() as f64) / SMOOTH_ZOOM_DURATION).exp();
+            let fov = Angle(math::utils::log_interpolate(fov.0, target.0, alpha));
+
+            if (fov.0 / target.0).ln().abs() < 1e-3 {
+                self.camera.set_aperture(target, &self.projection);
+                self.zoom_target = None;
+            } else {
+                self.camera.set_aperture(fov, &self.projection);
+            }
+            self.request_for_new_tiles = true;
+        }
+
+        if let Some(anim) = &self.move_animation {
+            if let Some((rot, fov)) = anim.interpolate(Time::now()) {
+                self.camera.set_rotation(&rot, &self.projection);
+                self.camera.set_aperture(fov, &self.projection);
+            } else {
+                self.camera.set_rotation(&anim.goal_anim_rot, &self.projection);
+                self.camera.set_aperture(anim.goal_fov, &self.projection);
+
+                let _ = anim.done.try_send(true);
+                self.move_animation = None;
+            }
+
+            self.request_for_new_tiles = true;
+        }
+
+        if let Some(anim) = &self.projection_animation {
+            let t = ((Time::now() - anim.time_start_anim).as_millis() / anim.duration) as f64;
+            if t >= 1.0 {
+                let _ = anim.done.try_send(true);
+                self.end_projection_morph()?;
+            } else if let ProjectionType::Morph(morph) = &mut self.projection {
+                morph.alpha = math::utils::ease_in_out_cubic(t.max(0.0));
+                // Recompute the ndc_to_clip and the clip zoom factor
+                self.camera.set_projection(&self.projection);
+            }
+
+            self.request_for_new_tiles = true;
+        }
+
         if let Some(InertiaAnimation {
             time_start_anim,
             d0,
@@ -506,9 +1047,13 @@ impl App {
             }
         }
 
+        // The views following the active one
+        if self.camera.has_moved() {
+            self.sync_locked_views();
+        }
+
         // The rendering is done following these different situations:
-        // - the camera has moved
-        let has_camera_moved = self.camera.has_moved();
+        // - the camera of a view has moved
         {
             // Newly available tiles must lead to
             // 1. Surveys must be aware of the new available tiles
@@ -517,154 +1062,90 @@ impl App {
             /*let is_there_new_available_tiles = self
             .downloader
             .get_resolved_tiles(/*&available_tiles, */&mut self.surveys);*/
-            let rscs = self.downloader.get_received_resources();
-
-            let mut num_tile_received = 0;
-            let mut tile_copied = false;
-            for rsc in rscs.into_iter() {
-                if !has_camera_moved || (Time::now() - self.start_time_frame < DeltaTime::from(24.0)) || !tile_copied {
-                    match rsc {
-                        Resource::Tile(tile) => {
-                            let is_tile_root = tile.cell().is_root();
-
-                            if let Some(survey) = self.layers.get_mut_hips_from_url(&tile.get_hips_url()) {
-                                let cfg = survey.get_config();
-                                if cfg.get_format() == tile.format {
-                                    // If the format of the survey has changed then we discard tiles of the previous format
+            let num_views = self.views.len();
+            let mut rscs = std::mem::take(&mut self.delayed_rscs);
+            rscs.extend(
+                self.downloader
+                    .get_received_resources()
+                    .into_iter()
+                    .map(|rsc| DelayedResource {
+                        rsc,
+                        views: (0..num_views).collect(),
+                        cached: false,
+                    }),
+            );
 
-                                    if is_tile_root {
-                                        let is_missing = tile.missing();
-                                        let Tile {
-                                            cell,
-                                            image,
-                                            time_req,
-                                            ..
-                                        } = tile;
-            
-                                        let image = if is_missing {
-                                            // Otherwise we push nothing, it is probably the case where:
-                                            // - an request error occured on a valid tile
-                                            // - the tile is not present, e.g. chandra HiPS have not the 0, 1 and 2 order tiles
-                                            None
-                                        } else {
-                                            Some(image)
-                                        };
-                                        survey.add_tile(&cell, image, time_req)?;
-                                        tile_copied = true;
+            // The layers of every view are given the resources they have not taken yet
+            let receptions = self.for_each_view(|app| {
+                let view = app.active_view;
+                let view_rscs = rscs
+                    .iter()
+                    .filter(|delayed| delayed.views.contains(&view))
+                    .map(|delayed| &delayed.rsc)
+                    .collect::<Vec<_>>();
+                let mut receptions = app.receive_resources(&view_rscs)?.into_iter();
+
+                Ok(rscs
+                    .iter()
+                    .map(|delayed| {
+                        if delayed.views.contains(&view) {
+                            receptions.next()
+                        } else {
+                            None
+                        }
+                    })
+                    .collect::<Vec<_>>())
+            })?;
 
-                                        self.request_redraw = true;
-                                    } else {
-                                        let fov_coverage = survey.get_view().get_coverage();
-                                        let texture_cell = tile.cell().get_texture_cell(cfg);
-                                        let included_or_near_coverage = texture_cell.get_tile_cells(cfg)
-                                            .any(|neighbor_tile_cell| {
-                                                fov_coverage.contains(&neighbor_tile_cell)
-                                            });
-
-                                        // do not perform tex_sub costly GPU calls while the camera is moving
-                                        if included_or_near_coverage && !has_camera_moved {
-                                            let is_missing = tile.missing();
-                                            let Tile {
-                                                cell,
-                                                image,
-                                                time_req,
-                                                ..
-                                            } = tile;
-
-                                            let image = if is_missing {
-                                                // Otherwise we push nothing, it is probably the case where:
-                                                // - an request error occured on a valid tile
-                                                // - the tile is not present, e.g. chandra HiPS have not the 0, 1 and 2 order tiles
-                                                None
-                                            } else {
-                                                Some(image)
-                                            };
-
-                                            survey.add_tile(&cell, image, time_req)?;
-                                            tile_copied = true;
-
-                                            self.request_redraw = true;
-                                        } else {
-                                            self.downloader.cache_rsc(Resource::Tile(tile));
-                                        }
-                                    }
-                                }
-                            }
+            let mut tile_urls = vec![];
+            for (idx, mut delayed) in rscs.into_iter().enumerate() {
+                delayed.cached |= receptions.iter().any(|r| r[idx] == Some(Reception::Cached));
+                delayed.views.retain(|&view| receptions[view][idx] == Some(Reception::Delayed));
 
-                            num_tile_received += 1;
-                        }
-                        Resource::Allsky(allsky) => {
-                            let hips_url = allsky.get_hips_url();
-
-                            if let Some(survey) = self.layers.get_mut_hips_from_url(hips_url) {
-                                let is_missing = allsky.missing();
-                                if is_missing {
-                                    // The allsky image is missing so we donwload all the tiles contained into
-                                    // the 0's cell
-                                    let cfg = survey.get_config();
-                                    for texture_cell in crate::healpix::cell::ALLSKY_HPX_CELLS_D0 {
-                                        for cell in texture_cell.get_tile_cells(cfg) {
-                                            let query = query::Tile::new(&cell, cfg);
-                                            self.tile_fetcher
-                                                .append_base_tile(query, &mut self.downloader);
-                                        }
-                                    }
-                                } else {
-                                    // tell the survey to not download tiles which order is <= 3 because the allsky
-                                    // give them already
-                                    survey.add_allsky(allsky)?;
-                                    // Once received ask for redraw
-                                    self.request_redraw = true;
-                                }
-                            }
-                        },
-                        Resource::PixelMetadata(metadata) => {
-                            if let Some(hips) = self.layers.get_mut_hips_from_url(&metadata.hips_url) {
-                                let mut cfg = hips.get_config_mut();
-
-                                if let Some(metadata) = *metadata.value.lock().unwrap_abort() {
-                                    cfg.blank = metadata.blank;
-                                    cfg.offset = metadata.offset;
-                                    cfg.scale = metadata.scale;
-                                }
-                            }
-                        },
-                        Resource::Moc(moc) => {
-                            let moc_url = moc.get_url();
-                            let url = &moc_url[..moc_url.find("/Moc.fits").unwrap_abort()];
-                            if let Some(hips) = self.layers.get_mut_hips_from_url(url) {
-                                let request::moc::Moc {
-                                    moc,
-                                    ..
-                                } = moc;
-    
-                                if let Some(moc) = &*moc.lock().unwrap_abort() {
-                                    hips.set_moc(moc.clone());
+                if !delayed.views.is_empty() {
+                    self.delayed_rscs.push(delayed);
+                } else {
+                    if let Resource::Tile(tile) = &delayed.rsc {
+                        tile_urls.push((tile.get_hips_url().clone(), tile.get_url().clone()));
+                    }
 
-                                    self.request_for_new_tiles = true;
-                                    self.request_redraw = true;
-                                };
-                            }
-                        },
+                    if delayed.cached {
+                        self.downloader.cache_rsc(delayed.rsc);
                     }
-                } else {
-                    self.downloader.delay_rsc(rsc);
                 }
             }
 
-            if num_tile_received > 0 {
-                self.tile_fetcher
-                    .notify(num_tile_received, &mut self.downloader);
+            if !tile_urls.is_empty() {
+                for (hips_url, tile_url) in tile_urls {
+                    self.tile_fetcher
+                        .notify(&hips_url, &tile_url, &mut self.downloader);
+                }
                 self.time_start_blending = Time::now();
             }
+
+            // The tiles received may have taken new texture slices
+            self.fit_memory_budget();
+
+            self.switch_unhealthy_mirrors()?;
             //self.layers.add_resolved_tiles(resolved_tiles);
             // 3. Try sending new tile requests after
             //self.downloader.try_sending_tile_requests()?;
         }
 
         // Then, check for new tiles
+        let has_camera_moved = self.for_each_view(|app| {
+            let has_camera_moved = app.camera.has_moved();
+            if has_camera_moved {
+                app.layers.refresh_views(&mut app.camera);
+            }
+
+            Ok(has_camera_moved)
+        })?.into_iter().any(|moved| moved);
+
         if has_camera_moved {
-            self.layers.refresh_views(&mut self.camera);
+            // Do not waste bandwidth on the tiles that have left the views
+            let layers = get_all_layers(&self.layers, &self.views);
+            self.tile_fetcher.cancel_out_of_view(&layers);
         }
 
         if self.request_for_new_tiles && Time::now() - self.last_time_request_for_new_tiles > DeltaTime::from(100_f32) {
@@ -678,30 +1159,30 @@ impl App {
         let blending_anim_occuring =
             (Time::now().0 - self.time_start_blending.0) < BLENDING_ANIM_DURATION;
 
-        let mut start_fading = false;
-        for hips in self.layers.values_hips() {
-            if let Some(start_time) = hips.get_ready_time() {
-                start_fading |= Time::now().0 - start_time.0 < BLENDING_ANIM_DURATION;
-                if start_fading {
-                    break;
-                }
-            }
-        }
+        let start_fading = self.for_each_view(|app| Ok(app.is_fading()))?
+            .into_iter()
+            .any(|fading| fading);
 
         self.rendering =
             blending_anim_occuring | has_camera_moved | self.request_redraw | start_fading;
         self.request_redraw = false;
 
-        // Finally update the camera that reset the flag camera changed
-        if has_camera_moved {
-            // Catalogues update
-            /*if let Some(view) = self.layers.get_view() {
-                self.manager.update(&self.camera, view);
-            }*/
-            self.grid.update(&self.camera, &self.projection);
-            // MOCs update
-            self.moc.update(&self.camera, &self.projection);
-        }
+        self.for_each_view(|app| {
+            // Finally update the camera that reset the flag camera changed
+            if app.camera.has_moved() {
+                // Catalogues update
+                /*if let Some(view) = self.layers.get_view() {
+                    self.manager.update(&self.camera, view);
+                }*/
+                app.grid.update(&app.camera, &app.projection);
+                // MOCs update
+                app.moc.update(&app.camera, &app.projection);
+            }
+            // Contours are also updated when new tiles have been contoured
+            app.contours.update(&app.camera, &app.projection, &app.layers);
+
+            Ok(())
+        })?;
 
         /*{
             let events = self.ui.lock().update();
@@ -717,6 +1198,11 @@ impl App {
         }*/
 
         // Check for async retrieval
+        while let Ok((hips, added_send)) = self.hips_recv.try_recv() {
+            // A HiPS that cannot be added only rejects its own promise
+            let _ = added_send.try_send(self.add_image_survey(hips));
+        }
+
         if let Ok(fits) = self.fits_recv.try_recv() {
             let params = fits.get_params();
             self.layers.add_image_fits(fits, &mut self.camera, &self.projection)?;
@@ -730,50 +1216,630 @@ impl App {
             })
         }
 
+        self.update_snapshot()?;
         self.draw(false)?;
 
         Ok(())
     }
 
-    pub(crate) fn reset_north_orientation(&mut self) {
-        // Reset the rotation around the center if there is one
-        self.camera.set_rotation_around_center(Angle(0.0), &self.projection);
-        // Reset the camera position to its current position
-        // this will keep the current position but reset the orientation
-        // so that the north pole is at the top of the center.
-        self.set_center(&self.get_center());
-    }
+    // Give the received resources to the layers of the active view
+    //
+    // Returns what has been done with each resource
+    fn receive_resources(&mut self, rscs: &[&Resource]) -> Result<Vec<Reception>, JsValue> {
+        let has_camera_moved = self.camera.has_moved();
 
-    pub(crate) fn read_pixel(&self, pos: &Vector2<f64>, layer: &str) -> Result<JsValue, JsValue> {
-        if let Some(lonlat) = self.screen_to_world(pos) {
-            if let Some(survey) = self.layers.get_hips_from_layer(layer) {
-                survey.read_pixel(&lonlat, &self.camera)
-            } else if let Some(_image) = self.layers.get_image_from_layer(layer) {
-                Err(JsValue::from_str("TODO: read pixel value"))
-            } else {
-                Err(JsValue::from_str("Survey not found"))
-            }
-        } else {
-            Err(JsValue::from_str(&"position is out of projection"))
-        }
-    }
+        let mut receptions = Vec::with_capacity(rscs.len());
+        let mut tile_copied = false;
+        for rsc in rscs {
+            let reception = if !has_camera_moved || (Time::now() - self.start_time_frame < DeltaTime::from(24.0)) || !tile_copied {
+                let mut reception = Reception::Done;
+                match rsc {
+                    Resource::Tile(tile) => {
+                        let is_tile_root = tile.cell().is_root();
+                        if let Some(survey) = self.layers.get_mut_hips_from_url(&tile.get_hips_url()) {
+                            let cfg = survey.get_config();
+                            if cfg.get_format() == tile.format && cfg.get_cube_frame() == tile.cube_frame {
+                                // If the format or the cube frame of the survey has changed then we discard tiles of the previous ones
+
+                                if is_tile_root {
+                                    let is_missing = tile.missing();
+                                    let failed = tile.failed();
+                                    let Tile {
+                                        cell,
+                                        image,
+                                        time_req,
+                                        ..
+                                    } = tile.clone();
+        
+                                    let image = if is_missing {
+                                        // Otherwise we push nothing, it is probably the case where:
+                                        // - an request error occured on a valid tile
+                                        // - the tile is not present, e.g. chandra HiPS have not the 0, 1 and 2 order tiles
+                                        None
+                                    } else {
+                                        Some(image)
+                                    };
+                                    self.contours.add_tile(cfg.get_root_url(), &cell, image.as_ref(), &cfg);
+                                    // The textures may be shared, they are not borrowed anymore when the tile is pushed
+                                    drop(cfg);
+                                    survey.add_tile(&cell, image, time_req)?;
+                                    if failed {
+                                        survey.mark_tile_failed(&cell);
+                                    }
+                                    tile_copied = true;
 
-    pub(crate) fn draw(&mut self, force_render: bool) -> Result<(), JsValue> {
-        self.start_time_frame = Time::now();
+                                    self.request_redraw = true;
+                                } else {
+                                    let fov_coverage = survey.get_view().get_coverage();
+                                    let texture_cell = tile.cell().get_texture_cell(&cfg);
+                                    let included_or_near_coverage = texture_cell.get_tile_cells(&cfg)
+                                        .any(|neighbor_tile_cell| {
+                                            fov_coverage.contains(&neighbor_tile_cell)
+                                        });
+
+                                    // Tiles of deeper orders fetched ahead of time wait in the cache until the view needs them
+                                    let is_deeper = tile.cell().depth() > survey.get_view().get_depth();
+                                    // do not perform tex_sub costly GPU calls while the camera is moving
+                                    if included_or_near_coverage && !is_deeper && !has_camera_moved {
+                                        let is_missing = tile.missing();
+                                        let failed = tile.failed();
+                                        let Tile {
+                                            cell,
+                                            image,
+                                            time_req,
+                                            ..
+                                        } = tile.clone();
 
-        /*let scene_redraw = self.rendering | force_render;
-        let mut ui = self.ui.lock();
+                                        let image = if is_missing {
+                                            // Otherwise we push nothing, it is probably the case where:
+                                            // - an request error occured on a valid tile
+                                            // - the tile is not present, e.g. chandra HiPS have not the 0, 1 and 2 order tiles
+                                            None
+                                        } else {
+                                            Some(image)
+                                        };
 
-        if scene_redraw {
-            let shaders = &mut self.shaders;
-            let gl = self.gl.clone();
-            let camera = &self.camera;
+                                        self.contours.add_tile(cfg.get_root_url(), &cell, image.as_ref(), &cfg);
+                                        drop(cfg);
+                                        survey.add_tile(&cell, image, time_req)?;
+                                        if failed {
+                                            survey.mark_tile_failed(&cell);
+                                        }
+                                        tile_copied = true;
 
-            let grid = &mut self.grid;
-            let layers = &mut self.layers;
-            let catalogs = &self.manager;
-            let colormaps = &self.colormaps;
-            let fbo_view = &self.fbo_view;
+                                        self.request_redraw = true;
+                                    } else {
+                                        reception = Reception::Cached;
+                                    }
+                                }
+                            }
+                        }
+                    }
+                    Resource::Allsky(allsky) => {
+                        let hips_url = allsky.get_hips_url();
+
+                        let survey = self.layers.get_mut_hips_from_url(hips_url)
+                            // Discard the allsky of a cube frame that is not displayed anymore
+                            .filter(|survey| survey.get_config().get_cube_frame() == allsky.cube_frame);
+                        if let Some(survey) = survey {
+                            let is_missing = allsky.missing();
+                            if is_missing {
+                                // The allsky image is missing so we donwload all the tiles contained into
+                                // the 0's cell
+                                let cfg = survey.get_config();
+                                for texture_cell in crate::healpix::cell::ALLSKY_HPX_CELLS_D0 {
+                                    for cell in texture_cell.get_tile_cells(&cfg) {
+                                        let query = query::Tile::new(&cell, &cfg);
+                                        self.tile_fetcher
+                                            .append_base_tile(query, &mut self.downloader);
+                                    }
+                                }
+                            } else {
+                                // tell the survey to not download tiles which order is <= 3 because the allsky
+                                // give them already
+                                survey.add_allsky(allsky.clone())?;
+                                // Once received ask for redraw
+                                self.request_redraw = true;
+                            }
+                        }
+                    },
+                    Resource::PixelMetadata(metadata) => {
+                        if let Some(hips) = self.layers.get_mut_hips_from_url(&metadata.hips_url) {
+                            let mut cfg = hips.get_config_mut();
+
+                            if let Some(metadata) = &*metadata.value.lock().unwrap_abort() {
+                                cfg.blank = metadata.blank;
+                                cfg.offset = metadata.offset;
+                                cfg.scale = metadata.scale;
+                                cfg.bunit = metadata.bunit.clone();
+                            }
+                        }
+                    },
+                    Resource::Moc(moc) => {
+                        let moc_url = moc.get_url();
+                        let url = &moc_url[..moc_url.find("/Moc.fits").unwrap_abort()];
+                        if let Some(hips) = self.layers.get_mut_hips_from_url(url) {
+                            let request::moc::Moc {
+                                moc,
+                                ..
+                            } = moc;
+
+                            if let Some(moc) = &*moc.lock().unwrap_abort() {
+                                hips.set_moc(moc.clone());
+
+                                self.request_for_new_tiles = true;
+                                self.request_redraw = true;
+                            };
+                        }
+                    },
+                }
+
+                reception
+            } else {
+                Reception::Delayed
+            };
+
+            receptions.push(reception);
+        }
+
+        Ok(receptions)
+    }
+
+    // Exchange the active view with another one
+    fn swap_view(&mut self, view: &mut SkyView) {
+        std::mem::swap(&mut self.camera, &mut view.camera);
+        std::mem::swap(&mut self.projection, &mut view.projection);
+        std::mem::swap(&mut self.layers, &mut view.layers);
+        std::mem::swap(&mut self.grid, &mut view.grid);
+        std::mem::swap(&mut self.moc, &mut view.moc);
+        std::mem::swap(&mut self.contours, &mut view.contours);
+        std::mem::swap(&mut self.view_rect, &mut view.rect);
+    }
+
+    // Make a view the one the camera, the projection and the renderables of the app refer to
+    fn activate_view(&mut self, idx: usize) {
+        if idx == self.active_view {
+            return;
+        }
+
+        if let Some(mut view) = self.views[idx].take() {
+            self.swap_view(&mut view);
+            self.views[self.active_view] = Some(view);
+            self.active_view = idx;
+        }
+    }
+
+    // Call a function on each view made active in turn, the active view being restored afterwards
+    fn for_each_view<T>(&mut self, mut f: impl FnMut(&mut Self) -> Result<T, JsValue>) -> Result<Vec<T>, JsValue> {
+        let active_view = self.active_view;
+        let results = (0..self.views.len())
+            .map(|idx| {
+                self.activate_view(idx);
+                f(self)
+            })
+            .collect::<Result<Vec<_>, _>>();
+        self.activate_view(active_view);
+
+        results
+    }
+
+    // Whether some tiles of the active view are fading in
+    fn is_fading(&self) -> bool {
+        self.layers.values_hips().any(|hips| {
+            // Some cells are cross-fading between two HEALPix orders
+            hips.is_lod_fading() || hips.get_ready_time()
+                .map(|start_time| Time::now().0 - start_time.0 < BLENDING_ANIM_DURATION)
+                .unwrap_or(false)
+        })
+    }
+
+    // Give the center, the fov and the rotation of the active view to the views locked on it
+    fn sync_locked_views(&mut self) {
+        let lock = self.view_lock;
+        if !lock.any() {
+            return;
+        }
+
+        let system = *self.camera.get_system();
+        let center = self.camera.get_center().lonlat();
+        let fov = self.camera.get_aperture();
+        let rotation = *self.camera.get_rotation_around_center();
+        for view in self.views.iter_mut().flatten() {
+            let SkyView { camera, projection, .. } = view;
+            if lock.center {
+                camera.set_center(&center, &system, projection);
+            }
+            if lock.fov {
+                camera.set_aperture(fov, projection);
+            }
+            if lock.rotation {
+                camera.set_rotation_around_center(rotation, projection);
+            }
+        }
+    }
+
+    // Place the views in the canvas
+    fn layout_views(&mut self) {
+        let (width, height) = (self.canvas_size.x, self.canvas_size.y);
+        self.camera.set_screen_size(width, height, &self.projection);
+
+        let dpi = self.camera.get_dpi();
+        let _ = self.for_each_view(|app| {
+            let (x, y, w, h) = app.view_rect.to_pixels(width * dpi, height * dpi);
+            app.camera.set_viewport_origin(Vector2::new(x as i32, y as i32));
+            app.camera.set_render_size(w, h, &app.projection);
+            app.camera.set_aperture(app.camera.get_aperture(), &app.projection);
+
+            Ok(())
+        });
+
+        self.request_for_new_tiles = true;
+        self.request_redraw = true;
+    }
+
+    // The camera, the projection and the area of the canvas of a view
+    fn get_view(&self, idx: usize) -> Result<(&CameraViewPort, &ProjectionType, &ViewRect), JsValue> {
+        if idx == self.active_view {
+            Ok((&self.camera, &self.projection, &self.view_rect))
+        } else {
+            self.views.get(idx)
+                .and_then(|view| view.as_ref())
+                .map(|view| (&view.camera, &view.projection, &view.rect))
+                .ok_or_else(|| JsValue::from_str(&format!("No view at index {}", idx)))
+        }
+    }
+
+    // The top-left corner of an area of the canvas in CSS pixels
+    fn get_view_origin(&self, rect: &ViewRect) -> Vector2<f64> {
+        Vector2::new(
+            (rect.x * self.canvas_size.x) as f64,
+            (rect.y * self.canvas_size.y) as f64,
+        )
+    }
+
+    /// The index of the view under a canvas position in CSS pixels, the active view being on top
+    pub(crate) fn get_view_at(&self, sx: f64, sy: f64) -> usize {
+        let x = (sx as f32) / self.canvas_size.x;
+        let y = (sy as f32) / self.canvas_size.y;
+        if self.view_rect.contains(x, y) {
+            return self.active_view;
+        }
+
+        self.views
+            .iter()
+            .position(|view| view.as_ref().map(|view| view.rect.contains(x, y)).unwrap_or(false))
+            .unwrap_or(self.active_view)
+    }
+
+    // A canvas position in CSS pixels given relatively to the active view
+    fn get_view_position(&self, sx: f64, sy: f64) -> Vector2<f64> {
+        Vector2::new(sx, sy) - self.get_view_origin(&self.view_rect)
+    }
+
+    /// Add a view of the sky rendered in a rectangle of the canvas, returns its index
+    ///
+    /// The view starts at the position of the active one, without any layer
+    pub(crate) fn add_view(&mut self, rect: ViewRect) -> Result<usize, JsValue> {
+        // The projection reached at the end of a transition
+        let projection = ProjectionType::from_params(self.projection.get_name(), &self.projection.get_params())?;
+        let system = *self.camera.get_system();
+
+        let mut camera = CameraViewPort::new(&self.gl, system, &projection);
+        camera.set_center(&self.camera.get_center().lonlat(), &system, &projection);
+        camera.set_aperture(self.camera.get_aperture(), &projection);
+
+        let layers = Layers::new(&self.gl, &projection)?;
+        let grid = ProjetedGrid::new(&self.gl, &camera, &self.resources, &projection)?;
+        let moc = MOC::new(&self.gl);
+        let contours = Contours::new(&self.gl)?;
+
+        self.views.push(Some(SkyView {
+            camera,
+            projection,
+            layers,
+            grid,
+            moc,
+            contours,
+            rect,
+        }));
+        // The canvas size has been changed by the new camera
+        self.layout_views();
+
+        Ok(self.views.len() - 1)
+    }
+
+    /// Remove a view, the last one cannot be removed
+    pub(crate) fn remove_view(&mut self, idx: usize) -> Result<(), JsValue> {
+        if idx >= self.views.len() {
+            return Err(JsValue::from_str(&format!("No view at index {}", idx)));
+        }
+        if self.views.len() == 1 {
+            return Err(JsValue::from_str("The last view cannot be removed"));
+        }
+
+        if idx == self.active_view {
+            self.set_active_view(if idx == 0 { 1 } else { idx - 1 })?;
+        }
+
+        self.views.remove(idx);
+        if self.active_view > idx {
+            self.active_view -= 1;
+        }
+        for delayed in &mut self.delayed_rscs {
+            delayed.views.retain(|&view| view != idx);
+            for view in delayed.views.iter_mut().filter(|view| **view > idx) {
+                *view -= 1;
+            }
+        }
+
+        self.request_for_new_tiles = true;
+        self.request_redraw = true;
+
+        Ok(())
+    }
+
+    /// Make a view the active one, the layers, the projection and the moves then apply to it
+    pub(crate) fn set_active_view(&mut self, idx: usize) -> Result<(), JsValue> {
+        if idx >= self.views.len() {
+            return Err(JsValue::from_str(&format!("No view at index {}", idx)));
+        }
+
+        self.stop_camera_animations();
+        self.inertial_move_animation = None;
+        // The transition only runs on the view it has been started on
+        self.end_projection_morph()?;
+        self.activate_view(idx);
+
+        Ok(())
+    }
+
+    pub(crate) fn get_active_view(&self) -> usize {
+        self.active_view
+    }
+
+    /// Set the rectangle of the canvas the active view is rendered in
+    pub(crate) fn set_view_rect(&mut self, rect: ViewRect) {
+        self.view_rect = rect;
+        self.layout_views();
+    }
+
+    /// Set what the other views follow from the active one
+    pub(crate) fn set_view_lock(&mut self, lock: ViewLock) {
+        self.view_lock = lock;
+        self.sync_locked_views();
+        self.request_redraw = true;
+    }
+
+    pub(crate) fn reset_north_orientation(&mut self) {
+        // Reset the rotation around the center if there is one
+        self.camera.set_rotation_around_center(Angle(0.0), &self.projection);
+        // Reset the camera position to its current position
+        // this will keep the current position but reset the orientation
+        // so that the north pole is at the top of the center.
+        self.set_center(&self.get_center());
+    }
+
+    pub(crate) fn read_pixel(&self, pos: &Vector2<f64>, layer: &str) -> Result<JsValue, JsValue> {
+        if let Some(lonlat) = self.screen_to_world(pos, None)? {
+            if let Some(survey) = self.layers.get_hips_from_layer(layer) {
+                survey.read_pixel(&lonlat, &self.camera)
+            } else if let Some(_image) = self.layers.get_image_from_layer(layer) {
+                Err(JsValue::from_str("TODO: read pixel value"))
+            } else {
+                Err(JsValue::from_str("Survey not found"))
+            }
+        } else {
+            Err(JsValue::from_str(&"position is out of projection"))
+        }
+    }
+
+    /// Render the view offscreen at a high resolution into a PNG
+    ///
+    /// The image is rendered part by part, each one once the tiles it needs have been received.
+    /// The returned promise resolves with the bytes of the PNG.
+    pub(crate) fn snapshot(&mut self, cfg: SnapshotCfg) -> Result<js_sys::Promise, JsValue> {
+        if self.snapshot.is_some() {
+            return Err(JsValue::from_str("A snapshot is already being rendered"));
+        }
+
+        let SnapshotCfg { width, height, catalogs, timeout } = cfg;
+
+        // 1. The size of the image, the whole image is rendered through the viewport
+        let screen_size = (self.camera.get_width(), self.camera.get_height());
+        let screen_origin = self.camera.get_viewport_origin();
+        let (width, height) = self.get_image_size(width, height);
+
+        let max_viewport = js_sys::Int32Array::new(
+            &self.gl.get_parameter(WebGl2RenderingContext::MAX_VIEWPORT_DIMS)?
+        ).to_vec();
+        if width < 1.0 || height < 1.0 || width > max_viewport[0] as f32 || height > max_viewport[1] as f32 {
+            return Err(JsValue::from_str(&format!(
+                "The size of the image must be between 1x1 and {}x{} pixels",
+                max_viewport[0],
+                max_viewport[1]
+            )));
+        }
+        let tile_size = (self.get_max_texture_size()? as usize).min(SNAPSHOT_TILE_SIZE);
+
+        // The view does not move anymore
+        self.stop_camera_animations();
+        self.inertial_move_animation = None;
+
+        self.camera.set_viewport_origin(Vector2::new(0, 0));
+        self.camera.set_render_size(width, height, &self.projection);
+
+        // 2. The markers of the sources, their size is scaled like the image
+        let scale = self.camera.get_dpi() * width / screen_size.0;
+        let mut markers = RasterizedLinesRenderManager::new(&self.gl)?;
+        markers.begin_frame();
+        for catalog in catalogs {
+            let size = catalog.size * scale;
+            for [ra, dec] in catalog.sources {
+                let lonlat = LonLatT::new(ArcDeg(ra).into(), ArcDeg(dec).into());
+                let xyzw = crate::coosys::apply_coo_system(
+                    &CooSystem::ICRSJ2000,
+                    self.camera.get_system(),
+                    &lonlat.vector::<Vector4<f64>>(),
+                );
+
+                if let Some(ndc) = self.projection.model_to_normalized_device_space(&xyzw, &self.camera) {
+                    // Position in pixels of the image
+                    let pos = Vector2::new(
+                        (ndc.x as f32 * 0.5 + 0.5) * width,
+                        (0.5 - ndc.y as f32 * 0.5) * height,
+                    );
+                    let inside = pos.x > -size && pos.x < width + size && pos.y > -size && pos.y < height + size;
+                    if inside {
+                        for path in crate::export::snapshot::marker_paths(catalog.shape, &pos, size) {
+                            markers.add_path(&path, scale, &catalog.color);
+                        }
+                    }
+                }
+            }
+        }
+        markers.end_frame();
+
+        // 3. Start waiting for the tiles of the first part
+        let (width, height) = (width as usize, height as usize);
+        let tiles = crate::export::snapshot::split(width, height, tile_size);
+        self.camera.set_render_window(Some(tiles[0].ndc_window(width, height)), &self.projection);
+        self.request_for_new_tiles = true;
+
+        let (done, done_recv) = async_channel::bounded(1);
+        self.snapshot = Some(Snapshot {
+            width,
+            height,
+            tiles,
+            idx_tile: 0,
+            time_start_tile: Time::now(),
+            timeout: timeout * 1000.0,
+            pixels: vec![0; 4 * width * height],
+            markers,
+            screen_size,
+            screen_origin,
+            done,
+        });
+
+        Ok(wasm_bindgen_futures::future_to_promise(async move {
+            match done_recv.recv().await {
+                Ok(png) => png.map(|png| js_sys::Uint8Array::from(&png[..]).into()),
+                Err(_) => Err(JsValue::from_str("The snapshot has been interrupted")),
+            }
+        }))
+    }
+
+    // Render the current part of the snapshot once its tiles have been received
+    fn update_snapshot(&mut self) -> Result<(), JsValue> {
+        let mut snapshot = if let Some(snapshot) = self.snapshot.take() {
+            snapshot
+        } else {
+            return Ok(());
+        };
+
+        let tiles_received = self.layers.values_hips().all(|hips| hips.is_view_complete());
+        let timed_out = Time::now() - snapshot.time_start_tile > DeltaTime::from(snapshot.timeout);
+        if !tiles_received && !timed_out {
+            // The views of the layers have been refreshed for the current part
+            self.camera.reset();
+            self.request_for_new_tiles = true;
+            self.snapshot = Some(snapshot);
+
+            return Ok(());
+        }
+
+        let tile = snapshot.tiles[snapshot.idx_tile];
+        let res = self.draw_snapshot_tile(&snapshot, &tile);
+
+        let res = res.and_then(|pixels| {
+            crate::export::snapshot::copy_tile(&mut snapshot.pixels, snapshot.width, &tile, &pixels);
+            snapshot.idx_tile += 1;
+
+            if let Some(next_tile) = snapshot.tiles.get(snapshot.idx_tile) {
+                let window = next_tile.ndc_window(snapshot.width, snapshot.height);
+                self.camera.set_render_window(Some(window), &self.projection);
+                self.request_for_new_tiles = true;
+                snapshot.time_start_tile = Time::now();
+
+                Ok(None)
+            } else {
+                crate::export::encode_png(snapshot.width, snapshot.height, &snapshot.pixels).map(Some)
+            }
+        });
+
+        match res {
+            Ok(None) => self.snapshot = Some(snapshot),
+            Ok(Some(png)) => {
+                self.end_snapshot(&snapshot);
+                let _ = snapshot.done.try_send(Ok(png));
+            }
+            Err(err) => {
+                self.end_snapshot(&snapshot);
+                let _ = snapshot.done.try_send(Err(err));
+            }
+        }
+
+        Ok(())
+    }
+
+    // Render a part of the snapshot and read its pixels
+    fn draw_snapshot_tile(&mut self, snapshot: &Snapshot, tile: &TileRect) -> Result<Vec<u8>, JsValue> {
+        // The grid labels are placed on the border of the whole image
+        self.camera.set_render_window(None, &self.projection);
+        self.grid.update(&self.camera, &self.projection);
+        let window = tile.ndc_window(snapshot.width, snapshot.height);
+        self.camera.set_render_window(Some(window), &self.projection);
+        self.moc.update(&self.camera, &self.projection);
+
+        let fbo = FrameBufferObject::new(&self.gl, tile.width, tile.height)?;
+
+        let shaders = &mut self.shaders;
+        let layers = &mut self.layers;
+        let (camera, colormaps, projection) = (&self.camera, &self.colormaps, &self.projection);
+        let (moc, contours, grid) = (&mut self.moc, &mut self.contours, &mut self.grid);
+        fbo.draw_onto(
+            || {
+                camera.restore_viewport();
+
+                layers.draw(camera, shaders, colormaps, projection)?;
+                moc.draw(shaders, camera);
+                contours.draw(shaders, camera)?;
+                grid.draw(camera, shaders)?;
+                snapshot.markers.draw(shaders, camera)
+            },
+            None,
+        )?;
+        self.camera.reset();
+
+        fbo.read_pixels_u8(0, 0, tile.width as i32, tile.height as i32)
+    }
+
+    // Go back to the view on the screen
+    fn end_snapshot(&mut self, snapshot: &Snapshot) {
+        let (width, height) = snapshot.screen_size;
+        self.camera.set_render_window(None, &self.projection);
+        self.camera.set_viewport_origin(snapshot.screen_origin);
+        self.camera.set_render_size(width, height, &self.projection);
+
+        self.request_for_new_tiles = true;
+        self.request_redraw = true;
+    }
+
+    pub(crate) fn draw(&mut self, force_render: bool) -> Result<(), JsValue> {
+        self.start_time_frame = Time::now();
+
+        /*let scene_redraw = self.rendering | force_render;
+        let mut ui = self.ui.lock();
+
+        if scene_redraw {
+            let shaders = &mut self.shaders;
+            let gl = self.gl.clone();
+            let camera = &self.camera;
+
+            let grid = &mut self.grid;
+            let layers = &mut self.layers;
+            let catalogs = &self.manager;
+            let colormaps = &self.colormaps;
+            let fbo_view = &self.fbo_view;
 
             fbo_view.draw_onto(
                 move || {
@@ -819,37 +1885,49 @@ impl App {
 
         self.layers.reset_frame();*/
 
+        // The screen keeps its last frame while a snapshot is rendered offscreen
+        if self.snapshot.is_some() {
+            return Ok(());
+        }
+
         let scene_redraw = self.rendering | force_render;
         //let mut ui = self.ui.lock();
         //let ui_redraw = ui.redraw_needed();
         //if scene_redraw || ui_redraw {
         if scene_redraw {
-            let shaders = &mut self.shaders;
-
             //let catalogs = &self.manager;
             // Render the scene
             // Clear all the screen first (only the region set by the scissor)
             self.gl.clear(web_sys::WebGl2RenderingContext::COLOR_BUFFER_BIT);
 
-            self.layers.draw(&self.camera, shaders, &self.colormaps, &self.projection)?;
-            self.moc.draw(shaders, &self.camera);
+            self.for_each_view(|app| {
+                // Each view is rendered in its own area of the canvas
+                app.camera.restore_viewport();
 
-            // Draw the catalog
-            //let fbo_view = &self.fbo_view;
-            //catalogs.draw(&gl, shaders, camera, colormaps, fbo_view)?;
-            //catalogs.draw(&gl, shaders, camera, colormaps, None, self.projection)?;
-            self.grid.draw(&self.camera, shaders)?;
+                let shaders = &mut app.shaders;
+                app.layers.draw(&app.camera, shaders, &app.colormaps, &app.projection)?;
+                app.moc.draw(shaders, &app.camera);
+                app.contours.draw(shaders, &app.camera)?;
 
-            //let dpi  = self.camera.get_dpi();
-            //ui.draw(&gl, dpi)?;
+                // Draw the catalog
+                //let fbo_view = &self.fbo_view;
+                //catalogs.draw(&gl, shaders, camera, colormaps, fbo_view)?;
+                //catalogs.draw(&gl, shaders, camera, colormaps, None, self.projection)?;
+                app.grid.draw(&app.camera, shaders)?;
 
-            // Reset the flags about the user action
-            self.camera.reset();
+                //let dpi  = self.camera.get_dpi();
+                //ui.draw(&gl, dpi)?;
 
-            if self.rendering {
-                self.layers.reset_frame();
-                self.moc.reset_frame();
-            }
+                // Reset the flags about the user action
+                app.camera.reset();
+
+                if app.rendering {
+                    app.layers.reset_frame();
+                    app.moc.reset_frame();
+                }
+
+                Ok(())
+            })?;
         }
 
         Ok(())
@@ -857,6 +1935,8 @@ impl App {
 
     pub(crate) fn remove_layer(&mut self, layer: &str) -> Result<(), JsValue> {
         self.layers.remove_layer(layer, &mut self.camera, &self.projection)?;
+        // The memory of the textures removed can be used for caching resources
+        self.fit_memory_budget();
 
         self.request_redraw = true;
 
@@ -876,8 +1956,26 @@ impl App {
     }
 
     pub(crate) fn add_image_survey(&mut self, hips_cfg: HiPSCfg) -> Result<(), JsValue> {
-        let hips = self.layers.add_image_survey(&self.gl, hips_cfg, &mut self.camera, &self.projection)?;
-        self.tile_fetcher.launch_starting_hips_requests(hips, &mut self.downloader);
+        // The textures are allocated as tiles are received, the least recently used ones
+        // of all the HiPS being released when the budget is exceeded
+        let url = hips_cfg.get_properties().get_url().to_string();
+        // The views displaying the same HiPS in the same format share its tiles
+        let img_format = hips_cfg.meta.img_format;
+        let shared = self.views.iter()
+            .flatten()
+            .filter_map(|view| view.layers.get_hips_from_url(&url))
+            .find(|hips| hips.get_config().get_format().ext == img_format);
+        let is_shared = shared.is_some();
+        self.layers.add_image_survey(&self.gl, hips_cfg, self.memory_budget, shared, &mut self.camera, &self.projection)?;
+
+        let hips = self.layers.get_mut_hips_from_url(&url).unwrap_abort();
+        if !is_shared {
+            if let Some(tile_source) = self.tile_sources.get(&url) {
+                hips.get_config_mut().set_tile_source(tile_source.clone());
+            }
+            self.tile_fetcher.launch_starting_hips_requests(hips, &mut self.downloader);
+        }
+        self.fit_memory_budget();
 
         // Once its added, request the tiles in the view (unless the viewer is at depth 0)
         self.request_for_new_tiles = true;
@@ -887,6 +1985,44 @@ impl App {
         Ok(())
     }
 
+    // Fetch and parse the properties of a HiPS before adding it
+    //
+    // The promise returned resolves to the keywords of the properties file
+    pub(crate) fn add_image_survey_from_url(&mut self, layer: String, url: String, meta: ImageMetadata) -> Result<js_sys::Promise, JsValue> {
+        let source = self.tile_sources.get(&url)
+            .cloned()
+            .unwrap_or_else(|| Rc::new(HttpSource));
+        let hips_send = self.hips_send.clone();
+
+        let fut = async move {
+            // The properties are retried like any other file of the HiPS
+            let num_retries = std::cell::Cell::new(0);
+            let bytes = request::retry(|| source.fetch_bytes(&url, "properties"), &num_retries).await?;
+            let file = String::from_utf8_lossy(&bytes).parse::<HiPSPropertiesFile>()?;
+
+            let properties = HiPSProperties::from_file(url.clone(), &file)?;
+            if !properties.get_formats().contains(&meta.img_format) {
+                return Err(JsValue::from_str(&format!("{} does not provide {} tiles", url, meta.img_format)));
+            }
+
+            let mirrors = file.hips_service_url.iter()
+                .chain(file.hips_mirror_urls.iter())
+                .cloned()
+                .collect();
+
+            let (added_send, added_recv) = async_channel::bounded(1);
+            hips_send.send((HiPSCfg { layer, properties, meta, mirrors }, added_send)).await
+                .map_err(|_| JsValue::from_str("Problem sending the HiPS"))?;
+            // Resolve once the HiPS has been added
+            added_recv.recv().await
+                .map_err(|_| JsValue::from_str("Problem adding the HiPS"))??;
+
+            serde_wasm_bindgen::to_value(&PropertiesKeywords::from(&file)).map_err(|e| e.into())
+        };
+
+        Ok(wasm_bindgen_futures::future_to_promise(fut))
+    }
+
     pub(crate) fn add_image_fits(&mut self, cfg: FITSCfg) -> Result<js_sys::Promise, JsValue> {
         let FITSCfg { layer, url, meta } = cfg;
         let gl = self.gl.clone();
@@ -1051,46 +2187,414 @@ impl App {
                 }
             }
 
-            if !images_params.is_empty() {
-                serde_wasm_bindgen::to_value(&images_params).map_err(|e| e.into())
-            } else {
-                Err(JsValue::from_str("The fits file has no extension that had been parsed"))
+            if !images_params.is_empty() {
+                serde_wasm_bindgen::to_value(&images_params).map_err(|e| e.into())
+            } else {
+                Err(JsValue::from_str("The fits file has no extension that had been parsed"))
+            }
+        };
+        
+        let reenable_inertia = Closure::new(move || {
+            // renable inertia again
+            *(disable_inertia.borrow_mut()) = false;
+        });
+
+        let promise = wasm_bindgen_futures::future_to_promise(fut)
+            // Reenable inertia independantly from whether the
+            // fits has been correctly parsed or not
+            .finally(
+                &reenable_inertia
+            );
+
+        // forget the closure, it is not very proper to do this as
+        // it won't be deallocated
+        reenable_inertia.forget();
+
+        Ok(promise)
+    }
+
+    pub(crate) fn add_image_with_wcs(&mut self, cfg: WCSImageCfg, image: JsValue) -> Result<js_sys::Promise, JsValue> {
+        use crate::renderable::image::{self, Image};
+
+        let WCSImageCfg { layer, url, wcs, meta } = cfg;
+
+        let (pixels, width, height) = image::decode_rgba8(&image)?;
+        let (wcs, native) = image::header::wcs_from_keywords(&wcs, width, height)?;
+
+        let gl = self.gl.clone();
+        let fits_sender = self.fits_send.clone();
+        let ack_recv = self.ack_recv.clone();
+
+        let fut = async move {
+            let image = Image::from_rgba8_pixels(&gl, pixels, wcs, native).await?;
+
+            let img = ImageCfg {
+                image,
+                layer,
+                url,
+                meta
+            };
+
+            fits_sender.send(img).await
+                .unwrap();
+
+            // Wait for the ack here
+            let image_params = ack_recv.recv().await
+                .map_err(|_| JsValue::from_str("Problem receiving image"))?;
+
+            serde_wasm_bindgen::to_value(&[image_params]).map_err(|e| e.into())
+        };
+
+        Ok(wasm_bindgen_futures::future_to_promise(fut))
+    }
+
+    pub(crate) fn add_rgb_composite(&mut self, cfg: RGBCompositeCfg) -> Result<(), JsValue> {
+        self.layers.add_rgb_composite(cfg, &mut self.camera, &self.projection)?;
+
+        self.request_redraw = true;
+
+        Ok(())
+    }
+
+    pub(crate) fn add_layer_arithmetic(&mut self, cfg: LayerArithmeticCfg) -> Result<(), JsValue> {
+        self.layers.add_layer_arithmetic(cfg, &mut self.camera, &self.projection)?;
+
+        self.request_redraw = true;
+
+        Ok(())
+    }
+
+    pub(crate) fn get_layer_cfg(&self, layer: &str) -> Result<ImageMetadata, JsValue> {
+        self.layers.get_layer_cfg(layer)
+    }
+
+    pub(crate) fn set_hips_url(&mut self, past_url: String, new_url: String) -> Result<(), JsValue> {
+        self.tile_fetcher.set_hips_url(&past_url, &new_url);
+        self.layers.set_survey_url(past_url.clone(), new_url.clone())?;
+
+        let hips = self.layers.get_hips_from_url(&new_url).unwrap_abort();
+        // The url is also the one of the HiPS sharing its textures in the other views
+        for view in self.views.iter_mut().flatten() {
+            let is_shared = view.layers.get_hips_from_url(&past_url)
+                .map(|other| other.shares_textures_with(hips))
+                .unwrap_or(false);
+            if is_shared {
+                view.layers.set_survey_url(past_url.clone(), new_url.clone())?;
+            }
+        }
+
+        // Relaunch the base tiles for the survey to be ready with the new url
+        self.tile_fetcher.launch_starting_hips_requests(hips, &mut self.downloader);
+
+        Ok(())
+    }
+
+    // Fetch the tiles of a HiPS from another of its mirrors when the one queried keeps failing
+    fn switch_unhealthy_mirrors(&mut self) -> Result<(), JsValue> {
+        let switches = self.layers.values_hips()
+            .filter_map(|hips| {
+                let cfg = hips.get_config();
+                let url = cfg.get_root_url();
+
+                self.downloader.select_mirror(url, cfg.get_mirrors())
+                    .map(|mirror| (url.clone(), mirror.clone()))
+            })
+            .collect::<Vec<_>>();
+
+        for (past_url, new_url) in switches {
+            al_core::log::console_warn(format!("{} keeps failing, switching to the mirror {}", past_url, new_url));
+
+            if let Some(hips) = self.layers.get_mut_hips_from_url(&past_url) {
+                // The tiles that could not be downloaded will be requested from the new mirror
+                hips.reset_failed_tiles();
+            }
+            for contours in std::iter::once(&mut self.contours).chain(self.views.iter_mut().flatten().map(|view| &mut view.contours)) {
+                contours.set_hips_url(&past_url, &new_url);
+            }
+            self.downloader.reset_tile_stats(&past_url);
+            self.set_hips_url(past_url, new_url)?;
+
+            self.request_for_new_tiles = true;
+        }
+
+        Ok(())
+    }
+
+    // Read the files of a HiPS from another source than its web server
+    //
+    // The source is kept for the HiPS added later with that root url
+    pub(crate) fn set_hips_tile_source(&mut self, hips_url: String, tile_source: Rc<dyn TileSource>) {
+        // The resources read from the previous source must not be given back
+        self.downloader.remove_hips_cached_rscs(&hips_url);
+
+        if let Some(hips) = self.layers.get_mut_hips_from_url(&hips_url) {
+            hips.get_config_mut().set_tile_source(tile_source.clone());
+            hips.reset_failed_tiles();
+            self.downloader.reset_tile_stats(&hips_url);
+
+            let hips = self.layers.get_hips_from_url(&hips_url).unwrap_abort();
+            self.tile_fetcher.launch_starting_hips_requests(hips, &mut self.downloader);
+
+            self.request_for_new_tiles = true;
+            self.request_redraw = true;
+        }
+
+        self.tile_sources.insert(hips_url, tile_source);
+    }
+
+    fn get_textures_num_bytes(&self) -> usize {
+        let layers = get_all_layers(&self.layers, &self.views);
+        get_distinct_hips(layers.iter().flat_map(|layers| layers.values_hips()))
+            .into_iter()
+            .map(|hips| hips.get_num_bytes())
+            .sum()
+    }
+
+    // Free memory so that the textures and the cached resources fit in the memory budget
+    //
+    // The textures of the cube frames not displayed are released first for the hidden HiPS,
+    // then for the visible ones. If it is not enough, the texture slices of the HiPS of all
+    // the views are released, the least recently used first. The remaining memory is given
+    // to the resource cache that evicts its least recently used resources.
+    fn fit_memory_budget(&mut self) {
+        for visible in [false, true] {
+            let mut textures_num_bytes = self.get_textures_num_bytes();
+            if textures_num_bytes <= self.memory_budget {
+                break;
+            }
+
+            let layers = get_all_layers(&self.layers, &self.views);
+            let hipses = layers.iter()
+                .enumerate()
+                .flat_map(|(idx, layers)| layers.values_hips()
+                    .map(move |hips| (idx, hips, layers.is_hips_visible(hips.get_config().get_root_url())))
+                )
+                .collect::<Vec<_>>();
+            // The textures shared by several views are visible if one of them shows them
+            let urls = hipses.iter()
+                .filter(|(_, hips, _)| {
+                    hipses.iter().any(|(_, other, shown)| *shown && other.shares_textures_with(hips)) == visible
+                })
+                .map(|(idx, hips, _)| (*idx, hips.get_config().get_root_url().clone()))
+                .collect::<Vec<_>>();
+
+            let mut layers = get_all_layers_mut(&mut self.layers, &mut self.views);
+            for (idx, url) in urls {
+                if textures_num_bytes <= self.memory_budget {
+                    break;
+                }
+
+                if let Some(hips) = layers[idx].get_mut_hips_from_url(&url) {
+                    textures_num_bytes -= hips.release_stored_frames();
+                }
+            }
+        }
+
+        let textures_num_bytes = self.get_textures_num_bytes();
+        if textures_num_bytes > self.memory_budget {
+            let mut layers = get_all_layers_mut(&mut self.layers, &mut self.views);
+            let hipses = get_distinct_hips(layers.iter_mut().flat_map(|layers| layers.values_mut_hips()));
+            let slices_last_use = hipses.iter()
+                .map(|hips| (hips.get_config().num_bytes_by_slice(), hips.get_releasable_slices_last_use()))
+                .collect::<Vec<_>>();
+            let num_slices = budget::num_slices_to_release(&slices_last_use, textures_num_bytes, self.memory_budget);
+
+            for (hips, num_slices) in hipses.into_iter().zip(num_slices) {
+                for _ in 0..num_slices {
+                    hips.release_last_slice();
+                }
+            }
+
+            // The tiles of the released textures may be needed again
+            self.request_for_new_tiles = true;
+        }
+
+        let textures_num_bytes = self.get_textures_num_bytes();
+        self.downloader.set_cache_capacity(self.memory_budget.saturating_sub(textures_num_bytes));
+    }
+
+    pub(crate) fn set_memory_budget(&mut self, num_bytes: usize) {
+        self.memory_budget = num_bytes;
+        // The HiPS can take again the memory they released for a lower budget
+        for layers in get_all_layers_mut(&mut self.layers, &mut self.views) {
+            for hips in layers.values_mut_hips() {
+                hips.fit_textures_in(num_bytes);
             }
+        }
+        self.fit_memory_budget();
+    }
+
+    pub(crate) fn get_cache_stats(&self) -> CacheStats {
+        let (num_hits, num_misses) = self.downloader.get_cache_hits_misses();
+        let layers = self.layers.get_layers()
+            .filter_map(|layer| {
+                let hips = self.layers.get_hips_from_layer(layer)?;
+                let url = hips.get_config().get_root_url().clone();
+
+                Some((layer.clone(), LayerCacheStats {
+                    cpu_num_bytes: self.downloader.get_hips_cache_num_bytes(&url),
+                    gpu_num_bytes: hips.get_num_bytes(),
+                }))
+            })
+            .collect();
+
+        CacheStats {
+            budget: self.memory_budget,
+            num_bytes: self.downloader.get_cache_num_bytes() + self.get_textures_num_bytes(),
+            num_hits,
+            num_misses,
+            layers,
+        }
+    }
+
+    pub(crate) fn set_tile_fetch_concurrency(&mut self, max_num_fetching: usize) {
+        self.tile_fetcher.set_max_num_fetching(max_num_fetching, &mut self.downloader);
+    }
+
+    pub(crate) fn set_hips_tile_fetch_concurrency(&mut self, layer: &str, max_num_fetching: Option<usize>) -> Result<(), JsValue> {
+        let hips = self.layers
+            .get_hips_from_layer(layer)
+            .ok_or_else(|| JsValue::from_str("Layer not found"))?;
+
+        self.tile_fetcher.set_hips_max_num_fetching(hips.get_config().get_root_url(), max_num_fetching, &mut self.downloader);
+
+        Ok(())
+    }
+
+    pub(crate) fn set_tile_prefetching(&mut self, enabled: bool) {
+        self.prefetch_tiles = enabled;
+        self.request_for_new_tiles = true;
+    }
+
+    /// Fetch ahead of time the tiles of a view the camera is going to
+    ///
+    /// Typically called when starting a camera animation whose target is known
+    pub(crate) fn prefetch_view(&mut self, lonlat: &LonLatT<f64>, fov: Angle<f64>) {
+        self.prefetch_target = Some((*lonlat, fov));
+
+        self.append_target_tile_queries();
+        self.tile_fetcher.fetch(&mut self.downloader);
+    }
+
+    // Queue the tiles of the view fetched ahead of time that are neither cached nor in the textures
+    //
+    // The target is forgotten once all its tiles have been retrieved
+    fn append_target_tile_queries(&mut self) {
+        let (lonlat, fov) = if let Some(target) = self.prefetch_target {
+            target
+        } else {
+            return;
         };
-        
-        let reenable_inertia = Closure::new(move || {
-            // renable inertia again
-            *(disable_inertia.borrow_mut()) = false;
-        });
+        let target: Vector4<f64> = lonlat.vector();
 
-        let promise = wasm_bindgen_futures::future_to_promise(fut)
-            // Reenable inertia independantly from whether the
-            // fits has been correctly parsed or not
-            .finally(
-                &reenable_inertia
+        let mut queries = vec![];
+        for hips in self.layers.values_hips() {
+            let center = crate::coosys::apply_coo_system(
+                &CooSystem::ICRSJ2000,
+                &hips.get_config().get_frame(),
+                &target,
             );
 
-        // forget the closure, it is not very proper to do this as
-        // it won't be deallocated
-        reenable_inertia.forget();
+            let cfg = hips.get_config();
+            for tile_cell in hips.get_tile_cells_in_view(&center, fov, &self.camera) {
+                let query = query::Tile::new(&tile_cell, &cfg);
+                if hips.contains_tile(&tile_cell) || self.downloader.is_cached(&query.url) {
+                    continue;
+                }
 
-        Ok(promise)
+                let priority = TilePriority::new(&tile_cell, &center);
+                queries.push((priority, query));
+            }
+        }
+
+        if queries.is_empty() {
+            self.prefetch_target = None;
+        }
+        self.tile_fetcher.set_target(queries.into_iter());
     }
 
-    pub(crate) fn get_layer_cfg(&self, layer: &str) -> Result<ImageMetadata, JsValue> {
-        self.layers.get_layer_cfg(layer)
+    /// The base url from which the tiles of a HiPS layer are currently fetched
+    pub(crate) fn get_hips_url(&self, layer: &str) -> Result<String, JsValue> {
+        self.layers
+            .get_hips_from_layer(layer)
+            .map(|hips| hips.get_config().get_root_url().clone())
+            .ok_or_else(|| JsValue::from_str("Layer not found"))
     }
 
-    pub(crate) fn set_hips_url(&mut self, past_url: String, new_url: String) -> Result<(), JsValue> {
-        self.layers.set_survey_url(past_url, new_url.clone())?;
+    pub(crate) fn get_hips_data_range(&self, layer: &str) -> Result<Option<[f32; 2]>, JsValue> {
+        self.layers
+            .get_hips_from_layer(layer)
+            .map(|hips| hips.get_config().get_data_range())
+            .ok_or_else(|| JsValue::from_str("Layer not found"))
+    }
 
-        let hips = self.layers.get_hips_from_url(&new_url).unwrap_abort();
-        // Relaunch the base tiles for the survey to be ready with the new url
-        self.tile_fetcher.launch_starting_hips_requests(hips, &mut self.downloader);
+    pub(crate) fn get_hips_default_cuts(&self, layer: &str) -> Result<Option<[f32; 2]>, JsValue> {
+        self.layers
+            .get_hips_from_layer(layer)
+            .map(|hips| hips.get_config().get_default_cuts())
+            .ok_or_else(|| JsValue::from_str("Layer not found"))
+    }
+
+    pub(crate) fn get_hips_tile_stats(&self, layer: &str) -> Result<HiPSTileStats, JsValue> {
+        let hips = self.layers
+            .get_hips_from_layer(layer)
+            .ok_or_else(|| JsValue::from_str("Layer not found"))?;
+
+        Ok(self.downloader.get_tile_stats(hips.get_config().get_root_url()))
+    }
+
+    // Request again the tiles that could not be downloaded, e.g. after a network outage
+    pub(crate) fn reset_failed_tiles(&mut self) {
+        let mut layers = get_all_layers_mut(&mut self.layers, &mut self.views);
+        for hips in get_distinct_hips(layers.iter_mut().flat_map(|layers| layers.values_mut_hips())) {
+            let base_tiles_reset = hips.reset_failed_tiles();
+            self.downloader.reset_tile_stats(hips.get_config().get_root_url());
+
+            if base_tiles_reset {
+                self.tile_fetcher.launch_starting_hips_requests(hips, &mut self.downloader);
+            }
+        }
+
+        self.request_for_new_tiles = true;
+        self.request_redraw = true;
+    }
+
+    pub(crate) fn set_hips_cube_frame(&mut self, layer: &str, frame: u32) -> Result<(), JsValue> {
+        let hips = self.layers
+            .get_mut_hips_from_layer(layer)
+            .ok_or_else(|| JsValue::from_str("Layer not found"))?;
+        hips.set_cube_frame(frame)?;
+
+        // The frame has never been displayed or its textures have been reused
+        if !hips.is_ready() {
+            self.tile_fetcher.launch_starting_hips_requests(hips, &mut self.downloader);
+        }
+        // The contours of the previous frame are not valid anymore, in the views sharing the textures too
+        let url = hips.get_config().get_root_url().clone();
+        for contours in std::iter::once(&mut self.contours).chain(self.views.iter_mut().flatten().map(|view| &mut view.contours)) {
+            contours.reset_tiles(&url);
+        }
+        // The textures of the previous frame are kept if the budget allows it
+        self.fit_memory_budget();
+
+        self.request_for_new_tiles = true;
+        self.request_redraw = true;
 
         Ok(())
     }
 
+    pub(crate) fn get_hips_cube_frame_value(&self, layer: &str) -> Result<Option<f64>, JsValue> {
+        let hips = self.layers
+            .get_hips_from_layer(layer)
+            .ok_or_else(|| JsValue::from_str("Layer not found"))?;
+        let cfg = hips.get_config();
+        let cube = cfg
+            .get_cube()
+            .ok_or_else(|| JsValue::from_str("The HiPS is not a cube"))?;
+
+        Ok(cube.get_spectral_value(cube.current))
+    }
+
     pub(crate) fn set_image_survey_color_cfg(
         &mut self,
         layer: String,
@@ -1123,6 +2627,8 @@ impl App {
 
     // Width and height given are in pixels
     pub(crate) fn set_projection(&mut self, projection: ProjectionType) -> Result<(), JsValue> {
+        // A running transition is interrupted
+        self.projection_animation = None;
         self.projection = projection;
 
         // Recompute the ndc_to_clip
@@ -1136,6 +2642,53 @@ impl App {
         Ok(())
     }
 
+    /// Change the projection progressively, the sky being interpolated between both projections
+    ///
+    /// The duration is given in ms. The returned promise resolves with true once the transition
+    /// is over, false if it is interrupted.
+    pub(crate) fn morph_projection(&mut self, projection: ProjectionType, duration: f32) -> Result<js_sys::Promise, JsValue> {
+        // A running transition is finished right away
+        self.end_projection_morph()?;
+
+        let from = self.projection.clone();
+        self.projection = ProjectionType::Morph(Box::new(Morph {
+            from,
+            to: projection,
+            alpha: 0.0,
+        }));
+        self.camera.set_projection(&self.projection);
+
+        let (done, done_recv) = async_channel::bounded(1);
+        self.projection_animation = Some(ProjectionAnimation {
+            time_start_anim: Time::now(),
+            duration: duration.max(1.0),
+            done,
+        });
+        self.request_redraw = true;
+
+        Ok(wasm_bindgen_futures::future_to_promise(async move {
+            // The sender is dropped without sending if the transition is interrupted
+            let finished = done_recv.recv().await.unwrap_or(false);
+            Ok(JsValue::from_bool(finished))
+        }))
+    }
+
+    // Give its final projection to the view if it is in transition
+    fn end_projection_morph(&mut self) -> Result<(), JsValue> {
+        self.projection_animation = None;
+
+        if let ProjectionType::Morph(morph) = &self.projection {
+            let projection = morph.to.clone();
+            self.set_projection(projection)?;
+        }
+
+        Ok(())
+    }
+
+    pub(crate) fn is_morphing_projection(&self) -> bool {
+        self.projection_animation.is_some()
+    }
+
     pub(crate) fn get_max_fov(&self) -> f64 {
         self.projection.aperture_start()
     }
@@ -1173,8 +2726,8 @@ impl App {
     }
 
     pub(crate) fn resize(&mut self, width: f32, height: f32) {
-        self.camera.set_screen_size(width, height, &self.projection);
-        self.camera.set_aperture(self.camera.get_aperture(), &self.projection);
+        self.canvas_size = Vector2::new(width, height);
+        self.layout_views();
         // resize the view fbo
         //self.fbo_view.resize(w as usize, h as usize);
         // resize the ui fbo
@@ -1229,16 +2782,23 @@ impl App {
         self.request_redraw = true;
     }
 
-    pub(crate) fn world_to_screen(&self, ra: f64, dec: f64) -> Option<Vector2<f64>> {
+    /// Project a position on the canvas through a view, the active one by default
+    pub(crate) fn world_to_screen(&self, ra: f64, dec: f64, view: Option<usize>) -> Result<Option<Vector2<f64>>, JsValue> {
+        let (camera, projection, rect) = self.get_view(view.unwrap_or(self.active_view))?;
         let lonlat = LonLatT::new(ArcDeg(ra).into(), ArcDeg(dec).into());
         let model_pos_xyz = lonlat.vector();
 
-        self.projection.view_to_screen_space(&model_pos_xyz, &self.camera)
+        // The view is rendered in a part of the canvas
+        let origin = self.get_view_origin(rect);
+        Ok(projection.view_to_screen_space(&model_pos_xyz, camera).map(|pos| pos + origin))
     }
 
-    pub(crate) fn screen_to_world(&self, pos: &Vector2<f64>) -> Option<LonLatT<f64>> {
-        // Select the HiPS layer rendered lastly
-        self.projection.screen_to_model_space(pos, &self.camera).map(|model_pos| model_pos.lonlat())
+    /// Unproject a position of the canvas through a view, the active one by default
+    pub(crate) fn screen_to_world(&self, pos: &Vector2<f64>, view: Option<usize>) -> Result<Option<LonLatT<f64>>, JsValue> {
+        let (camera, projection, rect) = self.get_view(view.unwrap_or(self.active_view))?;
+        let pos = pos - self.get_view_origin(rect);
+
+        Ok(projection.screen_to_model_space(&pos, camera).map(|model_pos| model_pos.lonlat()))
     }
 
     pub(crate) fn view_to_icrsj2000_coosys(&self, lonlat: &LonLatT<f64>) -> LonLatT<f64> {
@@ -1266,6 +2826,7 @@ impl App {
     }
 
     pub(crate) fn set_center(&mut self, lonlat: &LonLatT<f64>) {
+        self.stop_camera_animations();
         self.prev_cam_position = self.camera.get_center().truncate();
         self.camera.set_center(lonlat, &CooSystem::ICRSJ2000, &self.projection);
         self.request_for_new_tiles = true;
@@ -1275,6 +2836,7 @@ impl App {
     }
 
     pub(crate) fn press_left_button_mouse(&mut self, _sx: f32, _sy: f32) {
+        self.stop_camera_animations();
         self.prev_center = self.camera.get_center().truncate();
         self.inertial_move_animation = None;
         self.request_for_new_tiles = true;
@@ -1321,23 +2883,321 @@ impl App {
         }
     }
 
-    /*fn start_moving_to(&mut self, lonlat: &LonLatT<f64>) {
+    pub(crate) fn set_input_cfg(&mut self, cfg: InputCfg) {
+        self.input.set_cfg(cfg);
+    }
+
+    pub(crate) fn get_input_cfg(&self) -> &InputCfg {
+        self.input.get_cfg()
+    }
+
+    /// A pointer (mouse, finger or pen) is pressed at a screen position
+    ///
+    /// The first pointer pressed makes the view under it the active one
+    pub(crate) fn pointer_down(&mut self, id: i32, sx: f64, sy: f64) {
+        if !self.input.has_pointers() {
+            let idx = self.get_view_at(sx, sy);
+            if idx != self.active_view {
+                let _ = self.set_active_view(idx);
+            }
+        }
+
+        let pos = self.get_view_position(sx, sy);
+        let actions = self.input.pointer_down(id, pos);
+        self.apply_input_actions(actions);
+    }
+
+    pub(crate) fn pointer_move(&mut self, id: i32, sx: f64, sy: f64) {
+        let pos = self.get_view_position(sx, sy);
+        let actions = self.input.pointer_move(id, pos);
+        self.apply_input_actions(actions);
+    }
+
+    pub(crate) fn pointer_up(&mut self, id: i32) {
+        let actions = self.input.pointer_up(id);
+        self.apply_input_actions(actions);
+    }
+
+    /// A wheel move, `delta` being the number of notches, positive to zoom in
+    pub(crate) fn wheel(&mut self, delta: f64) {
+        let actions = self.input.wheel(delta);
+        self.apply_input_actions(actions);
+    }
+
+    /// A key press, returns whether the key has been handled
+    pub(crate) fn key_down(&mut self, key: &str) -> bool {
+        let actions = self.input.key_down(key);
+        let handled = !actions.is_empty();
+        self.apply_input_actions(actions);
+
+        handled
+    }
+
+    /// Whether the view keeps moving after the inputs, i.e. by inertia or zooming smoothly
+    pub(crate) fn is_input_animating(&self) -> bool {
+        self.inertial_move_animation.is_some() || self.zoom_target.is_some()
+    }
+
+    fn apply_input_actions(&mut self, actions: Vec<InputAction>) {
+        // The view is frozen while a snapshot is rendered
+        if self.snapshot.is_some() {
+            return;
+        }
+
+        for action in actions {
+            match action {
+                InputAction::DragStart(pos) => self.press_left_button_mouse(pos.x as f32, pos.y as f32),
+                InputAction::Drag(from, to) => self.go_from_to(from.x, from.y, to.x, to.y),
+                InputAction::DragEnd => self.release_left_button_mouse(0.0, 0.0),
+                InputAction::Zoom { factor, smooth } => {
+                    // Zoom further from where a smooth zoom is going
+                    let fov = self.zoom_target.unwrap_or_else(|| self.camera.get_aperture());
+                    let fov: ArcDeg<f64> = Angle(fov.0 * factor).into();
+                    let fov: Angle<f64> = ArcDeg(fov.0.clamp(MIN_FOV, self.get_max_fov())).into();
+
+                    if smooth {
+                        self.stop_camera_animations();
+                        self.zoom_target = Some(fov);
+                        self.request_redraw = true;
+                    } else {
+                        self.set_fov(fov);
+                    }
+                }
+                InputAction::Rotate(angle) => {
+                    let angle = if self.camera.get_longitude_reversed() { -angle } else { angle };
+                    let theta: ArcDeg<f64> = (*self.get_rotation_around_center()).into();
+                    self.rotate_around_center(ArcDeg(theta.0 + angle));
+                }
+                InputAction::Pan(offset) => {
+                    // Screen positions are given in css pixels
+                    let size = self.camera.get_screen_size() / self.camera.get_dpi();
+                    let (cx, cy) = (size.x as f64 * 0.5, size.y as f64 * 0.5);
+                    let (dx, dy) = (offset.x * size.x as f64, offset.y * size.y as f64);
+
+                    self.go_from_to(cx + dx, cy + dy, cx, cy);
+                }
+            }
+        }
+    }
+
+    /// Start an animated transition of the camera to a position (given in ICRS) and a fov
+    ///
+    /// The promise resolves to `true` once the transition is finished, `false` if it has been interrupted
+    pub(crate) fn fly_to(&mut self, lonlat: &LonLatT<f64>, fov: Angle<f64>, duration: f32) -> js_sys::Promise {
+        // Stop the current transitions
+        self.move_animation = None;
+        self.inertial_move_animation = None;
+
         // Get the XYZ cartesian position from the lonlat
-        let goal_pos: Vector4<f64> = lonlat.vector();
+        let goal_pos = crate::coosys::apply_coo_system(
+            &CooSystem::ICRSJ2000,
+            self.camera.get_system(),
+            &lonlat.vector::<Vector4<f64>>(),
+        );
 
         // Convert these positions to rotations
         let start_anim_rot = *self.camera.get_rotation();
         let goal_anim_rot = Rotation::from_sky_position(&goal_pos);
 
-        // Set the moving animation object
+        // Zoom out to see both the start and the goal positions when they are far away
+        let start_fov = self.camera.get_aperture();
+        let dist = math::vector::angle3(&self.camera.get_center().truncate(), &goal_pos.truncate());
+        let max_fov = Angle(dist.0.min(std::f64::consts::PI));
+
+        // The tiles of the goal view can be fetched right now
+        self.prefetch_view(lonlat, fov);
+
+        let (done, done_recv) = async_channel::bounded(1);
         self.move_animation = Some(MoveAnimation {
             time_start_anim: Time::now(),
+            duration: duration.max(1.0),
             start_anim_rot,
             goal_anim_rot,
+            start_fov,
+            goal_fov: fov,
+            max_fov,
+            done,
         });
-    }*/
+        self.request_redraw = true;
+
+        wasm_bindgen_futures::future_to_promise(async move {
+            // The sender is dropped without sending if the transition is interrupted
+            let finished = done_recv.recv().await.unwrap_or(false);
+            Ok(JsValue::from_bool(finished))
+        })
+    }
+
+    // Stop the camera transitions as the user takes the control of the view
+    fn stop_camera_animations(&mut self) {
+        self.move_animation = None;
+        self.zoom_target = None;
+
+        if let Some(player) = &mut self.tour_player {
+            player.pause();
+        }
+    }
+
+    pub(crate) fn is_flying(&self) -> bool {
+        self.move_animation.is_some()
+    }
+
+    // Set the view between two keyframes of a tour, alpha going from 0 to 1
+    fn apply_tour_view(&mut self, k0: &Keyframe, k1: &Keyframe, alpha: f64) -> Result<(), JsValue> {
+        let alpha = math::utils::ease_in_out_cubic(alpha);
+
+        // The projection switches at the beginning of the segment
+        if let Some(cfg) = &k0.projection {
+            let projection = ProjectionType::from_params(&cfg.name, &cfg.params)?;
+            if projection.get_name() != self.projection.get_name() || projection.get_params() != self.projection.get_params() {
+                self.set_projection(projection)?;
+            }
+        }
+
+        let rotation = |k: &Keyframe| {
+            let lonlat = LonLatT::new(ArcDeg(k.ra).into(), ArcDeg(k.dec).into());
+            let pos = crate::coosys::apply_coo_system(
+                &CooSystem::ICRSJ2000,
+                self.camera.get_system(),
+                &lonlat.vector::<Vector4<f64>>(),
+            );
+
+            Rotation::from_sky_position(&pos)
+        };
+        let rot = rotation(k0).slerp(&rotation(k1), alpha);
+        self.camera.set_rotation(&rot, &self.projection);
+
+        let fov = ArcDeg(math::utils::log_interpolate(k0.fov, k1.fov, alpha));
+        self.camera.set_aperture(fov.into(), &self.projection);
+
+        let theta = ArcDeg(k0.rotation + (k1.rotation - k0.rotation) * alpha);
+        self.camera.set_rotation_around_center(theta.into(), &self.projection);
+
+        for (layer, &o0) in &k0.opacities {
+            let o1 = k1.opacities.get(layer).copied().unwrap_or(o0);
+            let opacity = o0 + (o1 - o0) * alpha as f32;
+
+            // The layers of the tour that are not displayed are skipped
+            if let Ok(mut meta) = self.layers.get_layer_cfg(layer) {
+                if meta.opacity != opacity {
+                    meta.opacity = opacity;
+                    self.layers.set_layer_cfg(layer.clone(), meta, &self.camera, &self.projection)?;
+                }
+            }
+        }
+
+        self.request_for_new_tiles = true;
+        self.request_redraw = true;
+
+        Ok(())
+    }
+
+    // The current view as a keyframe of a tour
+    fn get_current_keyframe(&self, time: f64) -> Result<Keyframe, JsValue> {
+        let center = self.view_to_icrsj2000_coosys(&self.get_center());
+        let ra: ArcDeg<f64> = center.lon().into();
+        let dec: ArcDeg<f64> = center.lat().into();
+        let rotation: ArcDeg<f64> = (*self.get_rotation_around_center()).into();
+
+        let opacities = self.layers.get_layers()
+            .map(|layer| Ok((layer.clone(), self.layers.get_layer_cfg(layer)?.opacity)))
+            .collect::<Result<_, JsValue>>()?;
+
+        Ok(Keyframe {
+            time,
+            ra: ra.0,
+            dec: dec.0,
+            fov: self.get_fov(),
+            rotation: rotation.0,
+            projection: Some(ProjectionCfg {
+                name: self.projection.get_name().to_string(),
+                params: self.projection.get_params(),
+            }),
+            opacities,
+        })
+    }
+
+    /// Load a tour, it is played back from its start
+    ///
+    /// The projections of all its keyframes are checked beforehand
+    pub(crate) fn load_tour(&mut self, tour: Tour) -> Result<(), JsValue> {
+        for cfg in tour.keyframes().iter().filter_map(|k| k.projection.as_ref()) {
+            ProjectionType::from_params(&cfg.name, &cfg.params)?;
+        }
+
+        self.tour_player = Some(TourPlayer::new(tour));
+
+        Ok(())
+    }
+
+    pub(crate) fn get_tour(&self) -> Option<&Tour> {
+        self.tour_player.as_ref().map(|player| player.get_tour())
+    }
+
+    fn get_tour_player(&mut self) -> Result<&mut TourPlayer, JsValue> {
+        self.tour_player.as_mut()
+            .ok_or_else(|| JsValue::from_str("No tour has been loaded"))
+    }
+
+    pub(crate) fn play_tour(&mut self) -> Result<(), JsValue> {
+        self.move_animation = None;
+        self.inertial_move_animation = None;
+
+        self.get_tour_player()?.play();
+        Ok(())
+    }
+
+    pub(crate) fn pause_tour(&mut self) -> Result<(), JsValue> {
+        self.get_tour_player()?.pause();
+        Ok(())
+    }
+
+    /// Move to a time of the tour in seconds
+    pub(crate) fn seek_tour(&mut self, time: f64) -> Result<(), JsValue> {
+        self.get_tour_player()?.seek(time);
+        Ok(())
+    }
+
+    pub(crate) fn get_tour_time(&self) -> Option<f64> {
+        self.tour_player.as_ref().map(|player| player.get_time())
+    }
+
+    pub(crate) fn is_tour_playing(&self) -> bool {
+        self.tour_player.as_ref()
+            .map(|player| player.is_playing())
+            .unwrap_or(false)
+    }
+
+    /// Start recording a tour, the keyframes are timed from now
+    pub(crate) fn start_tour_recording(&mut self) {
+        self.tour_recording = Some((Tour::default(), Time::now()));
+    }
+
+    /// Add the current view to the tour being recorded
+    pub(crate) fn record_tour_keyframe(&mut self) -> Result<(), JsValue> {
+        let start = self.tour_recording.as_ref()
+            .map(|(_, start)| *start)
+            .ok_or_else(|| JsValue::from_str("No tour is being recorded"))?;
+
+        let time = ((Time::now() - start).as_millis() / 1000.0) as f64;
+        let keyframe = self.get_current_keyframe(time)?;
+        if let Some((tour, _)) = &mut self.tour_recording {
+            tour.push(keyframe);
+        }
+
+        Ok(())
+    }
+
+    /// Stop recording the tour, it is loaded to be played back
+    pub(crate) fn stop_tour_recording(&mut self) -> Result<&Tour, JsValue> {
+        let (tour, _) = self.tour_recording.take()
+            .ok_or_else(|| JsValue::from_str("No tour is being recorded"))?;
+        let player = self.tour_player.insert(TourPlayer::new(tour));
+
+        Ok(player.get_tour())
+    }
 
     pub(crate) fn rotate_around_center(&mut self, theta: ArcDeg<f64>) {
+        self.stop_camera_animations();
         self.camera.set_rotation_around_center(theta.into(), &self.projection);
         // New tiles can be needed and some tiles can be removed
         self.request_for_new_tiles = true;
@@ -1350,6 +3210,7 @@ impl App {
     }
 
     pub(crate) fn set_fov(&mut self, fov: Angle<f64>) {
+        self.stop_camera_animations();
         // For the moment, no animation is triggered.
         // The fov is directly set
         self.camera.set_aperture(fov, &self.projection);
@@ -1357,6 +3218,83 @@ impl App {
         self.request_redraw = true;
     }
 
+    /// Set the view in the projection of an image layer so that the image is drawn as it is,
+    /// one image pixel covering one screen pixel
+    ///
+    /// The view is centered on the reference pixel of the image and rotated so that the image is upright.
+    /// The HiPS layers are reprojected around the image. Returns the name of the projection set.
+    pub(crate) fn set_native_image_view(&mut self, layer: &str) -> Result<&'static str, JsValue> {
+        use wcs::ImgXY;
+
+        let not_found = || JsValue::from_str(&format!("Image layer {} not found", layer));
+        let not_projected = || JsValue::from_str("The reference pixel of the image cannot be projected");
+
+        let image = self.layers.get_image_from_layer(layer).ok_or_else(not_found)?;
+        let native = image.get_native_projection();
+        let projection = native.get_projection()?;
+        let frame = native.frame()
+            .ok_or_else(|| JsValue::from_str("The frame of the image cannot be reproduced by the view"))?;
+        let (cx, cy) = native.crpix;
+        let center = image.get_wcs().unproj(&ImgXY::new(cx, cy)).ok_or_else(not_projected)?;
+        // The center of the view is given in ICRS
+        let center = crate::math::lonlat::radec_to_xyzw(Angle(center.lon()), Angle(center.lat()));
+        let center = crate::coosys::apply_coo_system(&frame, &CooSystem::ICRSJ2000, &center).lonlat();
+
+        self.set_projection(projection)?;
+        self.set_coo_system(frame);
+        self.set_center(&center);
+        self.camera.set_rotation_around_center(Angle(0.0), &self.projection);
+        self.camera.set_longitude_reversed(false, &self.projection);
+
+        let image = self.layers.get_image_from_layer(layer).ok_or_else(not_found)?;
+        let (camera, projection) = (&mut self.camera, &self.projection);
+        let get_axes = |camera: &CameraViewPort| image.get_axes(camera, projection).ok_or_else(not_projected);
+
+        // The image is seen from behind, i.e. its east is on the right
+        let mut axes = get_axes(camera)?;
+        if axes.ex.perp_dot(axes.ey) < 0.0 {
+            camera.set_longitude_reversed(true, projection);
+            axes = get_axes(camera)?;
+        }
+
+        // Rotate the view so that the y axis of the image points upwards
+        let theta = axes.ey.x.atan2(axes.ey.y);
+        if theta != 0.0 {
+            let mut candidates = vec![];
+            for angle in [theta, -theta] {
+                camera.set_rotation_around_center(Angle(angle), projection);
+                let axes = get_axes(camera)?;
+                candidates.push((axes.ey.x.atan2(axes.ey.y).abs(), angle));
+            }
+
+            let (_, angle) = candidates.into_iter()
+                .fold((f64::INFINITY, 0.0), |best, c| if c.0 < best.0 { c } else { best });
+            camera.set_rotation_around_center(Angle(angle), projection);
+            axes = get_axes(camera)?;
+        }
+
+        // Zoom until an image pixel covers a screen pixel
+        for _ in 0..10 {
+            let scale = axes.ey.magnitude();
+            if (scale - 1.0).abs() < 1e-9 {
+                break;
+            }
+
+            let fov = camera.get_aperture();
+            camera.set_aperture(Angle(fov.0 * scale), projection);
+            axes = get_axes(camera)?;
+        }
+
+        if image.get_native_axes(camera, projection).is_none() {
+            return Err(JsValue::from_str("The image cannot be drawn as it is by the view"));
+        }
+
+        self.request_for_new_tiles = true;
+        self.request_redraw = true;
+
+        Ok(self.projection.get_name())
+    }
+
     /*pub(crate) fn project_line(&self, lon1: f64, lat1: f64, lon2: f64, lat2: f64) -> Vec<Vector2<f64>> {
         let v1: Vector3<f64> = LonLatT::new(ArcDeg(lon1).into(), ArcDeg(lat1).into()).vector();
         let v2: Vector3<f64> = LonLatT::new(ArcDeg(lon2).into(), ArcDeg(lat2).into()).vector();
@@ -1365,6 +3303,7 @@ impl App {
     }*/
 
     pub(crate) fn go_from_to(&mut self, s1x: f64, s1y: f64, s2x: f64, s2y: f64) {
+        self.stop_camera_animations();
         // Select the HiPS layer rendered lastly
         if let Some(w1) = self.projection.screen_to_model_space(&Vector2::new(s1x, s1y), &self.camera) {
             if let Some(w2) = self.projection.screen_to_model_space(&Vector2::new(s2x, s2y), &self.camera) {
@@ -1390,8 +3329,101 @@ impl App {
         self.out_of_fov = true;
     }
 
-    pub(crate) fn add_cmap(&mut self, label: String, cmap: Colormap) -> Result<(), JsValue> {
-        self.colormaps.add_cmap(label, cmap)
+    /// Create a colormap interpolating a list of hex colors
+    pub(crate) fn add_custom_cmap(&mut self, cfg: CustomColormap) -> Result<(), JsValue> {
+        let rgba_colors = cfg.colors
+            .iter()
+            .map(|hex_color| {
+                let color = Color::hexToRgba(hex_color.clone());
+                let color_rgba: ColorRGBA = color.try_into()?;
+
+                Ok(colorgrad::Color::new(
+                    color_rgba.r as f64,
+                    color_rgba.g as f64,
+                    color_rgba.b as f64,
+                    color_rgba.a as f64)
+                )
+            })
+            .collect::<Result<Vec<_>, JsValue>>()?;
+
+        let grad = colorgrad::CustomGradient::new()
+            .colors(&rgba_colors)
+            .build()
+            .map_err(|err| JsValue::from_str(&format!("{:?}", err)))?;
+
+        let cmap = Colormap::new(&cfg.label, grad);
+        self.colormaps.add_cmap(cfg.label.clone(), cmap)?;
+
+        // Keep the colors to save the colormap in the view state
+        self.custom_colormaps.retain(|c| c.label != cfg.label);
+        self.custom_colormaps.push(cfg);
+
+        Ok(())
+    }
+
+    /// The camera, the projection, the frame, the grid, the colormaps created,
+    /// the composites, the arithmetic layers and the contours of the active view
+    pub(crate) fn get_view_state(&self) -> Result<ViewState, JsValue> {
+        // The tiles read from the page cannot be saved
+        if let Some(hips) = self.layers.values_hips().find(|hips| self.tile_sources.contains_key(hips.get_config().get_root_url())) {
+            return Err(JsValue::from_str(&format!("The HiPS {:?} reads its tiles from a tile source and cannot be saved in the state.", hips.get_config().get_root_url())));
+        }
+
+        let center = self.view_to_icrsj2000_coosys(&self.get_center());
+        let ra: ArcDeg<f64> = center.lon().into();
+        let dec: ArcDeg<f64> = center.lat().into();
+        let rotation: ArcDeg<f64> = (*self.get_rotation_around_center()).into();
+        let (composites, arithmetic_layers) = self.layers.get_composite_cfgs();
+
+        Ok(ViewState {
+            ra: ra.0,
+            dec: dec.0,
+            fov: self.get_fov(),
+            rotation: rotation.0,
+            projection: self.projection.get_name().to_string(),
+            projection_params: self.projection.get_params(),
+            frame: *self.camera.get_system(),
+            grid: self.grid.get_cfg(),
+            colormaps: self.custom_colormaps.clone(),
+            composites,
+            arithmetic_layers,
+            contours: self.contours.get_cfgs(),
+        })
+    }
+
+    /// Restore a view state given by `get_view_state`
+    ///
+    /// The composites, the arithmetic layers and the contours of the view are removed.
+    /// Those of the state are added back by the javascript side once the layers they
+    /// are computed from are added.
+    pub(crate) fn set_view_state(&mut self, state: ViewState) -> Result<(), JsValue> {
+        let projection = ProjectionType::from_params(&state.projection, &state.projection_params)?;
+
+        for cfg in self.contours.get_cfgs() {
+            self.remove_contours(&cfg.id)?;
+        }
+        let composite_layers = self.layers.get_layers()
+            .filter(|layer| self.layers.is_composite(layer))
+            .cloned()
+            .collect::<Vec<_>>();
+        for layer in composite_layers {
+            self.remove_layer(&layer)?;
+        }
+
+        // The colormaps must exist before the layers refer to them
+        for cmap in state.colormaps {
+            self.add_custom_cmap(cmap)?;
+        }
+
+        self.set_projection(projection)?;
+        self.set_coo_system(state.frame);
+        self.set_grid_cfg(state.grid)?;
+
+        self.set_center(&LonLatT::new(ArcDeg(state.ra).into(), ArcDeg(state.dec).into()));
+        self.set_fov(ArcDeg(state.fov).into());
+        self.rotate_around_center(ArcDeg(state.rotation));
+
+        Ok(())
     }
 
     // Accessors
@@ -1407,6 +3439,10 @@ impl App {
         self.camera.get_clip_zoom_factor()
     }
 
+    pub(crate) fn get_coo_system(&self) -> CooSystem {
+        *self.camera.get_system()
+    }
+
     pub(crate) fn get_fov(&self) -> f64 {
         let deg: ArcDeg<f64> = self.camera.get_aperture().into();
         deg.0
@@ -1424,3 +3460,68 @@ impl App {
         self.rendering
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use al_api::coo_system::CooSystem;
+    use al_api::hips::LayerOp;
+    use al_api::state::ViewState;
+
+    #[test]
+    fn test_view_state_round_trip() {
+        let state: ViewState = serde_json::from_str(r##"{
+            "ra": 83.6, "dec": 22.0, "fov": 30, "rotation": 15,
+            "projection": "COD", "projectionParams": {"thetaA": 45, "eta": 10},
+            "frame": "GAL",
+            "grid": {"color": {"r": 0, "g": 1, "b": 0}, "opacity": 0.5, "enabled": true},
+            "colormaps": [{"label": "fire", "colors": ["#000000ff", "#ff0000ff", "#ffff00ff"]}],
+            "composites": [{
+                "layer": "composite", "red": "i", "green": "r", "blue": "g",
+                "lupton": {"q": 8, "stretch": 0.5},
+                "meta": {
+                    "color": {
+                        "stretch": "linear", "minCut": 0, "maxCut": 1, "reversed": false, "cmapName": "native",
+                        "kGamma": 1, "kSaturation": 0, "kContrast": 0, "kBrightness": 0
+                    },
+                    "opacity": 1, "longitudeReversed": false, "imgFormat": "png"
+                }
+            }],
+            "arithmeticLayers": [{
+                "layer": "arithmetic", "a": "i", "b": "r", "op": "ratio",
+                "meta": {
+                    "color": {
+                        "stretch": "asinh", "minCut": 0.5, "maxCut": 2, "reversed": true, "cmapName": "fire",
+                        "kGamma": 1, "kSaturation": 0, "kContrast": 0, "kBrightness": 0
+                    },
+                    "opacity": 0.8, "longitudeReversed": false, "imgFormat": "fits"
+                }
+            }],
+            "contours": [{"id": "c", "layer": "i", "levels": [1, 10], "color": {"r": 0, "g": 1, "b": 0, "a": 1}}]
+        }"##).unwrap();
+
+        let json = serde_json::to_value(&state).unwrap();
+        let restored: ViewState = serde_json::from_value(json.clone()).unwrap();
+        assert_eq!(serde_json::to_value(&restored).unwrap(), json);
+
+        assert_eq!((restored.ra, restored.dec, restored.fov, restored.rotation), (83.6, 22.0, 30.0, 15.0));
+        assert_eq!(restored.projection, "COD");
+        assert_eq!((restored.projection_params.theta_a, restored.projection_params.eta), (Some(45.0), Some(10.0)));
+        assert_eq!(restored.frame, CooSystem::GAL);
+        assert_eq!((restored.grid.opacity, restored.grid.enabled), (Some(0.5), Some(true)));
+        assert_eq!(restored.colormaps, state.colormaps);
+        assert_eq!(restored.composites[0].lupton.map(|l| (l.q, l.stretch)), Some((8.0, 0.5)));
+        assert_eq!(restored.arithmetic_layers[0].op, LayerOp::Ratio);
+        assert_eq!(restored.arithmetic_layers[0].meta.opacity, 0.8);
+        assert_eq!(restored.contours[0].levels, vec![1.0, 10.0]);
+        assert_eq!(restored.contours[0].thickness, 1.0);
+
+        // The rotation, the projection parameters, the colormaps and the layers computed are optional
+        let state: ViewState = serde_json::from_str(r#"{
+            "ra": 0, "dec": 0, "fov": 180, "projection": "AIT", "frame": "ICRSJ2000", "grid": {}
+        }"#).unwrap();
+        assert_eq!(state.rotation, 0.0);
+        assert_eq!(state.projection_params, Default::default());
+        assert!(state.colormaps.is_empty());
+        assert!(state.composites.is_empty() && state.arithmetic_layers.is_empty() && state.contours.is_empty());
+    }
+}
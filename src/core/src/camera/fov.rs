@@ -51,12 +51,43 @@ fn linspace(a: f64, b: f64, num: usize) -> Vec<f64> {
 const NUM_VERTICES_WIDTH: usize = 4;
 const NUM_VERTICES_HEIGHT: usize = 4;
 const NUM_VERTICES: usize = 4 + 2 * NUM_VERTICES_WIDTH + 2 * NUM_VERTICES_HEIGHT;
+
+// Sample the border of a rectangle of the NDC space
+fn ndc_border(min: &XYNDC, max: &XYNDC) -> Vec<XYNDC> {
+    let mut x_ndc = linspace(min.x, max.x, NUM_VERTICES_WIDTH + 2);
+
+    x_ndc.extend(iter::repeat(max.x).take(NUM_VERTICES_HEIGHT));
+    x_ndc.extend(linspace(max.x, min.x, NUM_VERTICES_WIDTH + 2));
+    x_ndc.extend(iter::repeat(min.x).take(NUM_VERTICES_HEIGHT));
+
+    let mut y_ndc = iter::repeat(min.y)
+        .take(NUM_VERTICES_WIDTH + 1)
+        .collect::<Vec<_>>();
+
+    y_ndc.extend(linspace(min.y, max.y, NUM_VERTICES_HEIGHT + 2));
+    y_ndc.extend(iter::repeat(max.y).take(NUM_VERTICES_WIDTH));
+    y_ndc.extend(linspace(max.y, min.y, NUM_VERTICES_HEIGHT + 2));
+    y_ndc.pop();
+
+    let mut ndc_coo = Vec::with_capacity(NUM_VERTICES);
+    for idx_vertex in 0..NUM_VERTICES {
+        ndc_coo.push(Vector2::new(x_ndc[idx_vertex], y_ndc[idx_vertex]));
+    }
+
+    ndc_coo
+}
+
 // This struct belongs to the CameraViewPort
 pub struct FieldOfViewVertices {
     ndc_coo: Vec<XYNDC>,
     world_coo: Option<Vec<XYZWWorld>>,
     model_coo: Option<Vec<XYZWModel>>,
 
+    // Center of the window the vertices are sampled from. It is used
+    // to know which side of the border is inside the field of view
+    ndc_center: XYNDC,
+    world_center: Option<XYZWWorld>,
+
     // Meridians and parallels contained
     // in the field of view
     great_circles: FieldOfViewType,
@@ -73,43 +104,28 @@ impl FieldOfViewVertices {
         center: &Vector4<f64>,
         projection: &ProjectionType
     ) -> Self {
-        let mut x_ndc = linspace(-1., 1., NUM_VERTICES_WIDTH + 2);
-
-        x_ndc.extend(iter::repeat(1.0).take(NUM_VERTICES_HEIGHT));
-        x_ndc.extend(linspace(1., -1., NUM_VERTICES_WIDTH + 2));
-        x_ndc.extend(iter::repeat(-1.0).take(NUM_VERTICES_HEIGHT));
-
-        let mut y_ndc = iter::repeat(-1.0)
-            .take(NUM_VERTICES_WIDTH + 1)
-            .collect::<Vec<_>>();
-
-        y_ndc.extend(linspace(-1., 1., NUM_VERTICES_HEIGHT + 2));
-        y_ndc.extend(iter::repeat(1.0).take(NUM_VERTICES_WIDTH));
-        y_ndc.extend(linspace(1., -1., NUM_VERTICES_HEIGHT + 2));
-        y_ndc.pop();
+        let ndc_coo = ndc_border(&Vector2::new(-1.0, -1.0), &Vector2::new(1.0, 1.0));
+        let ndc_center = Vector2::new(0.0, 0.0);
 
-        let mut ndc_coo = Vec::with_capacity(NUM_VERTICES);
-        for idx_vertex in 0..NUM_VERTICES {
-            ndc_coo.push(Vector2::new(x_ndc[idx_vertex], y_ndc[idx_vertex]));
-        }
-
-        let world_coo = ndc_to_world(&ndc_coo, ndc_to_clip, clip_zoom_factor, projection);
-        let model_coo = world_coo
-            .as_ref()
-            .map(|world_coo| world_to_model(world_coo, mat));
-
-        let great_circles = if let Some(vertices) = &model_coo {
-            FieldOfViewType::new_polygon(vertices, center)
-        } else {
-            FieldOfViewType::Allsky
+        let mut fov = FieldOfViewVertices {
+            ndc_coo,
+            world_coo: None,
+            model_coo: None,
+            ndc_center,
+            world_center: None,
+            great_circles: FieldOfViewType::Allsky,
         };
+        fov.set_fov(ndc_to_clip, clip_zoom_factor, mat, center, projection);
 
-        FieldOfViewVertices {
-            ndc_coo,
-            world_coo,
-            model_coo,
-            great_circles,
-        }
+        fov
+    }
+
+    /// Sample the field of view from a rectangle of the NDC space instead of the whole screen
+    ///
+    /// It is taken into account by the next call to `set_fov`
+    pub fn set_ndc_window(&mut self, min: &XYNDC, max: &XYNDC) {
+        self.ndc_coo = ndc_border(min, max);
+        self.ndc_center = (min + max) * 0.5;
     }
 
     pub fn set_fov(
@@ -121,6 +137,8 @@ impl FieldOfViewVertices {
         projection: &ProjectionType
     ) {
         self.world_coo = ndc_to_world(&self.ndc_coo, ndc_to_clip, clip_zoom_factor, projection);
+        self.world_center = ndc_to_world(&[self.ndc_center], ndc_to_clip, clip_zoom_factor, projection)
+            .map(|world_coo| world_coo[0]);
         self.set_rotation(w2m, center);
     }
 
@@ -135,12 +153,16 @@ impl FieldOfViewVertices {
             self.model_coo = None;
         }
 
-        self.set_great_circles(center);
+        // The center of the window is inside the field of view
+        let control_point = self.world_center
+            .map(|world_center| w2m * world_center)
+            .unwrap_or(*center);
+        self.set_great_circles(&control_point);
     }
 
-    fn set_great_circles(&mut self, center: &Vector4<f64>) {
+    fn set_great_circles(&mut self, control_point: &Vector4<f64>) {
         if let Some(vertices) = &self.model_coo {
-            self.great_circles = FieldOfViewType::new_polygon(vertices, center);
+            self.great_circles = FieldOfViewType::new_polygon(vertices, control_point);
         } else {
             self.great_circles = FieldOfViewType::Allsky;
         }
@@ -168,3 +190,57 @@ impl FieldOfViewVertices {
 }
 use crate::math::{projection::Projection, spherical::BoundingBox};
 use std::iter;
+
+#[cfg(test)]
+mod tests {
+    use super::FieldOfViewVertices;
+    use crate::healpix::{cell::HEALPixCell, coverage::HEALPixCoverage};
+    use crate::math::angle::Angle;
+    use crate::math::lonlat::{radec_to_xyzw, xyzw_to_radec};
+    use crate::math::projection::{Morph, Projection, ProjectionType};
+    use cgmath::{Matrix4, SquareMatrix, Vector2, Vector4};
+
+    #[test]
+    fn test_view_cells_cover_morphing_footprint() {
+        // Half way from SIN to AIT
+        let projection = ProjectionType::Morph(Box::new(Morph {
+            from: ProjectionType::from_name("SIN").unwrap(),
+            to: ProjectionType::from_name("AIT").unwrap(),
+            alpha: 0.5,
+        }));
+
+        // A view 60 degrees wide on a 4:3 screen, computed as the camera does
+        let ndc_to_clip = Vector2::new(1.0, 0.75 * projection.bounds_size_ratio());
+        let half_aperture = Angle(30.0_f64.to_radians());
+        let p0 = projection.world_to_clip_space(&radec_to_xyzw(-half_aperture, Angle(0.0))).unwrap();
+        let p1 = projection.world_to_clip_space(&radec_to_xyzw(half_aperture, Angle(0.0))).unwrap();
+        let clip_zoom_factor = 0.5 * (p1.x - p0.x).abs();
+
+        let center = Vector4::new(0.0, 0.0, 1.0, 1.0);
+        let fov = FieldOfViewVertices::new(&ndc_to_clip, clip_zoom_factor, &Matrix4::identity(), &center, &projection);
+        let depth = 5;
+        let coverage = HEALPixCoverage::new(depth, fov.get_vertices().unwrap(), &center.truncate());
+
+        // Every position drawn on the screen is in a cell of the view
+        let mut num_drawn = 0;
+        for i in -90..=90 {
+            for j in -90..=90 {
+                let xyzw = radec_to_xyzw(Angle((i as f64).to_radians()), Angle((j as f64).to_radians()));
+                let drawn = projection.world_to_clip_space(&xyzw)
+                    .map(|xy| {
+                        (xy.x / (ndc_to_clip.x * clip_zoom_factor)).abs() <= 1.0
+                            && (xy.y / (ndc_to_clip.y * clip_zoom_factor)).abs() <= 1.0
+                    })
+                    .unwrap_or(false);
+
+                if drawn {
+                    let (lon, lat) = xyzw_to_radec(&xyzw);
+                    let cell = HEALPixCell(depth, cdshealpix::nested::hash(depth, lon.0, lat.0));
+                    assert!(coverage.contains(&cell), "({}, {}) is drawn out of the view cells", i, j);
+                    num_drawn += 1;
+                }
+            }
+        }
+        assert!(num_drawn > 0);
+    }
+}
@@ -8,7 +8,7 @@ pub enum UserAction {
 
 use super::fov::FieldOfViewVertices;
 use crate::math::{
-    projection::coo_space::XYZWModel,
+    projection::coo_space::{XYNDC, XYZWModel},
     spherical::BoundingBox,
     projection::domain::sdf::ProjDef
 };
@@ -38,6 +38,10 @@ pub struct CameraViewPort {
 
     // HEALPix depth of 512 large tiles
     tile_depth: u8,
+    // Position in pixels of the lower-left corner of the rendered window
+    window_offset: Vector2<i32>,
+    // Position in pixels of the lower-left corner of the view in the canvas
+    viewport_origin: Vector2<i32>,
 
     // Internal variable used for projection purposes
     ndc_to_clip: Vector2<f64>,
@@ -126,6 +130,8 @@ impl CameraViewPort {
         let reversed_longitude = false;
 
         let tile_depth = 0;
+        let window_offset = Vector2::new(0, 0);
+        let viewport_origin = Vector2::new(0, 0);
 
         let camera = CameraViewPort {
             // The field of view angle
@@ -164,6 +170,8 @@ impl CameraViewPort {
             time_last_move,
 
             tile_depth,
+            window_offset,
+            viewport_origin,
 
             // A reference to the WebGL2 context
             gl,
@@ -222,6 +230,25 @@ impl CameraViewPort {
         self.gl.viewport(0, 0, self.width as i32, self.height as i32);
     }
 
+    /// Set back the viewport and scissor of the screen,
+    /// e.g. after having rendered something offscreen
+    pub fn restore_viewport(&self) {
+        let origin = self.viewport_origin - self.window_offset;
+        self.gl.viewport(origin.x, origin.y, self.width as i32, self.height as i32);
+        self.recompute_scissor();
+    }
+
+    /// Set the position in pixels of the lower-left corner of the view in the canvas
+    ///
+    /// The canvas can then be shared by several views, each one having its own render size
+    pub fn set_viewport_origin(&mut self, origin: Vector2<i32>) {
+        self.viewport_origin = origin;
+    }
+
+    pub fn get_viewport_origin(&self) -> Vector2<i32> {
+        self.viewport_origin
+    }
+
     pub fn contains_pole(&self) -> bool {
         self.vertices.contains_pole()
     }
@@ -272,6 +299,58 @@ impl CameraViewPort {
         self.recompute_scissor();
     }
 
+    /// Set the size of the rendering in physical pixels, leaving the canvas untouched
+    ///
+    /// This is used to render the view offscreen at another resolution than the screen one.
+    /// The size of the screen must be set back once the rendering is done.
+    pub fn set_render_size(&mut self, width: f32, height: f32, projection: &ProjectionType) {
+        self.width = width;
+        self.height = height;
+        self.aspect = width / height;
+
+        self.compute_ndc_to_clip_factor(projection);
+        self.vertices.set_fov(
+            &self.ndc_to_clip,
+            self.clip_zoom_factor,
+            &self.w2m,
+            &self.center,
+            projection,
+        );
+        let proj_area = projection.get_area();
+        self.is_allsky = !proj_area.is_in(&math::projection::ndc_to_clip_space(
+            &Vector2::new(-1.0, -1.0),
+            self,
+        ));
+        // The tiles depth depends on the number of pixels
+        self.compute_tile_depth();
+        // The renderables must recompute their vertices
+        self.moved = true;
+
+        self.restore_viewport();
+    }
+
+    /// Restrict the field of view to a rectangle of the NDC space given by its lower-left and upper-right corners
+    ///
+    /// The views of the layers are then computed for that rectangle only and the viewport is shifted
+    /// so that the rectangle is rendered from the origin of the framebuffer. `None` gives back the whole view
+    pub fn set_render_window(&mut self, window: Option<(XYNDC, XYNDC)>, projection: &ProjectionType) {
+        let (min, max) = window.unwrap_or((Vector2::new(-1.0, -1.0), Vector2::new(1.0, 1.0)));
+        self.window_offset = Vector2::new(
+            ((min.x + 1.0) * 0.5 * (self.width as f64)).round() as i32,
+            ((min.y + 1.0) * 0.5 * (self.height as f64)).round() as i32,
+        );
+        self.vertices.set_ndc_window(&min, &max);
+        self.vertices.set_fov(
+            &self.ndc_to_clip,
+            self.clip_zoom_factor,
+            &self.w2m,
+            &self.center,
+            projection,
+        );
+        // The renderables must recompute their vertices
+        self.moved = true;
+    }
+
     pub fn compute_ndc_to_clip_factor(&mut self, proj: &ProjectionType) {
         self.ndc_to_clip = if self.height < self.width {
             Vector2::new(
@@ -308,7 +387,7 @@ impl CameraViewPort {
         };
 
         let can_unzoom_more = match proj {
-            ProjectionType::Tan(_) | ProjectionType::Mer(_) | ProjectionType::Air(_) | ProjectionType::Stg(_) | ProjectionType::Car(_) | ProjectionType::Cea(_) | ProjectionType::Cyp(_) | ProjectionType::Hpx(_) => false,
+            ProjectionType::Tan(_) | ProjectionType::Mer(_) | ProjectionType::Air(_) | ProjectionType::Stg(_) | ProjectionType::Car(_) | ProjectionType::Cea { .. } | ProjectionType::Cyp { .. } | ProjectionType::Hpx(_) => false,
             _ => true
         };
 
@@ -364,9 +443,14 @@ impl CameraViewPort {
     }
 
     fn compute_tile_depth(&mut self) {
+        self.tile_depth = self.get_tile_depth_for_aperture(self.aperture);
+    }
+
+    /// The depth of the tiles that would be displayed with a given aperture
+    pub fn get_tile_depth_for_aperture(&self, aperture: Angle<f64>) -> u8 {
         // Compute a depth from a number of pixels on screen
         let width = self.width;
-        let aperture = self.aperture.0 as f32;
+        let aperture = aperture.0 as f32;
 
         let angle_per_pixel = aperture / width;
 
@@ -380,11 +464,11 @@ impl CameraViewPort {
         const DEPTH_OFFSET_TEXTURE: u32 = 9;
         // The depth of the texture corresponds to the depth of a pixel
         // minus the offset depth of the texture
-        self.tile_depth = if DEPTH_OFFSET_TEXTURE > depth_pixel {
+        if DEPTH_OFFSET_TEXTURE > depth_pixel {
             0_u8
         } else {
             (depth_pixel - DEPTH_OFFSET_TEXTURE) as u8
-        };
+        }
     }
 
     pub fn get_tile_depth(&self) -> u8 {
@@ -414,7 +498,7 @@ impl CameraViewPort {
         self.set_rotation(&rot, projection);
     }
 
-    fn set_rotation(&mut self, rot: &Rotation<f64>, projection: &ProjectionType) {
+    pub fn set_rotation(&mut self, rot: &Rotation<f64>, projection: &ProjectionType) {
         self.w2m_rot = *rot;
 
         self.update_rot_matrices(projection);
@@ -0,0 +1,217 @@
+// Readers of the zip and tar archives a HiPS can be packed in
+use std::collections::HashMap;
+
+type Files = HashMap<String, Vec<u8>>;
+
+const ZIP_LOCAL_HEADER_SIGNATURE: u32 = 0x04034b50;
+const ZIP_CENTRAL_HEADER_SIGNATURE: u32 = 0x02014b50;
+const ZIP_END_SIGNATURE: u32 = 0x06054b50;
+const TAR_BLOCK_SIZE: usize = 512;
+
+/// Read the regular files of a zip or a tar archive indexed by their path
+pub fn read(bytes: &[u8]) -> Result<Files, &'static str> {
+    if read_u32(bytes, 0) == Some(ZIP_LOCAL_HEADER_SIGNATURE) {
+        read_zip(bytes)
+    } else if bytes.get(257..262) == Some(b"ustar") {
+        read_tar(bytes)
+    } else {
+        Err("The archive must be a zip or a tar file")
+    }
+}
+
+fn read_u16(bytes: &[u8], offset: usize) -> Option<u16> {
+    let b = bytes.get(offset..offset + 2)?;
+    Some(u16::from_le_bytes([b[0], b[1]]))
+}
+
+fn read_u32(bytes: &[u8], offset: usize) -> Option<u32> {
+    let b = bytes.get(offset..offset + 4)?;
+    Some(u32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+}
+
+fn read_zip(bytes: &[u8]) -> Result<Files, &'static str> {
+    const ERR: &str = "Corrupted zip archive";
+
+    // The end of central directory record is at the end of the archive, followed by a comment
+    let end = (0..=bytes.len().saturating_sub(22))
+        .rev()
+        .find(|&offset| read_u32(bytes, offset) == Some(ZIP_END_SIGNATURE))
+        .ok_or(ERR)?;
+    let num_entries = read_u16(bytes, end + 10).ok_or(ERR)? as usize;
+    let mut offset = read_u32(bytes, end + 16).ok_or(ERR)? as usize;
+
+    let mut files = HashMap::with_capacity(num_entries);
+    for _ in 0..num_entries {
+        if read_u32(bytes, offset) != Some(ZIP_CENTRAL_HEADER_SIGNATURE) {
+            return Err(ERR);
+        }
+
+        let method = read_u16(bytes, offset + 10).ok_or(ERR)?;
+        let compressed_size = read_u32(bytes, offset + 20).ok_or(ERR)? as usize;
+        let name_len = read_u16(bytes, offset + 28).ok_or(ERR)? as usize;
+        let extra_len = read_u16(bytes, offset + 30).ok_or(ERR)? as usize;
+        let comment_len = read_u16(bytes, offset + 32).ok_or(ERR)? as usize;
+        let local_offset = read_u32(bytes, offset + 42).ok_or(ERR)? as usize;
+        let name = bytes.get(offset + 46..offset + 46 + name_len).ok_or(ERR)?;
+        let name = String::from_utf8_lossy(name).to_string();
+
+        offset += 46 + name_len + extra_len + comment_len;
+
+        // Skip the directories
+        if name.ends_with('/') {
+            continue;
+        }
+
+        if compressed_size == u32::MAX as usize || local_offset == u32::MAX as usize {
+            return Err("Zip64 archives are not supported");
+        }
+
+        if read_u32(bytes, local_offset) != Some(ZIP_LOCAL_HEADER_SIGNATURE) {
+            return Err(ERR);
+        }
+        let local_name_len = read_u16(bytes, local_offset + 26).ok_or(ERR)? as usize;
+        let local_extra_len = read_u16(bytes, local_offset + 28).ok_or(ERR)? as usize;
+        let start = local_offset + 30 + local_name_len + local_extra_len;
+        let data = bytes.get(start..start + compressed_size).ok_or(ERR)?;
+
+        let data = match method {
+            // Stored
+            0 => data.to_vec(),
+            // Deflated
+            8 => miniz_oxide::inflate::decompress_to_vec(data).map_err(|_| ERR)?,
+            _ => return Err("Only stored and deflated zip entries are supported"),
+        };
+
+        files.insert(name, data);
+    }
+
+    Ok(files)
+}
+
+// Parse a null terminated string of a tar header
+fn read_tar_str(field: &[u8]) -> String {
+    let len = field.iter().position(|&b| b == 0).unwrap_or(field.len());
+    String::from_utf8_lossy(&field[..len]).to_string()
+}
+
+fn read_tar(bytes: &[u8]) -> Result<Files, &'static str> {
+    const ERR: &str = "Corrupted tar archive";
+
+    let mut files = HashMap::new();
+    let mut offset = 0;
+    while let Some(header) = bytes.get(offset..offset + TAR_BLOCK_SIZE) {
+        // The archive ends with empty blocks
+        if header.iter().all(|&b| b == 0) {
+            break;
+        }
+
+        let name = read_tar_str(&header[0..100]);
+        let size = usize::from_str_radix(read_tar_str(&header[124..136]).trim(), 8).map_err(|_| ERR)?;
+        let type_flag = header[156];
+        let prefix = read_tar_str(&header[345..500]);
+
+        let start = offset + TAR_BLOCK_SIZE;
+        let data = bytes.get(start..start + size).ok_or(ERR)?;
+
+        // Only keep the regular files
+        if type_flag == b'0' || type_flag == 0 {
+            let path = if prefix.is_empty() {
+                name
+            } else {
+                format!("{}/{}", prefix, name)
+            };
+            files.insert(path, data.to_vec());
+        }
+
+        // The data are padded to a whole number of blocks
+        offset = start + size.div_ceil(TAR_BLOCK_SIZE) * TAR_BLOCK_SIZE;
+    }
+
+    Ok(files)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::read;
+
+    fn zip_entry(name: &str, data: &[u8], method: u16, offset: usize) -> (Vec<u8>, Vec<u8>) {
+        let stored = if method == 8 {
+            miniz_oxide::deflate::compress_to_vec(data, 6)
+        } else {
+            data.to_vec()
+        };
+
+        let mut local = vec![];
+        local.extend(0x04034b50_u32.to_le_bytes());
+        local.extend([20, 0, 0, 0]);
+        local.extend(method.to_le_bytes());
+        local.extend([0; 8]);
+        local.extend((stored.len() as u32).to_le_bytes());
+        local.extend((data.len() as u32).to_le_bytes());
+        local.extend((name.len() as u16).to_le_bytes());
+        local.extend([0, 0]);
+        local.extend(name.as_bytes());
+        local.extend(&stored);
+
+        let mut central = vec![];
+        central.extend(0x02014b50_u32.to_le_bytes());
+        central.extend([20, 0, 20, 0, 0, 0]);
+        central.extend(method.to_le_bytes());
+        central.extend([0; 8]);
+        central.extend((stored.len() as u32).to_le_bytes());
+        central.extend((data.len() as u32).to_le_bytes());
+        central.extend((name.len() as u16).to_le_bytes());
+        central.extend([0; 12]);
+        central.extend((offset as u32).to_le_bytes());
+        central.extend(name.as_bytes());
+
+        (local, central)
+    }
+
+    #[test]
+    fn test_read_zip() {
+        let (mut zip, mut central) = zip_entry("DSS/properties", b"hips_order = 3", 0, 0);
+        let tile = vec![42_u8; 1000];
+        let (local2, central2) = zip_entry("DSS/Norder3/Dir0/Npix0.jpg", &tile, 8, zip.len());
+        zip.extend(local2);
+        central.extend(central2);
+
+        let central_offset = zip.len();
+        zip.extend(&central);
+        zip.extend(0x06054b50_u32.to_le_bytes());
+        zip.extend([0, 0, 0, 0, 2, 0, 2, 0]);
+        zip.extend((central.len() as u32).to_le_bytes());
+        zip.extend((central_offset as u32).to_le_bytes());
+        zip.extend([0, 0]);
+
+        let files = read(&zip).unwrap();
+        assert_eq!(files.len(), 2);
+        assert_eq!(files["DSS/properties"], b"hips_order = 3");
+        assert_eq!(files["DSS/Norder3/Dir0/Npix0.jpg"], tile);
+    }
+
+    fn tar_header(name: &str, size: usize, type_flag: u8) -> Vec<u8> {
+        let mut header = vec![0_u8; 512];
+        header[..name.len()].copy_from_slice(name.as_bytes());
+        let size = format!("{:011o}", size);
+        header[124..135].copy_from_slice(size.as_bytes());
+        header[156] = type_flag;
+        header[257..262].copy_from_slice(b"ustar");
+
+        header
+    }
+
+    #[test]
+    fn test_read_tar() {
+        let mut tar = tar_header("DSS/", 0, b'5');
+        tar.extend(tar_header("DSS/properties", 14, b'0'));
+        let mut data = b"hips_order = 3".to_vec();
+        data.resize(512, 0);
+        tar.extend(data);
+        tar.extend(vec![0; 1024]);
+
+        let files = read(&tar).unwrap();
+        assert_eq!(files.len(), 1);
+        assert_eq!(files["DSS/properties"], b"hips_order = 3");
+    }
+}
@@ -0,0 +1,183 @@
+use crate::renderable::Url;
+use crate::time::{DeltaTime, Time};
+use std::collections::HashMap;
+
+// Number of consecutive errors after which a mirror is considered unhealthy
+const MAX_CONSECUTIVE_ERRORS: u32 = 5;
+// Duration after its last error for which an unhealthy mirror is not queried
+const COOL_DOWN: DeltaTime = DeltaTime(60_000.0);
+// Weight of the last request in the moving average of the latency
+const LATENCY_SMOOTHING: f32 = 0.2;
+
+/// Latency and error rate of a HiPS mirror
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct MirrorStats {
+    pub num_requests: u32,
+    pub num_errors: u32,
+    consecutive_errors: u32,
+    last_error: Option<Time>,
+    // Moving average of the duration of the requests in ms
+    pub latency: Option<f32>,
+}
+
+impl MirrorStats {
+    fn record_success(&mut self, latency: f32) {
+        self.num_requests += 1;
+        self.consecutive_errors = 0;
+        self.latency = Some(
+            self.latency
+                .map(|l| l + LATENCY_SMOOTHING * (latency - l))
+                .unwrap_or(latency),
+        );
+    }
+
+    fn record_error(&mut self, time: Time) {
+        self.num_requests += 1;
+        self.num_errors += 1;
+        self.consecutive_errors += 1;
+        self.last_error = Some(time);
+    }
+
+    /// A mirror failing repeatedly is healthy again once the cool-down has elapsed.
+    /// It becomes unhealthy at its next error unless a request succeeds first.
+    pub fn is_healthy(&self, time: Time) -> bool {
+        self.consecutive_errors < MAX_CONSECUTIVE_ERRORS
+            || self
+                .last_error
+                .map(|last_error| time - last_error >= COOL_DOWN)
+                .unwrap_or(true)
+    }
+
+    pub fn error_rate(&self) -> f32 {
+        if self.num_requests == 0 {
+            0.0
+        } else {
+            self.num_errors as f32 / self.num_requests as f32
+        }
+    }
+}
+
+/// The statistics of the mirrors queried, indexed by their base url
+pub struct Mirrors {
+    stats: HashMap<Url, MirrorStats>,
+}
+
+impl Mirrors {
+    pub fn new() -> Self {
+        Self {
+            stats: HashMap::new(),
+        }
+    }
+
+    pub fn get(&self, url: &str) -> MirrorStats {
+        self.stats.get(url).copied().unwrap_or_default()
+    }
+
+    pub fn record_success(&mut self, url: &str, latency: f32) {
+        self.stats
+            .entry(url.to_string())
+            .or_default()
+            .record_success(latency);
+    }
+
+    pub fn record_error(&mut self, url: &str, time: Time) {
+        self.stats.entry(url.to_string()).or_default().record_error(time);
+    }
+
+    /// Select the mirror to switch to when the `current` one has failed repeatedly
+    ///
+    /// The healthy mirror with the lowest error rate, then the lowest latency is chosen.
+    /// Mirrors never queried are preferred to slow ones so that they get a chance to be measured.
+    /// `time` is the current time, from which the cool-down of the unhealthy mirrors is checked.
+    pub fn select<'a>(&self, current: &str, mirrors: &'a [Url], time: Time) -> Option<&'a Url> {
+        if self.get(current).is_healthy(time) {
+            return None;
+        }
+
+        mirrors
+            .iter()
+            .filter(|url| url.as_str() != current)
+            .map(|url| (url, self.get(url)))
+            .filter(|(_, stats)| stats.is_healthy(time))
+            .min_by(|(_, s1), (_, s2)| {
+                let k1 = (s1.error_rate(), s1.latency.unwrap_or(0.0));
+                let k2 = (s2.error_rate(), s2.latency.unwrap_or(0.0));
+
+                k1.partial_cmp(&k2).unwrap_or(std::cmp::Ordering::Equal)
+            })
+            .map(|(url, _)| url)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Mirrors;
+    use crate::time::Time;
+
+    #[test]
+    fn test_select_mirror() {
+        let urls = vec![
+            "https://alasky.cds.unistra.fr/DSS".to_string(),
+            "https://alaskybis.cds.unistra.fr/DSS".to_string(),
+            "https://skies.esac.esa.int/DSS".to_string(),
+        ];
+
+        let mut mirrors = Mirrors::new();
+        let t = Time(0.0);
+        mirrors.record_success(&urls[0], 100.0);
+        mirrors.record_success(&urls[1], 300.0);
+        mirrors.record_success(&urls[2], 200.0);
+
+        // The current mirror is healthy
+        assert_eq!(mirrors.select(&urls[0], &urls, t), None);
+
+        for _ in 0..5 {
+            mirrors.record_error(&urls[0], t);
+        }
+        // The fastest other mirror is chosen
+        assert_eq!(mirrors.select(&urls[0], &urls, t), Some(&urls[2]));
+
+        for _ in 0..5 {
+            mirrors.record_error(&urls[2], t);
+        }
+        assert_eq!(mirrors.select(&urls[0], &urls, t), Some(&urls[1]));
+
+        // A success makes the mirror healthy again
+        mirrors.record_success(&urls[0], 100.0);
+        assert_eq!(mirrors.select(&urls[0], &urls, t), None);
+    }
+
+    #[test]
+    fn test_mirror_cool_down() {
+        let urls = vec![
+            "https://alasky.cds.unistra.fr/DSS".to_string(),
+            "https://skies.esac.esa.int/DSS".to_string(),
+        ];
+
+        let mut mirrors = Mirrors::new();
+        mirrors.record_success(&urls[0], 100.0);
+        mirrors.record_success(&urls[1], 200.0);
+
+        for _ in 0..5 {
+            mirrors.record_error(&urls[1], Time(0.0));
+        }
+        for _ in 0..5 {
+            mirrors.record_error(&urls[0], Time(10_000.0));
+        }
+        // Both mirrors are failing, there is no other to switch to
+        assert_eq!(mirrors.select(&urls[0], &urls, Time(20_000.0)), None);
+        assert!(!mirrors.get(&urls[0]).is_healthy(Time(20_000.0)));
+
+        // Once cooled down, the other mirror is tried again
+        assert_eq!(mirrors.select(&urls[0], &urls, Time(65_000.0)), Some(&urls[1]));
+
+        // A mirror that has cooled down fails again at its next error
+        mirrors.record_error(&urls[1], Time(65_000.0));
+        assert!(!mirrors.get(&urls[1]).is_healthy(Time(70_000.0)));
+        assert_eq!(mirrors.select(&urls[0], &urls, Time(70_000.0)), None);
+
+        // The current mirror recovers after its own cool-down
+        assert!(mirrors.get(&urls[0]).is_healthy(Time(70_000.0)));
+        assert_eq!(mirrors.select(&urls[0], &urls, Time(70_000.0)), None);
+    }
+}
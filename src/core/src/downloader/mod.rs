@@ -1,9 +1,15 @@
+mod archive;
+pub mod mirror;
 pub mod query;
 pub mod request;
+pub mod source;
 
 use crate::renderable::Url;
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
 
+use al_api::hips::HiPSTileStats;
+
+use mirror::Mirrors;
 use query::QueryId;
 
 pub struct Downloader {
@@ -13,12 +19,21 @@ pub struct Downloader {
 
     cache: Cache<Url, Resource>,
     queried_cached_urls: Vec<Url>,
+    // Number of fetches served by the cache or not
+    num_cache_hits: u32,
+    num_cache_misses: u32,
+
+    // Tile download statistics indexed by the HiPS url
+    tile_stats: HashMap<Url, HiPSTileStats>,
+    // Latency and error rates of the HiPS mirrors
+    mirrors: Mirrors,
 }
 
-use crate::fifo_cache::Cache;
+use crate::lru_cache::Cache;
+use crate::time::Time;
 
 use query::Query;
-use request::{RequestType, Resource};
+use request::{RequestType, ResolvedStatus, Resource};
 
 
 impl Downloader {
@@ -27,11 +42,17 @@ impl Downloader {
         let queried_list = HashSet::with_capacity(64);
         let cache = Cache::new();
         let queried_cached_urls = Vec::with_capacity(64);
+        let tile_stats = HashMap::new();
+        let mirrors = Mirrors::new();
         Self {
             requests,
             queried_list,
             cache,
             queried_cached_urls,
+            num_cache_hits: 0,
+            num_cache_misses: 0,
+            tile_stats,
+            mirrors,
         }
     }
     // Returns true if the fetch has been done
@@ -42,7 +63,9 @@ impl Downloader {
     {
         let url = query.url();
         if self.cache.contains(url) {
+            self.cache.touch(url);
             self.queried_cached_urls.push(url.clone());
+            self.num_cache_hits += 1;
             false
         } else {
             let query_id = query.id();
@@ -52,6 +75,7 @@ impl Downloader {
             // The cell is not already requested
             if not_already_requested {
                 self.queried_list.insert(query_id);
+                self.num_cache_misses += 1;
     
                 let request = T::Request::from(query);
                 self.requests.push(request.into());
@@ -61,16 +85,50 @@ impl Downloader {
         }
     }
 
+    pub fn is_cached(&self, url: &Url) -> bool {
+        self.cache.contains(url)
+    }
+
     pub fn get_received_resources(&mut self) -> Vec<Resource> {
         let mut rscs = vec![];
 
         let mut finished_query_list = vec![];
+        let tile_stats = &mut self.tile_stats;
+        let mirrors = &mut self.mirrors;
         self.requests = self
             .requests
             .drain(..)
             .filter(|request| {
                 // If the request resolves into a resource
                 if let Some(rsc) = request.into() {
+                    if let RequestType::Tile(request) = request {
+                        let hips_url = request.get_hips_url();
+                        let stats = tile_stats.entry(hips_url.clone())
+                            .or_default();
+                        match request.resolve_status() {
+                            ResolvedStatus::Found => stats.num_found += 1,
+                            ResolvedStatus::Missing => stats.num_missing += 1,
+                            ResolvedStatus::Failed => stats.num_failed += 1,
+                            ResolvedStatus::NotResolved => (),
+                        }
+                        stats.num_retries += request.get_num_retries();
+
+                        // Every retry is an error of the mirror queried
+                        let now = Time::now();
+                        for _ in 0..request.get_num_retries() {
+                            mirrors.record_error(hips_url, now);
+                        }
+                        match request.resolve_status() {
+                            ResolvedStatus::Failed => mirrors.record_error(hips_url, now),
+                            ResolvedStatus::Found => {
+                                let latency = now - request.get_time_request();
+                                mirrors.record_success(hips_url, latency.as_millis());
+                            }
+                            // A missing tile tells nothing about the health of the mirror
+                            ResolvedStatus::Missing | ResolvedStatus::NotResolved => (),
+                        }
+                    }
+
                     rscs.push(rsc);
                     finished_query_list.push(request.id().clone());
 
@@ -95,12 +153,63 @@ impl Downloader {
         rscs
     }
 
+    pub fn get_tile_stats(&self, hips_url: &str) -> HiPSTileStats {
+        self.tile_stats.get(hips_url)
+            .copied()
+            .unwrap_or_default()
+    }
+
+    pub fn reset_tile_stats(&mut self, hips_url: &str) {
+        self.tile_stats.remove(hips_url);
+    }
+
+    /// Select a healthier mirror of a HiPS if the one currently queried keeps failing
+    pub fn select_mirror<'a>(&self, hips_url: &str, mirrors: &'a [Url]) -> Option<&'a Url> {
+        self.mirrors.select(hips_url, mirrors, Time::now())
+    }
+
     pub fn cache_rsc(&mut self, rsc: Resource) {
-        self.cache.insert(rsc.url().clone(), rsc);
+        // A tile that failed may exist, it must be requested again
+        if let Resource::Tile(tile) = &rsc {
+            if tile.failed() {
+                return;
+            }
+        }
+
+        let num_bytes = rsc.num_bytes();
+        self.cache.insert(rsc.url().clone(), rsc, num_bytes);
+    }
+
+    /// Set the memory the cached resources can take, the least recently used ones are evicted if needed
+    pub fn set_cache_capacity(&mut self, num_bytes: usize) {
+        self.cache.set_capacity(num_bytes);
+    }
+
+    pub fn get_cache_num_bytes(&self) -> usize {
+        self.cache.num_bytes()
+    }
+
+    /// Memory taken by the cached resources of a HiPS
+    pub fn get_hips_cache_num_bytes(&self, hips_url: &str) -> usize {
+        self.cache.iter()
+            .filter(|(url, _, _)| url.starts_with(&format!("{}/", hips_url)))
+            .map(|(_, _, num_bytes)| num_bytes)
+            .sum()
+    }
+
+    /// Remove the cached resources of a HiPS, e.g. when its files are read from another source
+    pub fn remove_hips_cached_rscs(&mut self, hips_url: &str) {
+        let urls = self.cache.iter()
+            .filter(|(url, _, _)| url.starts_with(&format!("{}/", hips_url)))
+            .map(|(url, _, _)| url.clone())
+            .collect::<Vec<_>>();
+
+        for url in urls {
+            self.cache.extract(&url);
+        }
     }
 
-    pub fn delay_rsc(&mut self, rsc: Resource) {
-        self.queried_cached_urls.push(rsc.url().clone());
-        self.cache.insert(rsc.url().clone(), rsc);
+    pub fn get_cache_hits_misses(&self) -> (u32, u32) {
+        (self.num_cache_hits, self.num_cache_misses)
     }
 }
@@ -14,27 +14,44 @@ use al_core::image::format::ImageFormatType;
 pub struct Tile {
     pub cell: HEALPixCell,
     pub format: ImageFormatType,
+    // The frame of the tile for HiPS cubes
+    pub cube_frame: Option<u32>,
     // The root url of the HiPS
     pub hips_url: Url,
+    // The path of the tile relative to the root of the HiPS
+    pub path: String,
     // The total url of the query
     pub url: Url,
+    // Where the tile is read from
+    pub source: Rc<dyn TileSource>,
+}
+
+// Suffix of the tile files of a HiPS cube frame
+fn get_cube_frame_suffix(cube_frame: Option<u32>) -> String {
+    cube_frame
+        .map(|frame| format!("_{}", frame))
+        .unwrap_or_default()
 }
 
 use crate::{healpix::cell::HEALPixCell, survey::config::HiPSConfig};
+use super::source::TileSource;
+use std::rc::Rc;
 impl Tile {
     pub fn new(cell: &HEALPixCell, cfg: &HiPSConfig) -> Self {
         let hips_url = cfg.get_root_url().clone();
         let format = cfg.get_format();
         let ext = format.get_ext_file();
+        let cube_frame = cfg.get_cube_frame();
 
         let HEALPixCell(depth, idx) = *cell;
 
         let dir_idx = (idx / 10000) * 10000;
 
-        let url = format!(
-            "{}/Norder{}/Dir{}/Npix{}.{}",
-            hips_url, depth, dir_idx, idx, ext
+        let path = format!(
+            "Norder{}/Dir{}/Npix{}{}.{}",
+            depth, dir_idx, idx, get_cube_frame_suffix(cube_frame), ext
         );
+        let url = format!("{}/{}", hips_url, path);
 
         // Check if this is a root tile
         /*let delta_depth = cfg.delta_depth();
@@ -47,9 +64,12 @@ impl Tile {
 
         Tile {
             hips_url,
+            path,
             url,
             cell: *cell,
             format,
+            cube_frame,
+            source: cfg.get_tile_source().clone(),
         }
     }
 }
@@ -72,10 +92,16 @@ pub struct Allsky {
     pub format: ImageFormatType,
     pub tile_size: i32,
     pub texture_size: i32,
+    // The frame of the allsky for HiPS cubes
+    pub cube_frame: Option<u32>,
     // The root url of the HiPS
     pub hips_url: Url,
+    // The path of the allsky relative to the root of the HiPS
+    pub path: String,
     // The total url of the query
     pub url: Url,
+    // Where the allsky is read from
+    pub source: Rc<dyn TileSource>,
 }
 
 impl Allsky {
@@ -85,15 +111,20 @@ impl Allsky {
         let texture_size = cfg.get_texture_size();
         let format = cfg.get_format();
         let ext = format.get_ext_file();
+        let cube_frame = cfg.get_cube_frame();
 
-        let url = format!("{}/Norder3/Allsky.{}", hips_url, ext);
+        let path = format!("Norder3/Allsky{}.{}", get_cube_frame_suffix(cube_frame), ext);
+        let url = format!("{}/{}", hips_url, path);
 
         Allsky {
             tile_size,
             texture_size,
+            cube_frame,
             hips_url,
+            path,
             url,
             format,
+            source: cfg.get_tile_source().clone(),
         }
     }
 }
@@ -117,8 +148,12 @@ pub struct PixelMetadata {
     pub format: ImageFormatType,
     // The root url of the HiPS
     pub hips_url: Url,
+    // The path of the file relative to the root of the HiPS
+    pub path: String,
     // The total url of the query
     pub url: Url,
+    // Where the file is read from
+    pub source: Rc<dyn TileSource>,
 }
 
 impl PixelMetadata {
@@ -127,12 +162,15 @@ impl PixelMetadata {
         let format = cfg.get_format();
         let ext = format.get_ext_file();
 
-        let url = format!("{}/Norder3/Allsky.{}", hips_url, ext);
+        let path = format!("Norder3/Allsky{}.{}", get_cube_frame_suffix(cfg.get_cube_frame()), ext);
+        let url = format!("{}/{}", hips_url, path);
 
         PixelMetadata {
             hips_url,
+            path,
             url,
             format,
+            source: cfg.get_tile_source().clone(),
         }
     }
 }
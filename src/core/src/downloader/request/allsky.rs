@@ -12,13 +12,17 @@ use fitsrs::{
 
 use super::{Request, RequestType};
 use crate::downloader::QueryId;
+use std::sync::{Arc, Mutex};
 pub struct AllskyRequest {
     pub hips_url: Url,
     pub url: Url,
     pub depth_tile: u8,
+    pub cube_frame: Option<u32>,
     pub id: QueryId,
 
     request: Request<Vec<ImageType>>,
+    // Statistics of the pixel values of a FITS allsky
+    stats: Arc<Mutex<Option<AllskyStats>>>,
 }
 
 impl From<AllskyRequest> for RequestType {
@@ -28,55 +32,11 @@ impl From<AllskyRequest> for RequestType {
 }
 
 use crate::renderable::Url;
-use wasm_bindgen_futures::JsFuture;
-use web_sys::{RequestInit, RequestMode, Response};
 
 use al_core::{image::raw::ImageBuffer, texture::pixel::Pixel};
-use wasm_bindgen::JsCast;
 use crate::downloader::query::Query;
 use wasm_bindgen::JsValue;
 
-async fn query_image(url: &str) -> Result<ImageBuffer<RGBA8U>, JsValue> {
-    let image = web_sys::HtmlImageElement::new().unwrap_abort();
-    let image_cloned = image.clone();
-
-    let html_img_elt_promise = js_sys::Promise::new(
-        &mut (Box::new(move |resolve, reject| {
-            image_cloned.set_cross_origin(Some(""));
-            image_cloned.set_onload(
-                Some(&resolve)
-            );
-            image_cloned.set_onerror(
-                Some(&reject)
-            );
-            image_cloned.set_src(&url);
-        }) as Box<dyn FnMut(js_sys::Function, js_sys::Function)>)
-    );
-
-    let _ = JsFuture::from(html_img_elt_promise).await?;
-
-    // The image has been received here
-    let document = web_sys::window().unwrap_abort().document().unwrap_abort();
-    let canvas = document
-        .create_element("canvas")?
-        .dyn_into::<web_sys::HtmlCanvasElement>()?;
-    canvas.set_width(image.width());
-    canvas.set_height(image.height());
-    let context = canvas
-        .get_context("2d")?
-        .unwrap_abort()
-        .dyn_into::<web_sys::CanvasRenderingContext2d>()?;
-    context.draw_image_with_html_image_element(&image, 0.0, 0.0)?;
-    
-    let w = image.width();
-    let h = image.height();
-    let image_data = context.get_image_data(0.0, 0.0, w as f64, h as f64)?;
-
-    let raw_bytes = image_data.data();
-
-    Ok(ImageBuffer::from_raw_bytes(raw_bytes.0, w as i32, h as i32))
-}
-
 impl From<query::Allsky> for AllskyRequest {
     // Create a tile request associated to a HiPS
     fn from(query: query::Allsky) -> Self {
@@ -86,121 +46,132 @@ impl From<query::Allsky> for AllskyRequest {
             tile_size,
             url,
             hips_url,
+            path,
+            source,
             texture_size,
+            cube_frame,
         } = query;
 
         let depth_tile = crate::math::utils::log_2_unchecked(texture_size / tile_size) as u8;
         let channel = format.get_channel();
-        let url_clone = url.clone();
-
-        let request = Request::new(async move {
-            match channel {
-                ChannelType::RGB8U => {
-                    let allsky_tile_size = std::cmp::min(tile_size, 64);
-                    let allsky = query_image(&url_clone).await?;
-
-                    let allsky_tiles = handle_allsky_file::<RGBA8U>(allsky, allsky_tile_size, texture_size, tile_size)?
-                        .into_iter()
-                        .map(|image| {
-                            let ImageBuffer { data, size } = image;
-                            let data = data.into_iter().enumerate().filter(|&(i, _)| i % 4 != 3).map(|(_, v)| v).collect();
-                            let image = ImageBuffer::new(data, size.x, size.y);
-
-                            ImageType::RawRgb8u { image }
-                        })
-                        .collect();
-
-                    Ok(allsky_tiles)
-                }
-                ChannelType::RGBA8U => {
-                    let allsky_tile_size = std::cmp::min(tile_size, 64);
-                    let allsky = query_image(&url_clone).await?;
+        let hips_url_clone = hips_url.clone();
+        let stats = Arc::new(Mutex::new(None));
+        let stats_clone = stats.clone();
+
+        let request = Request::with_retries(move || {
+            let source = source.clone();
+            let hips_url_clone = hips_url_clone.clone();
+            let path = path.clone();
+            let stats_clone = stats_clone.clone();
+
+            async move {
+                match channel {
+                    ChannelType::RGB8U => {
+                        let allsky_tile_size = std::cmp::min(tile_size, 64);
+                        let allsky = source.fetch_rgba_buffer(&hips_url_clone, &path).await?;
+
+                        let allsky_tiles = handle_allsky_file::<RGBA8U>(allsky, allsky_tile_size, texture_size, tile_size)?
+                            .into_iter()
+                            .map(|image| {
+                                let ImageBuffer { data, size } = image;
+                                let data = data.into_iter().enumerate().filter(|&(i, _)| i % 4 != 3).map(|(_, v)| v).collect();
+                                let image = ImageBuffer::new(data, size.x, size.y);
+
+                                ImageType::RawRgb8u { image }
+                            })
+                            .collect();
+
+                        Ok(allsky_tiles)
+                    }
+                    ChannelType::RGBA8U => {
+                        let allsky_tile_size = std::cmp::min(tile_size, 64);
+                        let allsky = source.fetch_rgba_buffer(&hips_url_clone, &path).await?;
 
-                    let allsky_tiles = handle_allsky_file(allsky, allsky_tile_size, texture_size, tile_size)?
-                        .into_iter()
-                        .map(|image| ImageType::RawRgba8u { image })
-                        .collect();
+                        let allsky_tiles = handle_allsky_file(allsky, allsky_tile_size, texture_size, tile_size)?
+                            .into_iter()
+                            .map(|image| ImageType::RawRgba8u { image })
+                            .collect();
 
-                    Ok(allsky_tiles)
-                }
-                _ => {
-                    let mut opts = RequestInit::new();
-                    opts.method("GET");
-                    opts.mode(RequestMode::Cors);
-                    let window = web_sys::window().unwrap_abort();
-        
-                    let request = web_sys::Request::new_with_str_and_init(&url_clone, &opts)?;
-                    let resp_value = JsFuture::from(window.fetch_with_request(&request)).await?;
-                    // `resp_value` is a `Response` object.
-                    debug_assert!(resp_value.is_instance_of::<Response>());
-                    let resp: Response = resp_value.dyn_into()?;
-                    // See https://github.com/MattiasBuelens/wasm-streams/blob/f6dacf58a8826dc67923ab4a3bae87635690ca64/examples/fetch_as_stream.rs#L25-L33
-                    /*let raw_body = resp.body().ok_or(JsValue::from_str("Cannot extract readable stream"))?;
-                    let body = ReadableStream::from_raw(raw_body.dyn_into()?);
-    
-                    // Convert the JS ReadableStream to a Rust stream
-                    let mut reader = body.try_into_async_read().map_err(|_| JsValue::from_str("readable stream locked"))?;*/
-                    
-                    let array_buffer = JsFuture::from(resp.array_buffer()?).await?;
-                    let bytes_buffer = js_sys::Uint8Array::new(&array_buffer);
-    
-                    let num_bytes = bytes_buffer.length() as usize;
-                    let mut raw_bytes = Vec::with_capacity(num_bytes);
-                    unsafe { raw_bytes.set_len(num_bytes); }
-                    bytes_buffer.copy_to(&mut raw_bytes[..]);
-                    let mut reader = Cursor::new(&raw_bytes[..]);
-                    let Fits { hdu } = Fits::from_reader(&mut reader)
-                        .map_err(|_| {
-                            JsValue::from_str("Parsing fits error of allsky")
-                        })?;
+                        Ok(allsky_tiles)
+                    }
+                    _ => {
+                        let raw_bytes = source.fetch_bytes(&hips_url_clone, &path).await?;
+                        let mut reader = Cursor::new(&raw_bytes[..]);
+                        let Fits { hdu } = Fits::from_reader(&mut reader)
+                            .map_err(|_| {
+                                JsValue::from_str("Parsing fits error of allsky")
+                            })?;
         
-                    //let width_allsky_px = 27 * std::cmp::min(tile_size, 64) as i32;
-                    //let height_allsky_px = 29 * std::cmp::min(tile_size, 64) as i32;
-                    let data = hdu.get_data();
-
-                    match data {
-                        InMemData::U8(data) => {
-                            Ok(
-                                handle_allsky_fits(&data, tile_size, texture_size)?
-                                    .into_iter()
+                        //let width_allsky_px = 27 * std::cmp::min(tile_size, 64) as i32;
+                        //let height_allsky_px = 29 * std::cmp::min(tile_size, 64) as i32;
+                        let header = hdu.get_header();
+                        let get_value = |key: &[u8; 8]| match header.get(key) {
+                            Some(fitsrs::card::Value::Float(v)) => Some(*v as f32),
+                            Some(fitsrs::card::Value::Integer(v)) => Some(*v as f32),
+                            _ => None,
+                        };
+                        let pixel_params = PixelParams {
+                            scale: get_value(b"BSCALE  ").unwrap_or(1.0),
+                            offset: get_value(b"BZERO   ").unwrap_or(0.0),
+                            blank: get_value(b"BLANK   "),
+                        };
+
+                        let data = hdu.get_data();
+
+                        let allsky_tiles = match data {
+                            InMemData::U8(data) => {
+                                let (tiles, stats) = handle_allsky_fits(&data, tile_size, texture_size, &pixel_params)?;
+                                *stats_clone.lock().unwrap_abort() = stats;
+
+                                tiles.into_iter()
                                     .map(|image| ImageType::RawR8ui { image })
                                     .collect()
-                            )
-                        }
-                        InMemData::I16(data) => {
-                            Ok(
-                                handle_allsky_fits(&data, tile_size, texture_size)?
-                                    .into_iter()
+                            }
+                            InMemData::I16(data) => {
+                                let (tiles, stats) = handle_allsky_fits(&data, tile_size, texture_size, &pixel_params)?;
+                                *stats_clone.lock().unwrap_abort() = stats;
+
+                                tiles.into_iter()
                                     .map(|image| ImageType::RawR16i { image })
                                     .collect()
-                            )
-                        }
-                        InMemData::I32(data) => {
-                            Ok(handle_allsky_fits(&data, tile_size, texture_size)?
-                                .into_iter()
-                                .map(|image| ImageType::RawR32i { image })
-                                .collect())
-                        }
-                        InMemData::F32(data) => {
-                            Ok(handle_allsky_fits(&data, tile_size, texture_size)?
-                                .into_iter()
-                                .map(|image| ImageType::RawR32f { image })
-                                .collect())
-                        }
-                        InMemData::I64(data) => {
-                            let data = data.iter().map(|v| *v as i32).collect::<Vec<_>>();
-                            Ok(handle_allsky_fits(&data, tile_size, texture_size)?
-                                .into_iter()
-                                .map(|image| ImageType::RawR32i { image })
-                                .collect())
-                        },
-                        InMemData::F64(data) => {
-                            let data = data.iter().map(|v| *v as f32).collect::<Vec<_>>();
-                            Ok(handle_allsky_fits(&data, tile_size, texture_size)?
-                                .into_iter()
-                                .map(|image| ImageType::RawR32f { image })
-                                .collect())
-                        }
+                            }
+                            InMemData::I32(data) => {
+                                let (tiles, stats) = handle_allsky_fits(&data, tile_size, texture_size, &pixel_params)?;
+                                *stats_clone.lock().unwrap_abort() = stats;
+
+                                tiles.into_iter()
+                                    .map(|image| ImageType::RawR32i { image })
+                                    .collect()
+                            }
+                            InMemData::F32(data) => {
+                                let (tiles, stats) = handle_allsky_fits(&data, tile_size, texture_size, &pixel_params)?;
+                                *stats_clone.lock().unwrap_abort() = stats;
+
+                                tiles.into_iter()
+                                    .map(|image| ImageType::RawR32f { image })
+                                    .collect()
+                            }
+                            InMemData::I64(data) => {
+                                let data = data.iter().map(|v| *v as i32).collect::<Vec<_>>();
+                                let (tiles, stats) = handle_allsky_fits(&data, tile_size, texture_size, &pixel_params)?;
+                                *stats_clone.lock().unwrap_abort() = stats;
+
+                                tiles.into_iter()
+                                    .map(|image| ImageType::RawR32i { image })
+                                    .collect()
+                            },
+                            InMemData::F64(data) => {
+                                let data = data.iter().map(|v| *v as f32).collect::<Vec<_>>();
+                                let (tiles, stats) = handle_allsky_fits(&data, tile_size, texture_size, &pixel_params)?;
+                                *stats_clone.lock().unwrap_abort() = stats;
+
+                                tiles.into_iter()
+                                    .map(|image| ImageType::RawR32f { image })
+                                    .collect()
+                            }
+                        };
+
+                        Ok(allsky_tiles)
                     }
                 }
             }
@@ -210,8 +181,10 @@ impl From<query::Allsky> for AllskyRequest {
             id,
             hips_url,
             depth_tile,
+            cube_frame,
             url,
             request,
+            stats,
         }
     }
 }
@@ -264,14 +237,78 @@ fn handle_allsky_file<F: ImageFormat>(
     Ok(tiles)
 }
 
+// Parameters giving the physical values of the pixels of a FITS allsky
+struct PixelParams {
+    scale: f32,
+    offset: f32,
+    blank: Option<f32>,
+}
+
+/// Statistics of the physical values of a FITS allsky
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct AllskyStats {
+    /// Minimum and maximum values
+    pub range: [f32; 2],
+    /// 0.5% and 99.5% percentiles
+    pub cuts: [f32; 2],
+}
+
+impl AllskyStats {
+    // Blank and NaN pixels are discarded
+    fn new(values: impl Iterator<Item = f32>, params: &PixelParams) -> Option<Self> {
+        let mut values = values
+            .filter(|v| !v.is_nan() && Some(*v) != params.blank)
+            .map(|v| v * params.scale + params.offset)
+            .collect::<Vec<_>>();
+
+        if values.is_empty() {
+            return None;
+        }
+
+        let cmp = |a: &f32, b: &f32| a.partial_cmp(b).unwrap_or(std::cmp::Ordering::Equal);
+        let n = values.len();
+        let (low_idx, high_idx) = (n / 200, n - 1 - n / 200);
+
+        let low = *values.select_nth_unstable_by(low_idx, cmp).1;
+        let high = *values.select_nth_unstable_by(high_idx, cmp).1;
+        let min = values.iter().copied().fold(f32::INFINITY, f32::min);
+        let max = values.iter().copied().fold(f32::NEG_INFINITY, f32::max);
+
+        Some(AllskyStats {
+            range: [min, max],
+            cuts: [low, high],
+        })
+    }
+}
+
 fn handle_allsky_fits<F: ImageFormat>(
     allsky_data: &[<<F as ImageFormat>::P as Pixel>::Item],
     tile_size: i32,
     texture_size: i32,
-) -> Result<Vec<ImageBuffer<F>>, JsValue> {
+    pixel_params: &PixelParams,
+) -> Result<(Vec<ImageBuffer<F>>, Option<AllskyStats>), JsValue>
+where
+    <<F as ImageFormat>::P as Pixel>::Item: Into<f64>,
+{
     let allsky_tile_size = std::cmp::min(tile_size, 64);
     let width_allsky_px = 27 * allsky_tile_size;
     let height_allsky_px = 29 * allsky_tile_size;
+
+    // Computed to give default cuts to the HiPS not giving them in its properties
+    // The tiles fill the allsky from its top left corner, the end of its last row being
+    // zero padded. The padding must not be taken into account
+    let num_allsky_tiles = 12 * (texture_size / allsky_tile_size) * (texture_size / allsky_tile_size);
+    let tile_values = allsky_data.iter()
+        .enumerate()
+        .filter(|(idx, _)| {
+            // The rows of the FITS data are stored from the bottom of the image
+            let y = height_allsky_px - 1 - (*idx as i32) / width_allsky_px;
+            let x = (*idx as i32) % width_allsky_px;
+
+            (y / allsky_tile_size) * 27 + x / allsky_tile_size < num_allsky_tiles
+        })
+        .map(|(_, v)| (*v).into() as f32);
+    let stats = AllskyStats::new(tile_values, pixel_params);
     // The fits image layout stores rows in reverse
     let reversed_rows_data = allsky_data
         .chunks(width_allsky_px as usize)
@@ -297,17 +334,19 @@ fn handle_allsky_fits<F: ImageFormat>(
         })
         .collect();
 
-    Ok(allsky_tiles)
+    Ok((allsky_tiles, stats))
 }
 
 use al_core::image::format::RGBA8U;
 
 use crate::time::Time;
-use std::sync::{Arc, Mutex};
+#[derive(Clone)]
 pub struct Allsky {
     pub image: Arc<Mutex<Option<Vec<ImageType>>>>,
     pub time_req: Time,
     pub depth_tile: u8,
+    pub cube_frame: Option<u32>,
+    pub stats: Option<AllskyStats>,
 
     pub hips_url: Url,
     url: Url,
@@ -335,7 +374,9 @@ impl<'a> From<&'a AllskyRequest> for Option<Allsky> {
             request,
             hips_url,
             depth_tile,
+            cube_frame,
             url,
+            stats,
             ..
         } = request;
         if request.is_resolved() {
@@ -349,9 +390,52 @@ impl<'a> From<&'a AllskyRequest> for Option<Allsky> {
                 hips_url: hips_url.clone(),
                 url: url.clone(),
                 depth_tile: *depth_tile,
+                cube_frame: *cube_frame,
+                stats: *stats.lock().unwrap_abort(),
             })
         } else {
             None
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{handle_allsky_fits, AllskyStats, PixelParams};
+    use al_core::image::format::R32F;
+
+    #[test]
+    fn test_allsky_stats() {
+        // 48 tiles of 4x4 pixels laid out 27 by row
+        let (tile_size, texture_size) = (4, 8);
+        let (width, height) = (27 * 4, 29 * 4);
+
+        // The tiles are given the values 1, 2, ... and the padding is zero
+        let mut num_values = 0;
+        let data = (0..width * height)
+            .map(|idx| {
+                let y = height - 1 - idx / width;
+                let x = idx % width;
+                if (y / 4) * 27 + x / 4 < 48 {
+                    num_values += 1;
+                    num_values as f32
+                } else {
+                    0.0
+                }
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(num_values, 768);
+
+        let params = PixelParams { scale: 1.0, offset: 0.0, blank: None };
+        let (tiles, stats) = handle_allsky_fits::<R32F>(&data, tile_size, texture_size, &params).unwrap();
+        assert_eq!(tiles.len(), 48);
+
+        // The 0.5% percentiles of 768 values are the 4th and the 765th ones
+        assert_eq!(stats, Some(AllskyStats { range: [1.0, 768.0], cuts: [4.0, 765.0] }));
+
+        // The physical values are given by BSCALE and BZERO
+        let params = PixelParams { scale: 2.0, offset: -1.0, blank: Some(1.0) };
+        let (_, stats) = handle_allsky_fits::<R32F>(&data, tile_size, texture_size, &params).unwrap();
+        assert_eq!(stats.map(|stats| stats.range), Some([3.0, 1535.0]));
+    }
+}
@@ -19,9 +19,7 @@ impl From<MOCRequest> for RequestType {
     }
 }
 use crate::renderable::Url;
-use wasm_bindgen_futures::JsFuture;
-use web_sys::{RequestInit, RequestMode, Response};
-use wasm_bindgen::JsCast;
+use crate::downloader::source::fetch;
 use moclib::deser::fits;
 
 use moclib::moc::range::op::convert::convert_to_u64;
@@ -45,7 +43,6 @@ use moclib::deser::fits::MocIdxType;
 use moclib::deser::fits::MocQtyType;
 use wasm_bindgen::JsValue;
 use crate::healpix::coverage::HEALPixCoverage;
-use crate::Abort;
 impl From<query::Moc> for MOCRequest {
     // Create a tile request associated to a HiPS
     fn from(query: query::Moc) -> Self {
@@ -57,34 +54,22 @@ impl From<query::Moc> for MOCRequest {
 
         let url_clone = url.clone();
 
-        let window = web_sys::window().unwrap_abort();
-        let request =  Request::new(async move {
-            let mut opts = RequestInit::new();
-            opts.method("GET");
-            opts.mode(RequestMode::Cors);
+        let request = Request::with_retries(move || {
+            let url_clone = url_clone.clone();
 
-            let request = web_sys::Request::new_with_str_and_init(&url_clone, &opts).unwrap_abort();
-            let resp_value = JsFuture::from(window.fetch_with_request(&request)).await?;
-            // `resp_value` is a `Response` object.
-            debug_assert!(resp_value.is_instance_of::<Response>());
-            let resp: Response = resp_value.dyn_into()?;
-            let array_buffer = JsFuture::from(resp.array_buffer()?).await?;
+            async move {
+                let bytes = fetch(&url_clone).await?;
 
-            let bytes_buf = js_sys::Uint8Array::new(&array_buffer);
-            let num_bytes = bytes_buf.length() as usize;
-            let mut bytes = Vec::with_capacity(num_bytes);
-            unsafe { bytes.set_len(num_bytes); }
-            bytes_buf.copy_to(&mut bytes[..]);
-    
-            // Coosys is permissive because we load a moc
-            let smoc = match fits::from_fits_ivoa_custom(Cursor::new(&bytes[..]), true).map_err(|e| JsValue::from_str(&e.to_string()))? {
-                MocIdxType::U16(MocQtyType::<u16, _>::Hpx(moc)) => Ok(from_fits_hpx(moc)),
-                MocIdxType::U32(MocQtyType::<u32, _>::Hpx(moc)) => Ok(from_fits_hpx(moc)),
-                MocIdxType::U64(MocQtyType::<u64, _>::Hpx(moc)) => Ok(from_fits_hpx(moc)),
-                _ => Err(JsValue::from_str("MOC not supported. Must be a HPX MOC"))
-            }?;
+                // Coosys is permissive because we load a moc
+                let smoc = match fits::from_fits_ivoa_custom(Cursor::new(&bytes[..]), true).map_err(|e| JsValue::from_str(&e.to_string()))? {
+                    MocIdxType::U16(MocQtyType::<u16, _>::Hpx(moc)) => Ok(from_fits_hpx(moc)),
+                    MocIdxType::U32(MocQtyType::<u32, _>::Hpx(moc)) => Ok(from_fits_hpx(moc)),
+                    MocIdxType::U64(MocQtyType::<u64, _>::Hpx(moc)) => Ok(from_fits_hpx(moc)),
+                    _ => Err(JsValue::from_str("MOC not supported. Must be a HPX MOC"))
+                }?;
 
-            Ok(HEALPixCoverage(smoc))
+                Ok(HEALPixCoverage(smoc))
+            }
         });
 
         Self {
@@ -97,6 +82,7 @@ impl From<query::Moc> for MOCRequest {
 }
 
 use std::sync::{Arc, Mutex};
+#[derive(Clone)]
 pub struct Moc {
     pub moc: Arc<Mutex<Option<HEALPixCoverage>>>,
     pub params: al_api::moc::MOC,
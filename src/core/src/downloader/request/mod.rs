@@ -8,9 +8,13 @@ pub mod moc;
 /* ------------------------------------- */
 
 use crate::{time::Time};
+use crate::Abort;
 use std::cell::Cell;
+use std::future::Future;
 use std::rc::Rc;
 use std::sync::{Arc, Mutex};
+use wasm_bindgen::JsValue;
+
 pub struct Request<R> {
     data: Arc<Mutex<Option<R>>>,
     time_request: Time,
@@ -18,17 +22,104 @@ pub struct Request<R> {
     // the HtmlImageElement can be reused to download another tile
     //ready: bool,
     resolved: Rc<Cell<ResolvedStatus>>,
+    // Number of times the request has been retried
+    num_retries: Rc<Cell<u32>>,
 }
 
-#[derive(Clone, Copy, PartialEq)]
+#[derive(Clone, Copy, PartialEq, Debug)]
 pub enum ResolvedStatus {
     NotResolved,
+    // The request has failed, possibly after several retries
     Failed,
+    // The resource does not exist on the server (e.g. HTTP 404)
+    Missing,
     Found,
 }
-use crate::Abort;
-use std::future::Future;
-use wasm_bindgen::JsValue;
+
+/// Error of a request that can be retried
+#[derive(Debug, PartialEq)]
+pub enum RequestError {
+    /// The resource does not exist. Retrying is useless
+    NotFound,
+    /// Server errors (HTTP 5xx), network resets, ...
+    Transient(String),
+    /// The server refuses the request (HTTP 401, 403), e.g. for a missing authorization
+    /// or a rate limit. Retrying at once is useless but the request can succeed later
+    Denied(String),
+}
+
+impl From<JsValue> for RequestError {
+    fn from(err: JsValue) -> Self {
+        RequestError::Transient(err.as_string().unwrap_or_else(|| format!("{:?}", err)))
+    }
+}
+
+impl From<RequestError> for JsValue {
+    fn from(err: RequestError) -> Self {
+        match err {
+            RequestError::NotFound => JsValue::from_str("Resource not found"),
+            RequestError::Transient(err) | RequestError::Denied(err) => JsValue::from_str(&err),
+        }
+    }
+}
+
+impl RequestError {
+    /// Classify an HTTP error status
+    pub fn from_status(status: u16) -> Self {
+        match status {
+            // Request timeout and too many requests can succeed later
+            408 | 429 => RequestError::Transient(format!("HTTP status {}", status)),
+            401 | 403 => RequestError::Denied(format!("HTTP status {}", status)),
+            400..=499 => RequestError::NotFound,
+            _ => RequestError::Transient(format!("HTTP status {}", status)),
+        }
+    }
+}
+
+// Maximum number of retries of a request failing with transient errors
+const MAX_NUM_RETRIES: u32 = 4;
+// Delay before the first retry in ms. It doubles at each retry
+const RETRY_BASE_DELAY: f64 = 500.0;
+
+async fn sleep(ms: i32) -> Result<(), JsValue> {
+    let promise = js_sys::Promise::new(&mut |resolve, _| {
+        if let Some(window) = web_sys::window() {
+            let _ = window.set_timeout_with_callback_and_timeout_and_arguments_0(&resolve, ms);
+        }
+    });
+    wasm_bindgen_futures::JsFuture::from(promise).await?;
+
+    Ok(())
+}
+
+/// Call `f` until it succeeds, retrying with an exponential backoff
+///
+/// Transient errors are retried at most `MAX_NUM_RETRIES` times whereas absent
+/// resources and denied requests are not. `num_retries` counts the retries done.
+pub async fn retry<R, F, Fut>(f: F, num_retries: &Cell<u32>) -> Result<R, RequestError>
+where
+    F: Fn() -> Fut,
+    Fut: Future<Output = Result<R, RequestError>>,
+{
+    loop {
+        match f().await {
+            Err(RequestError::Transient(err)) => {
+                let n = num_retries.get();
+                if n >= MAX_NUM_RETRIES {
+                    return Err(RequestError::Transient(err));
+                }
+
+                // Add some jitter so that the failed requests are not sent all together
+                let delay = RETRY_BASE_DELAY * (1 << n) as f64 * (1.0 + 0.5 * js_sys::Math::random());
+                sleep(delay as i32).await?;
+                num_retries.set(n + 1);
+            }
+            // Absent resources and denied requests are not retried
+            res => return res,
+        }
+    }
+}
+
 impl<R> Request<R>
 where
     R: 'static
@@ -64,17 +155,69 @@ where
             data,
             resolved,
             time_request,
+            num_retries: Rc::new(Cell::new(0)),
+        }
+    }
+
+    /// Create a request retried with an exponential backoff
+    ///
+    /// `f` is called each time the request is sent, see [`retry`].
+    /// All the resources are requested that way.
+    pub fn with_retries<F, Fut>(f: F) -> Self
+    where
+        F: Fn() -> Fut + 'static,
+        Fut: Future<Output = Result<R, RequestError>> + 'static,
+    {
+        let resolved = Rc::new(Cell::new(ResolvedStatus::NotResolved));
+        let num_retries = Rc::new(Cell::new(0));
+        let time_request = Time::now();
+
+        let data = Arc::new(Mutex::new(None));
+
+        {
+            let data_cloned = data.clone();
+            let resolved_cloned = resolved.clone();
+            let num_retries_cloned = num_retries.clone();
+
+            let fut = async move {
+                match retry(f, &num_retries_cloned).await {
+                    Ok(resp) => {
+                        *(data_cloned.lock().unwrap_abort()) = Some(resp);
+                        resolved_cloned.set(ResolvedStatus::Found);
+                    }
+                    Err(RequestError::NotFound) => {
+                        resolved_cloned.set(ResolvedStatus::Missing);
+                    }
+                    // The failed tiles can be requested again, see `reset_failed_tiles`
+                    Err(RequestError::Transient(err)) | Err(RequestError::Denied(err)) => {
+                        al_core::log::console_warn(&err);
+                        resolved_cloned.set(ResolvedStatus::Failed);
+                    }
+                }
+            };
+
+            wasm_bindgen_futures::spawn_local(fut);
+        }
+
+        Self {
+            data,
+            resolved,
+            time_request,
+            num_retries,
         }
     }
 
     pub fn is_resolved(&self) -> bool {
-        let resolved = self.resolve_status();
-        resolved == ResolvedStatus::Found || resolved == ResolvedStatus::Failed
+        self.resolve_status() != ResolvedStatus::NotResolved
     }
 
     pub fn resolve_status(&self) -> ResolvedStatus {
         self.resolved.get()
     }
+
+    pub fn get_num_retries(&self) -> u32 {
+        self.num_retries.get()
+    }
 }
 
 use allsky::AllskyRequest;
@@ -124,6 +267,7 @@ use allsky::Allsky;
 use tile::Tile;
 use blank::PixelMetadata;
 use moc::Moc;
+#[derive(Clone)]
 pub enum Resource {
     Tile(Tile),
     Allsky(Allsky),
@@ -140,4 +284,37 @@ impl Resource {
             Resource::Moc(moc) => moc.get_url(),
         }
     }
+
+    /// Memory taken by the resource in bytes
+    pub fn num_bytes(&self) -> usize {
+        match self {
+            Resource::Tile(tile) => tile.image.lock().unwrap_abort()
+                .as_ref()
+                .map(|image| image.num_bytes())
+                .unwrap_or(0),
+            Resource::Allsky(allsky) => allsky.image.lock().unwrap_abort()
+                .as_ref()
+                .map(|images| images.iter().map(|image| image.num_bytes()).sum())
+                .unwrap_or(0),
+            Resource::PixelMetadata(_) => std::mem::size_of::<blank::Metadata>(),
+            // A MOC is stored as a list of ranges of 2 u64
+            Resource::Moc(moc) => moc.moc.lock().unwrap_abort()
+                .as_ref()
+                .map(|moc| 16 * moc.len())
+                .unwrap_or(0),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::RequestError;
+
+    #[test]
+    fn test_request_error_from_status() {
+        assert_eq!(RequestError::from_status(404), RequestError::NotFound);
+        assert_eq!(RequestError::from_status(403), RequestError::Denied("HTTP status 403".to_string()));
+        assert!(matches!(RequestError::from_status(429), RequestError::Transient(_)));
+        assert!(matches!(RequestError::from_status(503), RequestError::Transient(_)));
+    }
 }
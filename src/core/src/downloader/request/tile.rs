@@ -1,10 +1,10 @@
 use crate::{healpix::cell::HEALPixCell};
-use al_core::image::format::{ChannelType, ImageFormatType, RGBA8U, RGB8U};
+use al_core::image::format::{ChannelType, ImageFormatType};
 
 use crate::downloader::query;
 use al_core::image::ImageType;
 
-use super::{Request, RequestType};
+use super::{Request, RequestType, ResolvedStatus};
 use crate::downloader::query::Query;
 use crate::downloader::QueryId;
 
@@ -15,45 +15,36 @@ pub struct TileRequest {
     hips_url: Url,
     url: Url,
     format: ImageFormatType,
+    cube_frame: Option<u32>,
 
     request: Request<ImageType>,
 }
 
+impl TileRequest {
+    pub fn get_hips_url(&self) -> &Url {
+        &self.hips_url
+    }
+
+    pub fn resolve_status(&self) -> ResolvedStatus {
+        self.request.resolve_status()
+    }
+
+    pub fn get_num_retries(&self) -> u32 {
+        self.request.get_num_retries()
+    }
+
+    pub fn get_time_request(&self) -> Time {
+        self.request.time_request
+    }
+}
+
 impl From<TileRequest> for RequestType {
     fn from(request: TileRequest) -> Self {
         RequestType::Tile(request)
     }
 }
 
-async fn query_html_image(url: &str) -> Result<HtmlImageElement, JsValue> {
-    let image = web_sys::HtmlImageElement::new().unwrap_abort();
-    let image_cloned = image.clone();
-
-    let html_img_elt_promise = js_sys::Promise::new(
-        &mut (Box::new(move |resolve, reject| {
-            // Ask for CORS permissions
-            image_cloned.set_cross_origin(Some(""));
-            image_cloned.set_onload(
-                Some(&resolve)
-            );
-            image_cloned.set_onerror(
-                Some(&reject)
-            );
-            image_cloned.set_src(&url);
-        }) as Box<dyn FnMut(js_sys::Function, js_sys::Function)>)
-    );
-
-    let _ = JsFuture::from(html_img_elt_promise).await?;
-
-    Ok(image)
-}
-
-use al_core::image::html::HTMLImage;
-use wasm_bindgen::JsValue;
 use crate::renderable::Url;
-use wasm_bindgen_futures::JsFuture;
-use web_sys::{RequestInit, RequestMode, Response, HtmlImageElement};
-use wasm_bindgen::JsCast;
 impl From<query::Tile> for TileRequest {
     // Create a tile request associated to a HiPS
     fn from(query: query::Tile) -> Self {
@@ -64,106 +55,22 @@ impl From<query::Tile> for TileRequest {
             cell,
             url,
             hips_url,
+            path,
+            cube_frame,
+            source,
         } = query;
 
-        let url_clone = url.clone();
+        let hips_url_clone = hips_url.clone();
         let channel = format.get_channel();
 
-        let window = web_sys::window().unwrap_abort();
         let request = match channel {
-            ChannelType::RGB8U => Request::new(async move {
-                /*let mut opts = RequestInit::new();
-                opts.method("GET");
-                opts.mode(RequestMode::Cors);
-
-                let request = web_sys::Request::new_with_str_and_init(&url_clone, &opts).unwrap_abort();
-                let resp_value = JsFuture::from(window.fetch_with_request(&request)).await?;
-                // `resp_value` is a `Response` object.
-                debug_assert!(resp_value.is_instance_of::<Response>());
-                let resp: Response = resp_value.dyn_into()?;*/
-
-                
-                /*/// Bitmap version
-                let blob = JsFuture::from(resp.blob()?).await?.into();
-                let image = JsFuture::from(window.create_image_bitmap_with_blob(&blob)?)
-                    .await?
-                    .into();
-
-                let image = Bitmap::new(image);
-                Ok(ImageType::JpgImageRgb8u { image })*/
-                /*
-                /// Raw image decoding
-                
-                let buf = JsFuture::from(resp.array_buffer()?).await?;
-                let raw_bytes = js_sys::Uint8Array::new(&buf).to_vec();
-                let image = ImageBuffer::<RGB8U>::from_raw_bytes(&raw_bytes[..], 512, 512)?;
-
-                Ok(ImageType::RawRgb8u { image })
-                */
-                // HTMLImageElement
-                let image = query_html_image(&url_clone).await?;
-                // The image has been resolved
-                Ok(ImageType::HTMLImageRgb8u { image: HTMLImage::<RGB8U>::new(image) })
-            }),
-            ChannelType::RGBA8U => Request::new(async move {
-                /*let mut opts = RequestInit::new();
-                opts.method("GET");
-                opts.mode(RequestMode::Cors);
-
-                let request = web_sys::Request::new_with_str_and_init(&url_clone, &opts).unwrap_abort();
-                let resp_value = JsFuture::from(window.fetch_with_request(&request)).await?;
-                // `resp_value` is a `Response` object.
-                debug_assert!(resp_value.is_instance_of::<Response>());
-                let resp: Response = resp_value.dyn_into()?;*/
-
-                
-                /*/// Bitmap version
-                let blob = JsFuture::from(resp.blob()?).await?.into();
-                let image = JsFuture::from(window.create_image_bitmap_with_blob(&blob)?)
-                    .await?
-                    .into();
-
-                let image = Bitmap::new(image);
-                Ok(ImageType::PngImageRgba8u { image })*/
-                
-                /*
-                /// Raw image decoding
-                let buf = JsFuture::from(resp.array_buffer()?).await?;
-                let raw_bytes = js_sys::Uint8Array::new(&buf).to_vec();
-                let image = ImageBuffer::<RGBA8U>::from_raw_bytes(&raw_bytes[..], 512, 512)?;
-
-                Ok(ImageType::RawRgba8u { image })
-                */
-                // HTMLImageElement
-                let image = query_html_image(&url_clone).await?;
-                // The image has been resolved
-                Ok(ImageType::HTMLImageRgba8u { image: HTMLImage::<RGBA8U>::new(image) })
+            ChannelType::RGB8U | ChannelType::RGBA8U => Request::with_retries(move || {
+                source.fetch_image(&hips_url_clone, &path, channel)
             }),
-            ChannelType::R32F | ChannelType::R64F | ChannelType::R32I | ChannelType::R16I | ChannelType::R8UI => Request::new(async move {
-                let mut opts = RequestInit::new();
-                opts.method("GET");
-                opts.mode(RequestMode::Cors);
-
-                let request = web_sys::Request::new_with_str_and_init(&url_clone, &opts).unwrap_abort();
-                let resp_value = JsFuture::from(window.fetch_with_request(&request)).await?;
-                // `resp_value` is a `Response` object.
-                debug_assert!(resp_value.is_instance_of::<Response>());
-                let resp: Response = resp_value.dyn_into()?;
-                // See https://github.com/MattiasBuelens/wasm-streams/blob/f6dacf58a8826dc67923ab4a3bae87635690ca64/examples/fetch_as_stream.rs#L25-L33
-                /*let raw_body = resp.body().ok_or(JsValue::from_str("Cannot extract readable stream"))?;
-                let body = ReadableStream::from_raw(raw_body.dyn_into()?);
-
-                // Convert the JS ReadableStream to a Rust stream
-                let mut reader = body.try_into_async_read().map_err(|_| JsValue::from_str("readable stream locked"))?;
-                let image = Fits::new(reader).await?;
-                */
-                if resp.ok() {
-                    let array_buffer = JsFuture::from(resp.array_buffer()?).await?;
-                    let raw_bytes = js_sys::Uint8Array::new(&array_buffer);
-
-                    Ok(ImageType::FitsImage { raw_bytes })
-                } else {
-                    Err(JsValue::from_str("Response status code not between 200-299."))
+            ChannelType::R32F | ChannelType::R64F | ChannelType::R32I | ChannelType::R16I | ChannelType::R8UI => Request::with_retries(move || {
+                let raw_bytes = source.fetch_bytes(&hips_url_clone, &path);
+                async move {
+                    Ok(ImageType::FitsImage { raw_bytes: js_sys::Uint8Array::from(&raw_bytes.await?[..]) })
                 }
             }),
             _ => todo!(),
@@ -172,6 +79,7 @@ impl From<query::Tile> for TileRequest {
         Self {
             cell,
             format,
+            cube_frame,
             id,
             hips_url,
             url,
@@ -182,11 +90,14 @@ impl From<query::Tile> for TileRequest {
 
 use crate::time::Time;
 use std::sync::{Arc, Mutex};
+#[derive(Clone)]
 pub struct Tile {
     pub image: Arc<Mutex<Option<ImageType>>>,
     pub time_req: Time,
     pub cell: HEALPixCell,
     pub format: ImageFormatType,
+    pub cube_frame: Option<u32>,
+    status: ResolvedStatus,
     hips_url: Url,
     url: Url,
 }
@@ -197,6 +108,12 @@ impl Tile {
         self.image.lock().unwrap_abort().is_none()
     }
 
+    /// The tile could not be retrieved because of transient errors.
+    /// It may exist and be requested again later
+    pub fn failed(&self) -> bool {
+        self.status == ResolvedStatus::Failed
+    }
+
     pub fn get_hips_url(&self) -> &Url {
         &self.hips_url
     }
@@ -218,6 +135,7 @@ impl<'a> From<&'a TileRequest> for Option<Tile> {
             hips_url,
             url,
             format,
+            cube_frame,
             ..
         } = request;
         if request.is_resolved() {
@@ -232,6 +150,8 @@ impl<'a> From<&'a TileRequest> for Option<Tile> {
                 hips_url: hips_url.clone(),
                 url: url.clone(),
                 format: *format,
+                cube_frame: *cube_frame,
+                status: request.resolve_status(),
             })
         } else {
             None
@@ -0,0 +1,346 @@
+use al_core::image::format::{ChannelType, RGB8U, RGBA8U};
+use al_core::image::html::HTMLImage;
+use al_core::image::raw::ImageBuffer;
+use al_core::image::ImageType;
+
+use std::collections::HashMap;
+use std::future::Future;
+use std::pin::Pin;
+
+use wasm_bindgen::{JsCast, JsValue};
+use wasm_bindgen_futures::JsFuture;
+use web_sys::{HtmlImageElement, RequestInit, RequestMode, Response};
+
+use super::archive;
+use super::request::RequestError;
+use crate::Abort;
+
+pub type SourceFuture<T> = Pin<Box<dyn Future<Output = Result<T, RequestError>>>>;
+
+/// Where the files of a HiPS are read from
+///
+/// The files are given by their path relative to the root of the HiPS,
+/// e.g. `Norder3/Dir0/Npix42.fits`.
+pub trait TileSource: std::fmt::Debug {
+    /// Read the bytes of a file
+    fn fetch_bytes(&self, hips_url: &str, path: &str) -> SourceFuture<Vec<u8>>;
+
+    /// Read a jpeg or png tile into an image ready to be sent to the GPU
+    fn fetch_image(&self, hips_url: &str, path: &str, channel: ChannelType) -> SourceFuture<ImageType> {
+        let bytes = self.fetch_bytes(hips_url, path);
+        Box::pin(async move {
+            let image = decode_image(&bytes.await?)?;
+
+            match channel {
+                ChannelType::RGB8U => {
+                    let image = image.into_rgb8();
+                    let (w, h) = image.dimensions();
+
+                    Ok(ImageType::RawRgb8u { image: ImageBuffer::<RGB8U>::new(image.into_raw(), w as i32, h as i32) })
+                }
+                ChannelType::RGBA8U => {
+                    let image = image.into_rgba8();
+                    let (w, h) = image.dimensions();
+
+                    Ok(ImageType::RawRgba8u { image: ImageBuffer::<RGBA8U>::new(image.into_raw(), w as i32, h as i32) })
+                }
+                _ => Err(RequestError::Transient("Only jpeg and png tiles can be decoded".to_string())),
+            }
+        })
+    }
+
+    /// Read a jpeg or png file into a RGBA buffer, e.g. an allsky to split into tiles
+    fn fetch_rgba_buffer(&self, hips_url: &str, path: &str) -> SourceFuture<ImageBuffer<RGBA8U>> {
+        let bytes = self.fetch_bytes(hips_url, path);
+        Box::pin(async move {
+            let image = decode_image(&bytes.await?)?.into_rgba8();
+            let (w, h) = image.dimensions();
+
+            Ok(ImageBuffer::<RGBA8U>::new(image.into_raw(), w as i32, h as i32))
+        })
+    }
+
+    /// Tell whether the files are requested to a web server
+    fn is_http(&self) -> bool {
+        false
+    }
+}
+
+fn decode_image(bytes: &[u8]) -> Result<image_decoder::DynamicImage, RequestError> {
+    image_decoder::load_from_memory(bytes)
+        .map_err(|e| RequestError::Transient(format!("Image decoding: reason: {}", e)))
+}
+
+/* ---------------------------------- */
+/// The files are requested to the HiPS web server
+#[derive(Debug, Default)]
+pub struct HttpSource;
+
+/// Fetch a file, the HTTP error status telling whether it is worth retrying
+pub async fn fetch(url: &str) -> Result<Vec<u8>, RequestError> {
+    let window = web_sys::window().unwrap_abort();
+    let opts = RequestInit::new();
+    opts.set_method("GET");
+    opts.set_mode(RequestMode::Cors);
+
+    let request = web_sys::Request::new_with_str_and_init(url, &opts)?;
+    let resp_value = JsFuture::from(window.fetch_with_request(&request)).await?;
+    // `resp_value` is a `Response` object.
+    debug_assert!(resp_value.is_instance_of::<Response>());
+    let resp: Response = resp_value.dyn_into()?;
+
+    if resp.ok() {
+        let array_buffer = JsFuture::from(resp.array_buffer()?).await?;
+        Ok(js_sys::Uint8Array::new(&array_buffer).to_vec())
+    } else {
+        Err(RequestError::from_status(resp.status()))
+    }
+}
+
+async fn query_html_image(url: &str) -> Result<HtmlImageElement, JsValue> {
+    let image = web_sys::HtmlImageElement::new().unwrap_abort();
+    let image_cloned = image.clone();
+
+    let html_img_elt_promise = js_sys::Promise::new(
+        &mut (Box::new(move |resolve, reject| {
+            // Ask for CORS permissions
+            image_cloned.set_cross_origin(Some(""));
+            image_cloned.set_onload(
+                Some(&resolve)
+            );
+            image_cloned.set_onerror(
+                Some(&reject)
+            );
+            image_cloned.set_src(url);
+        }) as Box<dyn FnMut(js_sys::Function, js_sys::Function)>)
+    );
+
+    let _ = JsFuture::from(html_img_elt_promise).await?;
+
+    Ok(image)
+}
+
+async fn query_image(url: &str) -> Result<ImageBuffer<RGBA8U>, JsValue> {
+    let image = query_html_image(url).await?;
+
+    // The image has been received here
+    let document = web_sys::window().unwrap_abort().document().unwrap_abort();
+    let canvas = document
+        .create_element("canvas")?
+        .dyn_into::<web_sys::HtmlCanvasElement>()?;
+    canvas.set_width(image.width());
+    canvas.set_height(image.height());
+    let context = canvas
+        .get_context("2d")?
+        .unwrap_abort()
+        .dyn_into::<web_sys::CanvasRenderingContext2d>()?;
+    context.draw_image_with_html_image_element(&image, 0.0, 0.0)?;
+
+    let w = image.width();
+    let h = image.height();
+    let image_data = context.get_image_data(0.0, 0.0, w as f64, h as f64)?;
+
+    let raw_bytes = image_data.data();
+
+    Ok(ImageBuffer::from_raw_bytes(raw_bytes.0, w as i32, h as i32))
+}
+
+// An image element does not give the HTTP status of its failure.
+// It is retrieved with a HEAD request
+async fn classify_image_error(url: &str) -> RequestError {
+    let status = async {
+        let window = web_sys::window().unwrap_abort();
+        let opts = RequestInit::new();
+        opts.set_method("HEAD");
+        opts.set_mode(RequestMode::Cors);
+
+        let request = web_sys::Request::new_with_str_and_init(url, &opts)?;
+        let resp: Response = JsFuture::from(window.fetch_with_request(&request)).await?.dyn_into()?;
+
+        Ok::<_, JsValue>(resp.status())
+    }.await;
+
+    match status {
+        Ok(status) if status >= 400 => RequestError::from_status(status),
+        // The image could not be decoded, it may have been truncated
+        Ok(_) => RequestError::Transient("Image cannot be decoded".to_string()),
+        Err(err) => err.into(),
+    }
+}
+
+impl TileSource for HttpSource {
+    fn fetch_bytes(&self, hips_url: &str, path: &str) -> SourceFuture<Vec<u8>> {
+        let url = format!("{}/{}", hips_url, path);
+        Box::pin(async move { fetch(&url).await })
+    }
+
+    // The browser decodes the images itself
+    fn fetch_image(&self, hips_url: &str, path: &str, channel: ChannelType) -> SourceFuture<ImageType> {
+        let url = format!("{}/{}", hips_url, path);
+        Box::pin(async move {
+            // HTMLImageElement
+            let image = match query_html_image(&url).await {
+                Ok(image) => image,
+                Err(_) => return Err(classify_image_error(&url).await),
+            };
+
+            // The image has been resolved
+            match channel {
+                ChannelType::RGB8U => Ok(ImageType::HTMLImageRgb8u { image: HTMLImage::<RGB8U>::new(image) }),
+                _ => Ok(ImageType::HTMLImageRgba8u { image: HTMLImage::<RGBA8U>::new(image) }),
+            }
+        })
+    }
+
+    fn fetch_rgba_buffer(&self, hips_url: &str, path: &str) -> SourceFuture<ImageBuffer<RGBA8U>> {
+        let url = format!("{}/{}", hips_url, path);
+        Box::pin(async move { Ok(query_image(&url).await?) })
+    }
+
+    fn is_http(&self) -> bool {
+        true
+    }
+}
+
+/* ---------------------------------- */
+/// The files are stored in memory, e.g. the content of an archive of a HiPS
+#[derive(Debug, Default)]
+pub struct MemorySource {
+    files: HashMap<String, Vec<u8>>,
+}
+
+impl MemorySource {
+    pub fn new(files: HashMap<String, Vec<u8>>) -> Self {
+        Self { files }
+    }
+
+    /// Read the files of a zip or tar archive of a HiPS
+    ///
+    /// The directory containing the `properties` file is considered as the root of the HiPS
+    pub fn from_archive(bytes: &[u8]) -> Result<Self, JsValue> {
+        let files = archive::read(bytes).map_err(JsValue::from_str)?;
+        let root = files.keys()
+            .filter_map(|path| path.strip_suffix("properties"))
+            .filter(|dir| dir.is_empty() || dir.ends_with('/'))
+            .min_by_key(|dir| dir.len())
+            .unwrap_or("")
+            .to_string();
+
+        let files = files.into_iter()
+            .filter_map(|(path, bytes)| {
+                path.strip_prefix(&root).map(|path| (path.to_string(), bytes))
+            })
+            .collect();
+
+        Ok(Self { files })
+    }
+
+    pub fn get(&self, path: &str) -> Option<&[u8]> {
+        self.files.get(path).map(|bytes| &bytes[..])
+    }
+
+    /// The content of the `properties` file of the HiPS
+    pub fn get_properties(&self) -> Option<String> {
+        self.get("properties")
+            .map(|bytes| String::from_utf8_lossy(bytes).to_string())
+    }
+}
+
+impl TileSource for MemorySource {
+    fn fetch_bytes(&self, _hips_url: &str, path: &str) -> SourceFuture<Vec<u8>> {
+        let bytes = self.get(path).map(|bytes| bytes.to_vec());
+        Box::pin(async move { bytes.ok_or(RequestError::NotFound) })
+    }
+}
+
+/* ---------------------------------- */
+/// The files are given by a JS function
+///
+/// It is called with the path of the file and returns its bytes as an `Uint8Array` or an `ArrayBuffer`,
+/// possibly through a `Promise`. `null` or `undefined` tells the file does not exist.
+#[derive(Debug)]
+pub struct CallbackSource {
+    callback: js_sys::Function,
+}
+
+impl CallbackSource {
+    pub fn new(callback: js_sys::Function) -> Self {
+        Self { callback }
+    }
+}
+
+impl TileSource for CallbackSource {
+    fn fetch_bytes(&self, _hips_url: &str, path: &str) -> SourceFuture<Vec<u8>> {
+        let result = self.callback.call1(&JsValue::NULL, &JsValue::from_str(path));
+        Box::pin(async move {
+            let value = JsFuture::from(js_sys::Promise::resolve(&result?)).await?;
+
+            if value.is_null() || value.is_undefined() {
+                Err(RequestError::NotFound)
+            } else if let Some(bytes) = value.dyn_ref::<js_sys::Uint8Array>() {
+                Ok(bytes.to_vec())
+            } else if let Some(buffer) = value.dyn_ref::<js_sys::ArrayBuffer>() {
+                Ok(js_sys::Uint8Array::new(buffer).to_vec())
+            } else {
+                Err(RequestError::Transient("The tile source must give an Uint8Array or an ArrayBuffer".to_string()))
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{SourceFuture, TileSource};
+    use crate::downloader::request::{retry, RequestError};
+
+    use std::cell::Cell;
+    use std::future::Future;
+    use std::task::{Context, Poll, Waker};
+
+    // Serves the properties of a HiPS that has no tile
+    #[derive(Debug, Default)]
+    struct FakeSource {
+        num_fetches: Cell<u32>,
+    }
+
+    impl TileSource for FakeSource {
+        fn fetch_bytes(&self, _hips_url: &str, path: &str) -> SourceFuture<Vec<u8>> {
+            self.num_fetches.set(self.num_fetches.get() + 1);
+
+            let bytes = if path == "properties" {
+                Some(b"hips_order = 3".to_vec())
+            } else {
+                None
+            };
+            Box::pin(async move { bytes.ok_or(RequestError::NotFound) })
+        }
+    }
+
+    // The futures of the fake source are ready at once
+    fn poll_ready<T>(fut: impl Future<Output = T>) -> T {
+        let mut fut = Box::pin(fut);
+        match fut.as_mut().poll(&mut Context::from_waker(Waker::noop())) {
+            Poll::Ready(output) => output,
+            Poll::Pending => panic!("the future is not ready"),
+        }
+    }
+
+    #[test]
+    fn test_fetch_from_fake_source() {
+        let source = FakeSource::default();
+        let num_retries = Cell::new(0);
+
+        let properties = poll_ready(retry(|| source.fetch_bytes("http://hips", "properties"), &num_retries));
+        assert_eq!(properties, Ok(b"hips_order = 3".to_vec()));
+
+        // A missing tile is not requested again
+        let tile = poll_ready(retry(|| source.fetch_bytes("http://hips", "Norder3/Dir0/Npix42.fits"), &num_retries));
+        assert_eq!(tile, Err(RequestError::NotFound));
+        assert_eq!(num_retries.get(), 0);
+        assert_eq!(source.num_fetches.get(), 2);
+
+        // The bytes read are not an image
+        let image = poll_ready(source.fetch_rgba_buffer("http://hips", "properties"));
+        assert!(matches!(image, Err(RequestError::Transient(_))));
+    }
+}
@@ -0,0 +1,210 @@
+// Length of a FITS block in bytes
+const BLOCK_SIZE: usize = 2880;
+// Length of a header card in bytes
+const CARD_SIZE: usize = 80;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    Logical(bool),
+    Integer(i64),
+    Float(f64),
+    String(String),
+}
+
+impl From<bool> for Value {
+    fn from(v: bool) -> Self {
+        Value::Logical(v)
+    }
+}
+
+impl From<i64> for Value {
+    fn from(v: i64) -> Self {
+        Value::Integer(v)
+    }
+}
+
+impl From<f64> for Value {
+    fn from(v: f64) -> Self {
+        Value::Float(v)
+    }
+}
+
+impl From<&str> for Value {
+    fn from(v: &str) -> Self {
+        Value::String(v.to_string())
+    }
+}
+
+/// A keyword/value record of a FITS header
+#[derive(Debug, Clone, PartialEq)]
+pub struct Card {
+    pub keyword: String,
+    pub value: Value,
+}
+
+impl Card {
+    pub fn new<V: Into<Value>>(keyword: &str, value: V) -> Self {
+        Self {
+            keyword: keyword.to_string(),
+            value: value.into(),
+        }
+    }
+
+    fn write(&self, bytes: &mut Vec<u8>) {
+        let value = match &self.value {
+            Value::Logical(v) => format!("{:>20}", if *v { "T" } else { "F" }),
+            Value::Integer(v) => format!("{:>20}", v),
+            Value::Float(v) => format!("{:>20}", format!("{:.12E}", v)),
+            // Quotes are escaped by doubling them
+            Value::String(v) => format!("'{:<8}'", v.replace('\'', "''")),
+        };
+
+        let card = format!("{:<8}= {}", self.keyword, value);
+        write_card(bytes, &card);
+    }
+}
+
+fn write_card(bytes: &mut Vec<u8>, card: &str) {
+    let card = &card.as_bytes()[..card.len().min(CARD_SIZE)];
+    bytes.extend(card);
+    bytes.extend(std::iter::repeat_n(b' ', CARD_SIZE - card.len()));
+}
+
+// Pad the bytes to a multiple of the block size
+fn pad_block(bytes: &mut Vec<u8>, pad: u8) {
+    let rem = bytes.len() % BLOCK_SIZE;
+    if rem > 0 {
+        bytes.extend(std::iter::repeat_n(pad, BLOCK_SIZE - rem));
+    }
+}
+
+// Write the header of a primary HDU padded to a block
+fn write_header(bytes: &mut Vec<u8>, mandatory: &[Card], cards: &[Card]) {
+    for card in mandatory.iter().chain(cards.iter()) {
+        card.write(bytes);
+    }
+    write_card(bytes, "END");
+    pad_block(bytes, b' ');
+}
+
+/// Write a FITS file made of one 32 bits floating point image
+///
+/// `data` is given row by row, the first row being the bottom one.
+/// Undefined values are given as NaN.
+pub fn write_f32_image(width: usize, height: usize, data: &[f32], cards: &[Card]) -> Vec<u8> {
+    let mut bytes = Vec::with_capacity(BLOCK_SIZE + data.len() * 4);
+
+    let mandatory = [
+        Card::new("SIMPLE", true),
+        Card::new("BITPIX", -32_i64),
+        Card::new("NAXIS", 2_i64),
+        Card::new("NAXIS1", width as i64),
+        Card::new("NAXIS2", height as i64),
+    ];
+    write_header(&mut bytes, &mandatory, cards);
+
+    for v in data.iter().take(width * height) {
+        bytes.extend(&v.to_be_bytes());
+    }
+    pad_block(&mut bytes, 0);
+
+    bytes
+}
+
+/// Write a FITS file made of a 8 bits cube of 3 planes: red, green and blue
+///
+/// `data` gives the RGBA pixels row by row, the first row being the bottom one.
+/// The alpha channel is not stored.
+pub fn write_rgb_image(width: usize, height: usize, data: &[u8], cards: &[Card]) -> Vec<u8> {
+    let mut bytes = Vec::with_capacity(BLOCK_SIZE + width * height * 3);
+
+    let mandatory = [
+        Card::new("SIMPLE", true),
+        Card::new("BITPIX", 8_i64),
+        Card::new("NAXIS", 3_i64),
+        Card::new("NAXIS1", width as i64),
+        Card::new("NAXIS2", height as i64),
+        Card::new("NAXIS3", 3_i64),
+    ];
+    write_header(&mut bytes, &mandatory, cards);
+
+    for channel in 0..3 {
+        bytes.extend(data.chunks(4).take(width * height).map(|rgba| rgba[channel]));
+    }
+    pad_block(&mut bytes, 0);
+
+    bytes
+}
+
+/// Write a FITS file without data, only storing the header cards
+///
+/// This is used for giving the WCS of an image stored in another format
+pub fn write_header_only(cards: &[Card]) -> Vec<u8> {
+    let mut bytes = Vec::with_capacity(BLOCK_SIZE);
+
+    let mandatory = [
+        Card::new("SIMPLE", true),
+        Card::new("BITPIX", 8_i64),
+        Card::new("NAXIS", 0_i64),
+    ];
+    write_header(&mut bytes, &mandatory, cards);
+
+    bytes
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Card, write_f32_image, write_rgb_image};
+
+    #[test]
+    fn test_write_f32_image() {
+        let (w, h) = (3, 2);
+        let data = [0.0, 1.0, 2.0, f32::NAN, 4.0, 5.0];
+        let bytes = write_f32_image(
+            w,
+            h,
+            &data,
+            &[
+                Card::new("CTYPE1", "RA---TAN"),
+                Card::new("CRVAL1", 83.63),
+                Card::new("BUNIT", "Jy/beam"),
+            ],
+        );
+        assert_eq!(bytes.len() % 2880, 0);
+        assert_eq!(bytes.len(), 2 * 2880);
+
+        let mut reader = std::io::Cursor::new(&bytes[..]);
+        let fitsrs::fits::Fits { hdu } = fitsrs::fits::Fits::from_reader(&mut reader).unwrap();
+        let header = hdu.get_header();
+        assert_eq!(header.get_xtension().get_naxisn(1), Some(&3));
+        assert_eq!(header.get_xtension().get_naxisn(2), Some(&2));
+        assert_eq!(
+            header.get_parsed::<f64>(b"CRVAL1  ").unwrap().unwrap(),
+            83.63
+        );
+        assert_eq!(
+            header.get_parsed::<String>(b"CTYPE1  ").unwrap().unwrap().trim(),
+            "RA---TAN"
+        );
+
+        if let fitsrs::hdu::data::InMemData::F32(values) = hdu.get_data() {
+            assert_eq!(values.len(), 6);
+            assert_eq!(values[4], 4.0);
+            assert!(values[3].is_nan());
+        } else {
+            panic!("BITPIX should be -32");
+        }
+    }
+
+    #[test]
+    fn test_write_rgb_image() {
+        let (w, h) = (2, 1);
+        let data = [1, 2, 3, 255, 4, 5, 6, 255];
+        let bytes = write_rgb_image(w, h, &data, &[]);
+        assert_eq!(bytes.len(), 2 * 2880);
+
+        // The planes follow the header
+        assert_eq!(&bytes[2880..2886], &[1, 4, 2, 5, 3, 6]);
+        assert!(bytes[2886..].iter().all(|b| *b == 0));
+    }
+}
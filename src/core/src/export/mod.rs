@@ -0,0 +1,26 @@
+pub mod fits;
+pub mod wcs;
+pub mod snapshot;
+
+/// An image of the view with its WCS
+pub struct ExportedView {
+    /// The content of the image file
+    pub image: Vec<u8>,
+    /// A FITS header giving the WCS of the image when it is not stored in it
+    pub wcs: Option<Vec<u8>>,
+}
+
+/// Encode RGBA pixels given row by row from the bottom row into a PNG
+pub fn encode_png(width: usize, height: usize, data: &[u8]) -> Result<Vec<u8>, wasm_bindgen::JsValue> {
+    use image_decoder::ImageEncoder;
+
+    // The PNG rows are given from the top
+    let rows = data.chunks(4 * width).rev().flatten().copied().collect::<Vec<_>>();
+
+    let mut bytes = vec![];
+    image_decoder::codecs::png::PngEncoder::new(&mut bytes)
+        .write_image(&rows, width as u32, height as u32, image_decoder::ColorType::Rgba8)
+        .map_err(|err| wasm_bindgen::JsValue::from_str(&format!("{:?}", err)))?;
+
+    Ok(bytes)
+}
@@ -0,0 +1,110 @@
+use al_api::export::MarkerShape;
+use cgmath::Vector2;
+
+use crate::math::projection::coo_space::XYNDC;
+
+/// A rectangle of an image in pixels, `y` being counted from the bottom row
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TileRect {
+    pub x: usize,
+    pub y: usize,
+    pub width: usize,
+    pub height: usize,
+}
+
+impl TileRect {
+    /// The lower-left and upper-right corners of the tile in the NDC space of the image
+    pub fn ndc_window(&self, width: usize, height: usize) -> (XYNDC, XYNDC) {
+        let to_ndc = |x: usize, y: usize| Vector2::new(
+            2.0 * (x as f64) / (width as f64) - 1.0,
+            2.0 * (y as f64) / (height as f64) - 1.0,
+        );
+
+        (
+            to_ndc(self.x, self.y),
+            to_ndc(self.x + self.width, self.y + self.height),
+        )
+    }
+}
+
+/// Split an image into tiles of at most `size` pixels large, from the bottom row
+pub fn split(width: usize, height: usize, size: usize) -> Vec<TileRect> {
+    let mut tiles = vec![];
+    for y in (0..height).step_by(size) {
+        for x in (0..width).step_by(size) {
+            tiles.push(TileRect {
+                x,
+                y,
+                width: size.min(width - x),
+                height: size.min(height - y),
+            });
+        }
+    }
+
+    tiles
+}
+
+/// Copy the RGBA pixels of a tile into an image, both being given row by row from the bottom row
+pub fn copy_tile(image: &mut [u8], width: usize, tile: &TileRect, pixels: &[u8]) {
+    let row_len = 4 * tile.width;
+    for (i, row) in pixels.chunks(row_len).enumerate() {
+        let off = 4 * ((tile.y + i) * width + tile.x);
+        image[off..(off + row_len)].copy_from_slice(row);
+    }
+}
+
+/// The polylines of a marker centered on a screen position, `size` being its width in pixels
+pub fn marker_paths(shape: MarkerShape, pos: &Vector2<f32>, size: f32) -> Vec<Vec<Vector2<f32>>> {
+    let r = 0.5 * size;
+    let p = |dx: f32, dy: f32| Vector2::new(pos.x + dx, pos.y + dy);
+
+    match shape {
+        MarkerShape::Square => vec![vec![p(-r, -r), p(r, -r), p(r, r), p(-r, r), p(-r, -r)]],
+        MarkerShape::Rhomb => vec![vec![p(0.0, -r), p(r, 0.0), p(0.0, r), p(-r, 0.0), p(0.0, -r)]],
+        MarkerShape::Triangle => vec![vec![p(0.0, -r), p(r, r), p(-r, r), p(0.0, -r)]],
+        MarkerShape::Plus => vec![
+            vec![p(-r, 0.0), p(r, 0.0)],
+            vec![p(0.0, -r), p(0.0, r)],
+        ],
+        MarkerShape::Cross => vec![
+            vec![p(-r, -r), p(r, r)],
+            vec![p(r, -r), p(-r, r)],
+        ],
+        MarkerShape::Circle => {
+            const NUM_VERTICES: usize = 24;
+            let circle = (0..=NUM_VERTICES)
+                .map(|i| {
+                    let theta = 2.0 * std::f32::consts::PI * (i as f32) / (NUM_VERTICES as f32);
+                    p(r * theta.cos(), r * theta.sin())
+                })
+                .collect();
+
+            vec![circle]
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{copy_tile, split, TileRect};
+
+    #[test]
+    fn test_split_and_copy_tiles() {
+        let tiles = split(5, 3, 2);
+        assert_eq!(tiles.len(), 6);
+        assert_eq!(tiles[2], TileRect { x: 4, y: 0, width: 1, height: 2 });
+        assert_eq!(tiles[5], TileRect { x: 4, y: 2, width: 1, height: 1 });
+
+        // The tiles cover the whole image
+        let mut image = vec![0; 4 * 5 * 3];
+        for tile in &tiles {
+            let pixels = vec![255; 4 * tile.width * tile.height];
+            copy_tile(&mut image, 5, tile, &pixels);
+        }
+        assert!(image.iter().all(|&v| v == 255));
+
+        let (min, max) = split(4, 2, 2)[1].ndc_window(4, 2);
+        assert_eq!((min.x, min.y), (0.0, -1.0));
+        assert_eq!((max.x, max.y), (1.0, 1.0));
+    }
+}
@@ -0,0 +1,272 @@
+use al_api::coo_system::CooSystem;
+use al_api::cutout::ScreenRect;
+
+use cgmath::{Matrix, Matrix4, Vector2, Vector4};
+use wasm_bindgen::JsValue;
+
+use crate::camera::CameraViewPort;
+use crate::math::lonlat::xyzw_to_radec;
+use crate::math::projection::Projection;
+use crate::math::PI;
+use crate::ProjectionType;
+
+use super::fits::Card;
+
+/// What the drawing of the view depends on
+struct ViewGeometry {
+    system: CooSystem,
+    w2m: Matrix4<f64>,
+    reversed: bool,
+    screen_size: Vector2<f64>,
+    ndc_to_clip: Vector2<f64>,
+    clip_zoom_factor: f64,
+}
+
+impl ViewGeometry {
+    fn new(camera: &CameraViewPort) -> Self {
+        let screen_size = camera.get_screen_size();
+
+        ViewGeometry {
+            system: *camera.get_system(),
+            w2m: *camera.get_w2m(),
+            reversed: camera.get_longitude_reversed(),
+            screen_size: Vector2::new(screen_size.x as f64, screen_size.y as f64),
+            ndc_to_clip: *camera.get_ndc_to_clip(),
+            clip_zoom_factor: camera.get_clip_zoom_factor(),
+        }
+    }
+}
+
+/// FITS WCS keywords of a region of the view
+///
+/// The region is given in physical screen pixels (i.e. dpi included) and is mapped
+/// to an image of `width` x `height` pixels. The WCS reproduces the current projection,
+/// center, rotation and longitude orientation of the view.
+///
+/// The keywords are derived from the way the view is drawn:
+/// * The projection plane of `mapproj` is linearly mapped to the screen. The rotation of the
+///   view and the orientation of the longitudes are given by the CD matrix.
+/// * The native north of the reference point is the celestial one, so that LONPOLE and LATPOLE
+///   keep their default values. For the cylindrical projections, the reference point is moved
+///   along the native equator to the meridian of the celestial pole.
+pub fn get_view_wcs(
+    camera: &CameraViewPort,
+    projection: &ProjectionType,
+    rect: &ScreenRect,
+    width: usize,
+    height: usize,
+) -> Result<Vec<Card>, JsValue> {
+    view_wcs(&ViewGeometry::new(camera), projection, rect, width, height)
+}
+
+fn view_wcs(
+    view: &ViewGeometry,
+    projection: &ProjectionType,
+    rect: &ScreenRect,
+    width: usize,
+    height: usize,
+) -> Result<Vec<Card>, JsValue> {
+    let (code, pv) = projection
+        .get_fits_code()
+        .ok_or_else(|| JsValue::from_str("The current projection has no FITS equivalent"))?;
+
+    let (lon_prefix, lat_prefix) = match view.system {
+        CooSystem::ICRSJ2000 => ("RA---", "DEC--"),
+        CooSystem::GAL => ("GLON-", "GLAT-"),
+    };
+
+    // Scale of the projection plane in degrees per pixel of the image
+    let plane_size = projection.get_plane_size();
+    let sx = rect.width / (width as f64);
+    let sy = rect.height / (height as f64);
+
+    let kx = plane_size.x.to_degrees() * view.clip_zoom_factor * view.ndc_to_clip.x / view.screen_size.x * sx;
+    let ky = plane_size.y.to_degrees() * view.clip_zoom_factor * view.ndc_to_clip.y / view.screen_size.y * sy;
+    // The world space of the camera is mirrored compared to the celestial sphere:
+    // the longitudes increase towards the left when not reversed
+    let kx = if view.reversed { kx } else { -kx };
+
+    let m2w = view.w2m.transpose();
+    // Position of the celestial pole in the world space of the camera
+    let pole = m2w * Vector4::new(0.0, 1.0, 0.0, 0.0);
+
+    // Reference point in the world space of the camera and the CD matrix
+    let (reference, cd) = if projection.is_zenithal() {
+        // The native pole is the center of the view. The rotation of the view is
+        // the angle between the north at the center and the y axis of the screen
+        let reference = Vector4::new(0.0, 0.0, 1.0, 0.0);
+
+        let center = view.w2m * reference;
+        let north = m2w * (Vector4::new(0.0, 1.0, 0.0, 0.0) - center * center.y);
+        let (sin, cos) = north.x.atan2(north.y).sin_cos();
+
+        (reference, [[cos * kx, -sin * ky], [sin * kx, cos * ky]])
+    } else {
+        // A rotation of the view rotates the sphere around the center, that is not a rotation
+        // of the projection plane. The native equator is kept and the reference point is taken
+        // on the meridian of the celestial pole, the nearest one to the center.
+        let mut azimuth = pole.x.atan2(pole.z);
+        if azimuth > PI / 2.0 {
+            azimuth -= PI;
+        } else if azimuth <= -PI / 2.0 {
+            azimuth += PI;
+        }
+
+        // Moving the reference point along the native equator is a translation
+        // of the projection plane for the cylindrical projections only
+        if !projection.is_cylindrical() && azimuth.abs() > 1e-9 {
+            return Err(JsValue::from_str(&format!(
+                "The WCS of a rotated view cannot be given in the {} projection",
+                projection.get_name()
+            )));
+        }
+        let reference = Vector4::new(azimuth.sin(), 0.0, azimuth.cos(), 0.0);
+
+        // The celestial pole is in the native southern hemisphere: the projection
+        // plane is rotated by 180 degrees
+        let sign = if pole.y < 0.0 { -1.0 } else { 1.0 };
+
+        (reference, [[sign * kx, 0.0], [0.0, sign * ky]])
+    };
+
+    // Pixel where the reference point is drawn
+    let mut world = reference;
+    if view.reversed {
+        world.x = -world.x;
+    }
+    let clip = projection
+        .world_to_clip_space(&world)
+        .ok_or_else(|| JsValue::from_str("The reference point of the WCS cannot be projected"))?;
+    let px = (clip.x / (view.ndc_to_clip.x * view.clip_zoom_factor) * 0.5 + 0.5) * view.screen_size.x;
+    let py = (0.5 - clip.y / (view.ndc_to_clip.y * view.clip_zoom_factor) * 0.5) * view.screen_size.y;
+
+    // FITS pixel centers are located at integer values starting from 1
+    // and the rows are given from the bottom of the image.
+    let crpix1 = (px - rect.x) / sx + 0.5;
+    let crpix2 = (height as f64) + 0.5 - (py - rect.y) / sy;
+
+    let (lon0, lat0) = xyzw_to_radec(&(view.w2m * reference));
+    let mut cards = vec![
+        Card::new("CTYPE1", format!("{}{}", lon_prefix, code).as_str()),
+        Card::new("CTYPE2", format!("{}{}", lat_prefix, code).as_str()),
+        Card::new("CUNIT1", "deg"),
+        Card::new("CUNIT2", "deg"),
+        Card::new("CRPIX1", crpix1),
+        Card::new("CRPIX2", crpix2),
+        Card::new("CRVAL1", lon0.0.to_degrees().rem_euclid(360.0)),
+        Card::new("CRVAL2", lat0.0.to_degrees().clamp(-90.0, 90.0)),
+        Card::new("CD1_1", cd[0][0]),
+        Card::new("CD1_2", cd[0][1]),
+        Card::new("CD2_1", cd[1][0]),
+        Card::new("CD2_2", cd[1][1]),
+    ];
+
+    for (m, value) in pv {
+        cards.push(Card::new(&format!("PV2_{}", m), value));
+    }
+
+    if view.system == CooSystem::ICRSJ2000 {
+        cards.push(Card::new("RADESYS", "ICRS"));
+    }
+
+    Ok(cards)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::{BufReader, Cursor};
+
+    use al_api::coo_system::CooSystem;
+    use al_api::cutout::ScreenRect;
+    use cgmath::{InnerSpace, Matrix4, Vector2, Vector4};
+    use fitsrs::fits::Fits;
+    use wcs::{ImgXY, WCS};
+
+    use super::{view_wcs, ViewGeometry};
+    use crate::export::fits::write_f32_image;
+    use crate::math::angle::Angle;
+    use crate::math::lonlat::{radec_to_xyzw, xyzw_to_radec};
+    use crate::math::projection::{Projection, ProjectionType};
+    use crate::math::rotation::Rotation;
+
+    /// Angular distance in degrees
+    fn distance(a: &Vector4<f64>, b: &Vector4<f64>) -> f64 {
+        a.truncate().cross(b.truncate()).magnitude().atan2(a.truncate().dot(b.truncate())).to_degrees()
+    }
+
+    #[test]
+    fn test_view_wcs_round_trip() {
+        let (w_screen, h_screen) = (800.0, 600.0);
+        let center = radec_to_xyzw(Angle(83.6_f64.to_radians()), Angle(22.0_f64.to_radians()));
+
+        for name in ["TAN", "CAR"] {
+            let projection = ProjectionType::from_name(name).unwrap();
+
+            for rotation in [30.0_f64, 150.0] {
+                // The camera rotation of a view rotated around its center
+                let rot = Rotation::from_axis_angle(&center.truncate(), Angle(rotation.to_radians()))
+                    * Rotation::from_sky_position(&center);
+                let w2m: Matrix4<f64> = (&rot).into();
+
+                let view = ViewGeometry {
+                    system: CooSystem::ICRSJ2000,
+                    w2m,
+                    reversed: true,
+                    screen_size: Vector2::new(w_screen, h_screen),
+                    ndc_to_clip: Vector2::new(1.0, h_screen / w_screen * projection.bounds_size_ratio()),
+                    clip_zoom_factor: 0.1,
+                };
+
+                // The whole screen and a downsampled region of it
+                let regions = [
+                    (ScreenRect { x: 0.0, y: 0.0, width: w_screen, height: h_screen }, 800, 600),
+                    (ScreenRect { x: 100.0, y: 50.0, width: 400.0, height: 300.0 }, 200, 150),
+                ];
+                for (rect, width, height) in regions {
+                    let cards = view_wcs(&view, &projection, &rect, width, height).unwrap();
+                    let data = vec![0.0; width * height];
+                    let bytes = write_f32_image(width, height, &data, &cards);
+
+                    let mut reader = BufReader::new(Cursor::new(bytes));
+                    let Fits { hdu } = Fits::from_reader(&mut reader).unwrap();
+                    let wcs = WCS::new(hdu.get_header()).unwrap();
+
+                    for i in 0..=4 {
+                        for j in 0..=4 {
+                            // 0-based image pixel, the row being given from the bottom
+                            let (x, y) = ((width - 1) as f64 * i as f64 / 4.0, (height - 1) as f64 * j as f64 / 4.0);
+
+                            // Deprojection of the camera, as done for the screen position of the pixel
+                            let px = rect.x + (x + 0.5) * rect.width / (width as f64);
+                            let py = rect.y + ((height as f64) - y - 0.5) * rect.height / (height as f64);
+                            let clip = Vector2::new(
+                                (2.0 * px / w_screen - 1.0) * view.ndc_to_clip.x * view.clip_zoom_factor,
+                                (1.0 - 2.0 * py / h_screen) * view.ndc_to_clip.y * view.clip_zoom_factor,
+                            );
+                            let mut world = projection.clip_to_world_space(&clip).unwrap();
+                            world.x = -world.x;
+                            let expected = w2m * world.truncate().normalize().extend(0.0);
+
+                            let lonlat = wcs.unproj_lonlat(&ImgXY::new(x, y)).unwrap();
+                            let pos = radec_to_xyzw(Angle(lonlat.lon()), Angle(lonlat.lat()));
+
+                            let (lon, lat) = xyzw_to_radec(&expected);
+                            assert!(
+                                distance(&pos, &expected) < 1e-6,
+                                "{} rotated by {}: pixel ({}, {}) at ({}, {}) instead of ({}, {})",
+                                name,
+                                rotation,
+                                x,
+                                y,
+                                lonlat.lon().to_degrees(),
+                                lonlat.lat().to_degrees(),
+                                lon.0.to_degrees(),
+                                lat.0.to_degrees(),
+                            );
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
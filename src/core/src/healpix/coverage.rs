@@ -40,6 +40,19 @@ impl HEALPixCoverage {
         HEALPixCoverage(moc)
     }
 
+    /// The cells of a cone given by its center and its radius in radians
+    pub fn from_cone(center: &Vector4<f64>, radius: f64, depth: u8) -> Self {
+        let (lon, lat) = math::lonlat::xyzw_to_radec(center);
+        let moc = RangeMOC::from_cone(lon.0, lat.0, radius.min(std::f64::consts::PI), depth, 2);
+        HEALPixCoverage(moc)
+    }
+
+    /// The cells surrounding the coverage, i.e. the neighbors of its border cells
+    pub fn border(&self) -> Self {
+        let moc = self.0.expanded().minus(&self.0);
+        HEALPixCoverage(moc)
+    }
+
     pub fn allsky(depth_max: u8) -> Self {
         let moc = RangeMOC::new_full_domain(depth_max);
         HEALPixCoverage(moc)
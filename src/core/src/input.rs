@@ -0,0 +1,218 @@
+use al_api::input::InputCfg;
+use cgmath::{InnerSpace, Vector2};
+
+use std::collections::HashMap;
+
+// The fingers must have turned by that angle in degrees for the rotation to start
+const ROTATION_START_ANGLE: f64 = 7.0;
+
+/// What the view has to do following the user inputs
+#[derive(Debug, PartialEq)]
+pub enum InputAction {
+    // Start dragging the view from a screen position
+    DragStart(Vector2<f64>),
+    // Drag the view from a screen position to another one
+    Drag(Vector2<f64>, Vector2<f64>),
+    // Release the view, it keeps moving by inertia
+    DragEnd,
+    // Multiply the fov by a factor, smoothly or at once
+    Zoom { factor: f64, smooth: bool },
+    // Rotate the view around its center by an angle in degrees
+    Rotate(f64),
+    // Move the view by a fraction of its size along the screen axis
+    Pan(Vector2<f64>),
+}
+
+// The gesture done with two pointers
+struct Pinch {
+    // The pointers involved
+    ids: [i32; 2],
+    dist: f64,
+    // Angle in degrees of the line joining the pointers
+    angle: f64,
+    rotating: bool,
+}
+
+/// Turns raw pointer, wheel and key events into moves of the view
+///
+/// The pointers are identified by the ids given by the browser. A mouse is a single pointer.
+pub struct InputController {
+    cfg: InputCfg,
+    // Screen positions of the pointers pressed on the view
+    pointers: HashMap<i32, Vector2<f64>>,
+    pinch: Option<Pinch>,
+}
+
+impl InputController {
+    pub fn new() -> Self {
+        Self {
+            cfg: InputCfg::default(),
+            pointers: HashMap::new(),
+            pinch: None,
+        }
+    }
+
+    pub fn set_cfg(&mut self, cfg: InputCfg) {
+        self.cfg = cfg;
+    }
+
+    pub fn get_cfg(&self) -> &InputCfg {
+        &self.cfg
+    }
+
+    /// Whether a pointer is pressed on the view
+    pub fn has_pointers(&self) -> bool {
+        !self.pointers.is_empty()
+    }
+
+    pub fn pointer_down(&mut self, id: i32, pos: Vector2<f64>) -> Vec<InputAction> {
+        self.pointers.insert(id, pos);
+
+        match self.pointers.len() {
+            1 if self.cfg.drag => vec![InputAction::DragStart(pos)],
+            2 => {
+                // A second pointer turns the drag into a pinch, the view does not keep moving
+                self.pinch = Some(self.start_pinch());
+                vec![]
+            }
+            _ => vec![],
+        }
+    }
+
+    pub fn pointer_move(&mut self, id: i32, pos: Vector2<f64>) -> Vec<InputAction> {
+        let prev_pos = if let Some(prev_pos) = self.pointers.get_mut(&id) {
+            std::mem::replace(prev_pos, pos)
+        } else {
+            // The pointer is only hovering the view
+            return vec![];
+        };
+
+        if let Some(pinch) = &mut self.pinch {
+            let (p0, p1) = (self.pointers[&pinch.ids[0]], self.pointers[&pinch.ids[1]]);
+            let dist = (p1 - p0).magnitude();
+            let angle = (p1.y - p0.y).atan2(p1.x - p0.x).to_degrees();
+
+            let mut actions = vec![];
+            if self.cfg.pinch_zoom && dist > 0.0 && pinch.dist > 0.0 {
+                actions.push(InputAction::Zoom { factor: pinch.dist / dist, smooth: false });
+            }
+            pinch.dist = dist;
+
+            // The rotation only starts once the fingers have clearly turned
+            let d_angle = angle - pinch.angle;
+            let d_angle = (d_angle + 540.0).rem_euclid(360.0) - 180.0;
+            if self.cfg.rotation {
+                if pinch.rotating {
+                    actions.push(InputAction::Rotate(-d_angle * self.cfg.rotation_sensitivity));
+                    pinch.angle = angle;
+                } else if d_angle.abs() >= ROTATION_START_ANGLE {
+                    pinch.rotating = true;
+                    pinch.angle = angle;
+                }
+            }
+
+            actions
+        } else if self.pointers.len() == 1 && self.cfg.drag {
+            vec![InputAction::Drag(prev_pos, pos)]
+        } else {
+            vec![]
+        }
+    }
+
+    pub fn pointer_up(&mut self, id: i32) -> Vec<InputAction> {
+        if self.pointers.remove(&id).is_none() {
+            return vec![];
+        }
+
+        let was_pinching = self.pinch.take().is_some();
+        match self.pointers.len() {
+            0 if !was_pinching && self.cfg.drag => vec![InputAction::DragEnd],
+            // The remaining pointer drags the view again
+            1 if was_pinching && self.cfg.drag => {
+                self.pointers.values()
+                    .map(|pos| InputAction::DragStart(*pos))
+                    .collect()
+            }
+            n if n >= 2 => {
+                self.pinch = Some(self.start_pinch());
+                vec![]
+            }
+            _ => vec![],
+        }
+    }
+
+    /// A wheel move, `delta` being the number of notches, positive to zoom in
+    pub fn wheel(&mut self, delta: f64) -> Vec<InputAction> {
+        if self.cfg.wheel_zoom && delta != 0.0 {
+            let factor = (1.0 + self.cfg.zoom_sensitivity).powf(-delta);
+            vec![InputAction::Zoom { factor, smooth: true }]
+        } else {
+            vec![]
+        }
+    }
+
+    /// A key press, `key` being the value of the `KeyboardEvent.key` property
+    pub fn key_down(&mut self, key: &str) -> Vec<InputAction> {
+        if !self.cfg.keyboard {
+            return vec![];
+        }
+
+        let zoom = 1.0 + self.cfg.zoom_sensitivity;
+        let step = self.cfg.pan_step;
+        match key {
+            "ArrowLeft" => vec![InputAction::Pan(Vector2::new(-step, 0.0))],
+            "ArrowRight" => vec![InputAction::Pan(Vector2::new(step, 0.0))],
+            "ArrowUp" => vec![InputAction::Pan(Vector2::new(0.0, -step))],
+            "ArrowDown" => vec![InputAction::Pan(Vector2::new(0.0, step))],
+            "+" | "=" => vec![InputAction::Zoom { factor: 1.0 / zoom, smooth: true }],
+            "-" | "_" => vec![InputAction::Zoom { factor: zoom, smooth: true }],
+            _ => vec![],
+        }
+    }
+
+    fn start_pinch(&self) -> Pinch {
+        let mut ids = self.pointers.keys().copied().collect::<Vec<_>>();
+        ids.sort_unstable();
+        let (p0, p1) = (self.pointers[&ids[0]], self.pointers[&ids[1]]);
+
+        Pinch {
+            ids: [ids[0], ids[1]],
+            dist: (p1 - p0).magnitude(),
+            angle: (p1.y - p0.y).atan2(p1.x - p0.x).to_degrees(),
+            rotating: false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{InputAction, InputController};
+    use al_api::input::InputCfg;
+    use cgmath::Vector2;
+
+    #[test]
+    fn test_pinch_gesture() {
+        let mut input = InputController::new();
+        assert_eq!(input.pointer_down(0, Vector2::new(0.0, 0.0)), vec![InputAction::DragStart(Vector2::new(0.0, 0.0))]);
+        assert!(input.pointer_down(1, Vector2::new(100.0, 0.0)).is_empty());
+
+        // Spreading the fingers zooms in
+        let actions = input.pointer_move(1, Vector2::new(200.0, 0.0));
+        assert_eq!(actions, vec![InputAction::Zoom { factor: 0.5, smooth: false }]);
+
+        // Releasing a finger goes back to dragging
+        let actions = input.pointer_up(0);
+        assert_eq!(actions, vec![InputAction::DragStart(Vector2::new(200.0, 0.0))]);
+    }
+
+    #[test]
+    fn test_disabled_gestures() {
+        let mut input = InputController::new();
+        input.set_cfg(InputCfg { drag: false, keyboard: false, ..Default::default() });
+
+        assert!(input.pointer_down(0, Vector2::new(0.0, 0.0)).is_empty());
+        assert!(input.pointer_move(0, Vector2::new(10.0, 0.0)).is_empty());
+        assert!(input.key_down("ArrowLeft").is_empty());
+        assert_eq!(input.wheel(1.0).len(), 1);
+    }
+}
@@ -81,13 +81,17 @@ mod shader;
 mod survey;
 mod tile_fetcher;
 mod time;
-mod fifo_cache;
+mod lru_cache;
+mod export;
+mod tour;
+mod input;
 
 use crate::{
     camera::CameraViewPort, math::lonlat::LonLatT, shader::ShaderManager, time::DeltaTime,
     healpix::coverage::HEALPixCoverage,
 };
 use crate::downloader::request::moc::from_fits_hpx;
+use crate::downloader::source::{CallbackSource, MemorySource};
 use moclib::deser::fits::MocQtyType;
 use moclib::deser::fits::MocIdxType;
 use moclib::deser::fits;
@@ -96,10 +100,13 @@ use std::io::Cursor;
 
 use al_api::hips::HiPSProperties;
 use al_api::coo_system::CooSystem;
-use al_api::color::{Color, ColorRGBA};
 use al_api::hips::FITSCfg;
+use al_api::hips::WCSImageCfg;
+use al_api::hips::RGBCompositeCfg;
+use al_api::hips::LayerArithmeticCfg;
+use al_api::state::CustomColormap;
+use al_api::projection::ProjectionCfg;
 
-use al_core::Colormap;
 use al_core::{WebGlContext};
 use al_core::colormap::Colormaps;
 
@@ -121,6 +128,8 @@ pub struct WebClient {
 }
 
 use al_api::hips::ImageMetadata;
+use std::rc::Rc;
+use std::collections::HashMap;
 use std::convert::TryInto;
 
 #[wasm_bindgen]
@@ -192,49 +201,60 @@ impl WebClient {
     ///
     /// # Arguments
     ///
-    /// * `name` - Can be aitoff, mollweide, arc, sinus, tan or mercator
+    /// * `projection` - The name of the projection, e.g. "AIT", "MOL", "TAN" or "MER", or an object
+    ///   giving the FITS WCS parameters of the conic and cylindrical projections,
+    ///   e.g. `{name: "COD", theta_a: 45, eta: 25}`, `{name: "CYP", mu: 1, lambda: 0.7}` or `{name: "CEA", lambda: 1}`
     #[wasm_bindgen(js_name = setProjection)]
-    pub fn set_projection(&mut self, projection: &str) -> Result<(), JsValue> {
-        match projection {
-            // Zenithal
-            "TAN" => self.app.set_projection(ProjectionType::Tan(mapproj::zenithal::tan::Tan::new())),	  /* Gnomonic projection      */
-            "STG" => self.app.set_projection(ProjectionType::Stg(mapproj::zenithal::stg::Stg::new())),	  /* Stereographic projection */
-            "SIN" => self.app.set_projection(ProjectionType::Sin(mapproj::zenithal::sin::Sin::new())),	  /* Orthographic		         */
-            "ZEA" => self.app.set_projection(ProjectionType::Zea(mapproj::zenithal::zea::Zea::new())),	  /* Equal-area 		         */
-            "FEYE" => self.app.set_projection(ProjectionType::Feye(mapproj::zenithal::feye::Feye::new())),
-            "AIR" => {
-                let air_proj = mapproj::zenithal::air::Air::new();
-                //air_proj.set_n_iter(10);
-                //air_proj.set_eps(1e-12);
-                self.app.set_projection(ProjectionType::Air(air_proj))
-            },
-            //"AZP",
-            "ARC" => self.app.set_projection(ProjectionType::Arc(mapproj::zenithal::arc::Arc::new())),
-            "NCP" => self.app.set_projection(ProjectionType::Ncp(mapproj::zenithal::ncp::Ncp::new())),
-            // Cylindrical
-            "MER" => self.app.set_projection(ProjectionType::Mer(mapproj::cylindrical::mer::Mer::new())),
-            "CAR" => self.app.set_projection(ProjectionType::Car(mapproj::cylindrical::car::Car::new())),
-            "CEA" => self.app.set_projection(ProjectionType::Cea(mapproj::cylindrical::cea::Cea::new())),
-            "CYP" => self.app.set_projection(ProjectionType::Cyp(mapproj::cylindrical::cyp::Cyp::new())),
-            // Pseudo-cylindrical
-            "AIT" => self.app.set_projection(ProjectionType::Ait(mapproj::pseudocyl::ait::Ait::new())),
-            "PAR" => self.app.set_projection(ProjectionType::Par(mapproj::pseudocyl::par::Par::new())),
-            "SFL" => self.app.set_projection(ProjectionType::Sfl(mapproj::pseudocyl::sfl::Sfl::new())),
-            "MOL" => {
-                let mut mol_proj = mapproj::pseudocyl::mol::Mol::new();
-                mol_proj.set_n_iter(10);
-                mol_proj.set_epsilon(1e-12);
-
-                self.app.set_projection(ProjectionType::Mol(mol_proj))
-            },
-            // Conic
-            "COD" => self.app.set_projection(ProjectionType::Cod(mapproj::conic::cod::Cod::new())),
-            // Hybrid
-            "HPX" => self.app.set_projection(ProjectionType::Hpx(mapproj::hybrid::hpx::Hpx::new())),
-            _ => {
-                Err(JsValue::from_str("Not a valid projection name. AIT, ARC, SIN, TAN, MOL, HPX and MER are accepted"))
-            }
-        }
+    pub fn set_projection(&mut self, projection: JsValue) -> Result<(), JsValue> {
+        let projection = parse_projection(projection)?;
+
+        self.app.set_projection(projection)
+    }
+
+    /// Change the projection progressively
+    ///
+    /// The positions of the layers, the grid and the MOCs are interpolated from
+    /// the current projection to the new one.
+    ///
+    /// # Arguments
+    ///
+    /// * `projection` - The projection to reach, as for `setProjection`
+    /// * `duration` - The duration of the transition in seconds
+    ///
+    /// # Return
+    ///
+    /// A promise resolving to `true` when the transition is finished, `false` if it has been interrupted
+    #[wasm_bindgen(js_name = morphProjection)]
+    pub fn morph_projection(&mut self, projection: JsValue, duration: f32) -> Result<js_sys::Promise, JsValue> {
+        let projection = parse_projection(projection)?;
+
+        self.app.morph_projection(projection, duration * 1000.0)
+    }
+
+    /// Tell whether the projection is in transition to another one
+    #[wasm_bindgen(js_name = isMorphingProjection)]
+    pub fn is_morphing_projection(&self) -> bool {
+        self.app.is_morphing_projection()
+    }
+
+    /// Set the view in the projection of an image layer so that the image is displayed
+    /// pixel per pixel, without any reprojection
+    ///
+    /// The view is centered on the reference pixel of the image, rotated and zoomed so that
+    /// one image pixel covers one screen pixel. The HiPS layers are reprojected around the image.
+    /// The view must be set again after a resize of the canvas to keep this scale.
+    ///
+    /// # Arguments
+    ///
+    /// * `layer` - The name of the FITS or PNG/JPEG image layer
+    ///
+    /// # Return
+    ///
+    /// The name of the projection of the image, e.g. "TAN". The view also takes the
+    /// frame of the image, given by [`WebClient::get_coo_system`]
+    #[wasm_bindgen(js_name = setNativeImageView)]
+    pub fn set_native_image_view(&mut self, layer: String) -> Result<String, JsValue> {
+        self.app.set_native_image_view(&layer).map(|name| name.to_string())
     }
 
     /// Check whether the app is ready
@@ -308,6 +328,24 @@ impl WebClient {
         Ok(())
     }
 
+    /// Add a HiPS layer from the url of its service
+    ///
+    /// The `properties` file of the HiPS is fetched and parsed to configure it.
+    /// The promise returned resolves to the keywords of that file once the HiPS is added,
+    /// and is rejected if the file cannot be retrieved or is not valid, or if the HiPS cannot be added.
+    ///
+    /// # Arguments
+    ///
+    /// * `layer` - The name of the layer
+    /// * `url` - The root url of the HiPS
+    /// * `meta` - The image metadata of the layer (color, blending, opacity, image format)
+    #[wasm_bindgen(js_name = addImageSurveyFromURL)]
+    pub fn add_image_survey_from_url(&mut self, layer: String, url: String, meta: JsValue) -> Result<js_sys::Promise, JsValue> {
+        let meta: ImageMetadata = serde_wasm_bindgen::from_value(meta)?;
+
+        self.app.add_image_survey_from_url(layer, url, meta)
+    }
+
     #[wasm_bindgen(js_name = addImageFITS)]
     pub fn add_image_fits(&mut self, fits_cfg: JsValue) -> Result<js_sys::Promise, JsValue> {
         let fits_cfg: FITSCfg = serde_wasm_bindgen::from_value(fits_cfg)?;
@@ -315,6 +353,56 @@ impl WebClient {
         self.app.add_image_fits(fits_cfg)
     }
 
+    /// Add a PNG/JPEG image as a layer
+    ///
+    /// The image is reprojected onto the sky from its WCS
+    ///
+    /// # Arguments
+    ///
+    /// * `cfg` - The layer config. The WCS keywords are given as a JSON object string
+    ///   (e.g. `{"CTYPE1": "RA---TAN", "CRVAL1": 83.63, ...}`) or as a FITS header string
+    /// * `image` - An `HTMLImageElement` already loaded or the bytes of the PNG/JPEG file
+    #[wasm_bindgen(js_name = addImageWithWCS)]
+    pub fn add_image_with_wcs(&mut self, cfg: JsValue, image: JsValue) -> Result<js_sys::Promise, JsValue> {
+        let cfg: WCSImageCfg = serde_wasm_bindgen::from_value(cfg)?;
+
+        self.app.add_image_with_wcs(cfg, image)
+    }
+
+    /// Add a color layer combining three layers already added
+    ///
+    /// Each channel layer is rendered with its own cuts and transfer function, in grayscale
+    /// and opaque. While the composite is visible, its channel layers are only drawn through it.
+    /// They cannot be removed before the composite.
+    ///
+    /// # Arguments
+    ///
+    /// * `cfg` - The composite config giving the `red`, `green` and `blue` layer names,
+    ///   an optional Lupton asinh scaling (e.g. `lupton: {q: 8, stretch: 0.5}`) and the
+    ///   meta of the layer (opacity, blending, tonal settings)
+    #[wasm_bindgen(js_name = addRGBComposite)]
+    pub fn add_rgb_composite(&mut self, cfg: JsValue) -> Result<(), JsValue> {
+        let cfg: RGBCompositeCfg = serde_wasm_bindgen::from_value(cfg)?;
+
+        self.app.add_rgb_composite(cfg)
+    }
+
+    /// Add a layer computing `a - b`, `a / b` or `(a - b) / (a + b)` from two layers already added
+    ///
+    /// The operand layers must store FITS values (i.e. FITS HiPS or FITS images).
+    /// The result is colormapped like any other layer.
+    ///
+    /// # Arguments
+    ///
+    /// * `cfg` - The config giving the `a` and `b` layer names, the operation
+    ///   (`"difference"`, `"ratio"` or `"normalizedDifference"`) and the meta of the layer
+    #[wasm_bindgen(js_name = addLayerArithmetic)]
+    pub fn add_layer_arithmetic(&mut self, cfg: JsValue) -> Result<(), JsValue> {
+        let cfg: LayerArithmeticCfg = serde_wasm_bindgen::from_value(cfg)?;
+
+        self.app.add_layer_arithmetic(cfg)
+    }
+
     #[wasm_bindgen(js_name = removeLayer)]
     pub fn remove_layer(&mut self, layer: String) -> Result<(), JsValue> {
         // Deserialize the survey objects that compose the survey
@@ -340,6 +428,209 @@ impl WebClient {
         self.app.set_hips_url(past_url, new_url)
     }
 
+    /// Set the memory shared by the HiPS layers in bytes
+    ///
+    /// It bounds the memory taken by the downloaded resources kept in cache
+    /// and by the textures of the HiPS of all the views. When it is exceeded, the least
+    /// recently used textures of all the HiPS are released first, then the least recently
+    /// used resources of the cache.
+    #[wasm_bindgen(js_name = setMemoryBudget)]
+    pub fn set_memory_budget(&mut self, num_bytes: usize) {
+        self.app.set_memory_budget(num_bytes);
+    }
+
+    /// Get the memory statistics of the resource cache and the textures
+    ///
+    /// The object returned gives the memory `budget`, the memory used `numBytes`,
+    /// the cache `numHits` and `numMisses` and for each HiPS layer the memory taken
+    /// by its cached resources (`cpuNumBytes`) and its textures (`gpuNumBytes`).
+    #[wasm_bindgen(js_name = getCacheStats)]
+    pub fn get_cache_stats(&self) -> Result<JsValue, JsValue> {
+        let stats = self.app.get_cache_stats();
+        Ok(serde_wasm_bindgen::to_value(&stats)?)
+    }
+
+    /// Set the maximum number of tiles downloaded at the same time, all the HiPS included
+    ///
+    /// Pending tiles are fetched coarsest first, then by increasing distance to the center of the view.
+    /// The default is 8.
+    #[wasm_bindgen(js_name = setTileFetchConcurrency)]
+    pub fn set_tile_fetch_concurrency(&mut self, max_num_fetching: usize) {
+        self.app.set_tile_fetch_concurrency(max_num_fetching);
+    }
+
+    /// Set the maximum number of tiles of a HiPS layer downloaded at the same time
+    ///
+    /// # Arguments
+    ///
+    /// * `layer` - The HiPS layer
+    /// * `max_num_fetching` - The limit, or `undefined` to only apply the global one
+    #[wasm_bindgen(js_name = setHiPSTileFetchConcurrency)]
+    pub fn set_hips_tile_fetch_concurrency(&mut self, layer: String, max_num_fetching: Option<usize>) -> Result<(), JsValue> {
+        self.app.set_hips_tile_fetch_concurrency(&layer, max_num_fetching)
+    }
+
+    /// Enable or disable the prefetching of tiles
+    ///
+    /// When the network is idle, the tiles surrounding the view and the ones of the next deeper order
+    /// are fetched with a low priority and kept in the cache. It is enabled by default.
+    #[wasm_bindgen(js_name = setTilePrefetching)]
+    pub fn set_tile_prefetching(&mut self, enabled: bool) {
+        self.app.set_tile_prefetching(enabled);
+    }
+
+    /// Fetch ahead of time the tiles of a view the camera is going to
+    ///
+    /// # Arguments
+    ///
+    /// * `lon` - The longitude of the center of the view in degrees
+    /// * `lat` - The latitude of the center of the view in degrees
+    /// * `fov` - The field of view in degrees
+    #[wasm_bindgen(js_name = prefetchView)]
+    pub fn prefetch_view(&mut self, lon: f64, lat: f64, fov: f64) {
+        let location = LonLatT::new(ArcDeg(lon).into(), ArcDeg(lat).into());
+
+        self.app.prefetch_view(&location, ArcDeg(fov).into());
+    }
+
+    /// Get the base url from which the tiles of a HiPS layer are fetched
+    ///
+    /// This tells which mirror is in use when the HiPS has been added with several mirrors.
+    /// Aladin Lite switches to another mirror when the one in use keeps failing. A failing
+    /// mirror can be switched back to after a minute.
+    #[wasm_bindgen(js_name = getHiPSUrl)]
+    pub fn get_hips_url(&self, layer: String) -> Result<String, JsValue> {
+        self.app.get_hips_url(&layer)
+    }
+
+    /// Read the files of a HiPS from a zip or tar archive instead of its web server
+    ///
+    /// The directory containing the `properties` file is taken as the root of the HiPS.
+    /// Returns the content of that `properties` file if there is one.
+    ///
+    /// # Arguments
+    ///
+    /// * `hips_url` - The root url identifying the HiPS, i.e. the one given to `addImageSurvey`
+    /// * `bytes` - The content of the archive
+    #[wasm_bindgen(js_name = setTileSourceFromArchive)]
+    pub fn set_tile_source_from_archive(&mut self, hips_url: String, bytes: &[u8]) -> Result<Option<String>, JsValue> {
+        let source = MemorySource::from_archive(bytes)?;
+        let properties = source.get_properties();
+
+        self.app.set_hips_tile_source(hips_url, Rc::new(source));
+
+        Ok(properties)
+    }
+
+    /// Read the files of a HiPS from memory instead of its web server
+    ///
+    /// # Arguments
+    ///
+    /// * `hips_url` - The root url identifying the HiPS, i.e. the one given to `addImageSurvey`
+    /// * `files` - An object whose keys are the paths of the files relative to the root of the HiPS
+    ///   (e.g. `Norder3/Dir0/Npix42.jpg`) and values their content as an `Uint8Array` or an `ArrayBuffer`
+    #[wasm_bindgen(js_name = setTileSourceFromFiles)]
+    pub fn set_tile_source_from_files(&mut self, hips_url: String, files: js_sys::Object) -> Result<(), JsValue> {
+        let files = js_sys::Object::entries(&files)
+            .iter()
+            .map(|entry| {
+                let entry = entry.dyn_into::<js_sys::Array>()?;
+                let path = entry.get(0).as_string().ok_or_else(|| JsValue::from_str("The paths must be strings"))?;
+                let bytes = entry.get(1);
+                let bytes = if let Some(buffer) = bytes.dyn_ref::<js_sys::ArrayBuffer>() {
+                    js_sys::Uint8Array::new(buffer)
+                } else {
+                    bytes.dyn_into::<js_sys::Uint8Array>()
+                        .map_err(|_| JsValue::from_str("The files must be given as Uint8Array or ArrayBuffer"))?
+                };
+
+                Ok((path, bytes.to_vec()))
+            })
+            .collect::<Result<HashMap<_, _>, JsValue>>()?;
+
+        self.app.set_hips_tile_source(hips_url, Rc::new(MemorySource::new(files)));
+
+        Ok(())
+    }
+
+    /// Read the files of a HiPS through a JS function instead of its web server
+    ///
+    /// # Arguments
+    ///
+    /// * `hips_url` - The root url identifying the HiPS, i.e. the one given to `addImageSurvey`
+    /// * `callback` - A function called with the path of a file relative to the root of the HiPS.
+    ///   It returns the content of the file as an `Uint8Array` or an `ArrayBuffer`, possibly through a `Promise`,
+    ///   and `null` if the file does not exist
+    #[wasm_bindgen(js_name = setTileSourceFromCallback)]
+    pub fn set_tile_source_from_callback(&mut self, hips_url: String, callback: js_sys::Function) -> Result<(), JsValue> {
+        self.app.set_hips_tile_source(hips_url, Rc::new(CallbackSource::new(callback)));
+
+        Ok(())
+    }
+
+    /// Get the range of the pixel values of a FITS HiPS layer, e.g. to bound the cut sliders
+    ///
+    /// It is given by the `hips_data_range` property, or computed from the allsky once received.
+    /// Returns `undefined` if it is not known yet.
+    #[wasm_bindgen(js_name = getHiPSDataRange)]
+    pub fn get_hips_data_range(&self, layer: String) -> Result<Option<Box<[f32]>>, JsValue> {
+        let range = self.app.get_hips_data_range(&layer)?;
+        Ok(range.map(|range| Box::new(range) as Box<[f32]>))
+    }
+
+    /// Get the cuts applied to a FITS HiPS layer when none are given in its color config
+    ///
+    /// They are given by the `hips_pixel_cut` property, then `hips_data_range`, or computed from the allsky.
+    /// Returns `undefined` if they are not known yet.
+    #[wasm_bindgen(js_name = getHiPSDefaultCuts)]
+    pub fn get_hips_default_cuts(&self, layer: String) -> Result<Option<Box<[f32]>>, JsValue> {
+        let cuts = self.app.get_hips_default_cuts(&layer)?;
+        Ok(cuts.map(|cuts| Box::new(cuts) as Box<[f32]>))
+    }
+
+    /// Get the download statistics of the tiles of a HiPS
+    ///
+    /// The object returned gives the number of tiles found, missing on the server
+    /// (`numMissing`), not retrieved after all the retries (`numFailed`) and the
+    /// total number of retries (`numRetries`).
+    #[wasm_bindgen(js_name = getHiPSTileStats)]
+    pub fn get_hips_tile_stats(&self, layer: String) -> Result<JsValue, JsValue> {
+        let stats = self.app.get_hips_tile_stats(&layer)?;
+        Ok(serde_wasm_bindgen::to_value(&stats)?)
+    }
+
+    /// Request again the tiles that could not be downloaded
+    ///
+    /// Tiles failing because of transient errors (server errors, network resets)
+    /// are displayed as missing once all their retries are exhausted. So are the
+    /// tiles denied by the server (HTTP 401, 403). This can be called e.g. after
+    /// a network outage or once authorized to retrieve them.
+    #[wasm_bindgen(js_name = resetFailedTiles)]
+    pub fn reset_failed_tiles(&mut self) {
+        self.app.reset_failed_tiles()
+    }
+
+    /// Display a frame of a HiPS cube
+    ///
+    /// # Arguments
+    ///
+    /// * `layer` - The layer name of the HiPS cube
+    /// * `frame` - The frame index, between `hips_cube_firstframe` and
+    ///   `hips_cube_firstframe + hips_cube_depth - 1`
+    #[wasm_bindgen(js_name = setHiPSCubeFrame)]
+    pub fn set_hips_cube_frame(&mut self, layer: String, frame: u32) -> Result<(), JsValue> {
+        self.app.set_hips_cube_frame(&layer, frame)
+    }
+
+    /// Get the spectral coordinate of the frame displayed for a HiPS cube
+    ///
+    /// It is computed from the `hips_cube_crpix3`, `hips_cube_crval3` and
+    /// `hips_cube_cdelt3` properties and is undefined if they are not given.
+    #[wasm_bindgen(js_name = getHiPSCubeFrameValue)]
+    pub fn get_hips_cube_frame_value(&self, layer: String) -> Result<Option<f64>, JsValue> {
+        self.app.get_hips_cube_frame_value(&layer)
+    }
+
     #[wasm_bindgen(js_name = getImageMetadata)]
     pub fn get_layer_cfg(&self, layer: String) -> Result<ImageMetadata, JsValue> {
         self.app.get_layer_cfg(&layer)
@@ -393,6 +684,12 @@ impl WebClient {
         Ok(())
     }
 
+    /// Get the coordinate system of the view
+    #[wasm_bindgen(js_name = getCooSystem)]
+    pub fn get_coo_system(&self) -> Result<CooSystem, JsValue> {
+        Ok(self.app.get_coo_system())
+    }
+
     /// Get the field of the view in degrees
     #[wasm_bindgen(js_name = getFieldOfView)]
     pub fn get_fov(&self) -> Result<f64, JsValue> {
@@ -481,6 +778,194 @@ impl WebClient {
         Ok(())
     }
 
+    /// Move the view smoothly to a location and a field of view
+    ///
+    /// The camera zooms out during the transition when the location is far away.
+    /// Any user interaction with the view interrupts it.
+    ///
+    /// # Arguments
+    ///
+    /// * `lon` - A longitude in degrees (ICRS)
+    /// * `lat` - A latitude in degrees (ICRS)
+    /// * `fov` - The field of view to reach in degrees
+    /// * `duration` - The duration of the transition in seconds
+    ///
+    /// # Return
+    ///
+    /// A promise resolving to `true` when the transition is finished, `false` if it has been interrupted
+    #[wasm_bindgen(js_name = flyTo)]
+    pub fn fly_to(&mut self, lon: f64, lat: f64, fov: f64, duration: f32) -> js_sys::Promise {
+        let location = LonLatT::new(ArcDeg(lon).into(), ArcDeg(lat).into());
+
+        self.app.fly_to(&location, ArcDeg(fov).into(), duration * 1000.0)
+    }
+
+    /// Tell whether an animated transition of the view is running
+    #[wasm_bindgen(js_name = isFlying)]
+    pub fn is_flying(&self) -> bool {
+        self.app.is_flying()
+    }
+
+    /// Load a tour
+    ///
+    /// # Arguments
+    ///
+    /// * `tour` - The tour as a JSON string or as an object having the form
+    ///   `{keyframes: [{time, ra, dec, fov, rotation, projection, opacities}, ...]}`.
+    ///   `time` is given in seconds, angles in degrees, `opacities` maps layer names to opacities.
+    ///   `projection` is a name or an object with a name and the parameters of the projection.
+    #[wasm_bindgen(js_name = loadTour)]
+    pub fn load_tour(&mut self, tour: JsValue) -> Result<(), JsValue> {
+        let tour: al_api::tour::Tour = if let Some(json) = tour.as_string() {
+            serde_json::from_str(&json).map_err(|e| JsValue::from_str(&e.to_string()))?
+        } else {
+            serde_wasm_bindgen::from_value(tour)?
+        };
+
+        self.app.load_tour(al_api::tour::Tour::new(tour.keyframes().to_vec()))
+    }
+
+    /// Get the tour loaded as an object, `undefined` if there is none
+    #[wasm_bindgen(js_name = getTour)]
+    pub fn get_tour(&self) -> Result<JsValue, JsValue> {
+        Ok(serde_wasm_bindgen::to_value(&self.app.get_tour())?)
+    }
+
+    /// Serialize the tour loaded to JSON
+    #[wasm_bindgen(js_name = exportTour)]
+    pub fn export_tour(&self) -> Result<String, JsValue> {
+        let tour = self.app.get_tour()
+            .ok_or_else(|| JsValue::from_str("No tour has been loaded"))?;
+
+        serde_json::to_string(tour).map_err(|e| JsValue::from_str(&e.to_string()))
+    }
+
+    /// Play the tour loaded from its current time
+    ///
+    /// Any user interaction with the view pauses it.
+    #[wasm_bindgen(js_name = playTour)]
+    pub fn play_tour(&mut self) -> Result<(), JsValue> {
+        self.app.play_tour()
+    }
+
+    #[wasm_bindgen(js_name = pauseTour)]
+    pub fn pause_tour(&mut self) -> Result<(), JsValue> {
+        self.app.pause_tour()
+    }
+
+    /// Move to a time of the tour loaded
+    ///
+    /// # Arguments
+    ///
+    /// * `time` - The time in seconds from the start of the tour
+    #[wasm_bindgen(js_name = seekTour)]
+    pub fn seek_tour(&mut self, time: f64) -> Result<(), JsValue> {
+        self.app.seek_tour(time)
+    }
+
+    /// Get the current time in seconds of the tour loaded
+    #[wasm_bindgen(js_name = getTourTime)]
+    pub fn get_tour_time(&self) -> Option<f64> {
+        self.app.get_tour_time()
+    }
+
+    #[wasm_bindgen(js_name = isTourPlaying)]
+    pub fn is_tour_playing(&self) -> bool {
+        self.app.is_tour_playing()
+    }
+
+    /// Start recording a tour
+    ///
+    /// The keyframes added by [`WebClient::record_tour_keyframe`] are timed from now.
+    #[wasm_bindgen(js_name = startTourRecording)]
+    pub fn start_tour_recording(&mut self) {
+        self.app.start_tour_recording();
+    }
+
+    /// Add the current view as a keyframe of the tour being recorded
+    #[wasm_bindgen(js_name = recordTourKeyframe)]
+    pub fn record_tour_keyframe(&mut self) -> Result<(), JsValue> {
+        self.app.record_tour_keyframe()
+    }
+
+    /// Stop recording the tour
+    ///
+    /// The tour recorded is loaded and returned as an object
+    #[wasm_bindgen(js_name = stopTourRecording)]
+    pub fn stop_tour_recording(&mut self) -> Result<JsValue, JsValue> {
+        let tour = self.app.stop_tour_recording()?;
+
+        Ok(serde_wasm_bindgen::to_value(tour)?)
+    }
+
+    /// A pointer (mouse, finger or pen) is pressed on the view
+    ///
+    /// # Arguments
+    ///
+    /// * `id` - The identifier of the pointer given by the browser
+    /// * `sx` - The x screen coordinate in pixels
+    /// * `sy` - The y screen coordinate in pixels
+    #[wasm_bindgen(js_name = pointerDown)]
+    pub fn pointer_down(&mut self, id: i32, sx: f64, sy: f64) {
+        self.app.pointer_down(id, sx, sy);
+    }
+
+    /// A pointer pressed on the view moves
+    #[wasm_bindgen(js_name = pointerMove)]
+    pub fn pointer_move(&mut self, id: i32, sx: f64, sy: f64) {
+        self.app.pointer_move(id, sx, sy);
+    }
+
+    /// A pointer is released
+    #[wasm_bindgen(js_name = pointerUp)]
+    pub fn pointer_up(&mut self, id: i32) {
+        self.app.pointer_up(id);
+    }
+
+    /// Zoom smoothly with the wheel
+    ///
+    /// # Arguments
+    ///
+    /// * `delta` - The number of wheel notches, positive to zoom in
+    pub fn wheel(&mut self, delta: f64) {
+        self.app.wheel(delta);
+    }
+
+    /// A key is pressed, returns whether the key moved the view
+    ///
+    /// # Arguments
+    ///
+    /// * `key` - The `KeyboardEvent.key` value
+    #[wasm_bindgen(js_name = keyDown)]
+    pub fn key_down(&mut self, key: &str) -> bool {
+        self.app.key_down(key)
+    }
+
+    /// Whether the view is still moving after the inputs (inertia or smooth zoom)
+    #[wasm_bindgen(js_name = isInputAnimating)]
+    pub fn is_input_animating(&self) -> bool {
+        self.app.is_input_animating()
+    }
+
+    /// Enable/disable the gestures and set their sensitivity
+    ///
+    /// # Arguments
+    ///
+    /// * `cfg` - An object of the form `{drag, pinchZoom, rotation, wheelZoom, keyboard,
+    ///   zoomSensitivity, panStep, rotationSensitivity}`. The missing fields take their default value.
+    #[wasm_bindgen(js_name = setInputConfig)]
+    pub fn set_input_config(&mut self, cfg: JsValue) -> Result<(), JsValue> {
+        let cfg = serde_wasm_bindgen::from_value(cfg)?;
+        self.app.set_input_cfg(cfg);
+
+        Ok(())
+    }
+
+    #[wasm_bindgen(js_name = getInputConfig)]
+    pub fn get_input_config(&self) -> Result<JsValue, JsValue> {
+        Ok(serde_wasm_bindgen::to_value(self.app.get_input_cfg())?)
+    }
+
     /// Get the center of the view
     ///
     /// This returns a javascript array of size 2.
@@ -561,33 +1046,40 @@ impl WebClient {
 
     /// World to screen projection
     ///
-    /// Coordinates must be given in the ICRS coo system
+    /// Coordinates must be given in the ICRS coo system.
+    /// The position returned is given in pixels from the top-left corner of the canvas.
     ///
     /// # Arguments
     ///
     /// * `lon` - A longitude in degrees
     /// * `lat` - A latitude in degrees
+    /// * `view` - The index of the view projecting the position, the active one if not given
     #[wasm_bindgen(js_name = worldToScreen)]
-    pub fn world_to_screen(&self, lon: f64, lat: f64) -> Option<Box<[f64]>> {
-        self.app.world_to_screen(lon, lat)
-            .map(|v| Box::new([v.x, v.y]) as Box<[f64]>)
+    pub fn world_to_screen(&self, lon: f64, lat: f64, view: Option<usize>) -> Result<Option<Box<[f64]>>, JsValue> {
+        let pos = self.app.world_to_screen(lon, lat, view)?
+            .map(|v| Box::new([v.x, v.y]) as Box<[f64]>);
+
+        Ok(pos)
     }
 
     /// Screen to world unprojection
     ///
     /// # Arguments
     ///
-    /// * `pos_x` - The x screen coordinate in pixels
-    /// * `pos_y` - The y screen coordinate in pixels
+    /// * `pos_x` - The x screen coordinate in pixels from the left of the canvas
+    /// * `pos_y` - The y screen coordinate in pixels from the top of the canvas
+    /// * `view` - The index of the view unprojecting the position, the active one if not given
     #[wasm_bindgen(js_name = screenToWorld)]
-    pub fn screen_to_world(&self, pos_x: f64, pos_y: f64) -> Option<Box<[f64]>> {
-        self.app.screen_to_world(&Vector2::new(pos_x, pos_y))
+    pub fn screen_to_world(&self, pos_x: f64, pos_y: f64, view: Option<usize>) -> Result<Option<Box<[f64]>>, JsValue> {
+        let lonlat = self.app.screen_to_world(&Vector2::new(pos_x, pos_y), view)?
             .map(|lonlat| {
                 let lon_deg: ArcDeg<f64> = lonlat.lon().into();
                 let lat_deg: ArcDeg<f64> = lonlat.lat().into();
 
                 Box::new([lon_deg.0, lat_deg.0]) as Box<[f64]>
-            })
+            });
+
+        Ok(lonlat)
     }
 
     /// Signal the backend when the left mouse button has been released.
@@ -735,30 +1227,42 @@ impl WebClient {
 
     #[wasm_bindgen(js_name = createCustomColormap)]
     pub fn add_custom_colormap(&mut self, label: String, hex_colors: Vec<JsValue>) -> Result<(), JsValue> {
-        let rgba_colors: Result<Vec<_>, JsValue> = hex_colors
+        let colors = hex_colors
             .into_iter()
-            .map(|hex_color| {
-                let hex_color = serde_wasm_bindgen::from_value(hex_color)?;
-                let color = Color::hexToRgba(hex_color);
-                let color_rgba: ColorRGBA = color.try_into()?;
-            
-                Ok(colorgrad::Color::new(
-                    color_rgba.r as f64,
-                    color_rgba.g as f64,
-                    color_rgba.b as f64,
-                    color_rgba.a as f64)
-                )
-            })
-            .collect();
+            .map(serde_wasm_bindgen::from_value)
+            .collect::<Result<Vec<String>, _>>()?;
 
-        let grad = colorgrad::CustomGradient::new()
-            .colors(&rgba_colors?)
-            .build()
-            .map_err(|err| JsValue::from_str(&format!("{:?}", err)))?;
+        self.app.add_custom_cmap(CustomColormap { label, colors })
+    }
 
-        let cmap = Colormap::new(&label, grad);
-        self.app.add_cmap(label, cmap)?;
-        Ok(())
+    /// Get the state of the view
+    ///
+    /// This returns an object `{ra, dec, fov, rotation, projection, frame, grid, colormaps,
+    /// composites, arithmeticLayers, contours}` giving the center (ICRS) and the rotation in degrees,
+    /// the grid config, the colormaps created with `createCustomColormap` and the configs given to
+    /// `addRGBComposite`, `addLayerArithmetic` and `addContours`.
+    /// It throws if the view has HiPS read from a tile source, as they are not part of a state.
+    /// Only the active view is saved when there are several views.
+    #[wasm_bindgen(js_name = getViewState)]
+    pub fn get_view_state(&self) -> Result<JsValue, JsValue> {
+        let state = self.app.get_view_state()?;
+        Ok(serde_wasm_bindgen::to_value(&state)?)
+    }
+
+    /// Restore the state of the view
+    ///
+    /// The state is given to the active view, the other views are left unchanged.
+    ///
+    /// # Arguments
+    ///
+    /// * `state` - An object given by `getViewState`
+    ///
+    /// The composites, arithmetic layers and contours of the view are removed. Those of
+    /// the state must be added once the layers they are computed from are added.
+    #[wasm_bindgen(js_name = setViewState)]
+    pub fn set_view_state(&mut self, state: JsValue) -> Result<(), JsValue> {
+        let state = serde_wasm_bindgen::from_value(state)?;
+        self.app.set_view_state(state)
     }
 
     /// Get the image canvas where the webgl rendering is done
@@ -838,6 +1342,174 @@ impl WebClient {
         Ok(())
     }
 
+    /// Add contours computed from the data of a FITS image or FITS HiPS layer
+    ///
+    /// For a HiPS, the contours are computed on each tile received and
+    /// are completed as new tiles arrive.
+    ///
+    /// # Arguments
+    ///
+    /// * `cfg` - The contours config giving its `id`, the `layer` name, the `levels`
+    ///   in physical values, the `color` of the lines and their `thickness` in pixels
+    #[wasm_bindgen(js_name = addContours)]
+    pub fn add_contours(&mut self, cfg: JsValue) -> Result<(), JsValue> {
+        let cfg: al_api::contour::ContourCfg = serde_wasm_bindgen::from_value(cfg)?;
+
+        self.app.add_contours(cfg)
+    }
+
+    /// Remove the contours of id `id`
+    #[wasm_bindgen(js_name = removeContours)]
+    pub fn remove_contours(&mut self, id: String) -> Result<(), JsValue> {
+        self.app.remove_contours(&id)
+    }
+
+    /// Extract the pixels of a FITS layer as a FITS file
+    ///
+    /// A HiPS layer is resampled on the current view so that the cutout is exactly
+    /// what is displayed, with the WCS of the current projection. This requires a
+    /// projection with a FITS equivalent. The pixels of an image layer drawn in the
+    /// rectangle are copied from its data, downsampled to 512 pixels a side at most,
+    /// with the WCS of the image.
+    /// The header also contains the original BSCALE, BZERO and BUNIT of the data.
+    ///
+    /// # Arguments
+    ///
+    /// * `cfg` - The cutout config giving the `layer` name and an optional `rect`
+    ///   (`x`, `y`, `width`, `height` in pixels from the top-left corner of the view).
+    ///   The whole view is extracted if no rectangle is given.
+    #[wasm_bindgen(js_name = getCutout)]
+    pub fn get_cutout(&mut self, cfg: JsValue) -> Result<Box<[u8]>, JsValue> {
+        let cfg: al_api::cutout::CutoutCfg = serde_wasm_bindgen::from_value(cfg)?;
+        let bytes = self.app.get_cutout(cfg)?;
+
+        Ok(bytes.into_boxed_slice())
+    }
+
+    /// Export the view as an image with its WCS
+    ///
+    /// The layers are rendered offscreen at the requested resolution. The WCS reproduces
+    /// the projection, the center, the rotation and the frame of the view.
+    ///
+    /// # Arguments
+    ///
+    /// * `cfg` - An object `{width, height, format}`. `width` and `height` are given in pixels,
+    ///   the size of the view being taken if not given and its aspect ratio kept if only one is given.
+    ///   `format` is "fits" (a RGB cube, default) or "png".
+    ///
+    /// This returns an object `{image, wcs}`: `image` stores the content of the file,
+    /// `wcs` a FITS header giving the WCS of the PNG image (undefined for a FITS export).
+    #[wasm_bindgen(js_name = exportView)]
+    pub fn export_view(&mut self, cfg: JsValue) -> Result<js_sys::Object, JsValue> {
+        let cfg: al_api::export::ExportCfg = serde_wasm_bindgen::from_value(cfg)?;
+        let view = self.app.export_view(cfg)?;
+
+        let obj = js_sys::Object::new();
+        js_sys::Reflect::set(&obj, &"image".into(), &js_sys::Uint8Array::from(&view.image[..]))?;
+        if let Some(wcs) = view.wcs {
+            js_sys::Reflect::set(&obj, &"wcs".into(), &js_sys::Uint8Array::from(&wcs[..]))?;
+        }
+
+        Ok(obj)
+    }
+
+    /// Render the view into a PNG at a high resolution
+    ///
+    /// The image is rendered offscreen part by part, each one once the tiles it needs have been received.
+    /// The view on the screen is frozen meanwhile.
+    ///
+    /// # Arguments
+    ///
+    /// * `cfg` - An object `{width, height, catalogs, timeout}`. `width` and `height` are given in pixels
+    ///   as for `exportView`. `catalogs` is a list of `{sources, color, size, shape}` whose sources, given
+    ///   as `[ra, dec]` in degrees, are drawn over the layers. `timeout` is the time in seconds waited
+    ///   for the tiles of each part of the image (30 by default).
+    ///
+    /// This returns a promise resolving with the bytes of the PNG.
+    #[wasm_bindgen(js_name = snapshot)]
+    pub fn snapshot(&mut self, cfg: JsValue) -> Result<js_sys::Promise, JsValue> {
+        let cfg: al_api::export::SnapshotCfg = serde_wasm_bindgen::from_value(cfg)?;
+
+        self.app.snapshot(cfg)
+    }
+
+    /// Add a view of the sky sharing the canvas with the others
+    ///
+    /// The view starts at the position of the active one and has no layer. The layers,
+    /// the projection and the moves apply to the active view.
+    ///
+    /// # Arguments
+    ///
+    /// * `rect` - An object `{x, y, width, height}` giving the area of the canvas the view is rendered in,
+    ///   in fractions of the canvas size from its top-left corner.
+    ///
+    /// This returns the index of the new view.
+    #[wasm_bindgen(js_name = addView)]
+    pub fn add_view(&mut self, rect: JsValue) -> Result<usize, JsValue> {
+        let rect = serde_wasm_bindgen::from_value(rect)?;
+
+        self.app.add_view(rect)
+    }
+
+    /// Remove a view given by its index, the last view cannot be removed
+    ///
+    /// The indices of the views after it are shifted down
+    #[wasm_bindgen(js_name = removeView)]
+    pub fn remove_view(&mut self, idx: usize) -> Result<(), JsValue> {
+        self.app.remove_view(idx)
+    }
+
+    /// Make a view the active one
+    ///
+    /// Pressing a pointer on a view also makes it the active one
+    #[wasm_bindgen(js_name = setActiveView)]
+    pub fn set_active_view(&mut self, idx: usize) -> Result<(), JsValue> {
+        self.app.set_active_view(idx)
+    }
+
+    #[wasm_bindgen(js_name = getActiveView)]
+    pub fn get_active_view(&self) -> usize {
+        self.app.get_active_view()
+    }
+
+    /// Get the index of the view under a position of the canvas, the active view being on top
+    ///
+    /// # Arguments
+    ///
+    /// * `pos_x` - The x screen coordinate in pixels from the left of the canvas
+    /// * `pos_y` - The y screen coordinate in pixels from the top of the canvas
+    #[wasm_bindgen(js_name = getViewAt)]
+    pub fn get_view_at(&self, pos_x: f64, pos_y: f64) -> usize {
+        self.app.get_view_at(pos_x, pos_y)
+    }
+
+    /// Set the area of the canvas the active view is rendered in
+    ///
+    /// # Arguments
+    ///
+    /// * `rect` - An object `{x, y, width, height}` in fractions of the canvas size from its top-left corner
+    #[wasm_bindgen(js_name = setViewRect)]
+    pub fn set_view_rect(&mut self, rect: JsValue) -> Result<(), JsValue> {
+        let rect = serde_wasm_bindgen::from_value(rect)?;
+        self.app.set_view_rect(rect);
+
+        Ok(())
+    }
+
+    /// Make the other views follow the active one
+    ///
+    /// # Arguments
+    ///
+    /// * `lock` - An object `{center, fov, rotation}` of booleans telling what the views share.
+    ///   Nothing is shared by default.
+    #[wasm_bindgen(js_name = lockViews)]
+    pub fn lock_views(&mut self, lock: JsValue) -> Result<(), JsValue> {
+        let lock = serde_wasm_bindgen::from_value(lock)?;
+        self.app.set_view_lock(lock);
+
+        Ok(())
+    }
+
     #[wasm_bindgen(js_name = setMocParams)]
     pub fn set_moc_params(&mut self, params: &al_api::moc::MOC) -> Result<(), JsValue> {
         self.app.set_moc_params(params.clone())?;
@@ -861,3 +1533,14 @@ impl WebClient {
         Ok(moc.coverage_percentage() as f32)
     }
 }
+
+// A projection is given by its name or by an object with its name and its parameters
+fn parse_projection(projection: JsValue) -> Result<ProjectionType, JsValue> {
+    if let Some(name) = projection.as_string() {
+        ProjectionType::from_name(&name)
+            .ok_or_else(|| JsValue::from_str("Not a valid projection name. AIT, ARC, SIN, TAN, MOL, HPX and MER are accepted"))
+    } else {
+        let cfg: ProjectionCfg = serde_wasm_bindgen::from_value(projection)?;
+        ProjectionType::from_params(&cfg.name, &cfg.params)
+    }
+}
@@ -0,0 +1,124 @@
+use std::collections::{HashMap, VecDeque};
+
+// Least recently used cache whose capacity is given in bytes
+pub struct Cache<K, V> {
+    data: HashMap<K, (V, usize)>,
+    // Keys from the least to the most recently used
+    order: VecDeque<K>,
+
+    num_bytes: usize,
+    capacity: usize,
+}
+
+// 256MB by default
+const DEFAULT_CAPACITY_RESOURCE_CACHE: usize = 256 * 1024 * 1024;
+use std::hash::Hash;
+impl<K, V> Cache<K, V>
+where
+    K: Clone + std::cmp::Eq + Hash
+{
+    pub fn new() -> Self {
+        let data = HashMap::new();
+        let order = VecDeque::new();
+        Cache {
+            data,
+            order,
+            num_bytes: 0,
+            capacity: DEFAULT_CAPACITY_RESOURCE_CACHE,
+        }
+    }
+
+    pub fn insert(&mut self, key: K, val: V, num_bytes: usize) {
+        self.remove(&key);
+
+        self.data.insert(key.clone(), (val, num_bytes));
+        self.order.push_back(key);
+        self.num_bytes += num_bytes;
+
+        self.evict();
+    }
+
+    pub fn extract(&mut self, key: &K) -> Option<V> {
+        self.remove(key)
+    }
+
+    pub fn contains(&self, key: &K) -> bool {
+        self.data.contains_key(key)
+    }
+
+    /// Mark a value as the most recently used one
+    pub fn touch(&mut self, key: &K) {
+        if let Some(idx) = self.order.iter().position(|k| k == key) {
+            if let Some(key) = self.order.remove(idx) {
+                self.order.push_back(key);
+            }
+        }
+    }
+
+    /// Set the capacity in bytes, the least recently used values are evicted if needed
+    pub fn set_capacity(&mut self, capacity: usize) {
+        self.capacity = capacity;
+        self.evict();
+    }
+
+    #[inline]
+    pub fn num_bytes(&self) -> usize {
+        self.num_bytes
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&K, &V, usize)> {
+        self.data.iter().map(|(k, (v, num_bytes))| (k, v, *num_bytes))
+    }
+
+    fn remove(&mut self, key: &K) -> Option<V> {
+        let (val, num_bytes) = self.data.remove(key)?;
+
+        self.num_bytes -= num_bytes;
+        if let Some(idx) = self.order.iter().position(|k| k == key) {
+            self.order.remove(idx);
+        }
+
+        Some(val)
+    }
+
+    fn evict(&mut self) {
+        while self.num_bytes > self.capacity {
+            if let Some(key) = self.order.pop_front() {
+                if let Some((_, num_bytes)) = self.data.remove(&key) {
+                    self.num_bytes -= num_bytes;
+                }
+            } else {
+                break;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Cache;
+
+    #[test]
+    fn test_cache_eviction() {
+        let mut cache = Cache::new();
+        cache.set_capacity(100);
+
+        cache.insert("a", 0, 40);
+        cache.insert("b", 1, 40);
+        assert_eq!(cache.num_bytes(), 80);
+
+        // "b" becomes the least recently used one
+        cache.touch(&"a");
+        cache.insert("c", 2, 40);
+        assert!(!cache.contains(&"b"));
+        assert!(cache.contains(&"a") && cache.contains(&"c"));
+        assert_eq!(cache.num_bytes(), 80);
+
+        assert_eq!(cache.extract(&"a"), Some(0));
+        assert_eq!(cache.num_bytes(), 40);
+
+        cache.set_capacity(10);
+        assert!(!cache.contains(&"c"));
+        assert_eq!(cache.num_bytes(), 0);
+    }
+}
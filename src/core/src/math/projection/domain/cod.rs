@@ -26,18 +26,39 @@ pub struct Cod {
 }
 
 impl Cod {
-    pub const fn new() -> Self {
+    /// The domain of the conic equidistant projection, `theta_a` and `eta` being given in radians
+    pub fn from_params(theta_a: f64, eta: f64) -> Self {
+        let (c, y0) = if eta == 0.0 {
+            (theta_a.sin(), 1.0 / theta_a.tan())
+        } else {
+            (theta_a.sin() * eta.sin() / eta, eta / (theta_a.tan() * eta.tan()))
+        };
+
+        let ta_plus_y0 = theta_a + y0;
+        let (r_min, r_max) = if ta_plus_y0 >= 0.0 {
+            (ta_plus_y0 - HALF_PI, ta_plus_y0 + HALF_PI)
+        } else {
+            (ta_plus_y0 + HALF_PI, ta_plus_y0 - HALF_PI)
+        };
+
+        let negative_ta = theta_a < 0.0;
+        let (y_min, y_max) = if negative_ta {
+            (y0 - r_max * (PI * c).cos().abs(), y0 + r_max)
+        } else {
+            (y0 - r_max, y0 + r_max * (PI * c).cos().abs())
+        };
+
         Self {
-            r_min: 0.2146018366,
-            r_max: 3.35619449019,
-            negative_ta: false,
-            c: 0.7071067811865475,
-            y0: 1.0000000000000002,
-
-            x_min: -3.356194490192345,
-            x_max: 3.356194490192345,
-            y_min: -2.356194490192345,
-            y_max: 3.0328465566001492,
+            r_min,
+            r_max,
+            negative_ta,
+            c,
+            y0,
+
+            x_min: -r_max,
+            x_max: r_max,
+            y_min,
+            y_max,
         }
     }
 
@@ -56,12 +77,12 @@ impl ProjDef for Cod {
 
         // Big frontier ellipse
         let a = 1.0;
-        let b = 2.0 * (2.356194490192345 + self.y0) / (2.356194490192345 + 3.0328465566001492);
+        let b = 2.0 * (self.y0 - self.y_min) / (self.y_max - self.y_min);
         let e = b / a;
         let ext_ellipse = Translate { off: center_ellipse, def: Ellipse { a: a, b: b } };
 
         // Small ellipse where projection is not defined
-        let b_int = 2.0 * self.r_min / (2.356194490192345 + 3.0328465566001492);
+        let b_int = 2.0 * self.r_min / (self.y_max - self.y_min);
         let a_int = b_int / e;
         let int_ellipse = Translate { off: center_ellipse, def: Ellipse { a: a_int, b: b_int } };
 
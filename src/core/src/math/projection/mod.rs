@@ -16,9 +16,10 @@ use crate::math::PI;
 use crate::math::{
     rotation::Rotation,
     angle::Angle,
-    HALF_PI
 };
 use cgmath::Vector2;
+use al_api::projection::ProjectionParams;
+use wasm_bindgen::JsValue;
 
 pub mod coo_space;
 pub mod domain;
@@ -146,19 +147,256 @@ pub enum ProjectionType {
     // CAR,                                 */
     Car(mapproj::cylindrical::car::Car),
     // CEA,                                 */
-    Cea(mapproj::cylindrical::cea::Cea),
+    Cea {
+        proj: mapproj::cylindrical::cea::Cea,
+        lambda: f64,
+    },
     // CYP,                                 */
-    Cyp(mapproj::cylindrical::cyp::Cyp),
+    Cyp {
+        proj: mapproj::cylindrical::cyp::Cyp,
+        mu: f64,
+        lambda: f64,
+    },
 
     // Conic projections
     // COD,                                 */
-    Cod(mapproj::conic::cod::Cod),
+    Cod {
+        proj: mapproj::conic::cod::Cod,
+        // The definition domain depends on the standard parallels
+        area: ProjDefType,
+        // Angles in degrees
+        theta_a: f64,
+        eta: f64,
+    },
 
     // HEALPix hybrid projection
     Hpx(mapproj::hybrid::hpx::Hpx),
+
+    // Transition between two projections
+    Morph(Box<Morph>),
+}
+
+/// A projection in transition to another one
+///
+/// The positions of the sky are interpolated between their positions in both projections,
+/// the clip space being scaled so that the screen positions are interpolated
+pub struct Morph {
+    pub from: ProjectionType,
+    pub to: ProjectionType,
+    // Progress of the transition, from 0 to 1
+    pub alpha: f64,
+}
+
+impl Morph {
+    /// The projection the transition is the closest to
+    pub fn nearest(&self) -> &ProjectionType {
+        if self.alpha < 0.5 {
+            &self.from
+        } else {
+            &self.to
+        }
+    }
+
+    fn bounds_size_ratio(&self) -> f64 {
+        let (r0, r1) = (self.from.bounds_size_ratio(), self.to.bounds_size_ratio());
+        r0 + (r1 - r0) * self.alpha
+    }
+
+    // Approximate deprojection, the one of the nearest projection
+    fn clip_to_world_space(&self, xy: &XYClip) -> Option<XYZWWorld> {
+        let proj = self.nearest();
+        let xy = XYClip::new(xy.x, xy.y * proj.bounds_size_ratio() / self.bounds_size_ratio());
+
+        proj.clip_to_world_space(&xy)
+    }
+
+    fn world_to_clip_space(&self, xyzw: &XYZWWorld) -> Option<XYClip> {
+        // The y axis of the clip space is scaled by the ratio of the projection bounds
+        let to_screen = |proj: &ProjectionType| {
+            proj.world_to_clip_space(xyzw)
+                .map(|xy| XYClip::new(xy.x, xy.y / proj.bounds_size_ratio()))
+        };
+
+        let xy = match (to_screen(&self.from), to_screen(&self.to)) {
+            (Some(xy0), Some(xy1)) => xy0 + (xy1 - xy0) * self.alpha,
+            // The positions defined in one projection only appear at the middle of the transition
+            (Some(xy0), None) if self.alpha < 0.5 => xy0,
+            (None, Some(xy1)) if self.alpha >= 0.5 => xy1,
+            _ => return None,
+        };
+
+        Some(XYClip::new(xy.x, xy.y * self.bounds_size_ratio()))
+    }
+}
+
+impl Clone for ProjectionType {
+    fn clone(&self) -> Self {
+        match self {
+            ProjectionType::Morph(morph) => ProjectionType::Morph(Box::new(Morph {
+                from: morph.from.clone(),
+                to: morph.to.clone(),
+                alpha: morph.alpha,
+            })),
+            // The mapproj projections are built again from their parameters
+            _ => {
+                use crate::Abort;
+                Self::from_params(self.get_name(), &self.get_params()).unwrap_abort()
+            }
+        }
+    }
 }
 
 impl ProjectionType {
+    /// Build a projection from its FITS-like name, e.g. "TAN" or "AIT", with its default parameters
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::from_params(name, &ProjectionParams::default()).ok()
+    }
+
+    /// Build a projection from its FITS-like name and its FITS WCS parameters
+    ///
+    /// Only the CEA, CYP and COD projections take parameters
+    pub fn from_params(name: &str, params: &ProjectionParams) -> Result<Self, JsValue> {
+        if !matches!(name, "CEA" | "CYP" | "COD") && *params != ProjectionParams::default() {
+            return Err(JsValue::from_str(&format!("The {} projection takes no parameters", name)));
+        }
+
+        let projection = match name {
+            // Zenithal
+            "TAN" => ProjectionType::Tan(mapproj::zenithal::tan::Tan::new()),	  /* Gnomonic projection      */
+            "STG" => ProjectionType::Stg(mapproj::zenithal::stg::Stg::new()),	  /* Stereographic projection */
+            "SIN" => ProjectionType::Sin(mapproj::zenithal::sin::Sin::new()),	  /* Orthographic		         */
+            "ZEA" => ProjectionType::Zea(mapproj::zenithal::zea::Zea::new()),	  /* Equal-area 		         */
+            "FEYE" => ProjectionType::Feye(mapproj::zenithal::feye::Feye::new()),
+            "AIR" => {
+                let air_proj = mapproj::zenithal::air::Air::new();
+                //air_proj.set_n_iter(10);
+                //air_proj.set_eps(1e-12);
+                ProjectionType::Air(air_proj)
+            },
+            //"AZP",
+            "ARC" => ProjectionType::Arc(mapproj::zenithal::arc::Arc::new()),
+            "NCP" => ProjectionType::Ncp(mapproj::zenithal::ncp::Ncp::new()),
+            // Cylindrical
+            "MER" => ProjectionType::Mer(mapproj::cylindrical::mer::Mer::new()),
+            "CAR" => ProjectionType::Car(mapproj::cylindrical::car::Car::new()),
+            "CEA" => {
+                let lambda = params.lambda.unwrap_or(1.0);
+                if !(lambda > 0.0 && lambda <= 1.0) {
+                    return Err(JsValue::from_str("CEA lambda must be in ]0, 1]"));
+                }
+
+                ProjectionType::Cea {
+                    proj: mapproj::cylindrical::cea::Cea::from_param(lambda),
+                    lambda,
+                }
+            },
+            "CYP" => {
+                let mu = params.mu.unwrap_or(1.0);
+                let lambda = params.lambda.unwrap_or(0.5 * 2.0_f64.sqrt());
+                if !(mu > 0.0 && mu.is_finite() && lambda > 0.0 && lambda.is_finite()) {
+                    return Err(JsValue::from_str("CYP mu and lambda must be strictly positive"));
+                }
+
+                ProjectionType::Cyp {
+                    proj: mapproj::cylindrical::cyp::Cyp::from_params(mu, lambda),
+                    mu,
+                    lambda,
+                }
+            },
+            // Pseudo-cylindrical
+            "AIT" => ProjectionType::Ait(mapproj::pseudocyl::ait::Ait::new()),
+            "PAR" => ProjectionType::Par(mapproj::pseudocyl::par::Par::new()),
+            "SFL" => ProjectionType::Sfl(mapproj::pseudocyl::sfl::Sfl::new()),
+            "MOL" => {
+                let mut mol_proj = mapproj::pseudocyl::mol::Mol::new();
+                mol_proj.set_n_iter(10);
+                mol_proj.set_epsilon(1e-12);
+
+                ProjectionType::Mol(mol_proj)
+            },
+            // Conic
+            "COD" => {
+                let theta_a = params.theta_a.unwrap_or(45.0);
+                let eta = params.eta.unwrap_or(0.0);
+                // The definition domain is drawn for a mean standard parallel in the north hemisphere
+                if !(theta_a > 0.0 && theta_a < 90.0 && eta >= 0.0 && theta_a + eta <= 90.0) {
+                    return Err(JsValue::from_str("COD theta_a must be in ]0, 90[ and eta in [0, 90 - theta_a]"));
+                }
+
+                let (ta, nu) = (theta_a.to_radians(), eta.to_radians());
+                ProjectionType::Cod {
+                    proj: mapproj::conic::cod::Cod::from_params(ta, nu),
+                    area: ProjDefType::Cod(Cod::from_params(ta, nu)),
+                    theta_a,
+                    eta,
+                }
+            },
+            // Hybrid
+            "HPX" => ProjectionType::Hpx(mapproj::hybrid::hpx::Hpx::new()),
+            _ => return Err(JsValue::from_str(&format!("{} is not a valid projection name. AIT, ARC, SIN, TAN, MOL, HPX and MER are accepted", name))),
+        };
+
+        Ok(projection)
+    }
+
+    /// The FITS WCS parameters the projection has been built with
+    pub fn get_params(&self) -> ProjectionParams {
+        match self {
+            ProjectionType::Cea { lambda, .. } => ProjectionParams {
+                lambda: Some(*lambda),
+                ..Default::default()
+            },
+            ProjectionType::Cyp { mu, lambda, .. } => ProjectionParams {
+                mu: Some(*mu),
+                lambda: Some(*lambda),
+                ..Default::default()
+            },
+            ProjectionType::Cod { theta_a, eta, .. } => ProjectionParams {
+                theta_a: Some(*theta_a),
+                eta: Some(*eta),
+                ..Default::default()
+            },
+            ProjectionType::Morph(morph) => morph.to.get_params(),
+            _ => ProjectionParams::default(),
+        }
+    }
+
+    /// The name of the projection as accepted by [`ProjectionType::from_name`]
+    pub fn get_name(&self) -> &'static str {
+        match self {
+            ProjectionType::Tan(_) => "TAN",
+            ProjectionType::Stg(_) => "STG",
+            ProjectionType::Sin(_) => "SIN",
+            ProjectionType::Zea(_) => "ZEA",
+            ProjectionType::Feye(_) => "FEYE",
+            ProjectionType::Air(_) => "AIR",
+            ProjectionType::Arc(_) => "ARC",
+            ProjectionType::Ncp(_) => "NCP",
+
+            ProjectionType::Ait(_) => "AIT",
+            ProjectionType::Mol(_) => "MOL",
+            ProjectionType::Par(_) => "PAR",
+            ProjectionType::Sfl(_) => "SFL",
+
+            ProjectionType::Mer(_) => "MER",
+            ProjectionType::Car(_) => "CAR",
+            ProjectionType::Cea { .. } => "CEA",
+            ProjectionType::Cyp { .. } => "CYP",
+
+            ProjectionType::Cod { .. } => "COD",
+
+            ProjectionType::Hpx(_) => "HPX",
+
+            // The projection reached at the end of the transition
+            ProjectionType::Morph(morph) => morph.to.get_name(),
+        }
+    }
+
+    /// Whether the projection is in transition to another one
+    pub fn is_morphing(&self) -> bool {
+        matches!(self, ProjectionType::Morph(_))
+    }
+
     /// Screen to model space deprojection
 
     /// Perform a screen to the world space deprojection
@@ -385,16 +623,118 @@ impl ProjectionType {
             // CAR,                                 */
             ProjectionType::Car(_) => 1.0,
             // CEA,                                 */
-            ProjectionType::Cea(_) => 1.0,
+            ProjectionType::Cea { .. } => 1.0,
             // CYP,                                 */
-            ProjectionType::Cyp(_) => 1.0,
+            ProjectionType::Cyp { .. } => 1.0,
 
             // Conic projections
             // COD,                                 */
-            ProjectionType::Cod(_) => 1.0,
+            ProjectionType::Cod { .. } => 1.0,
 
             // HEALPix hybrid projection
             ProjectionType::Hpx(_) => 2.0,
+
+            ProjectionType::Morph(morph) => morph.bounds_size_ratio(),
+        }
+    }
+
+    /// FITS WCS code of the projection with its `PV2_m` parameters
+    ///
+    /// None is returned for the projections having no FITS counterpart
+    /// matching the way they are drawn
+    pub fn get_fits_code(&self) -> Option<(&'static str, Vec<(u8, f64)>)> {
+        match self {
+            ProjectionType::Tan(_) => Some(("TAN", vec![])),
+            ProjectionType::Stg(_) => Some(("STG", vec![])),
+            ProjectionType::Sin(_) => Some(("SIN", vec![])),
+            ProjectionType::Zea(_) => Some(("ZEA", vec![])),
+            ProjectionType::Arc(_) => Some(("ARC", vec![])),
+
+            ProjectionType::Ait(_) => Some(("AIT", vec![])),
+            ProjectionType::Mol(_) => Some(("MOL", vec![])),
+            ProjectionType::Par(_) => Some(("PAR", vec![])),
+            ProjectionType::Sfl(_) => Some(("SFL", vec![])),
+
+            ProjectionType::Mer(_) => Some(("MER", vec![])),
+            ProjectionType::Car(_) => Some(("CAR", vec![])),
+            ProjectionType::Cea { lambda, .. } => Some(("CEA", vec![(1, *lambda)])),
+            // The default lambda parameter differs from the FITS one so that it is always written
+            ProjectionType::Cyp { mu, lambda, .. } => Some(("CYP", vec![(1, *mu), (2, *lambda)])),
+
+            _ => None,
+        }
+    }
+
+    /// Whether the reference point of the projection is its native pole
+    pub fn is_zenithal(&self) -> bool {
+        matches!(
+            self,
+            ProjectionType::Tan(_)
+                | ProjectionType::Stg(_)
+                | ProjectionType::Sin(_)
+                | ProjectionType::Zea(_)
+                | ProjectionType::Feye(_)
+                | ProjectionType::Air(_)
+                | ProjectionType::Arc(_)
+                | ProjectionType::Ncp(_)
+        ) || matches!(self, ProjectionType::Morph(morph) if morph.nearest().is_zenithal())
+    }
+
+    /// Whether the x axis of the projection plane is proportional to the native longitude
+    pub fn is_cylindrical(&self) -> bool {
+        matches!(
+            self,
+            ProjectionType::Mer(_)
+                | ProjectionType::Car(_)
+                | ProjectionType::Cea { .. }
+                | ProjectionType::Cyp { .. }
+        ) || matches!(self, ProjectionType::Morph(morph) if morph.nearest().is_cylindrical())
+    }
+
+    /// Size of the projection plane domain mapped to the [-1, 1] clip space
+    ///
+    /// It is given in the units of the projection plane (i.e. in radians)
+    pub fn get_plane_size(&self) -> Vector2<f64> {
+        fn plane_size<P: CanonicalProjection>(proj: &P) -> Vector2<f64> {
+            let proj_bounds = proj.bounds();
+            let x_proj_bounds = proj_bounds.x_bounds()
+                .as_ref()
+                .unwrap_or(&(-PI..=PI));
+            let y_proj_bounds = proj_bounds.y_bounds()
+                .as_ref()
+                .unwrap_or(&(-PI..=PI));
+
+            Vector2::new(
+                x_proj_bounds.end() - x_proj_bounds.start(),
+                y_proj_bounds.end() - y_proj_bounds.start(),
+            )
+        }
+
+        match self {
+            ProjectionType::Tan(p) => plane_size(p),
+            ProjectionType::Stg(p) => plane_size(p),
+            ProjectionType::Sin(p) => plane_size(p),
+            ProjectionType::Zea(p) => plane_size(p),
+            ProjectionType::Feye(p) => plane_size(p),
+            ProjectionType::Air(p) => plane_size(p),
+            ProjectionType::Arc(p) => plane_size(p),
+            ProjectionType::Ncp(p) => plane_size(p),
+
+            ProjectionType::Ait(p) => plane_size(p),
+            ProjectionType::Mol(p) => plane_size(p),
+            ProjectionType::Par(p) => plane_size(p),
+            ProjectionType::Sfl(p) => plane_size(p),
+
+            ProjectionType::Mer(p) => plane_size(p),
+            ProjectionType::Car(p) => plane_size(p),
+            ProjectionType::Cea { proj, .. } => plane_size(proj),
+            ProjectionType::Cyp { proj, .. } => plane_size(proj),
+
+            ProjectionType::Cod { proj, .. } => plane_size(proj),
+
+            ProjectionType::Hpx(p) => plane_size(p),
+
+            ProjectionType::Morph(morph) => morph.nearest().get_plane_size(),
         }
     }
 
@@ -436,16 +776,18 @@ impl ProjectionType {
             // CAR,                                 */
             ProjectionType::Car(_) => 360.0,
             // CEA,                                 */
-            ProjectionType::Cea(_) => 360.0,
+            ProjectionType::Cea { .. } => 360.0,
             // CYP,                                 */
-            ProjectionType::Cyp(_) => 360.0,
+            ProjectionType::Cyp { .. } => 360.0,
 
             // Conic projections
             // COD,                                 */
-            ProjectionType::Cod(_) => 330.0,
+            ProjectionType::Cod { .. } => 330.0,
 
             // HEALPix hybrid projection
             ProjectionType::Hpx(_) => 360.0,
+
+            ProjectionType::Morph(morph) => morph.nearest().aperture_start(),
         }
     }
 
@@ -529,27 +871,26 @@ impl ProjectionType {
                 &FULL_SCREEN
             },
             // CEA,                                 */
-            ProjectionType::Cea(_) => {
+            ProjectionType::Cea { .. } => {
                 const FULL_SCREEN: ProjDefType = ProjDefType::FullScreen(FullScreen);
                 &FULL_SCREEN
             },
             // CYP,                                 */
-            ProjectionType::Cyp(_) => {
+            ProjectionType::Cyp { .. } => {
                 const FULL_SCREEN: ProjDefType = ProjDefType::FullScreen(FullScreen);
                 &FULL_SCREEN
             },
 
             // Conic projections
             // COD,                                 */
-            ProjectionType::Cod(_) => {
-                const CONIC: ProjDefType = ProjDefType::Cod(Cod::new());
-                &CONIC
-            }
+            ProjectionType::Cod { area, .. } => area,
             // HEALPix hybrid projection
             ProjectionType::Hpx(_) => {
                 const HPX_DEF_REG: ProjDefType = ProjDefType::Hpx(Hpx);
                 &HPX_DEF_REG
             }
+
+            ProjectionType::Morph(morph) => morph.nearest().get_area(),
         }
     }
 }
@@ -594,22 +935,24 @@ impl Projection for ProjectionType {
             // CAR,                                 */
             ProjectionType::Car(car) => car.clip_to_world_space(xy),
             // CEA,                                 */
-            ProjectionType::Cea(cea) => cea.clip_to_world_space(xy),
+            ProjectionType::Cea { proj, .. } => proj.clip_to_world_space(xy),
             // CYP,                                 */
-            ProjectionType::Cyp(cyp) => cyp.clip_to_world_space(xy),
+            ProjectionType::Cyp { proj, .. } => proj.clip_to_world_space(xy),
 
             // Conic projections
             // COD,                                 */
-            ProjectionType::Cod(cod) => {
-                cod.clip_to_world_space(xy)
+            ProjectionType::Cod { proj, theta_a, .. } => {
+                proj.clip_to_world_space(xy)
                     .map(|xyzw| {
-                        let rot = Rotation::from_sky_position(&LonLatT(Angle(0.0_f64), Angle(HALF_PI * 0.5)).vector());
+                        let rot = Rotation::from_sky_position(&LonLatT(Angle(0.0_f64), Angle(theta_a.to_radians())).vector());
                         rot.inv_rotate(&xyzw)
                     })
             },
 
             // HEALPix hybrid projection
             ProjectionType::Hpx(hpx) => hpx.clip_to_world_space(xy),
+
+            ProjectionType::Morph(morph) => morph.clip_to_world_space(xy),
         }
     }
    
@@ -652,18 +995,20 @@ impl Projection for ProjectionType {
             // CAR,                                 */
             ProjectionType::Car(car) => car.world_to_clip_space(xyzw),
             // CEA,                                 */
-            ProjectionType::Cea(cea) => cea.world_to_clip_space(xyzw),
+            ProjectionType::Cea { proj, .. } => proj.world_to_clip_space(xyzw),
             // CYP,                                 */
-            ProjectionType::Cyp(cyp) => cyp.world_to_clip_space(xyzw),
+            ProjectionType::Cyp { proj, .. } => proj.world_to_clip_space(xyzw),
             // Conic projections
             // COD,                                 */
-            ProjectionType::Cod(cod) => {
-                // The Cod projection is centered on (0, 45 deg)
-                let rot = Rotation::from_sky_position(&LonLatT(Angle(0.0_f64), Angle(HALF_PI * 0.5)).vector());
-                cod.world_to_clip_space(&rot.rotate(&xyzw))
+            ProjectionType::Cod { proj, theta_a, .. } => {
+                // The Cod projection is centered on (0, theta_a)
+                let rot = Rotation::from_sky_position(&LonLatT(Angle(0.0_f64), Angle(theta_a.to_radians())).vector());
+                proj.world_to_clip_space(&rot.rotate(&xyzw))
             },
             // HEALPix hybrid projection
             ProjectionType::Hpx(hpx) => hpx.world_to_clip_space(xyzw),
+
+            ProjectionType::Morph(morph) => morph.world_to_clip_space(xyzw),
         }
     }
 }
@@ -836,16 +1181,68 @@ mod tests {
         // Cylindrical
         generate_projection_map("./../img/mer.png", ProjectionType::Mer(mapproj::cylindrical::mer::Mer));
         generate_projection_map("./../img/car.png", ProjectionType::Car(mapproj::cylindrical::car::Car));
-        generate_projection_map("./../img/cea.png", ProjectionType::Cea(mapproj::cylindrical::cea::Cea::new()));
-        generate_projection_map("./../img/cyp.png", ProjectionType::Cyp(mapproj::cylindrical::cyp::Cyp::new()));
+        generate_projection_map("./../img/cea.png", ProjectionType::from_name("CEA").unwrap_abort());
+        generate_projection_map("./../img/cyp.png", ProjectionType::from_name("CYP").unwrap_abort());
         // Pseudo-cylindrical
         generate_projection_map("./../img/mer.png", ProjectionType::Ait(mapproj::pseudocyl::ait::Ait));
         generate_projection_map("./../img/car.png", ProjectionType::Par(mapproj::pseudocyl::par::Par));
         generate_projection_map("./../img/cea.png", ProjectionType::Sfl(mapproj::pseudocyl::sfl::Sfl));
         generate_projection_map("./../img/cyp.png", ProjectionType::Mol(mapproj::pseudocyl::mol::Mol::new()));
         // Conic
-        generate_projection_map("./../img/cod.png", ProjectionType::Cod(mapproj::conic::cod::Cod::new()));
+        generate_projection_map("./../img/cod.png", ProjectionType::from_name("COD").unwrap_abort());
         // Hybrid
         generate_projection_map("./../img/hpx.png", ProjectionType::Hpx(mapproj::hybrid::hpx::Hpx));
     }
+
+    #[test]
+    fn morph_projections() {
+        use super::*;
+
+        let morph = |alpha: f64| ProjectionType::Morph(Box::new(Morph {
+            from: ProjectionType::from_name("SIN").unwrap(),
+            to: ProjectionType::from_name("AIT").unwrap(),
+            alpha,
+        }));
+
+        // The transition starts and ends on the projections
+        let front = cgmath::Vector3::new(0.3, 0.2, 0.9).normalize().extend(1.0);
+        let sin = ProjectionType::from_name("SIN").unwrap();
+        let ait = ProjectionType::from_name("AIT").unwrap();
+        assert_eq!(morph(0.0).world_to_clip_space(&front), sin.world_to_clip_space(&front));
+        assert_eq!(morph(1.0).world_to_clip_space(&front), ait.world_to_clip_space(&front));
+        assert_eq!(morph(0.3).get_name(), "AIT");
+
+        // The far side of the sky is not defined for SIN
+        let back = cgmath::Vector3::new(0.3, 0.2, -0.9).normalize().extend(1.0);
+        assert!(morph(0.4).world_to_clip_space(&back).is_none());
+        assert!(morph(0.6).world_to_clip_space(&back).is_some());
+    }
+
+    #[test]
+    fn parameterized_projections() {
+        use super::*;
+
+        // The default conic domain is the one for theta_a = 45 deg and eta = 0
+        let cod = Cod::from_params(45.0_f64.to_radians(), 0.0);
+        assert!((cod.r_min - 0.2146018366).abs() < 1e-9);
+        assert!((cod.y_min + 2.356194490192345).abs() < 1e-12);
+        assert!((cod.y_max - 3.0328465566001492).abs() < 1e-12);
+
+        let params = ProjectionParams {
+            theta_a: Some(45.0),
+            eta: Some(25.0),
+            ..Default::default()
+        };
+        let cod = ProjectionType::from_params("COD", &params).unwrap();
+        assert_eq!(cod.clone().get_params(), params);
+
+        // The center of the view is on the mean standard parallel
+        let center = LonLatT(Angle(0.0_f64), Angle(45.0_f64.to_radians())).vector();
+        let xy = cod.world_to_clip_space(&center).unwrap();
+        let xyzw = cod.clip_to_world_space(&xy).unwrap();
+        assert!((xyzw - center).magnitude() < 1e-9);
+
+        let cyp = ProjectionType::from_params("CYP", &ProjectionParams { lambda: Some(0.5), ..Default::default() }).unwrap();
+        assert_eq!(cyp.get_fits_code(), Some(("CYP", vec![(1, 1.0), (2, 0.5)])));
+    }
 }
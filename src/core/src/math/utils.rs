@@ -103,3 +103,19 @@ pub fn lambert_wm1(x: f32) -> f32 {
         - (2.0 / m1)
             * (1.0 - 1.0 / (1.0 + ((m1 * s_div_2_root) / (1.0 + m2 * s * (m3 * s_root).exp()))))
 }
+
+/// Ease in and out a transition, t going from 0 to 1
+#[inline]
+pub fn ease_in_out_cubic(t: f64) -> f64 {
+    if t < 0.5 {
+        4.0 * t * t * t
+    } else {
+        1.0 - (-2.0 * t + 2.0).powi(3) / 2.0
+    }
+}
+
+/// Interpolate logarithmically between two positive values, e.g. fovs so that the zoom speed looks constant
+#[inline]
+pub fn log_interpolate(a: f64, b: f64, alpha: f64) -> f64 {
+    (a.ln() * (1.0 - alpha) + b.ln() * alpha).exp()
+}
@@ -0,0 +1,223 @@
+use al_api::hips::{ImageMetadata, LayerOp, LuptonCfg};
+
+use al_core::image::format::RGBA32F;
+use al_core::colormap::Colormaps;
+use al_core::webgl_ctx::GlWrapper;
+use al_core::{FrameBufferObject, VertexArrayObject, WebGlContext};
+
+use crate::camera::CameraViewPort;
+use crate::shader::ShaderManager;
+
+use wasm_bindgen::JsValue;
+use web_sys::WebGl2RenderingContext;
+
+pub enum CompositeKind {
+    /// The channels give the red, green and blue components
+    RGB { lupton: Option<LuptonCfg> },
+    /// An operation between the values of two channels
+    Arithmetic { op: LayerOp },
+}
+
+/// A layer combining other layers
+///
+/// Each channel layer is first rendered offscreen. The renderings
+/// are then combined into one layer.
+pub struct Composite {
+    // Layer names of the channels
+    channels: Vec<String>,
+    kind: CompositeKind,
+
+    // Offscreen renderings of the channels
+    fbos: Vec<FrameBufferObject>,
+
+    gl: WebGlContext,
+}
+
+impl Composite {
+    /// The channel layers are rendered with their own cuts and transfer function
+    pub fn new_rgb(
+        gl: &WebGlContext,
+        channels: [String; 3],
+        lupton: Option<LuptonCfg>,
+        camera: &CameraViewPort,
+    ) -> Result<Self, JsValue> {
+        let w = camera.get_width() as usize;
+        let h = camera.get_height() as usize;
+
+        let fbos = (0..3)
+            .map(|_| FrameBufferObject::new(gl, w, h))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(Self {
+            channels: channels.to_vec(),
+            kind: CompositeKind::RGB { lupton },
+            fbos,
+            gl: gl.clone(),
+        })
+    }
+
+    /// The physical values of the channel layers are rendered into float textures
+    pub fn new_arithmetic(
+        gl: &WebGlContext,
+        channels: [String; 2],
+        op: LayerOp,
+        camera: &CameraViewPort,
+    ) -> Result<Self, JsValue> {
+        let w = camera.get_width() as usize;
+        let h = camera.get_height() as usize;
+
+        let fbos = (0..2)
+            .map(|_| {
+                FrameBufferObject::new_with_format::<RGBA32F>(
+                    gl,
+                    w,
+                    h,
+                    &[
+                        (
+                            WebGl2RenderingContext::TEXTURE_MIN_FILTER,
+                            WebGl2RenderingContext::NEAREST,
+                        ),
+                        (
+                            WebGl2RenderingContext::TEXTURE_MAG_FILTER,
+                            WebGl2RenderingContext::NEAREST,
+                        ),
+                        (
+                            WebGl2RenderingContext::TEXTURE_WRAP_S,
+                            WebGl2RenderingContext::CLAMP_TO_EDGE,
+                        ),
+                        (
+                            WebGl2RenderingContext::TEXTURE_WRAP_T,
+                            WebGl2RenderingContext::CLAMP_TO_EDGE,
+                        ),
+                    ],
+                )
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(Self {
+            channels: channels.to_vec(),
+            kind: CompositeKind::Arithmetic { op },
+            fbos,
+            gl: gl.clone(),
+        })
+    }
+
+    pub fn get_channels(&self) -> &[String] {
+        &self.channels
+    }
+
+    pub fn get_kind(&self) -> &CompositeKind {
+        &self.kind
+    }
+
+    pub fn get_channel_fbos(&self) -> &[FrameBufferObject] {
+        &self.fbos
+    }
+
+    /// Whether the channels are rendered as physical values
+    /// instead of colors
+    pub fn renders_raw_values(&self) -> bool {
+        matches!(self.kind, CompositeKind::Arithmetic { .. })
+    }
+
+    /// Rename a channel layer
+    pub fn rename_channel(&mut self, layer: &str, new_layer: &str) {
+        for channel in self.channels.iter_mut() {
+            if channel == layer {
+                *channel = new_layer.to_string();
+            }
+        }
+    }
+
+    /// Fit the offscreen renderings to the size of the screen
+    pub fn resize(&mut self, camera: &CameraViewPort) {
+        let w = camera.get_width() as usize;
+        let h = camera.get_height() as usize;
+
+        for fbo in self.fbos.iter_mut() {
+            fbo.resize(w, h);
+        }
+    }
+
+    /// Combine the channels rendered offscreen and draw the result on the screen
+    pub fn draw(
+        &self,
+        shaders: &mut ShaderManager,
+        colormaps: &Colormaps,
+        screen_vao: &VertexArrayObject,
+        cfg: &ImageMetadata,
+    ) -> Result<(), JsValue> {
+        let ImageMetadata {
+            color,
+            opacity,
+            blend_cfg,
+            ..
+        } = cfg;
+
+        self.gl.enable(WebGl2RenderingContext::BLEND);
+        blend_cfg.enable(&self.gl, || {
+            match self.kind {
+                CompositeKind::RGB { lupton } => {
+                    let (lupton, q, stretch) = if let Some(LuptonCfg { q, stretch }) = lupton {
+                        (1, q, stretch)
+                    } else {
+                        (0, 1.0, 1.0)
+                    };
+
+                    let shader = crate::shader::get_shader(&self.gl, shaders, "CompositeVS", "CompositeRGBFS")?;
+                    shader
+                        .bind(&self.gl)
+                        .attach_uniform("tex_r", &self.fbos[0].texture)
+                        .attach_uniform("tex_g", &self.fbos[1].texture)
+                        .attach_uniform("tex_b", &self.fbos[2].texture)
+                        .attach_uniforms_from(color)
+                        .attach_uniform("lupton", &lupton)
+                        .attach_uniform("lupton_q", &q)
+                        .attach_uniform("lupton_stretch", &stretch)
+                        .attach_uniform("opacity", opacity)
+                        .bind_vertex_array_object_ref(screen_vao)
+                        .draw_elements_with_i32(
+                            WebGl2RenderingContext::TRIANGLES,
+                            None,
+                            WebGl2RenderingContext::UNSIGNED_SHORT,
+                            0,
+                        );
+                }
+                CompositeKind::Arithmetic { op } => {
+                    let op = match op {
+                        LayerOp::Difference => 0,
+                        LayerOp::Ratio => 1,
+                        LayerOp::NormalizedDifference => 2,
+                    };
+
+                    let shader = crate::shader::get_shader(&self.gl, shaders, "CompositeVS", "CompositeArithmeticFS")?;
+                    shader
+                        .bind(&self.gl)
+                        .attach_uniform("tex_a", &self.fbos[0].texture)
+                        .attach_uniform("tex_b", &self.fbos[1].texture)
+                        .attach_uniform("op", &op)
+                        .attach_uniforms_with_params_from(color, colormaps)
+                        .attach_uniforms_from(colormaps)
+                        // The values computed are already physical ones
+                        .attach_uniform("scale", &1.0_f32)
+                        .attach_uniform("offset", &0.0_f32)
+                        .attach_uniform("blank", &f32::NAN)
+                        .attach_uniform("raw_value", &0_i32)
+                        .attach_uniform("opacity", opacity)
+                        .bind_vertex_array_object_ref(screen_vao)
+                        .draw_elements_with_i32(
+                            WebGl2RenderingContext::TRIANGLES,
+                            None,
+                            WebGl2RenderingContext::UNSIGNED_SHORT,
+                            0,
+                        );
+                }
+            }
+
+            Ok(())
+        })?;
+        self.gl.disable(WebGl2RenderingContext::BLEND);
+
+        Ok(())
+    }
+}
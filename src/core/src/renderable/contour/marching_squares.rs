@@ -0,0 +1,181 @@
+use std::collections::HashMap;
+
+// Edges of a cell of 4 pixels given in CCW order
+const BOTTOM: usize = 0;
+const RIGHT: usize = 1;
+const TOP: usize = 2;
+const LEFT: usize = 3;
+
+/// Compute the isolines of a grid of values at a specific level
+///
+/// `values` are given row by row, the grid having `width` columns and `height` rows.
+/// The vertices of the lines are given in pixel coordinates, i.e. the center of
+/// the pixel of the i-th column and j-th row is located at (i, j).
+///
+/// NaN values are considered outside the data, i.e. no line crosses
+/// the cells having one of their corners being NaN.
+///
+/// Closed lines end with their first vertex.
+pub fn isolines(values: &[f32], width: usize, height: usize, level: f32) -> Vec<Vec<[f32; 2]>> {
+    if width < 2 || height < 2 || values.len() < width * height {
+        return vec![];
+    }
+
+    // A vertex of the lines is located on an edge between two pixels
+    // and is identified by this edge
+    let h_edge = |i: usize, j: usize| -> usize { 2 * (j * width + i) };
+    let v_edge = |i: usize, j: usize| -> usize { 2 * (j * width + i) + 1 };
+
+    let mut vertices: HashMap<usize, [f32; 2]> = HashMap::new();
+    let mut adjacency: HashMap<usize, Vec<usize>> = HashMap::new();
+
+    for j in 0..(height - 1) {
+        for i in 0..(width - 1) {
+            let v00 = values[j * width + i];
+            let v10 = values[j * width + i + 1];
+            let v11 = values[(j + 1) * width + i + 1];
+            let v01 = values[(j + 1) * width + i];
+
+            if v00.is_nan() || v10.is_nan() || v11.is_nan() || v01.is_nan() {
+                continue;
+            }
+
+            let case = (v00 >= level) as u8
+                | ((v10 >= level) as u8) << 1
+                | ((v11 >= level) as u8) << 2
+                | ((v01 >= level) as u8) << 3;
+
+            let segments: &[(usize, usize)] = match case {
+                0 | 15 => &[],
+                1 | 14 => &[(LEFT, BOTTOM)],
+                2 | 13 => &[(BOTTOM, RIGHT)],
+                3 | 12 => &[(LEFT, RIGHT)],
+                4 | 11 => &[(RIGHT, TOP)],
+                6 | 9 => &[(BOTTOM, TOP)],
+                7 | 8 => &[(LEFT, TOP)],
+                // Saddle points, the ambiguity is resolved from the mean of the cell
+                5 | 10 => {
+                    let center_above = (v00 + v10 + v11 + v01) * 0.25 >= level;
+                    if center_above == (case == 5) {
+                        &[(LEFT, TOP), (BOTTOM, RIGHT)]
+                    } else {
+                        &[(LEFT, BOTTOM), (RIGHT, TOP)]
+                    }
+                }
+                _ => unreachable!(),
+            };
+
+            let interp = |a: f32, b: f32| -> f32 { (level - a) / (b - a) };
+            let mut vertex = |edge: usize| -> usize {
+                let (id, pos) = match edge {
+                    BOTTOM => (h_edge(i, j), [i as f32 + interp(v00, v10), j as f32]),
+                    RIGHT => (v_edge(i + 1, j), [(i + 1) as f32, j as f32 + interp(v10, v11)]),
+                    TOP => (h_edge(i, j + 1), [i as f32 + interp(v01, v11), (j + 1) as f32]),
+                    _ => (v_edge(i, j), [i as f32, j as f32 + interp(v00, v01)]),
+                };
+                vertices.insert(id, pos);
+
+                id
+            };
+
+            for &(e1, e2) in segments {
+                let (id1, id2) = (vertex(e1), vertex(e2));
+
+                adjacency.entry(id1).or_default().push(id2);
+                adjacency.entry(id2).or_default().push(id1);
+            }
+        }
+    }
+
+    // Chain the segments into lines. Each vertex being shared by at most two cells,
+    // it has at most two neighbors. The open lines start from a vertex having only one.
+    let mut starts = adjacency
+        .iter()
+        .filter_map(|(id, neighbors)| if neighbors.len() == 1 { Some(*id) } else { None })
+        .collect::<Vec<_>>();
+    starts.sort_unstable();
+    let mut others = adjacency.keys().cloned().collect::<Vec<_>>();
+    others.sort_unstable();
+    starts.extend(others);
+
+    let mut visited = std::collections::HashSet::new();
+    let mut lines = vec![];
+    for start in starts {
+        if visited.contains(&start) {
+            continue;
+        }
+
+        let mut line = vec![];
+        let mut cur = start;
+        loop {
+            line.push(vertices[&cur]);
+            visited.insert(cur);
+
+            if let Some(next) = adjacency[&cur].iter().find(|id| !visited.contains(*id)) {
+                cur = *next;
+            } else {
+                break;
+            }
+        }
+
+        // Close the line if it loops
+        if line.len() > 2 && adjacency[&cur].contains(&start) {
+            line.push(vertices[&start]);
+        }
+
+        lines.push(line);
+    }
+
+    lines
+}
+
+#[cfg(test)]
+mod tests {
+    #[test]
+    fn test_isolines_of_a_bump() {
+        let (w, h) = (40, 30);
+        let (cx, cy) = (20.0, 15.0);
+        let values = (0..h)
+            .flat_map(|j| {
+                (0..w).map(move |i| {
+                    let d2 = (i as f32 - cx).powi(2) + (j as f32 - cy).powi(2);
+                    (-d2 / 50.0).exp()
+                })
+            })
+            .collect::<Vec<_>>();
+
+        // The isoline at 0.5 is a circle of radius sqrt(50 * ln(2))
+        let r = (50.0_f32 * 2.0_f32.ln()).sqrt();
+        let lines = super::isolines(&values, w, h, 0.5);
+        assert_eq!(lines.len(), 1);
+
+        let line = &lines[0];
+        assert!(line.len() > 10);
+        assert_eq!(line.first(), line.last());
+        for [x, y] in line {
+            let d = ((x - cx).powi(2) + (y - cy).powi(2)).sqrt();
+            assert!((d - r).abs() < 0.2);
+        }
+
+        // No isoline above the maximum
+        assert!(super::isolines(&values, w, h, 2.0).is_empty());
+    }
+
+    #[test]
+    fn test_isolines_of_a_ramp_with_blanks() {
+        let (w, h) = (10, 10);
+        let values = (0..h)
+            .flat_map(|j| {
+                (0..w).map(move |i| if j == 5 { f32::NAN } else { i as f32 })
+            })
+            .collect::<Vec<_>>();
+
+        // The blank row cuts the vertical line into two open lines
+        let lines = super::isolines(&values, w, h, 4.5);
+        assert_eq!(lines.len(), 2);
+        for line in lines {
+            assert_ne!(line.first(), line.last());
+            assert!(line.iter().all(|[x, _]| (x - 4.5).abs() < 1e-6));
+        }
+    }
+}
@@ -0,0 +1,438 @@
+pub mod marching_squares;
+
+use std::collections::{HashMap, HashSet};
+use std::io::Cursor;
+use std::sync::{Arc, Mutex};
+
+use al_api::contour::ContourCfg;
+use al_api::coo_system::CooSystem;
+
+use al_core::image::fits::{Data, Fits};
+use al_core::image::ImageType;
+use al_core::WebGlContext;
+
+use cgmath::{InnerSpace, Vector2};
+use wasm_bindgen::JsValue;
+use wcs::ImgXY;
+
+use crate::camera::CameraViewPort;
+use crate::healpix::cell::HEALPixCell;
+use crate::math::angle::{Angle, ToAngle};
+use crate::math::projection::coo_space::{XYNDC, XYZWModel};
+use crate::renderable::lines::RasterizedLinesRenderManager;
+use crate::renderable::Layers;
+use crate::shader::ShaderManager;
+use crate::survey::config::HiPSConfig;
+use crate::Abort;
+use crate::ProjectionType;
+
+// Maximum number of contoured tiles kept for one contour layer
+const MAX_NUM_TILES: usize = 1024;
+// Maximum distance in NDC between two consecutive vertices of a line.
+// Above it, the line is supposed to cross the border of the projection.
+const MAX_NDC_JUMP: f64 = 0.2;
+
+type Polyline = Vec<XYZWModel>;
+
+enum Source {
+    /// Contours computed once from the values of a FITS image layer
+    Image { lines: Vec<Polyline> },
+    /// Contours computed for each FITS tile received for a HiPS layer
+    HiPS {
+        url: String,
+        tiles: HashMap<HEALPixCell, Vec<Polyline>>,
+    },
+}
+
+struct ContourLayer {
+    cfg: ContourCfg,
+    // Frame of the vertices of the lines
+    frame: CooSystem,
+    source: Source,
+}
+
+/// The contour overlays
+///
+/// Contours are generated with the marching squares algorithm
+/// on the CPU side and drawn as rasterized lines.
+pub struct Contours {
+    layers: HashMap<String, ContourLayer>,
+
+    lines: RasterizedLinesRenderManager,
+    // The lines must be tessellated again
+    changed: bool,
+}
+
+impl Contours {
+    pub fn new(gl: &WebGlContext) -> Result<Self, JsValue> {
+        let lines = RasterizedLinesRenderManager::new(gl)?;
+
+        Ok(Self {
+            layers: HashMap::new(),
+
+            lines,
+            changed: false,
+        })
+    }
+
+    /// Add contours on a FITS image or FITS HiPS layer
+    ///
+    /// Contours already defined with the same id are replaced.
+    pub fn add(&mut self, cfg: ContourCfg, layers: &Layers) -> Result<(), JsValue> {
+        let (frame, source) = if let Some(image) = layers.get_image_from_layer(&cfg.layer) {
+            let values = image.get_values().ok_or_else(|| {
+                JsValue::from_str(&format!("{} is not a FITS image layer", cfg.layer))
+            })?;
+            let wcs = image.get_wcs();
+
+            let lines = cfg
+                .levels
+                .iter()
+                .flat_map(|level| {
+                    marching_squares::isolines(
+                        values.get_values(),
+                        values.get_width(),
+                        values.get_height(),
+                        *level,
+                    )
+                })
+                .flat_map(|line| {
+                    // Split the line where the vertices cannot be unprojected
+                    let mut lines = vec![];
+                    let mut cur = vec![];
+                    for [x, y] in line {
+                        let (x, y) = values.to_img_xy(x, y);
+                        if let Some(lonlat) = wcs.unproj(&ImgXY::new(x, y)) {
+                            cur.push(crate::math::lonlat::radec_to_xyzw(
+                                lonlat.lon().to_angle(),
+                                lonlat.lat().to_angle(),
+                            ));
+                        } else if !cur.is_empty() {
+                            lines.push(std::mem::take(&mut cur));
+                        }
+                    }
+                    lines.push(cur);
+
+                    lines
+                })
+                .filter(|line| line.len() > 1)
+                .collect();
+
+            (CooSystem::ICRSJ2000, Source::Image { lines })
+        } else if let Some(hips) = layers.get_hips_from_layer(&cfg.layer) {
+            let hips_cfg = hips.get_config();
+            if !hips_cfg.tex_storing_fits {
+                return Err(JsValue::from_str(&format!(
+                    "{} is not a FITS HiPS layer",
+                    cfg.layer
+                )));
+            }
+
+            (
+                hips_cfg.get_frame(),
+                Source::HiPS {
+                    url: hips_cfg.get_root_url().clone(),
+                    tiles: HashMap::new(),
+                },
+            )
+        } else {
+            return Err(JsValue::from_str(&format!("Layer {} not found", cfg.layer)));
+        };
+
+        self.layers.insert(
+            cfg.id.clone(),
+            ContourLayer { cfg, frame, source },
+        );
+        self.changed = true;
+
+        Ok(())
+    }
+
+    pub fn remove(&mut self, id: &str) -> Result<(), JsValue> {
+        self.layers
+            .remove(id)
+            .ok_or_else(|| JsValue::from_str(&format!("Contours {} not found", id)))?;
+        self.changed = true;
+
+        Ok(())
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.layers.is_empty()
+    }
+
+    /// The configs of the contours, sorted by id
+    pub fn get_cfgs(&self) -> Vec<ContourCfg> {
+        let mut cfgs = self.layers.values()
+            .map(|layer| layer.cfg.clone())
+            .collect::<Vec<_>>();
+        cfgs.sort_by(|c1, c2| c1.id.cmp(&c2.id));
+
+        cfgs
+    }
+
+    /// Forget the contoured tiles of a HiPS, e.g. when its cube frame changes
+    pub fn reset_tiles(&mut self, url: &str) {
+        for layer in self.layers.values_mut() {
+            if let Source::HiPS { url: hips_url, tiles } = &mut layer.source {
+                if hips_url == url {
+                    tiles.clear();
+                    self.changed = true;
+                }
+            }
+        }
+    }
+
+    /// Follow a HiPS whose tiles are now fetched from another url, e.g. a mirror
+    pub fn set_hips_url(&mut self, past_url: &str, new_url: &str) {
+        for layer in self.layers.values_mut() {
+            if let Source::HiPS { url, .. } = &mut layer.source {
+                if url == past_url {
+                    *url = new_url.to_string();
+                }
+            }
+        }
+    }
+
+    /// Tell whether a tile of a HiPS has been contoured for all the contour layers of that HiPS
+    pub fn contains_tile(&self, url: &str, cell: &HEALPixCell) -> bool {
+        self.layers.values().all(|layer| match &layer.source {
+            Source::HiPS { url: hips_url, tiles } => hips_url != url || tiles.contains_key(cell),
+            _ => true,
+        })
+    }
+
+    /// Tell whether a tile of a HiPS still has to be contoured
+    pub fn needs_tile(&self, url: &str, cell: &HEALPixCell) -> bool {
+        !self.contains_tile(url, cell)
+    }
+
+    /// Contour a tile received for a HiPS
+    ///
+    /// Missing tiles or tiles that are not FITS ones are recorded with no line
+    /// so that they are not requested again.
+    pub fn add_tile(
+        &mut self,
+        url: &str,
+        cell: &HEALPixCell,
+        image: Option<&Arc<Mutex<Option<ImageType>>>>,
+        cfg: &HiPSConfig,
+    ) {
+        if !self.needs_tile(url, cell) {
+            return;
+        }
+
+        let values = image.and_then(|image| {
+            image.lock().unwrap_abort()
+                .as_ref()
+                .and_then(|image| tile_values(image, cfg))
+        });
+        for layer in self.layers.values_mut() {
+            if let Source::HiPS { url: hips_url, tiles } = &mut layer.source {
+                if hips_url != url || tiles.contains_key(cell) {
+                    continue;
+                }
+
+                let lines = if let Some((values, width, height)) = &values {
+                    layer
+                        .cfg
+                        .levels
+                        .iter()
+                        .flat_map(|level| marching_squares::isolines(values, *width, *height, *level))
+                        .map(|line| {
+                            line.into_iter()
+                                .map(|[x, y]| {
+                                    let (dx, dy) = tile_pixel_to_dxdy(x as f64, y as f64, *width, *height);
+                                    let (lon, lat) = cdshealpix::nested::sph_coo(cell.depth(), cell.idx(), dx, dy);
+
+                                    crate::math::lonlat::radec_to_xyzw(Angle(lon), Angle(lat))
+                                })
+                                .collect()
+                        })
+                        .collect()
+                } else {
+                    vec![]
+                };
+
+                tiles.insert(*cell, lines);
+                self.changed = true;
+            }
+        }
+    }
+
+    /// Tessellate the lines if the view or the contours have changed
+    pub fn update(&mut self, camera: &CameraViewPort, projection: &ProjectionType, layers: &Layers) {
+        if !camera.has_moved() && !self.changed {
+            return;
+        }
+        self.changed = false;
+
+        self.lines.begin_frame();
+        for layer in self.layers.values_mut() {
+            let ContourLayer { cfg, frame, source } = layer;
+
+            match source {
+                Source::Image { lines } => {
+                    if layers.get_image_from_layer(&cfg.layer).is_none() {
+                        continue;
+                    }
+
+                    for line in lines.iter() {
+                        add_line(&mut self.lines, line, frame, cfg, camera, projection);
+                    }
+                }
+                Source::HiPS { url, tiles } => {
+                    let hips = match layers.get_hips_from_layer(&cfg.layer) {
+                        Some(hips) if hips.get_config().get_root_url() == url => hips,
+                        _ => continue,
+                    };
+
+                    // For each cell in the view, draw the contours of its tile or,
+                    // if not received yet, of its closest received ancestor
+                    let displayed_tiles = hips
+                        .get_view()
+                        .get_cells()
+                        .filter_map(|cell| {
+                            let mut cell = *cell;
+                            loop {
+                                if tiles.contains_key(&cell) {
+                                    break Some(cell);
+                                } else if cell.is_root() {
+                                    break None;
+                                }
+
+                                cell = cell.parent();
+                            }
+                        })
+                        .collect::<HashSet<_>>();
+
+                    for tile in displayed_tiles.iter() {
+                        for line in tiles[tile].iter() {
+                            add_line(&mut self.lines, line, frame, cfg, camera, projection);
+                        }
+                    }
+
+                    if tiles.len() > MAX_NUM_TILES {
+                        tiles.retain(|cell, _| displayed_tiles.contains(cell));
+                    }
+                }
+            }
+        }
+        self.lines.end_frame();
+    }
+
+    pub fn draw(&self, shaders: &mut ShaderManager, camera: &CameraViewPort) -> Result<(), JsValue> {
+        self.lines.draw(shaders, camera)
+    }
+}
+
+/// Offset `(dx, dy)` in the HEALPix cell of a tile of a position given in pixels
+///
+/// The columns of the tile go along dy and its rows along dx. The pixel centers
+/// are located at integer positions.
+fn tile_pixel_to_dxdy(x: f64, y: f64, width: usize, height: usize) -> (f64, f64) {
+    let dy = (x + 0.5) / (width as f64);
+    let dx = 1.0 - (y + 0.5) / (height as f64);
+
+    (dx, dy)
+}
+
+/// Decode the physical values of a FITS tile
+fn tile_values(image: &ImageType, cfg: &HiPSConfig) -> Option<(Vec<f32>, usize, usize)> {
+    if let ImageType::FitsImage { raw_bytes } = image {
+        let raw_bytes = raw_bytes.to_vec();
+        let mut reader = Cursor::new(raw_bytes.as_slice());
+        let fits = Fits::from_byte_slice(&mut reader).ok()?;
+
+        let size = fits.get_size();
+        let (width, height) = (size.x as usize, size.y as usize);
+
+        let to_physical = |v: f32| -> f32 {
+            if v == cfg.blank || v.is_nan() {
+                f32::NAN
+            } else {
+                v * cfg.scale + cfg.offset
+            }
+        };
+        let values = match &fits.data {
+            Data::U8(data) => data.iter().map(|v| to_physical(*v as f32)).collect(),
+            Data::I16(data) => data.iter().map(|v| to_physical(*v as f32)).collect(),
+            Data::I32(data) => data.iter().map(|v| to_physical(*v as f32)).collect(),
+            Data::F32(data) => data.iter().map(|v| to_physical(*v)).collect(),
+        };
+
+        Some((values, width, height))
+    } else {
+        None
+    }
+}
+
+/// Project a line on the screen and add it to the lines to draw
+///
+/// The line is split where it goes out of the projection or jumps
+/// from one side of the projection to the other.
+fn add_line(
+    lines: &mut RasterizedLinesRenderManager,
+    line: &[XYZWModel],
+    frame: &CooSystem,
+    cfg: &ContourCfg,
+    camera: &CameraViewPort,
+    projection: &ProjectionType,
+) {
+    let size = camera.get_screen_size();
+    let thickness = cfg.thickness * camera.get_dpi();
+
+    let mut path = vec![];
+    let mut prev_ndc = None;
+    for xyzw in line {
+        let xyzw = crate::coosys::apply_coo_system(frame, camera.get_system(), xyzw);
+
+        if let Some(ndc) = projection.model_to_normalized_device_space(&xyzw, camera) {
+            if let Some(prev_ndc) = prev_ndc {
+                let jump: XYNDC = ndc - prev_ndc;
+                if jump.magnitude() > MAX_NDC_JUMP {
+                    lines.add_path(&path, thickness, &cfg.color);
+                    path.clear();
+                }
+            }
+
+            // Position in screen pixels
+            path.push(Vector2::new(
+                (ndc.x as f32 * 0.5 + 0.5) * size.x,
+                (0.5 - ndc.y as f32 * 0.5) * size.y,
+            ));
+            prev_ndc = Some(ndc);
+        } else {
+            lines.add_path(&path, thickness, &cfg.color);
+            path.clear();
+            prev_ndc = None;
+        }
+    }
+
+    lines.add_path(&path, thickness, &cfg.color);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::tile_pixel_to_dxdy;
+
+    #[test]
+    fn test_tile_pixel_to_dxdy() {
+        // The outer corners of the tile are the corners of the HEALPix cell
+        assert_eq!(tile_pixel_to_dxdy(-0.5, -0.5, 4, 8), (1.0, 0.0));
+        assert_eq!(tile_pixel_to_dxdy(3.5, 7.5, 4, 8), (0.0, 1.0));
+        assert_eq!(tile_pixel_to_dxdy(1.5, 3.5, 4, 8), (0.5, 0.5));
+
+        // The first pixel of the last row is the closest to the south vertex of the cell
+        let depth = 3;
+        let idx = 42;
+        let (dx, dy) = tile_pixel_to_dxdy(0.0, 7.0, 8, 8);
+        let (lon, lat) = cdshealpix::nested::sph_coo(depth, idx, dx, dy);
+        let vertices = cdshealpix::nested::vertices(depth, idx);
+        let dist = |(lon2, lat2): (f64, f64)| {
+            (lat - lat2).hypot((lon - lon2) * lat.cos())
+        };
+        let south = dist(vertices[0]);
+        assert!(vertices[1..].iter().all(|v| dist(*v) > south));
+    }
+}
@@ -116,6 +116,16 @@ impl ProjetedGrid {
         Ok(grid)
     }
 
+    pub fn get_cfg(&self) -> GridCfg {
+        GridCfg {
+            color: Some(self.color),
+            opacity: Some(self.opacity),
+            show_labels: Some(self.show_labels),
+            label_size: Some(self.label_scale),
+            enabled: Some(self.enabled),
+        }
+    }
+
     pub fn set_cfg(&mut self, new_cfg: GridCfg, camera: &CameraViewPort, projection: &ProjectionType) -> Result<(), JsValue> {
         let GridCfg {
             color,
@@ -29,6 +29,10 @@ use crate::downloader::request::allsky::Allsky;
 use crate::healpix::{cell::HEALPixCell, coverage::HEALPixCoverage};
 use crate::time::Time;
 
+use std::cell::{Ref, RefCell, RefMut};
+use std::collections::{HashMap, HashSet};
+use std::rc::Rc;
+
 // Recursively compute the number of subdivision needed for a cell
 // to not be too much skewed
 
@@ -112,6 +116,42 @@ fn num_subdivision(cell: &HEALPixCell, camera: &CameraViewPort, projection: &Pro
     num_sub
 }
 
+/// How a cell fades from the coarser texture it was drawn with to the texture drawn now
+#[derive(Debug, PartialEq)]
+enum LodFading {
+    /// Keep on fading since the given time
+    Continue(Time),
+    /// Start fading now
+    Start,
+    /// Fade since the reception of the texture drawn
+    FromTexture,
+}
+
+/// Decide how a cell fades between two computations of the vertices
+///
+/// # Arguments
+///
+/// * `prev` - The texture cell drawn previously for `cell` and the time its fading started
+/// * `ending_cell` - The cell of the texture drawn now
+/// * `cell` - The cell drawn
+/// * `depth_increased` - Whether the view has been refined since the previous computation
+fn cell_lod_fading(
+    prev: Option<&(HEALPixCell, Time)>,
+    ending_cell: &HEALPixCell,
+    cell: &HEALPixCell,
+    depth_increased: bool,
+) -> LodFading {
+    match prev {
+        // The same texture is still drawn, keep on fading
+        Some((prev_ending_cell, start_time)) if prev_ending_cell == ending_cell => LodFading::Continue(*start_time),
+        // A finer texture replaces the one drawn, fade from its parent
+        Some((prev_ending_cell, _)) if prev_ending_cell.depth() < ending_cell.depth() => LodFading::Start,
+        // The cell has just been refined by zooming in and its own texture is available
+        None if depth_increased && ending_cell == cell => LodFading::Start,
+        _ => LodFading::FromTexture,
+    }
+}
+
 pub struct TextureToDraw<'a, 'b> {
     pub starting_texture: &'a Texture,
     pub ending_texture: &'a Texture,
@@ -325,8 +365,10 @@ pub fn get_raytracer_shader<'a>(
 
 pub struct HiPS {
     //color: Color,
-    // The image survey texture buffer
-    textures: ImageSurveyTextures,
+    // The image survey texture buffer, shared by the views displaying the same HiPS
+    textures: Rc<RefCell<ImageSurveyTextures>>,
+    // Version of the textures the vertices have been computed with
+    textures_version: u32,
     // Keep track of the cells in the FOV
     view: HEALPixCellsInView,
 
@@ -364,6 +406,12 @@ pub struct HiPS {
     depth: u8,
     depth_tile: u8,
 
+    // For each cell drawn, the cell of the texture drawn and the time its fading from
+    // the coarser texture started
+    lod_fading: HashMap<HEALPixCell, (HEALPixCell, Time)>,
+    // The depth of the view when the vertices were last computed
+    lod_depth: u8,
+
     footprint_moc: Option<HEALPixCoverage>,
 }
 
@@ -373,6 +421,20 @@ impl HiPS {
         gl: &WebGlContext,
         _camera: &CameraViewPort,
     ) -> Result<Self, JsValue> {
+        let textures = Rc::new(RefCell::new(ImageSurveyTextures::new(gl, config)?));
+
+        Ok(Self::with_textures(textures, gl))
+    }
+
+    /// A HiPS drawn from the textures of another one
+    ///
+    /// The tiles received by one of them are available to both, as are their
+    /// image format and the cube frame they display.
+    pub fn sharing_textures_of(hips: &HiPS) -> Self {
+        Self::with_textures(hips.textures.clone(), &hips.gl)
+    }
+
+    fn with_textures(textures: Rc<RefCell<ImageSurveyTextures>>, gl: &WebGlContext) -> Self {
         let mut vao = VertexArrayObject::new(gl);
 
         // layout (location = 0) in vec2 lonlat;
@@ -484,20 +546,25 @@ impl HiPS {
             .unbind();
 
         let num_idx = 0;
-        let min_depth_tile = config.get_min_depth_tile();
+        let min_depth_tile = textures.borrow().config().get_min_depth_tile();
+        // The vertices are computed at the first update
+        let textures_version = textures.borrow().version().wrapping_sub(1);
 
-        let textures = ImageSurveyTextures::new(gl, config)?;
         let view = HEALPixCellsInView::new();
 
         let gl = gl.clone();
         let depth = 0;
         let depth_tile = 0;
 
+        let lod_fading = HashMap::new();
+        let lod_depth = 0;
+
         let footprint_moc = None;
         // request the allsky texture
-        Ok(HiPS {
+        HiPS {
             // The image survey texture buffer
             textures,
+            textures_version,
             // Keep track of the cells in the FOV
             view,
 
@@ -520,12 +587,23 @@ impl HiPS {
             depth,
             depth_tile,
 
+            lod_fading,
+            lod_depth,
+
             footprint_moc,
-        })
+        }
+    }
+
+    /// Tell whether the textures are the ones of another HiPS
+    pub fn shares_textures_with(&self, hips: &HiPS) -> bool {
+        Rc::ptr_eq(&self.textures, &hips.textures)
     }
 
     pub fn update(&mut self, camera: &CameraViewPort, projection: &ProjectionType) {
-        let vertices_recomputation_needed = self.textures.reset_available_tiles() | camera.has_moved();
+        // The textures may have been changed through another view sharing them
+        let textures_version = self.textures.borrow().version();
+        let vertices_recomputation_needed = textures_version != self.textures_version || camera.has_moved();
+        self.textures_version = textures_version;
         if vertices_recomputation_needed {
             self.recompute_vertices(camera, projection);
         }
@@ -542,11 +620,47 @@ impl HiPS {
     }
 
     pub fn set_img_format(&mut self, ext: ImageExt) -> Result<(), JsValue> {
-        self.textures.set_format(&self.gl, ext)
+        self.textures.borrow_mut().set_format(&self.gl, ext)
+    }
+
+    pub fn mark_tile_failed(&mut self, cell: &HEALPixCell) {
+        self.textures.borrow_mut().mark_failed(cell);
+    }
+
+    // Return true if the base tiles have to be requested again
+    pub fn reset_failed_tiles(&mut self) -> bool {
+        self.textures.borrow_mut().reset_failed_tiles()
+    }
+
+    pub fn set_cube_frame(&mut self, frame: u32) -> Result<(), JsValue> {
+        self.textures.borrow_mut().set_cube_frame(&self.gl, frame)
+    }
+
+    /// GPU memory taken by the textures in bytes
+    pub fn get_num_bytes(&self) -> usize {
+        self.textures.borrow().num_bytes()
+    }
+
+    pub fn release_stored_frames(&mut self) -> usize {
+        self.textures.borrow_mut().release_stored_frames()
+    }
+
+    pub fn get_releasable_slices_last_use(&self) -> Vec<Time> {
+        self.textures.borrow().get_releasable_slices_last_use()
+    }
+
+    pub fn release_last_slice(&mut self) -> usize {
+        self.textures.borrow_mut().release_last_slice()
+    }
+
+    /// Set the maximum GPU memory the textures can take in bytes
+    pub fn fit_textures_in(&mut self, num_bytes: usize) {
+        self.textures.borrow_mut().fit_textures_in(num_bytes);
     }
 
     pub fn get_fading_factor(&self) -> f32 {
         self.textures
+            .borrow()
             .start_time
             .map(|start_time| {
                 let fading = (Time::now().0 - start_time.0) / crate::app::BLENDING_ANIM_DURATION;
@@ -555,8 +669,16 @@ impl HiPS {
             .unwrap_or(0.0)
     }
 
+    /// Tell whether some cells are fading from a coarser texture to their own one
+    pub fn is_lod_fading(&self) -> bool {
+        let now = Time::now().0;
+        self.lod_fading
+            .values()
+            .any(|(_, start_time)| now - start_time.0 < crate::app::BLENDING_ANIM_DURATION)
+    }
+
     pub fn is_allsky(&self) -> bool {
-        self.textures.config().is_allsky
+        self.textures.borrow().config().is_allsky
     } 
 
     pub fn reset_frame(&mut self) {
@@ -570,19 +692,19 @@ impl HiPS {
         camera: &CameraViewPort,
     ) -> Result<JsValue, JsValue> {
         // 1. Convert it to the hips frame system
-        let cfg = self.textures.config();
+        let textures = self.textures.borrow();
+        let cfg = textures.config();
         let camera_frame = camera.get_system();
         let hips_frame = &cfg.get_frame();
 
         let pos = crate::coosys::apply_coo_system(camera_frame, hips_frame, &pos.vector());
 
         // Get the array of textures from that survey
-        let pos_tex = self
-            .textures
+        let pos_tex = textures
             .get_pixel_position_in_texture(&pos.lonlat(), self.view.get_depth())?;
 
         let slice_idx = pos_tex.z as usize;
-        let texture_array = self.textures.get_texture_array();
+        let texture_array = textures.get_texture_array();
 
         let value = texture_array[slice_idx].read_pixel(pos_tex.x, pos_tex.y)?;
 
@@ -608,7 +730,8 @@ impl HiPS {
         self.m1.clear();
         self.idx_vertices.clear();
 
-        let cfg = self.textures.config();
+        let textures = self.textures.borrow();
+        let cfg = textures.config();
         // Get the coo system transformation matrix
         let selected_frame = camera.get_system();
         let channel = cfg.get_format().get_channel();
@@ -620,6 +743,9 @@ impl HiPS {
         let v2w = w2v.transpose();
 
         let longitude_reversed = camera.get_longitude_reversed();
+        // The view has been refined, the cells were drawn with coarser textures until now
+        let depth_increased = self.view.get_depth() > self.lod_depth;
+        let mut lod_fading = HashMap::with_capacity(self.lod_fading.len());
         for cell in self.view.get_cells() {
             // filter textures that are not in the moc
             let cell = if let Some(moc) = self.footprint_moc.as_ref() {
@@ -641,11 +767,11 @@ impl HiPS {
             };
 
             if let Some(cell) = cell {
-                let texture_to_draw = if self.textures.contains(cell) {
-                    let parent_cell = self.textures.get_nearest_parent(cell);
+                let texture_to_draw = if textures.contains(cell) {
+                    let parent_cell = textures.get_nearest_parent(cell);
 
-                    if let Some(ending_cell_in_tex) = self.textures.get(cell) {
-                        if let Some(starting_cell_in_tex) = self.textures.get(&parent_cell) {
+                    if let Some(ending_cell_in_tex) = textures.get(cell) {
+                        if let Some(starting_cell_in_tex) = textures.get(&parent_cell) {
                             Some(TextureToDraw::new(
                                 starting_cell_in_tex,
                                 ending_cell_in_tex,
@@ -658,11 +784,11 @@ impl HiPS {
                         None
                     }
                 } else {
-                    let parent_cell = self.textures.get_nearest_parent(cell);
-                    let grand_parent_cell = self.textures.get_nearest_parent(&parent_cell);
+                    let parent_cell = textures.get_nearest_parent(cell);
+                    let grand_parent_cell = textures.get_nearest_parent(&parent_cell);
 
-                    if let Some(ending_cell_in_tex) = self.textures.get(&parent_cell) {
-                        if let Some(starting_cell_in_tex) = self.textures.get(&grand_parent_cell) {
+                    if let Some(ending_cell_in_tex) = textures.get(&parent_cell) {
+                        if let Some(starting_cell_in_tex) = textures.get(&grand_parent_cell) {
                             Some(TextureToDraw::new(
                                 starting_cell_in_tex,
                                 ending_cell_in_tex,
@@ -679,7 +805,14 @@ impl HiPS {
                 if let Some(TextureToDraw {cell, starting_texture, ending_texture}) = texture_to_draw {
                     let uv_0 = TileUVW::new(cell, starting_texture, cfg);
                     let uv_1 = TileUVW::new(cell, ending_texture, cfg);
-                    let start_time = ending_texture.start_time().as_millis();
+                    let ending_cell = *ending_texture.cell();
+                    let start_time = match cell_lod_fading(self.lod_fading.get(cell), &ending_cell, cell, depth_increased) {
+                        LodFading::Continue(start_time) => start_time,
+                        LodFading::Start => Time::now(),
+                        LodFading::FromTexture => ending_texture.start_time(),
+                    };
+                    lod_fading.insert(*cell, (ending_cell, start_time));
+                    let start_time = start_time.as_millis();
 
                     let miss_0 = (starting_texture.is_missing()) as i32 as f32;
                     let miss_1 = (ending_texture.is_missing()) as i32 as f32;
@@ -694,17 +827,18 @@ impl HiPS {
 
                     let ll = crate::healpix::utils::grid_lonlat::<f64>(cell, n_segments_by_side as u16);
                     let n_segments_by_side_f32 = n_segments_by_side as f32;
+                    // While morphing, the triangles having a vertex out of one of the projections are not drawn
+                    let morphing = projection.is_morphing();
+                    let mut projected = Vec::with_capacity(n_vertices_per_segment * n_vertices_per_segment);
 
                     for i in 0..n_vertices_per_segment {
                         for j in 0..n_vertices_per_segment {
                             let id_vertex_0 = (j + i * n_vertices_per_segment) as usize;
                             let world_pos: Vector4<f64> = v2w * ll[id_vertex_0].vector::<Vector4<f64>>();
 
-                            let ndc_pos = if let Some(ndc_pos) = projection.world_to_normalized_device_space(&world_pos, camera) {
-                                ndc_pos
-                            } else {
-                                Vector2::new(0.0, 0.0)
-                            };
+                            let ndc_pos = projection.world_to_normalized_device_space(&world_pos, camera);
+                            projected.push(ndc_pos.is_some());
+                            let ndc_pos = ndc_pos.unwrap_or(Vector2::new(0.0, 0.0));
 
                                 self.position.push(ndc_pos.x as f32);
                                 self.position.push(ndc_pos.y as f32);
@@ -748,14 +882,16 @@ impl HiPS {
                             
                                     let first_tri_ccw = crate::math::vector::ccw_tri(&c0, &c1, &c2);
                                     let second_tri_ccw = crate::math::vector::ccw_tri(&c1, &c3, &c2);
+                                    let first_tri_projected = !morphing || (projected[idx_0 as usize] && projected[idx_1 as usize] && projected[idx_2 as usize]);
+                                    let second_tri_projected = !morphing || (projected[idx_1 as usize] && projected[idx_3 as usize] && projected[idx_2 as usize]);
                 
-                                    if (!longitude_reversed && first_tri_ccw) || (longitude_reversed && !first_tri_ccw) {
+                                    if first_tri_projected && ((!longitude_reversed && first_tri_ccw) || (longitude_reversed && !first_tri_ccw)) {
                                         self.idx_vertices.push(off_idx_vertices + idx_0);
                                         self.idx_vertices.push(off_idx_vertices + idx_1);
                                         self.idx_vertices.push(off_idx_vertices + idx_2);
                                     }
                 
-                                    if (!longitude_reversed && second_tri_ccw) || (longitude_reversed && !second_tri_ccw) {
+                                    if second_tri_projected && ((!longitude_reversed && second_tri_ccw) || (longitude_reversed && !second_tri_ccw)) {
                                         self.idx_vertices.push(off_idx_vertices + idx_1);
                                         self.idx_vertices.push(off_idx_vertices + idx_3);
                                         self.idx_vertices.push(off_idx_vertices + idx_2);
@@ -768,6 +904,9 @@ impl HiPS {
             }
         }
 
+        self.lod_fading = lod_fading;
+        self.lod_depth = self.view.get_depth();
+
         self.num_idx = self.idx_vertices.len();
         /*self.position.shrink_to_fit();
         self.uv_start.shrink_to_fit();
@@ -815,7 +954,8 @@ impl HiPS {
     }
 
     pub fn refresh_view(&mut self, camera: &CameraViewPort) {
-        let cfg = self.textures.config();
+        let textures = self.textures.borrow();
+        let cfg = textures.config();
         let max_tile_depth = cfg.get_max_tile_depth();
         let delta_depth = cfg.delta_depth();
 
@@ -834,11 +974,79 @@ impl HiPS {
         self.view.refresh(self.depth_tile, hips_frame, camera);
     }
 
+    /// The tiles worth being fetched ahead of time while the network is idle
+    ///
+    /// These are the tiles of the cells surrounding the view and the ones of the next deeper order
+    pub fn get_prefetch_tile_cells(&self) -> Vec<HEALPixCell> {
+        let cfg = self.get_config();
+        let cfg = &*cfg;
+        let depth_tile = self.view.get_depth();
+
+        let mut tile_cells = self.view.get_coverage()
+            .border()
+            .flatten_to_fixed_depth_cells()
+            .map(|idx| HEALPixCell(depth_tile, idx))
+            .flat_map(|cell| cell.get_texture_cell(cfg).get_tile_cells(cfg))
+            .collect::<HashSet<_>>();
+
+        if depth_tile < cfg.get_max_tile_depth() {
+            tile_cells.extend(
+                self.view.get_cells()
+                    .flat_map(|cell| cell.get_children_cells(1))
+            );
+        }
+
+        self.filter_tiles_to_fetch(tile_cells)
+    }
+
+    /// The tiles of the view centered on `center` (given in the HiPS frame) with an aperture `fov`
+    pub fn get_tile_cells_in_view(&self, center: &Vector4<f64>, fov: Angle<f64>, camera: &CameraViewPort) -> Vec<HEALPixCell> {
+        let cfg = self.get_config();
+        let cfg = &*cfg;
+        let depth_tile = camera.get_tile_depth_for_aperture(fov).min(cfg.get_max_tile_depth());
+
+        // Radius of the cone enclosing the view
+        let aspect = (camera.get_height() / camera.get_width()) as f64;
+        let radius = 0.5 * fov.0 * (1.0 + aspect * aspect).sqrt();
+        let tile_cells = HEALPixCoverage::from_cone(center, radius, depth_tile)
+            .flatten_to_fixed_depth_cells()
+            .map(|idx| HEALPixCell(depth_tile, idx))
+            .flat_map(|cell| cell.get_texture_cell(cfg).get_tile_cells(cfg))
+            .collect::<HashSet<_>>();
+
+        self.filter_tiles_to_fetch(tile_cells)
+    }
+
+    /// Tell whether all the tiles of the view have been received
+    pub fn is_view_complete(&self) -> bool {
+        let cfg = self.get_config();
+        let cfg = &*cfg;
+        let tile_cells = self.view.get_cells()
+            .flat_map(|cell| cell.get_texture_cell(cfg).get_tile_cells(cfg))
+            .collect::<HashSet<_>>();
+
+        self.is_ready() && self.filter_tiles_to_fetch(tile_cells).is_empty()
+    }
+
+    // Keep the tiles not yet received that may contain data
+    fn filter_tiles_to_fetch(&self, tile_cells: HashSet<HEALPixCell>) -> Vec<HEALPixCell> {
+        tile_cells.into_iter()
+            .filter(|cell| cell.depth() >= self.min_depth_tile)
+            .filter(|cell| !self.contains_tile(cell))
+            .filter(|cell| self.footprint_moc.as_ref().map(|moc| moc.contains(cell)).unwrap_or(true))
+            .collect()
+    }
+
     // Return a boolean to signal if the tile is present or not in the survey
+    pub fn contains_tile(&self, cell: &HEALPixCell) -> bool {
+        self.textures.borrow().contains_tile(cell)
+    }
+
     pub fn update_priority_tile(&mut self, cell: &HEALPixCell) -> bool {
-        if self.textures.contains_tile(cell) {
+        let mut textures = self.textures.borrow_mut();
+        if textures.contains_tile(cell) {
             // The cell is present in the survey, we update its priority
-            self.textures.update_priority(cell);
+            textures.update_priority(cell);
             true
         } else {
             false
@@ -851,25 +1059,29 @@ impl HiPS {
         image: Option<I>,
         time_request: Time,
     ) -> Result<(), JsValue> {
-        self.textures.push(&cell, image, time_request)
+        self.textures.borrow_mut().push(&cell, image, time_request)
     }
 
     pub fn add_allsky(
         &mut self,
         allsky: Allsky,
     ) -> Result<(), JsValue> {
-        self.textures.push_allsky(allsky)
+        if let Some(stats) = &allsky.stats {
+            self.get_config_mut().set_allsky_stats(stats);
+        }
+
+        self.textures.borrow_mut().push_allsky(allsky)
     }
 
     /* Accessors */
     #[inline]
-    pub fn get_config(&self) -> &HiPSConfig {
-        self.textures.config()
+    pub fn get_config(&self) -> Ref<'_, HiPSConfig> {
+        Ref::map(self.textures.borrow(), |textures| textures.config())
     }
 
     #[inline]
-    pub fn get_config_mut(&mut self) -> &mut HiPSConfig {
-        self.textures.config_mut()
+    pub fn get_config_mut(&mut self) -> RefMut<'_, HiPSConfig> {
+        RefMut::map(self.textures.borrow_mut(), |textures| textures.config_mut())
     }
 
     #[inline]
@@ -889,14 +1101,15 @@ impl HiPS {
 
     #[inline]
     pub fn is_ready(&self) -> bool {
-        self.textures.is_ready()
+        self.textures.borrow().is_ready()
     }
 
     #[inline]
-    pub fn get_ready_time(&self) -> &Option<Time> {
-        &self.textures.start_time
+    pub fn get_ready_time(&self) -> Option<Time> {
+        self.textures.borrow().start_time
     }
 
+    #[allow(clippy::too_many_arguments)]
     pub fn draw(
         &self,
         //switch_from_raytrace_to_raster: bool,
@@ -904,11 +1117,15 @@ impl HiPS {
         colormaps: &Colormaps,
         camera: &CameraViewPort,
         raytracer: &RayTracer,
+        projection: &ProjectionType,
         cfg: &ImageMetadata,
+        // Draw the physical values instead of the colors
+        raw_value: bool,
     ) -> Result<(), JsValue> {
         // Get the coo system transformation matrix
         let selected_frame = camera.get_system();
-        let hips_cfg = self.textures.config();
+        let textures = self.textures.borrow();
+        let hips_cfg = textures.config();
         let hips_frame = hips_cfg.get_frame();
         let c = selected_frame.to(&hips_frame);
 
@@ -920,11 +1137,17 @@ impl HiPS {
         let w2v = c * (*camera.get_w2m()) * rl;
         let v2w = w2v.transpose();
 
-        let raytracing = raytracer.is_rendering(camera);
+        let raytracing = raytracer.is_rendering(camera, projection);
         let longitude_reversed = camera.get_longitude_reversed();
         let config = self.get_config();
 
-        self.gl.enable(WebGl2RenderingContext::BLEND);
+        // The physical values are drawn opaque into float framebuffers that cannot
+        // be blended onto without the EXT_float_blend extension
+        if raw_value {
+            self.gl.disable(WebGl2RenderingContext::BLEND);
+        } else {
+            self.gl.enable(WebGl2RenderingContext::BLEND);
+        }
 
         let ImageMetadata {
             color,
@@ -933,6 +1156,14 @@ impl HiPS {
             ..
         } = cfg;
 
+        // Cuts not given by the user are taken from the FITS HiPS
+        let mut color = color.clone();
+        if let Some([min_cut, max_cut]) = config.get_default_cuts().filter(|_| config.tex_storing_fits) {
+            color.min_cut = color.min_cut.or(Some(min_cut));
+            color.max_cut = color.max_cut.or(Some(max_cut));
+        }
+        let color = &color;
+
         // Add starting fading
         let fading = self.get_fading_factor();
         let opacity = opacity * fading;
@@ -954,7 +1185,7 @@ impl HiPS {
                 let shader = shader.bind(&self.gl);
                 shader
                     .attach_uniforms_from(camera)
-                    .attach_uniforms_from(&self.textures)
+                    .attach_uniforms_from(&*textures)
                     // send the cmap appart from the color config
                     .attach_uniforms_with_params_from(cmap, colormaps)
                     .attach_uniforms_from(color)
@@ -962,6 +1193,7 @@ impl HiPS {
                     .attach_uniform("inv_model", &v2w)
                     .attach_uniform("current_time", &utils::get_current_time())
                     .attach_uniform("opacity", &opacity)
+                    .attach_uniform("raw_value", &raw_value)
                     .attach_uniforms_from(colormaps);
     
                 raytracer.draw(&shader);
@@ -998,7 +1230,7 @@ impl HiPS {
     
                 shader
                     .attach_uniforms_from(camera)
-                    .attach_uniforms_from(&self.textures)
+                    .attach_uniforms_from(&*textures)
                     // send the cmap appart from the color config
                     .attach_uniforms_with_params_from(cmap, colormaps)
                     .attach_uniforms_from(color)
@@ -1006,6 +1238,7 @@ impl HiPS {
                     .attach_uniform("inv_model", &v2w)
                     .attach_uniform("current_time", &utils::get_current_time())
                     .attach_uniform("opacity", &opacity)
+                    .attach_uniform("raw_value", &raw_value)
                     .attach_uniforms_from(colormaps)
                     .bind_vertex_array_object_ref(&self.vao)
                     .draw_elements_with_i32(
@@ -1033,4 +1266,34 @@ impl HiPS {
 
         Ok(())
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{cell_lod_fading, LodFading};
+    use crate::healpix::cell::HEALPixCell;
+    use crate::time::Time;
+
+    #[test]
+    fn test_lod_fading() {
+        let cell = HEALPixCell(5, 1000);
+        let parent = HEALPixCell(4, 250);
+        let grand_parent = HEALPixCell(3, 62);
+        let t = Time(1000.0);
+
+        // The same texture is drawn, its fading goes on
+        assert_eq!(cell_lod_fading(Some(&(parent, t)), &parent, &cell, false), LodFading::Continue(t));
+        assert_eq!(cell_lod_fading(Some(&(cell, t)), &cell, &cell, true), LodFading::Continue(t));
+
+        // A finer texture arrives, it fades from the coarser one drawn until now
+        assert_eq!(cell_lod_fading(Some(&(grand_parent, t)), &parent, &cell, false), LodFading::Start);
+        assert_eq!(cell_lod_fading(Some(&(parent, t)), &cell, &cell, false), LodFading::Start);
+
+        // The view has been refined and the new cell has its own texture
+        assert_eq!(cell_lod_fading(None, &cell, &cell, true), LodFading::Start);
+        // It is drawn with the texture of its parent, which fades since its reception
+        assert_eq!(cell_lod_fading(None, &parent, &cell, true), LodFading::FromTexture);
+        // A cell newly in the view, without refinement
+        assert_eq!(cell_lod_fading(None, &cell, &cell, false), LodFading::FromTexture);
+    }
+}
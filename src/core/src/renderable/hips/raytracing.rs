@@ -240,7 +240,12 @@ impl RayTracer {
             )
     }
 
-    pub fn is_rendering(&self, camera: &CameraViewPort) -> bool {
+    pub fn is_rendering(&self, camera: &CameraViewPort, projection: &ProjectionType) -> bool {
+        // A projection in transition cannot be deprojected, its cells are rasterized
+        if projection.is_morphing() {
+            return false;
+        }
+
         // Check whether the tile depth is 0 for square projection
         // definition domains i.e. Mercator
         let depth = camera.get_tile_depth();
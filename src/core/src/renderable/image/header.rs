@@ -0,0 +1,307 @@
+use std::io::{BufReader, Cursor};
+
+use fitsrs::fits::Fits;
+use fitsrs::hdu::header::{extension::image::Image, Header};
+use wasm_bindgen::JsValue;
+use wcs::WCS;
+
+use super::native::NativeProjection;
+use crate::export::fits::{Card, Value};
+
+/// Keywords describing the data unit. They are given by the
+/// decoded image itself and must not be taken from the user keywords
+const DATA_KEYWORDS: &[&str] = &[
+    "SIMPLE", "BITPIX", "NAXIS", "NAXIS1", "NAXIS2", "NAXIS3", "EXTEND", "END",
+];
+
+const CARD_SIZE: usize = 80;
+const BLOCK_SIZE: usize = 2880;
+
+/// Keywords of the linear and celestial parts of a WCS
+const WCS_KEYWORDS: &[&str] = &[
+    "CTYPE1", "CTYPE2", "CUNIT1", "CUNIT2", "CRVAL1", "CRVAL2", "CRPIX1", "CRPIX2",
+    "CDELT1", "CDELT2", "CROTA2", "CD1_1", "CD1_2", "CD2_1", "CD2_2",
+    "PC1_1", "PC1_2", "PC2_1", "PC2_2", "PV2_1", "PV2_2", "PV2_3",
+    "LONPOLE", "LATPOLE", "RADESYS", "EQUINOX", "EPOCH", "MJD-OBS", "DATE-OBS",
+];
+// Maximum order of the SIP distortion polynomials
+const MAX_SIP_ORDER: usize = 9;
+
+/// Parse the WCS keywords of an image
+///
+/// Keywords can be given either:
+/// * as a JSON object, e.g. `{"CTYPE1": "RA---TAN", "CRVAL1": 10.5, ...}`
+/// * as a FITS header string, i.e. a list of 80 characters cards, optionally
+///   separated by new lines
+///
+/// The values returned are written the FITS way (i.e. strings are quoted)
+pub fn parse_keywords(keywords: &str) -> Result<Vec<(String, String)>, String> {
+    let keywords = keywords.trim();
+
+    if keywords.starts_with('{') {
+        parse_json_keywords(keywords)
+    } else {
+        Ok(parse_fits_keywords(keywords))
+    }
+}
+
+fn parse_json_keywords(keywords: &str) -> Result<Vec<(String, String)>, String> {
+    let obj: serde_json::Map<String, serde_json::Value> = serde_json::from_str(keywords)
+        .map_err(|e| format!("WCS keywords are not a valid JSON object: {}", e))?;
+
+    obj.into_iter()
+        .map(|(kw, value)| {
+            let value = match value {
+                serde_json::Value::String(s) => format!("'{}'", s.replace('\'', "''")),
+                serde_json::Value::Number(n) => n.to_string(),
+                serde_json::Value::Bool(b) => (if b { "T" } else { "F" }).to_string(),
+                _ => return Err(format!("{} keyword value must be a string, a number or a boolean", kw)),
+            };
+
+            Ok((kw.to_uppercase(), value))
+        })
+        .collect()
+}
+
+fn parse_fits_keywords(keywords: &str) -> Vec<(String, String)> {
+    let cards: Vec<String> = if keywords.contains('\n') {
+        keywords.lines().map(|l| l.to_string()).collect()
+    } else {
+        // Cards are concatenated
+        keywords
+            .chars()
+            .collect::<Vec<_>>()
+            .chunks(CARD_SIZE)
+            .map(|card| card.iter().collect())
+            .collect()
+    };
+
+    cards
+        .iter()
+        .filter_map(|card| {
+            let (kw, value) = card.split_once('=')?;
+            let kw = kw.trim();
+            if kw.is_empty() || kw.len() > 8 {
+                return None;
+            }
+
+            let value = strip_comment(value).trim();
+            if value.is_empty() {
+                None
+            } else {
+                Some((kw.to_uppercase(), value.to_string()))
+            }
+        })
+        .collect()
+}
+
+// Remove the comment following a value, i.e. what comes after the first '/'
+// not located inside a quoted string
+fn strip_comment(value: &str) -> &str {
+    let mut in_str = false;
+    for (idx, c) in value.char_indices() {
+        match c {
+            '\'' => in_str = !in_str,
+            '/' if !in_str => return &value[..idx],
+            _ => (),
+        }
+    }
+
+    value
+}
+
+/// Build the bytes of a FITS primary header describing an image
+/// of size `width` x `height` with the keywords given
+pub fn build_header(keywords: &[(String, String)], width: u64, height: u64) -> Vec<u8> {
+    let mut cards = vec![
+        ("SIMPLE".to_string(), "T".to_string()),
+        ("BITPIX".to_string(), "8".to_string()),
+        ("NAXIS".to_string(), "2".to_string()),
+        ("NAXIS1".to_string(), width.to_string()),
+        ("NAXIS2".to_string(), height.to_string()),
+    ];
+    cards.extend(
+        keywords
+            .iter()
+            .filter(|(kw, _)| !DATA_KEYWORDS.contains(&kw.as_str()))
+            .cloned(),
+    );
+
+    let mut header = cards
+        .into_iter()
+        .map(|(kw, value)| format!("{:<8}= {:>20}", kw, value))
+        .chain(std::iter::once("END".to_string()))
+        .flat_map(|card| {
+            let mut card = card.into_bytes();
+            card.resize(CARD_SIZE, b' ');
+            card
+        })
+        .collect::<Vec<_>>();
+
+    let num_bytes = header.len().div_ceil(BLOCK_SIZE) * BLOCK_SIZE;
+    header.resize(num_bytes, b' ');
+
+    header
+}
+
+/// Create the WCS of an image of size `width` x `height` from its keywords, with the projection they give
+pub fn wcs_from_keywords(keywords: &str, width: u64, height: u64) -> Result<(WCS, NativeProjection), JsValue> {
+    let keywords = parse_keywords(keywords).map_err(|e| JsValue::from_str(&e))?;
+    let header = build_header(&keywords, width, height);
+
+    let mut reader = BufReader::new(Cursor::new(header));
+    let Fits { hdu } = Fits::from_reader(&mut reader)
+        .map_err(|e| JsValue::from_str(&format!("WCS keywords parsing: reason: {}", e)))?;
+
+    let wcs = WCS::new(hdu.get_header())
+        .map_err(|e| JsValue::from_str(&format!("WCS parsing error: reason: {}", e)))?;
+
+    Ok((wcs, NativeProjection::from_header(hdu.get_header())))
+}
+
+fn get_card(header: &Header<Image>, keyword: &str) -> Option<Card> {
+    let mut key = [b' '; 8];
+    key[..keyword.len()].copy_from_slice(keyword.as_bytes());
+
+    let value = match header.get(&key)? {
+        fitsrs::card::Value::Integer(v) => Value::Integer(*v),
+        fitsrs::card::Value::Logical(v) => Value::Logical(*v),
+        fitsrs::card::Value::Float(v) => Value::Float(*v),
+        fitsrs::card::Value::String(v) => Value::String(v.trim().to_string()),
+        fitsrs::card::Value::Undefined => return None,
+    };
+
+    Some(Card { keyword: keyword.to_string(), value })
+}
+
+/// The WCS keywords of an image header, including its SIP distortion coefficients
+pub fn wcs_cards(header: &Header<Image>) -> Vec<Card> {
+    let mut keywords = WCS_KEYWORDS.iter().map(|kw| kw.to_string()).collect::<Vec<_>>();
+    for prefix in ["A", "B", "AP", "BP"].iter() {
+        keywords.push(format!("{}_ORDER", prefix));
+        for p in 0..=MAX_SIP_ORDER {
+            for q in 0..=(MAX_SIP_ORDER - p) {
+                keywords.push(format!("{}_{}_{}", prefix, p, q));
+            }
+        }
+    }
+
+    keywords.iter()
+        .filter_map(|kw| get_card(header, kw))
+        .collect()
+}
+
+/// The WCS keywords of a cutout of an image
+///
+/// The cutout starts at the 0-based pixel (`x0`, `y0`) of the image and keeps one
+/// pixel over `step` in both directions. The SIP distortion is only kept when
+/// the cutout is not downsampled.
+pub fn cutout_wcs_cards(cards: &[Card], x0: u64, y0: u64, step: u64) -> Vec<Card> {
+    let step_f = step as f64;
+    let get_f64 = |v: &Value| match v {
+        Value::Float(v) => Some(*v),
+        Value::Integer(v) => Some(*v as f64),
+        _ => None,
+    };
+
+    cards.iter()
+        .filter(|card| {
+            let is_sip = card.keyword.starts_with("A_") || card.keyword.starts_with("B_")
+                || card.keyword.starts_with("AP_") || card.keyword.starts_with("BP_");
+            step == 1 || !is_sip
+        })
+        .map(|card| {
+            let value = match (card.keyword.as_str(), get_f64(&card.value)) {
+                // The 1-based pixel x of the cutout is the pixel (x0 + x - 1) * step + 1 of the image
+                ("CRPIX1", Some(v)) => Value::Float((v - 1.0) / step_f - x0 as f64 / step_f + 1.0),
+                ("CRPIX2", Some(v)) => Value::Float((v - 1.0) / step_f - y0 as f64 / step_f + 1.0),
+                ("CDELT1" | "CDELT2" | "CD1_1" | "CD1_2" | "CD2_1" | "CD2_2", Some(v)) => Value::Float(v * step_f),
+                (kw, _) if step > 1 && kw.starts_with("CTYPE") => match &card.value {
+                    Value::String(ctype) => Value::String(ctype.trim_end_matches("-SIP").to_string()),
+                    value => value.clone(),
+                },
+                _ => card.value.clone(),
+            };
+
+            Card { keyword: card.keyword.clone(), value }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::{BufReader, Cursor};
+
+    use fitsrs::fits::Fits;
+    use wcs::{ImgXY, WCS};
+
+    #[test]
+    fn test_wcs_from_json_and_fits_keywords() {
+        let json = r#"{
+            "CTYPE1": "RA---TAN",
+            "CTYPE2": "DEC--TAN",
+            "CRPIX1": 50.0,
+            "CRPIX2": 25.0,
+            "CRVAL1": 83.63,
+            "CRVAL2": 22.01,
+            "CDELT1": -0.001,
+            "CDELT2": 0.001,
+            "NAXIS1": 1
+        }"#;
+        let fits = "CTYPE1  = 'RA---TAN'           / projection\n\
+                    CTYPE2  = 'DEC--TAN'\n\
+                    CRPIX1  =                 50.0\n\
+                    CRPIX2  =                 25.0\n\
+                    CRVAL1  =                83.63 / deg\n\
+                    CRVAL2  =                22.01\n\
+                    CDELT1  =               -0.001\n\
+                    CDELT2  =                0.001\n\
+                    COMMENT a comment card";
+
+        for keywords in [json, fits] {
+            let keywords = super::parse_keywords(keywords).unwrap();
+            let header = super::build_header(&keywords, 100, 50);
+            assert_eq!(header.len() % 2880, 0);
+
+            let mut reader = BufReader::new(Cursor::new(header));
+            let Fits { hdu } = Fits::from_reader(&mut reader).unwrap();
+            let wcs = WCS::new(hdu.get_header()).unwrap();
+
+            // The NAXIS keywords are given by the image
+            assert_eq!(wcs.img_dimensions(), (100, 50));
+
+            let center = wcs.unproj_lonlat(&ImgXY::new(49.0, 24.0)).unwrap();
+            assert!((center.lon().to_degrees() - 83.63).abs() < 1e-6);
+            assert!((center.lat().to_degrees() - 22.01).abs() < 1e-6);
+        }
+    }
+
+    #[test]
+    fn test_cutout_wcs_cards() {
+        use crate::export::fits::{Card, Value};
+
+        let cards = vec![
+            Card::new("CTYPE1", "RA---TAN-SIP"),
+            Card::new("CRPIX1", 50.0),
+            Card::new("CRPIX2", 25_i64),
+            Card::new("CD1_1", -0.001),
+            Card::new("A_ORDER", 2_i64),
+        ];
+
+        // Downsampled cutout: the SIP distortion is dropped
+        let cutout = super::cutout_wcs_cards(&cards, 10, 4, 2);
+        assert_eq!(cutout, vec![
+            Card::new("CTYPE1", "RA---TAN"),
+            Card::new("CRPIX1", 20.5),
+            Card::new("CRPIX2", 11.0),
+            Card::new("CD1_1", -0.002),
+        ]);
+
+        // Full resolution cutout: only the reference pixel moves
+        let cutout = super::cutout_wcs_cards(&cards, 10, 4, 1);
+        assert_eq!(cutout[0].value, Value::String("RA---TAN-SIP".to_string()));
+        assert_eq!(cutout[1].value, Value::Float(40.0));
+        assert_eq!(cutout[2].value, Value::Float(21.0));
+        assert_eq!(cutout.len(), 5);
+    }
+}
@@ -1,5 +1,8 @@
 pub mod grid;
+pub mod header;
+pub mod native;
 pub mod subdivide_texture;
+pub mod values;
 
 use std::vec;
 use std::marker::Unpin;
@@ -35,6 +38,14 @@ use crate::ProjectionType;
 use crate::ShaderManager;
 use crate::Colormaps;
 use crate::math::lonlat::LonLat;
+use crate::math::angle::Angle;
+use al_api::coo_system::CooSystem;
+use cgmath::InnerSpace;
+
+use values::ValueGrid;
+use crate::export::fits::Card;
+use al_api::cutout::ScreenRect;
+use native::{ImageAxes, NativeProjection};
 
 pub struct Image {
     /// A reference to the GL context
@@ -52,6 +63,7 @@ pub struct Image {
     blank: f32,
     scale: f32,
     offset: f32,
+    bunit: Option<String>,
 
     /// The center of the fits
     centered_fov: CenteredFoV,
@@ -62,6 +74,14 @@ pub struct Image {
     textures: Vec<Texture2D>,
     /// Texture indices that must be drawn
     idx_tex: Vec<usize>,
+
+    /// Downsampled physical values, FITS images only
+    values: Option<ValueGrid>,
+    /// The WCS keywords of the header, FITS images only
+    wcs_cards: Vec<Card>,
+
+    /// The projection given by the WCS keywords
+    native: NativeProjection,
 }
 
 use futures::io::BufReader;
@@ -96,23 +116,31 @@ impl Image {
             .unwrap() as f32;
         let blank = header
             .get_parsed::<f64>(b"BLANK   ")
-            .unwrap_or(Ok(std::f64::NAN))
+            .unwrap_or(Ok(f64::NAN))
             .unwrap() as f32;
+        let bunit = if let Some(fitsrs::card::Value::String(bunit)) = header.get(b"BUNIT   ") {
+            Some(bunit.trim().to_string())
+        } else {
+            None
+        };
 
         // Create a WCS from a specific header unit
         let wcs = WCS::new(&header)
             .map_err(|e| JsValue::from_str(&format!("WCS parsing error: reason: {}", e)))?;
+        let native = NativeProjection::from_header(header);
+        let wcs_cards = header::wcs_cards(header);
 
         let (w, h) = wcs.img_dimensions();
-        let width = w as f64;
-        let height = h as f64;
 
         let data = hdu.get_data_mut();
-        
+
+        let mut values = ValueGrid::new(w, h, scale, offset, blank);
+        let grid = &mut values;
         let (textures, channel) = match data {
             stream::Data::U8(data) => {
                 let reader = data
-                    .map_ok(|v| {
+                    .map_ok(move |v| {
+                        grid.push(v[0] as f32);
                         v[0].to_le_bytes()
                     })
                     .into_async_read();
@@ -122,7 +150,8 @@ impl Image {
             },
             stream::Data::I16(data) => {
                 let reader = data
-                    .map_ok(|v| {
+                    .map_ok(move |v| {
+                        grid.push(v[0] as f32);
                         v[0].to_le_bytes()
                     })
                     .into_async_read();
@@ -132,7 +161,8 @@ impl Image {
             },
             stream::Data::I32(data) => {
                 let reader = data
-                    .map_ok(|v| {
+                    .map_ok(move |v| {
+                        grid.push(v[0] as f32);
                         v[0].to_le_bytes()
                     })
                     .into_async_read();
@@ -142,8 +172,9 @@ impl Image {
             },
             stream::Data::I64(data) => {
                 let reader = data
-                    .map_ok(|v| {
+                    .map_ok(move |v| {
                         let v = v[0] as i32;
+                        grid.push(v as f32);
                         v.to_le_bytes()
                     })
                     .into_async_read();
@@ -153,7 +184,8 @@ impl Image {
             },
             stream::Data::F32(data) => {
                 let reader = data
-                    .map_ok(|v| {
+                    .map_ok(move |v| {
+                        grid.push(v[0]);
                         v[0].to_le_bytes()
                     })
                     .into_async_read();
@@ -163,8 +195,9 @@ impl Image {
             },
             stream::Data::F64(data) => {
                 let reader = data
-                    .map_ok(|v| {
+                    .map_ok(move |v| {
                         let v = v[0] as f32;
+                        grid.push(v);
                         v.to_le_bytes()
                     })
                     .into_async_read();
@@ -174,6 +207,50 @@ impl Image {
             },
         };
 
+        let mut image = Self::new(gl, textures, channel, wcs, native)?;
+        image.scale = scale;
+        image.offset = offset;
+        image.blank = blank;
+        image.values = Some(values);
+        image.wcs_cards = wcs_cards;
+        image.bunit = bunit;
+
+        Ok(image)
+    }
+
+    /// Create an image layer from 8 bits RGBA pixels
+    ///
+    /// This is used for PNG/JPEG images (e.g. press release pictures) whose WCS
+    /// is given aside. `pixels` must be ordered the FITS way, i.e. its first row
+    /// is the bottom one of the image.
+    pub async fn from_rgba8_pixels(
+        gl: &WebGlContext,
+        pixels: Vec<u8>,
+        wcs: WCS,
+        native: NativeProjection,
+    ) -> Result<Self, JsValue> {
+        let (w, h) = wcs.img_dimensions();
+        if pixels.len() as u64 != w * h * (RGBA8U::NUM_CHANNELS as u64) {
+            return Err(JsValue::from_str("The number of pixels does not match the image dimensions"));
+        }
+
+        let reader = futures::io::Cursor::new(pixels);
+        let textures = subdivide_texture::build::<RGBA8U, _>(gl, w, h, reader).await?;
+
+        Self::new(gl, textures, ChannelType::RGBA8U, wcs, native)
+    }
+
+    fn new(
+        gl: &WebGlContext,
+        textures: Vec<Texture2D>,
+        channel: ChannelType,
+        wcs: WCS,
+        native: NativeProjection,
+    ) -> Result<Self, JsValue> {
+        let (w, h) = wcs.img_dimensions();
+        let width = w as f64;
+        let height = h as f64;
+
         let num_indices = vec![];
         let indices = vec![];
         let pos = vec![];
@@ -282,9 +359,10 @@ impl Image {
 
             // Metadata extracted from the fits
             wcs,
-            scale,
-            offset,
-            blank,
+            scale: 1.0,
+            offset: 0.0,
+            blank: f32::NAN,
+            bunit: None,
 
             // Centered field of view allowing to locate the fits
             centered_fov,
@@ -294,6 +372,11 @@ impl Image {
             textures,
             // Indices of textures that must be drawn
             idx_tex,
+
+            values: None,
+            wcs_cards: vec![],
+
+            native,
         };
 
         Ok(image)
@@ -309,6 +392,19 @@ impl Image {
 
     pub fn recompute_vertices(&mut self, camera: &CameraViewPort, projection: &ProjectionType) -> Result<(), JsValue> {
         let (width, height) = self.wcs.img_dimensions();
+
+        // The image is drawn as it is when the view reproduces its projection
+        if let Some(mut axes) = self.get_native_axes(camera, projection) {
+            let screen_size = camera.get_screen_size();
+            let screen_size = cgmath::Vector2::new(screen_size.x as f64, screen_size.y as f64);
+            axes.snap_to_pixels(&screen_size);
+
+            let vertices = native::get_quads(&axes, width, height, MAX_TEX_SIZE as u64, &screen_size, camera.get_longitude_reversed());
+            self.idx_tex = vertices.idx_tex;
+
+            return self.set_vertices(vertices.pos, vertices.uv, vertices.indices, vertices.num_indices);
+        }
+
         let width = width as f64;
         let height = height as f64;
 
@@ -388,12 +484,17 @@ impl Image {
         let (pos, uv, indices, num_indices) = grid::get_grid_vertices(
             &(x_mesh_range.start, y_mesh_range.start),
             &(x_mesh_range.end.ceil(), y_mesh_range.end.ceil()),
-            MAX_TEX_SIZE as u64,
             num_vertices,
             camera,
             &self.wcs,
+            &self.get_frame(),
             projection
         );
+
+        self.set_vertices(pos, uv, indices, num_indices)
+    }
+
+    fn set_vertices(&mut self, pos: Vec<[f32; 2]>, uv: Vec<[f32; 2]>, indices: Vec<u16>, num_indices: Vec<u32>) -> Result<(), JsValue> {
         self.pos = unsafe { crate::utils::transmute_vec(pos).map_err(|s| JsValue::from_str(s))? };
         self.uv = unsafe { crate::utils::transmute_vec(uv).map_err(|s| JsValue::from_str(s))? };
 
@@ -422,8 +523,15 @@ impl Image {
     }
 
     // Draw the image
-    pub fn draw(&self, shaders: &mut ShaderManager, colormaps: &Colormaps, cfg: &ImageMetadata) -> Result<(), JsValue> {
-        self.gl.enable(WebGl2RenderingContext::BLEND);
+    /// Draw the image. If `raw_value` is set, the physical values are drawn instead of the colors
+    pub fn draw(&self, shaders: &mut ShaderManager, colormaps: &Colormaps, cfg: &ImageMetadata, raw_value: bool) -> Result<(), JsValue> {
+        // The physical values are drawn opaque into float framebuffers that cannot
+        // be blended onto without the EXT_float_blend extension
+        if raw_value {
+            self.gl.disable(WebGl2RenderingContext::BLEND);
+        } else {
+            self.gl.enable(WebGl2RenderingContext::BLEND);
+        }
 
         let ImageMetadata {
             color,
@@ -440,6 +548,8 @@ impl Image {
             ChannelType::R16I => crate::shader::get_shader(&self.gl, shaders, "FitsVS", "FitsFSInteger")?,
             #[cfg(feature = "webgl2")]
             ChannelType::R8UI => crate::shader::get_shader(&self.gl, shaders, "FitsVS", "FitsFSUnsigned")?,
+            #[cfg(feature = "webgl2")]
+            ChannelType::RGBA8U => crate::shader::get_shader(&self.gl, shaders, "FitsVS", "FitsFSColor")?,
             _ => return Err(JsValue::from_str("Image format type not supported"))
         };
 
@@ -458,6 +568,7 @@ impl Image {
                     .attach_uniform("scale", &self.scale)
                     .attach_uniform("offset", &self.offset)
                     .attach_uniform("blank", &self.blank)
+                    .attach_uniform("raw_value", &raw_value)
                     .bind_vertex_array_object_ref(&self.vao)
                     .draw_elements_with_i32(
                         WebGl2RenderingContext::TRIANGLES,
@@ -481,4 +592,196 @@ impl Image {
     pub fn get_centered_fov(&self) -> &CenteredFoV {
         &self.centered_fov
     }
+
+    /// The downsampled physical values of the image, if it is a FITS one
+    #[inline]
+    pub fn get_values(&self) -> Option<&ValueGrid> {
+        self.values.as_ref()
+    }
+
+    /// The scale, offset and unit giving the physical values from the stored ones
+    #[inline]
+    pub fn get_physical_params(&self) -> (f32, f32, Option<&String>) {
+        (self.scale, self.offset, self.bunit.as_ref())
+    }
+
+    #[inline]
+    pub fn get_wcs(&self) -> &WCS {
+        &self.wcs
+    }
+
+    #[inline]
+    pub fn get_channel(&self) -> ChannelType {
+        self.channel
+    }
+
+    #[inline]
+    pub fn get_native_projection(&self) -> &NativeProjection {
+        &self.native
+    }
+
+    /// Frame of the WCS coordinates, ICRS if it is not handled
+    #[inline]
+    pub fn get_frame(&self) -> CooSystem {
+        self.native.frame().unwrap_or(CooSystem::ICRSJ2000)
+    }
+
+    /// Copy the physical values of the image drawn inside a rectangle of the screen
+    ///
+    /// The rectangle is given in physical pixels from the top-left corner of the screen.
+    /// The values are taken from the downsampled grid of the image. They are returned with
+    /// the dimensions and the WCS keywords of the cutout.
+    pub fn get_cutout(&self, rect: &ScreenRect, camera: &CameraViewPort, projection: &ProjectionType) -> Result<(Vec<f32>, usize, usize, Vec<Card>), JsValue> {
+        let values = self.values.as_ref()
+            .ok_or_else(|| JsValue::from_str("The image does not store FITS values"))?;
+        let step = values.get_step();
+        let dpi = camera.get_dpi() as f64;
+
+        // Bounding box of the grid samples drawn inside the rectangle
+        let (mut i0, mut j0, mut i1, mut j1) = (usize::MAX, usize::MAX, 0, 0);
+        for j in 0..values.get_height() {
+            for i in 0..values.get_width() {
+                let img_xy = ImgXY::new((i as u64 * step) as f64, (j as u64 * step) as f64);
+                let pos = self.wcs.unproj(&img_xy).and_then(|lonlat| {
+                    let xyzw = crate::math::lonlat::radec_to_xyzw(Angle(lonlat.lon()), Angle(lonlat.lat()));
+                    let xyzw = crate::coosys::apply_coo_system(&self.get_frame(), camera.get_system(), &xyzw);
+
+                    projection.model_to_screen_space(&xyzw, camera)
+                });
+
+                if let Some(pos) = pos.map(|pos| pos * dpi) {
+                    if pos.x >= rect.x && pos.x < rect.x + rect.width && pos.y >= rect.y && pos.y < rect.y + rect.height {
+                        i0 = i0.min(i);
+                        j0 = j0.min(j);
+                        i1 = i1.max(i);
+                        j1 = j1.max(j);
+                    }
+                }
+            }
+        }
+
+        if i0 > i1 || j0 > j1 {
+            return Err(JsValue::from_str("The cutout rectangle does not intersect the image"));
+        }
+
+        let (width, height) = (i1 - i0 + 1, j1 - j0 + 1);
+        let data = values.get_values()
+            .chunks(values.get_width())
+            .skip(j0)
+            .take(height)
+            .flat_map(|row| row[i0..=i1].iter().cloned())
+            .collect();
+        let cards = header::cutout_wcs_cards(&self.wcs_cards, i0 as u64 * step, j0 as u64 * step, step);
+
+        Ok((data, width, height, cards))
+    }
+
+    // Screen position of an image pixel, in physical pixels from the center of the screen
+    fn pixel_to_screen(&self, x: f64, y: f64, camera: &CameraViewPort, projection: &ProjectionType) -> Option<cgmath::Vector2<f64>> {
+        let lonlat = self.wcs.unproj(&ImgXY::new(x, y))?;
+        let xyzw = crate::math::lonlat::radec_to_xyzw(Angle(lonlat.lon()), Angle(lonlat.lat()));
+        let xyzw = crate::coosys::apply_coo_system(&self.get_frame(), camera.get_system(), &xyzw);
+
+        let screen_size = camera.get_screen_size();
+        projection.model_to_normalized_device_space(&xyzw, camera)
+            .map(|ndc| cgmath::Vector2::new(ndc.x * (screen_size.x as f64) * 0.5, ndc.y * (screen_size.y as f64) * 0.5))
+    }
+
+    /// Screen positions of the image pixels located from the reference pixel and its neighbours
+    pub fn get_axes(&self, camera: &CameraViewPort, projection: &ProjectionType) -> Option<ImageAxes> {
+        let (cx, cy) = self.native.crpix;
+
+        let p0 = self.pixel_to_screen(cx, cy, camera, projection)?;
+        let ex = self.pixel_to_screen(cx + 1.0, cy, camera, projection)? - p0;
+        let ey = self.pixel_to_screen(cx, cy + 1.0, camera, projection)? - p0;
+
+        Some(ImageAxes {
+            origin: p0 - ex * cx - ey * cy,
+            ex,
+            ey,
+        })
+    }
+
+    /// Screen positions of the image pixels if the view reproduces the projection of the image
+    ///
+    /// The view reproduces the projection of the image if the pixels of the edges and of the center
+    /// do not move by more than half a screen pixel from the positions given by [`Image::get_axes`].
+    pub fn get_native_axes(&self, camera: &CameraViewPort, projection: &ProjectionType) -> Option<ImageAxes> {
+        if projection.get_name() != self.native.code() {
+            return None;
+        }
+
+        let axes = self.get_axes(camera, projection)?;
+
+        let (width, height) = self.wcs.img_dimensions();
+        let (w, h) = (width as f64, height as f64);
+        let xs = [-0.5, (w - 1.0) * 0.5, w - 0.5];
+        let ys = [-0.5, (h - 1.0) * 0.5, h - 0.5];
+        let is_native = xs.iter()
+            .flat_map(|&x| ys.iter().map(move |&y| (x, y)))
+            .all(|(x, y)| {
+                self.pixel_to_screen(x, y, camera, projection)
+                    .map(|p| (p - axes.to_screen(x, y)).magnitude() < 0.5)
+                    .unwrap_or(false)
+            });
+
+        if is_native {
+            Some(axes)
+        } else {
+            None
+        }
+    }
+}
+
+use wasm_bindgen::JsCast;
+/// Decode a PNG/JPEG image into 8 bits RGBA pixels
+///
+/// `image` can either be the bytes of the encoded image (`Uint8Array`)
+/// or an `HtmlImageElement` already loaded.
+///
+/// The rows of the pixels returned are flipped so that the first row is the bottom one
+/// as it is the case for FITS images. Returns the pixels with the width and the height
+/// of the image.
+pub fn decode_rgba8(image: &JsValue) -> Result<(Vec<u8>, u64, u64), JsValue> {
+    if let Some(image) = image.dyn_ref::<web_sys::HtmlImageElement>() {
+        let (w, h) = (image.natural_width(), image.natural_height());
+
+        let document = web_sys::window()
+            .and_then(|window| window.document())
+            .ok_or_else(|| JsValue::from_str("No document found"))?;
+        let canvas = document
+            .create_element("canvas")?
+            .dyn_into::<web_sys::HtmlCanvasElement>()?;
+        canvas.set_width(w);
+        canvas.set_height(h);
+        let context = canvas
+            .get_context("2d")?
+            .ok_or_else(|| JsValue::from_str("Cannot get the 2d context of the canvas"))?
+            .dyn_into::<web_sys::CanvasRenderingContext2d>()?;
+        context.draw_image_with_html_image_element(image, 0.0, 0.0)?;
+
+        let image_data = context.get_image_data(0.0, 0.0, w as f64, h as f64)?;
+        let mut pixels = image_data.data().0;
+        flip_rows(&mut pixels, (w as usize) * RGBA8U::NUM_CHANNELS);
+
+        Ok((pixels, w as u64, h as u64))
+    } else if let Some(bytes) = image.dyn_ref::<js_sys::Uint8Array>() {
+        let mut image = image_decoder::load_from_memory(&bytes.to_vec())
+            .map_err(|e| JsValue::from_str(&format!("Image decoding: reason: {}", e)))?
+            .into_rgba8();
+        image_decoder::imageops::flip_vertical_in_place(&mut image);
+
+        let (w, h) = image.dimensions();
+        Ok((image.into_raw(), w as u64, h as u64))
+    } else {
+        Err(JsValue::from_str("The image must be given as an HTMLImageElement or as the bytes of a PNG/JPEG file"))
+    }
+}
+
+fn flip_rows(pixels: &mut [u8], row_len: usize) {
+    let num_rows = pixels.len() / row_len;
+    for y in 0..(num_rows / 2) {
+        let (top, bottom) = pixels.split_at_mut((num_rows - 1 - y) * row_len);
+        top[y * row_len..(y + 1) * row_len].swap_with_slice(&mut bottom[..row_len]);
+    }
 }
@@ -0,0 +1,262 @@
+use cgmath::Vector2;
+use fitsrs::hdu::header::{extension::image::Image, Header};
+use wasm_bindgen::JsValue;
+
+use al_api::coo_system::CooSystem;
+use al_api::projection::ProjectionParams;
+
+use crate::math::projection::ProjectionType;
+
+/// The projection of an image as given by its WCS keywords
+///
+/// It allows to set the view in the projection of the image so that the image
+/// is drawn as it is, without any reprojection
+pub struct NativeProjection {
+    /// FITS code of the projection, e.g. "TAN"
+    code: String,
+    /// PV2_1 and PV2_2 keywords
+    pv: (Option<f64>, Option<f64>),
+    /// Frame of the image coordinates, None if it is not handled by the view
+    frame: Option<CooSystem>,
+    /// Reference pixel, the first pixel center being at (0, 0) as for the `wcs` crate image positions
+    pub crpix: (f64, f64),
+}
+
+impl NativeProjection {
+    pub fn from_header(header: &Header<Image>) -> Self {
+        let ctype = if let Some(fitsrs::card::Value::String(ctype)) = header.get(b"CTYPE1  ") {
+            ctype.as_str()
+        } else {
+            ""
+        };
+        // e.g. "RA---TAN" or "GLON-CAR-SIP"
+        let code = ctype.get(5..8).unwrap_or("").trim_matches('-').to_string();
+
+        let radesys = if let Some(fitsrs::card::Value::String(radesys)) = header.get(b"RADESYS ") {
+            radesys.trim()
+        } else {
+            ""
+        };
+        let frame = match (ctype.get(0..4), radesys) {
+            // FK5 J2000 is taken as ICRS as for the other images
+            (Some("RA--"), "" | "ICRS" | "FK5") => Some(CooSystem::ICRSJ2000),
+            (Some("GLON"), _) => Some(CooSystem::GAL),
+            _ => None,
+        };
+
+        let parse = |keyword: &[u8; 8]| header.get_parsed::<f64>(keyword).and_then(|v| v.ok());
+
+        Self {
+            code,
+            pv: (parse(b"PV2_1   "), parse(b"PV2_2   ")),
+            frame,
+            crpix: (
+                parse(b"CRPIX1  ").unwrap_or(0.0) - 1.0,
+                parse(b"CRPIX2  ").unwrap_or(0.0) - 1.0,
+            ),
+        }
+    }
+
+    /// FITS code of the projection, e.g. "TAN"
+    pub fn code(&self) -> &str {
+        &self.code
+    }
+
+    /// Frame of the image coordinates, e.g. GAL for a "GLON-TAN" image
+    pub fn frame(&self) -> Option<CooSystem> {
+        self.frame
+    }
+
+    /// The projection of the view reproducing the one of the image
+    pub fn get_projection(&self) -> Result<ProjectionType, JsValue> {
+        let (pv1, pv2) = self.pv;
+        let params = match self.code.as_str() {
+            "COD" => ProjectionParams { theta_a: pv1, eta: pv2, ..Default::default() },
+            "CYP" => ProjectionParams { mu: pv1, lambda: pv2, ..Default::default() },
+            "CEA" => ProjectionParams { lambda: pv1, ..Default::default() },
+            _ => ProjectionParams::default(),
+        };
+
+        let projection = ProjectionType::from_params(&self.code, &params)?;
+        // Only the projections drawn the FITS way can reproduce the image
+        match projection.get_fits_code() {
+            Some((code, _)) if code == self.code => Ok(projection),
+            _ => Err(JsValue::from_str(&format!("The {} projection of the image cannot be reproduced by the view", self.code))),
+        }
+    }
+}
+
+/// Screen position of the image pixels, in physical pixels from the center of the screen and the y axis pointing up
+///
+/// The image pixel (x, y) is located at `origin + x * ex + y * ey`
+pub struct ImageAxes {
+    pub origin: Vector2<f64>,
+    pub ex: Vector2<f64>,
+    pub ey: Vector2<f64>,
+}
+
+impl ImageAxes {
+    pub fn to_screen(&self, x: f64, y: f64) -> Vector2<f64> {
+        self.origin + self.ex * x + self.ey * y
+    }
+
+    /// Shift the image so that its pixel centers fall on the screen pixel centers when
+    /// one image pixel covers one screen pixel
+    ///
+    /// `screen_size` is given in physical pixels
+    pub fn snap_to_pixels(&mut self, screen_size: &Vector2<f64>) {
+        let is_unit = |v: &Vector2<f64>, x: f64, y: f64| (v.x.abs() - x).abs() < 1e-3 && (v.y.abs() - y).abs() < 1e-3;
+        if !is_unit(&self.ex, 1.0, 0.0) || !is_unit(&self.ey, 0.0, 1.0) {
+            return;
+        }
+
+        // Screen pixel centers are located at i + 0.5 - size / 2 from the center of the screen
+        let snap = |v: f64, size: f64| {
+            let off = 0.5 - size * 0.5;
+            (v - off).round() + off
+        };
+        self.origin = Vector2::new(snap(self.origin.x, screen_size.x), snap(self.origin.y, screen_size.y));
+    }
+}
+
+/// Vertices of the image drawn as it is: one quad per texture chunk
+pub struct NativeVertices {
+    /// NDC positions
+    pub pos: Vec<[f32; 2]>,
+    pub uv: Vec<[f32; 2]>,
+    pub indices: Vec<u16>,
+    /// Number of indices per texture drawn
+    pub num_indices: Vec<u32>,
+    /// The textures drawn
+    pub idx_tex: Vec<usize>,
+}
+
+/// Build the quads of an image of `width` x `height` pixels subdivided in textures
+/// of `max_tex_size` pixels large
+///
+/// The triangles are given in CCW order in the NDC space, CW if the longitudes are reversed
+pub fn get_quads(axes: &ImageAxes, width: u64, height: u64, max_tex_size: u64, screen_size: &Vector2<f64>, longitude_reversed: bool) -> NativeVertices {
+    let num_texture_x = (width / max_tex_size) + 1;
+    let num_texture_y = (height / max_tex_size) + 1;
+
+    let to_ndc = |u: u64, v: u64| {
+        // The texels edges are half a pixel from the pixel centers
+        let p = axes.to_screen(u as f64 - 0.5, v as f64 - 0.5);
+        Vector2::new(2.0 * p.x / screen_size.x, 2.0 * p.y / screen_size.y)
+    };
+
+    let mut vertices = NativeVertices {
+        pos: vec![],
+        uv: vec![],
+        indices: vec![],
+        num_indices: vec![],
+        idx_tex: vec![],
+    };
+    for id_tx in 0..num_texture_x {
+        for id_ty in 0..num_texture_y {
+            let (x0, y0) = (id_tx * max_tex_size, id_ty * max_tex_size);
+            let (x1, y1) = ((x0 + max_tex_size).min(width), (y0 + max_tex_size).min(height));
+            if x0 >= x1 || y0 >= y1 {
+                continue;
+            }
+
+            let off = vertices.pos.len() as u16;
+            for (u, v) in [(x0, y0), (x1, y0), (x1, y1), (x0, y1)] {
+                let ndc = to_ndc(u, v);
+                vertices.pos.push([ndc.x as f32, ndc.y as f32]);
+                vertices.uv.push([
+                    ((u - x0) as f32) / (max_tex_size as f32),
+                    ((v - y0) as f32) / (max_tex_size as f32),
+                ]);
+            }
+
+            let ccw = crate::math::vector::ccw_tri(&to_ndc(x0, y0), &to_ndc(x1, y0), &to_ndc(x1, y1));
+            let tris = if ccw != longitude_reversed {
+                [0, 1, 2, 0, 2, 3]
+            } else {
+                [0, 2, 1, 0, 3, 2]
+            };
+            vertices.indices.extend(tris.iter().map(|i| off + i));
+            vertices.num_indices.push(6);
+            vertices.idx_tex.push((id_ty + id_tx * num_texture_y) as usize);
+        }
+    }
+
+    vertices
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::{BufReader, Cursor};
+
+    use al_api::coo_system::CooSystem;
+    use cgmath::Vector2;
+    use fitsrs::fits::Fits;
+
+    use super::{get_quads, ImageAxes, NativeProjection};
+    use crate::renderable::image::header;
+
+    fn native_projection(keywords: &str) -> NativeProjection {
+        let keywords = header::parse_keywords(keywords).unwrap();
+        let header = header::build_header(&keywords, 100, 50);
+
+        let mut reader = BufReader::new(Cursor::new(header));
+        let Fits { hdu } = Fits::from_reader(&mut reader).unwrap();
+        NativeProjection::from_header(hdu.get_header())
+    }
+
+    #[test]
+    fn test_native_projection_from_header() {
+        let native = native_projection(
+            "CTYPE1  = 'GLON-CEA'\n\
+             CTYPE2  = 'GLAT-CEA'\n\
+             CRPIX1  =                 50.0\n\
+             CRPIX2  =                 25.0\n\
+             PV2_1   =                  0.5",
+        );
+        assert_eq!(native.code(), "CEA");
+        assert_eq!(native.pv, (Some(0.5), None));
+        assert_eq!(native.crpix, (49.0, 24.0));
+        assert_eq!(native.frame(), Some(CooSystem::GAL));
+
+        let projection = native.get_projection().unwrap();
+        assert_eq!(projection.get_fits_code(), Some(("CEA", vec![(1, 0.5)])));
+
+        let native = native_projection(
+            "CTYPE1  = 'RA---TAN-SIP'\n\
+             CTYPE2  = 'DEC--TAN-SIP'\n\
+             RADESYS = 'FK5'",
+        );
+        assert_eq!(native.code(), "TAN");
+        assert_eq!(native.frame(), Some(CooSystem::ICRSJ2000));
+        assert_eq!(native.get_projection().unwrap().get_name(), "TAN");
+
+        // FK4 coordinates are not handled by the view
+        let native = native_projection(
+            "CTYPE1  = 'RA---SIN'\n\
+             CTYPE2  = 'DEC--SIN'\n\
+             RADESYS = 'FK4'",
+        );
+        assert_eq!(native.frame(), None);
+    }
+
+    #[test]
+    fn test_native_quads() {
+        let mut axes = ImageAxes {
+            origin: Vector2::new(-2.3, 0.2),
+            ex: Vector2::new(1.0, 0.0),
+            ey: Vector2::new(0.0, 1.0),
+        };
+        let screen_size = Vector2::new(10.0, 8.0);
+        axes.snap_to_pixels(&screen_size);
+        assert_eq!(axes.origin, Vector2::new(-2.5, 0.5));
+
+        // Two textures along x, the last one being empty along y
+        let vertices = get_quads(&axes, 6, 4, 4, &screen_size, false);
+        assert_eq!(vertices.idx_tex, &[0, 2]);
+        assert_eq!(vertices.num_indices, &[6, 6]);
+        assert_eq!(vertices.pos[0], [-0.6, 0.0]);
+        assert_eq!(vertices.uv[5], [0.5, 0.0]);
+        assert_eq!(&vertices.indices[6..], &[4, 5, 6, 4, 6, 7]);
+    }
+}
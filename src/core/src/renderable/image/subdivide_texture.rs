@@ -18,7 +18,8 @@ where
     F: ImageFormat,
     R: AsyncReadExt + Unpin
 {
-    let mut buf = vec![0; MAX_TEX_SIZE * std::mem::size_of::<<F::P as Pixel>::Item>()];
+    let num_bytes_per_pixel = F::NUM_CHANNELS * std::mem::size_of::<<F::P as Pixel>::Item>();
+    let mut buf = vec![0; MAX_TEX_SIZE * num_bytes_per_pixel];
     let max_tex_size = MAX_TEX_SIZE as u64;
 
     // Subdivision
@@ -48,7 +49,7 @@ where
         } else {
             max_tex_size
         };
-        let num_bytes_to_read = (num_pixels_to_read as usize) * std::mem::size_of::<<F::P as Pixel>::Item>();
+        let num_bytes_to_read = (num_pixels_to_read as usize) * num_bytes_per_pixel;
         reader.read_exact(&mut buf[..num_bytes_to_read])
             .await
             .map_err(|_| JsValue::from_str("Read some bytes error"))?;
@@ -60,7 +61,7 @@ where
         let view = unsafe {
             let slice = std::slice::from_raw_parts(
                 buf[..num_bytes_to_read].as_ptr() as *const <F::P as Pixel>::Item,
-                num_pixels_to_read as usize
+                (num_pixels_to_read as usize) * F::NUM_CHANNELS
             );
             F::view(slice)
         };
@@ -0,0 +1,91 @@
+// Maximum number of samples along one side of the grid
+const MAX_GRID_SIZE: u64 = 512;
+
+/// A downsampled grid of the physical values of an image
+///
+/// It is filled while the data of the image is streamed to the GPU
+/// and is used for computing contours on the CPU side.
+pub struct ValueGrid {
+    /// Physical values, NaN for blank pixels
+    values: Vec<f32>,
+    /// Number of columns of the grid
+    width: usize,
+    /// Number of rows of the grid
+    height: usize,
+    /// One pixel over `step` is kept in both directions
+    step: u64,
+
+    // Dimensions of the image
+    img_width: u64,
+    // Index of the next pixel pushed
+    idx: u64,
+
+    scale: f32,
+    offset: f32,
+    blank: f32,
+}
+
+impl ValueGrid {
+    pub fn new(img_width: u64, img_height: u64, scale: f32, offset: f32, blank: f32) -> Self {
+        let step = img_width.max(img_height).div_ceil(MAX_GRID_SIZE).max(1);
+
+        let width = img_width.div_ceil(step) as usize;
+        let height = img_height.div_ceil(step) as usize;
+
+        Self {
+            values: Vec::with_capacity(width * height),
+            width,
+            height,
+            step,
+
+            img_width,
+            idx: 0,
+
+            scale,
+            offset,
+            blank,
+        }
+    }
+
+    /// Push the raw value of the next pixel of the image
+    ///
+    /// Pixels are given in the order of the FITS data, i.e. row by row
+    pub fn push(&mut self, raw: f32) {
+        let x = self.idx % self.img_width;
+        let y = self.idx / self.img_width;
+        self.idx += 1;
+
+        if x.is_multiple_of(self.step) && y.is_multiple_of(self.step) {
+            let value = if raw == self.blank || raw.is_nan() {
+                f32::NAN
+            } else {
+                raw * self.scale + self.offset
+            };
+
+            self.values.push(value);
+        }
+    }
+
+    pub fn get_values(&self) -> &[f32] {
+        &self.values
+    }
+
+    pub fn get_width(&self) -> usize {
+        self.width
+    }
+
+    pub fn get_height(&self) -> usize {
+        self.height
+    }
+
+    /// One pixel of the image over `step` is kept in both directions
+    pub fn get_step(&self) -> u64 {
+        self.step
+    }
+
+    /// Image pixel coordinates of a position given in the grid
+    pub fn to_img_xy(&self, x: f32, y: f32) -> (f64, f64) {
+        let step = self.step as f64;
+        (x as f64 * step + 0.5, y as f64 * step + 0.5)
+    }
+}
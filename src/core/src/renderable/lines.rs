@@ -1,33 +1,28 @@
-use al_core::webgl_ctx::WebGl2Context;
 use al_core::VertexArrayObject;
-use al_core::shader::Shader;
+use al_core::WebGlContext;
 
-
-use super::RenderManager;
+use al_api::color::ColorRGBA;
 
 struct LineMeta {
-    color: Color,
-    thickness: f32,
+    color: ColorRGBA,
     off_idx: usize,
     num_idx: usize,
 }
 
 pub struct RasterizedLinesRenderManager {
-    gl: WebGl2Context,
-    shader: Shader,
+    gl: WebGlContext,
     vao: VertexArrayObject,
 
     vertices: Vec<f32>,
-    indices: Vec<u16>,
+    indices: Vec<u32>,
     meta: Vec<LineMeta>,
 }
 use wasm_bindgen::JsValue;
 use cgmath::Vector2;
 use al_core::VecData;
 use web_sys::WebGl2RenderingContext;
-use crate::Color;
 use crate::camera::CameraViewPort;
-
+use crate::shader::ShaderManager;
 
 use lyon::math::point;
 use lyon::path::Path;
@@ -35,81 +30,86 @@ use lyon::tessellation::*;
 
 impl RasterizedLinesRenderManager {
     /// Init the buffers, VAO and shader
-    pub fn new(gl: WebGl2Context, camera: &CameraViewPort) -> Result<Self, JsValue> {
-        // Create the VAO for the screen
-        #[cfg(feature = "webgl1")]
-        let shader = Shader::new(
-            &gl,
-            include_str!("../shaders/webgl1/line/line_vertex.glsl"),
-            include_str!("../shaders/webgl1/line/line_frag.glsl")
-        )?;
+    pub fn new(gl: &WebGlContext) -> Result<Self, JsValue> {
+        let vertices = vec![];
+        let indices = vec![];
+        let mut vao = VertexArrayObject::new(gl);
+
         #[cfg(feature = "webgl2")]
-        let shader = Shader::new(
-            &gl,
-            include_str!("../shaders/webgl2/line/line_vertex.glsl"),
-            include_str!("../shaders/webgl2/line/line_frag.glsl")
-        )?;
-        let mut vao = VertexArrayObject::new(&gl);
-
-        shader
-            .bind(&gl)
-                .bind_vertex_array_object(&mut vao)
-                    .add_array_buffer(
-                        2 * std::mem::size_of::<f32>(),
-                        &[2],
-                        &[0],
-                        WebGl2RenderingContext::STREAM_DRAW,
-                        VecData::<f32>(&vec![]),
-                    )
-                    // Set the element buffer
-                    .add_element_buffer(
-                        WebGl2RenderingContext::STREAM_DRAW,
-                        VecData::<u16>(&vec![]),
-                    )
-                // Unbind the buffer
-                .unbind();
-        let meta = vec![];
+        vao.bind_for_update()
+            .add_array_buffer_single(
+                2,
+                "pos",
+                WebGl2RenderingContext::STREAM_DRAW,
+                VecData::<f32>(&vertices),
+            )
+            // Set the element buffer
+            .add_element_buffer(
+                WebGl2RenderingContext::STREAM_DRAW,
+                VecData::<u32>(&indices),
+            )
+            // Unbind the buffer
+            .unbind();
+        #[cfg(feature = "webgl1")]
+        vao.bind_for_update()
+            .add_array_buffer(
+                2,
+                "pos",
+                WebGl2RenderingContext::STREAM_DRAW,
+                VecData::<f32>(&vertices),
+            )
+            // Set the element buffer
+            .add_element_buffer(
+                WebGl2RenderingContext::STREAM_DRAW,
+                VecData::<u32>(&indices),
+            )
+            // Unbind the buffer
+            .unbind();
+
         Ok(
             Self {
-                gl,
-                shader,
+                gl: gl.clone(),
                 vao,
-                meta,
-                vertices: vec![],
-                indices: vec![],
+                meta: vec![],
+                vertices,
+                indices,
             }
         )
     }
 
-    pub fn add_path(&mut self, path: &[Vector2<f32>], thickness: f32, color: &Color) {
-        let mut builder = Path::builder();
-        if path.is_empty() {
+    /// Add a polyline given in screen space
+    pub fn add_path(&mut self, path: &[Vector2<f32>], thickness: f32, color: &ColorRGBA) {
+        if path.len() < 2 {
             return;
         }
 
+        let mut builder = Path::builder();
         builder.begin(point(path[0].x, path[0].y));
 
         for p in path.iter().skip(1) {
             builder.line_to(point(p.x, p.y));
         }
 
-        builder.end(true);
+        builder.end(false);
         let path = builder.build();
-        // Let's use our own custom vertex type instead of the default one.
         // Will contain the result of the tessellation.
-        let mut geometry: VertexBuffers<[f32; 2], u16> = VertexBuffers::new();
-        let mut tessellator = FillTessellator::new();
+        let mut geometry: VertexBuffers<[f32; 2], u32> = VertexBuffers::new();
+        let mut tessellator = StrokeTessellator::new();
         {
             // Compute the tessellation.
-            tessellator.tessellate_path(
+            let res = tessellator.tessellate_path(
                 &path,
-                &FillOptions::default(),
-                &mut BuffersBuilder::new(&mut geometry, |vertex: FillVertex| {
+                &StrokeOptions::default().with_line_width(thickness),
+                &mut BuffersBuilder::new(&mut geometry, |vertex: StrokeVertex| {
                     vertex.position().to_array()
                 }),
-            ).unwrap_abort();
+            );
+
+            if res.is_err() {
+                return;
+            }
         }
-        let num_vertices = (self.vertices.len() / 2) as u16;
+        let num_vertices = (self.vertices.len() / 2) as u32;
 
         self.vertices.extend(geometry.vertices.iter().flatten());
         for i in geometry.indices.iter_mut() {
@@ -124,50 +124,63 @@ impl RasterizedLinesRenderManager {
             LineMeta {
                 off_idx,
                 num_idx,
-                thickness,
-                color: color.clone(),
+                color: *color,
             }
         );
     }
-}
 
-impl RenderManager for RasterizedLinesRenderManager {
-    fn begin_frame(&mut self) {
+    pub fn begin_frame(&mut self) {
         self.vertices.clear();
         self.indices.clear();
 
         self.meta.clear();
     }
 
-    fn end_frame(&mut self) {
+    pub fn end_frame(&mut self) {
         // update to the GPU
         self.vao.bind_for_update()
-            .update_array(0, WebGl2RenderingContext::STREAM_DRAW, VecData(&self.vertices))
-            .update_element_array(WebGl2RenderingContext::STREAM_DRAW, VecData(&self.indices));
+            .update_array(
+                "pos",
+                WebGl2RenderingContext::STREAM_DRAW,
+                VecData(&self.vertices),
+            )
+            .update_element_array(
+                WebGl2RenderingContext::STREAM_DRAW,
+                VecData::<u32>(&self.indices),
+            );
     }
 
-    fn draw(&mut self, window_size: &Vector2<f32>) -> Result<(), JsValue> {
+    pub fn draw(&self, shaders: &mut ShaderManager, camera: &CameraViewPort) -> Result<(), JsValue> {
+        if self.meta.is_empty() {
+            return Ok(());
+        }
+
         self.gl.enable(WebGl2RenderingContext::BLEND);
-        self.gl.blend_func(WebGl2RenderingContext::ONE, WebGl2RenderingContext::ONE_MINUS_SRC_ALPHA); // premultiplied alpha
+        self.gl.blend_func_separate(
+            WebGl2RenderingContext::SRC_ALPHA,
+            WebGl2RenderingContext::ONE_MINUS_SRC_ALPHA,
+            WebGl2RenderingContext::ONE,
+            WebGl2RenderingContext::ONE,
+        );
 
-        let shader = self.shader.bind(&self.gl);
-        self.vao.bind(&shader);
+        let shader = crate::shader::get_shader(&self.gl, shaders, "LineVS", "LineFS")?
+            .bind(&self.gl);
 
         for meta in self.meta.iter() {
             shader
-                .attach_uniform("u_color", &meta.color) // Strengh of the kernel
-                .attach_uniform("u_screen_size", window_size);
-
-            self.gl.draw_elements_with_i32(
-                WebGl2RenderingContext::TRIANGLES,
-                meta.num_idx as i32,
-                WebGl2RenderingContext::UNSIGNED_SHORT,
-                (meta.off_idx as i32) * (std::mem::size_of::<u16>() as i32)
-            );
+                .attach_uniform("u_color", &meta.color)
+                .attach_uniform("u_screen_size", &camera.get_screen_size())
+                .bind_vertex_array_object_ref(&self.vao)
+                    .draw_elements_with_i32(
+                        WebGl2RenderingContext::TRIANGLES,
+                        Some(meta.num_idx as i32),
+                        WebGl2RenderingContext::UNSIGNED_INT,
+                        (meta.off_idx * std::mem::size_of::<u32>()) as i32
+                    );
         }
 
         self.gl.disable(WebGl2RenderingContext::BLEND);
 
         Ok(())
     }
-}
\ No newline at end of file
+}
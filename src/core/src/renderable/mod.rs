@@ -5,8 +5,12 @@ pub mod labels;
 pub mod moc;
 pub mod image;
 pub mod hips;
+pub mod composite;
+pub mod contour;
+pub mod lines;
 
 use crate::renderable::image::Image;
+use crate::renderable::composite::{Composite, CompositeKind};
 
 use al_core::image::format::ChannelType;
 pub use hips::HiPS;
@@ -18,9 +22,11 @@ pub use grid::ProjetedGrid;
 use al_api::hips::ImageMetadata;
 use al_api::color::ColorRGB;
 use al_api::hips::HiPSCfg;
+use al_api::hips::{RGBCompositeCfg, LayerArithmeticCfg};
 use al_api::image::ImageParams;
 
 use al_core::VertexArrayObject;
+use al_core::FrameBufferObject;
 use al_core::SliceData;
 use al_core::shader::Shader;
 use al_core::WebGlContext;
@@ -40,7 +46,7 @@ use hips::raytracing::RayTracer;
 use web_sys::{WebGl2RenderingContext};
 use wasm_bindgen::JsValue;
 use std::borrow::Cow;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 
 pub(crate) type Url = String;
 type LayerId = String;
@@ -48,6 +54,9 @@ pub struct Layers {
     // Surveys to query
     surveys: HashMap<Url, HiPS>,
     images: HashMap<Url, Image>,
+    // Layers composed of other layers. They are indexed by their layer name
+    // and have no url
+    composites: HashMap<LayerId, Composite>,
     // The meta data associated with a layer
     meta: HashMap<LayerId, ImageMetadata>,
     // Hashmap between urls and layers
@@ -96,6 +105,78 @@ impl ImageCfg {
     }
 }
 
+// The resources and the GL state needed to draw a layer
+struct RenderContext<'a> {
+    surveys: &'a mut HashMap<Url, HiPS>,
+    images: &'a mut HashMap<Url, Image>,
+    shaders: &'a mut ShaderManager,
+    colormaps: &'a Colormaps,
+    raytracer: &'a RayTracer,
+}
+
+impl<'a> RenderContext<'a> {
+    // Update and draw the HiPS or the image located at `url`.
+    // If `raw_value` is set, the physical values are drawn instead of the colors
+    fn draw_resource(
+        &mut self,
+        url: &Url,
+        meta: &ImageMetadata,
+        camera: &CameraViewPort,
+        projection: &ProjectionType,
+        raw_value: bool,
+    ) -> Result<(), JsValue> {
+        // 1. Update the survey if necessary
+        if let Some(survey) = self.surveys.get_mut(url) {
+            survey.update(camera, projection);
+
+            // 2. Draw it if its opacity is not null
+            survey.draw(
+                self.shaders,
+                self.colormaps,
+                camera,
+                self.raytracer,
+                projection,
+                meta,
+                raw_value,
+            )?;
+        } else if let Some(image) = self.images.get_mut(url) {
+            image.update(camera, projection)?;
+
+            // 2. Draw it if its opacity is not null
+            image.draw(
+                self.shaders,
+                self.colormaps,
+                meta,
+                raw_value,
+            )?;
+        }
+
+        Ok(())
+    }
+}
+
+// The channel layers of an RGB composite are rendered opaque in grayscale.
+// Only their cuts, transfer function and tonal settings are kept
+fn get_channel_meta(meta: &ImageMetadata) -> ImageMetadata {
+    let mut meta = meta.clone();
+    meta.opacity = 1.0;
+    meta.blend_cfg = Default::default();
+    meta.color.cmap_name = "grayscale".into();
+    meta.color.reversed = false;
+
+    meta
+}
+
+// The operand layers of an arithmetic layer are rendered opaque as physical values.
+// Their color settings are not used
+fn get_operand_meta(meta: &ImageMetadata) -> ImageMetadata {
+    let mut meta = meta.clone();
+    meta.opacity = 1.0;
+    meta.blend_cfg = Default::default();
+
+    meta
+}
+
 impl Layers {
     pub fn new(
         gl: &WebGlContext,
@@ -103,6 +184,7 @@ impl Layers {
     ) -> Result<Self, JsValue> {
         let surveys = HashMap::new();
         let images = HashMap::new();
+        let composites = HashMap::new();
         let meta = HashMap::new();
         let urls = HashMap::new();
         let layers = Vec::new();
@@ -156,6 +238,7 @@ impl Layers {
         Ok(Layers {
             surveys,
             images,
+            composites,
 
             meta,
             urls,
@@ -215,7 +298,7 @@ impl Layers {
         projection: &ProjectionType
     ) -> Result<(), JsValue> {
         let raytracer = &self.raytracer;
-        let raytracing = raytracer.is_rendering(camera/* , depth_texture*/);
+        let raytracing = raytracer.is_rendering(camera, projection/* , depth_texture*/);
 
         // Check whether a survey to plot is allsky
         // if neither are, we draw a font
@@ -223,8 +306,7 @@ impl Layers {
         let render_background_color = !self.layers.iter()
             .any(|layer| {
                 let meta = self.meta.get(layer).unwrap_abort();
-                let url = self.urls.get(layer).unwrap_abort();
-                if let Some(survey) = self.surveys.get(url) {
+                if let Some(survey) = self.urls.get(layer).and_then(|url| self.surveys.get(url)) {
                     let hips_cfg = survey.get_config();
                     (survey.is_allsky() || hips_cfg.get_format().get_channel() == ChannelType::RGB8U) && meta.opacity == 1.0
                 } else {
@@ -258,14 +340,22 @@ impl Layers {
 
         // The first layer must be paint independently of its alpha channel
         self.gl.enable(WebGl2RenderingContext::BLEND);
+        // The channel layers of the visible composites are only drawn through them
+        let channel_layers = self.composites.iter()
+            .filter(|(layer, _)| self.meta.get(*layer).map(|meta| meta.visible()).unwrap_or(false))
+            .flat_map(|(_, composite)| composite.get_channels().iter().cloned())
+            .collect::<HashSet<_>>();
+
         // Pre loop over the layers to see if a HiPS is entirely covering those behind
         // so that we do not have to render those
         let mut idx_start_layer = 0;
         for (idx_layer, layer) in self.layers.iter().enumerate().skip(1) {
-            let meta = self.meta.get(layer).expect("Meta should be found");
+            if channel_layers.contains(layer) {
+                continue;
+            }
 
-            let url = self.urls.get(layer).expect("Url should be found");
-            if let Some(survey) = self.surveys.get_mut(url) {
+            let meta = self.meta.get(layer).expect("Meta should be found");
+            if let Some(survey) = self.urls.get(layer).and_then(|url| self.surveys.get(url)) {
                 let hips_cfg = survey.get_config();
 
                 let fully_covering_survey = (survey.is_allsky() || hips_cfg.get_format().get_channel() == ChannelType::RGB8U) && meta.opacity == 1.0;
@@ -275,32 +365,67 @@ impl Layers {
             }
         }
 
-        let rendered_layers = &self.layers[idx_start_layer..];
+        let Layers {
+            surveys,
+            images,
+            composites,
+            meta,
+            urls,
+            layers,
+            screen_vao,
+            gl,
+            ..
+        } = self;
+        let mut ctx = RenderContext {
+            surveys,
+            images,
+            shaders,
+            colormaps,
+            raytracer,
+        };
+
+        let rendered_layers = &layers[idx_start_layer..];
         for layer in rendered_layers {
-            let draw_opt = self.meta.get(layer).expect("Meta should be found");
+            if channel_layers.contains(layer) {
+                continue;
+            }
+
+            let draw_opt = meta.get(layer).expect("Meta should be found");
             if draw_opt.visible() {
-                // 1. Update the survey if necessary
-                let url = self.urls.get(layer).expect("Url should be found");
-                if let Some(survey) = self.surveys.get_mut(url) {
-                    survey.update(camera, projection);
-
-                    // 2. Draw it if its opacity is not null
-                    survey.draw(
-                        shaders,
-                        colormaps,
-                        camera,
-                        raytracer,
-                        draw_opt
-                    )?;
-                } else if let Some(image) = self.images.get_mut(url) {
-                    image.update(camera, projection)?;
-
-                    // 2. Draw it if its opacity is not null
-                    image.draw(
-                        shaders,
-                        colormaps,
-                        draw_opt,
-                    )?;
+                if let Some(composite) = composites.get_mut(layer) {
+                    composite.resize(camera);
+
+                    // 1. Render each channel layer offscreen
+                    let clear_color = js_sys::Float32Array::new(
+                        &gl.get_parameter(WebGl2RenderingContext::COLOR_CLEAR_VALUE)?
+                    ).to_vec();
+                    for (channel, fbo) in composite.get_channels().iter().zip(composite.get_channel_fbos()) {
+                        fbo.draw_onto(|| {
+                            gl.clear_color(0.0, 0.0, 0.0, 0.0);
+                            gl.clear(WebGl2RenderingContext::COLOR_BUFFER_BIT);
+
+                            // The channel layers cannot be removed while the composite exists
+                            if let (Some(channel_url), Some(channel_meta)) = (urls.get(channel), meta.get(channel)) {
+                                let raw_value = composite.renders_raw_values();
+                                let channel_meta = if raw_value {
+                                    get_operand_meta(channel_meta)
+                                } else {
+                                    get_channel_meta(channel_meta)
+                                };
+                                ctx.draw_resource(channel_url, &channel_meta, camera, projection, raw_value)?;
+                            }
+
+                            Ok(())
+                        }, None)?;
+                    }
+                    gl.clear_color(clear_color[0], clear_color[1], clear_color[2], clear_color[3]);
+                    camera.restore_viewport();
+
+                    // 2. Combine them
+                    composite.draw(ctx.shaders, colormaps, screen_vao, draw_opt)?;
+                } else {
+                    let url = urls.get(layer).expect("Url should be found");
+                    ctx.draw_resource(url, draw_opt, camera, projection, false)?;
                 }
             }
         }
@@ -316,18 +441,103 @@ impl Layers {
         Ok(())
     }
 
+    /// Render the physical values of a FITS layer into a float fbo
+    ///
+    /// The values are stored in the red channel. Pixels not covered
+    /// by the layer have a null alpha.
+    pub fn draw_raw_values(
+        &mut self,
+        layer: &str,
+        fbo: &FrameBufferObject,
+        camera: &CameraViewPort,
+        shaders: &mut ShaderManager,
+        colormaps: &Colormaps,
+        projection: &ProjectionType
+    ) -> Result<(), JsValue> {
+        if self.composites.contains_key(layer) {
+            return Err(JsValue::from_str(&format!("Layer {:?} does not store FITS values.", layer)));
+        }
+
+        let url = self.urls.get(layer)
+            .ok_or_else(|| JsValue::from_str(&format!("Layer {:?} not found.", layer)))?;
+        let meta = self.meta.get(layer)
+            .ok_or_else(|| JsValue::from_str(&format!("Layer {:?} not found.", layer)))?;
+
+        let fits_valued = if let Some(survey) = self.surveys.get(url) {
+            survey.get_config().tex_storing_fits
+        } else if let Some(image) = self.images.get(url) {
+            image.get_channel() != ChannelType::RGBA8U
+        } else {
+            false
+        };
+
+        if !fits_valued {
+            return Err(JsValue::from_str(&format!("Layer {:?} does not store FITS values.", layer)));
+        }
+
+        let Layers {
+            surveys,
+            images,
+            raytracer,
+            gl,
+            ..
+        } = self;
+        let mut ctx = RenderContext {
+            surveys,
+            images,
+            shaders,
+            colormaps,
+            raytracer,
+        };
+
+        let clear_color = js_sys::Float32Array::new(
+            &gl.get_parameter(WebGl2RenderingContext::COLOR_CLEAR_VALUE)?
+        ).to_vec();
+
+        let res = fbo.draw_onto(|| {
+            gl.clear_color(0.0, 0.0, 0.0, 0.0);
+            gl.clear(WebGl2RenderingContext::COLOR_BUFFER_BIT);
+
+            let meta = get_operand_meta(meta);
+            ctx.draw_resource(url, &meta, camera, projection, true)
+        }, None);
+
+        gl.clear_color(clear_color[0], clear_color[1], clear_color[2], clear_color[3]);
+        camera.restore_viewport();
+
+        res
+    }
+
     pub fn remove_layer(&mut self, layer: &str, camera: &mut CameraViewPort, projection: &ProjectionType) -> Result<usize, JsValue> {
+        // The layers combining it must be removed first
+        let dependent = self.composites.iter()
+            .find(|(_, composite)| composite.get_channels().iter().any(|channel| channel == layer));
+        if let Some((dependent, _)) = dependent {
+            return Err(JsValue::from_str(&format!("Layer {:?} is used by the layer {:?}, so cannot be removed.", layer, dependent)));
+        }
+
+        self.take_layer(layer, camera, projection)
+    }
+
+    // Remove a layer, the layers combining it keep referring to its name
+    // so that it can be replaced
+    fn take_layer(&mut self, layer: &str, camera: &mut CameraViewPort, projection: &ProjectionType) -> Result<usize, JsValue> {
         let err_layer_not_found = JsValue::from_str(&format!("Layer {:?} not found, so cannot be removed.", layer));
         // Color configs, and urls are indexed by layer
         self.meta.remove(layer)
             .ok_or(err_layer_not_found.clone())?;
-        let url = self.urls.remove(layer).ok_or(err_layer_not_found.clone())?;
         // layer from layers does also need to be removed
         let id_layer = self.layers.iter()
             .position(|l| layer == l)
-            .ok_or(err_layer_not_found)?;
+            .ok_or(err_layer_not_found.clone())?;
         self.layers.remove(id_layer);
 
+        // A composite layer has no url
+        if self.composites.remove(layer).is_some() {
+            return Ok(id_layer);
+        }
+        let url = self.urls.remove(layer).ok_or(err_layer_not_found)?;
+
         // Loop over all the meta for its longitude reversed property
         // and set the camera to it if there is at least one
         let longitude_reversed = self.meta.values()
@@ -372,11 +582,20 @@ impl Layers {
 
         let meta = self.meta.remove(layer)
             .ok_or(err_layer_not_found.clone())?;
-        let url = self.urls.remove(layer).ok_or(err_layer_not_found)?;
+        if let Some(composite) = self.composites.remove(layer) {
+            self.composites.insert(new_layer.to_string(), composite);
+        } else {
+            let url = self.urls.remove(layer).ok_or(err_layer_not_found)?;
+            self.urls.insert(new_layer.to_string(), url);
+        }
 
         // Add the new
         self.meta.insert(new_layer.to_string(), meta);
-        self.urls.insert(new_layer.to_string(), url);
+
+        // The composites referring to that layer follow it
+        for composite in self.composites.values_mut() {
+            composite.rename_channel(layer, new_layer);
+        }
 
         Ok(())
     }
@@ -402,6 +621,10 @@ impl Layers {
         &mut self,
         gl: &WebGlContext,
         hips: HiPSCfg,
+        // Memory available for the textures of the HiPS in bytes
+        textures_num_bytes: usize,
+        // HiPS of another view whose textures are shared
+        shared: Option<&HiPS>,
         camera: &mut CameraViewPort,
         projection: &ProjectionType
     ) -> Result<&HiPS, JsValue> {
@@ -409,6 +632,7 @@ impl Layers {
             layer,
             properties,
             meta,
+            mirrors,
         } = hips;
 
         // 1. Add the layer name
@@ -418,8 +642,7 @@ impl Layers {
             });
 
         let idx = if layer_already_found {
-            let idx = self.remove_layer(&layer, camera, projection)?;
-            idx
+            self.take_layer(&layer, camera, projection)?
         } else {
             self.layers.len()
         };
@@ -436,9 +659,13 @@ impl Layers {
                 hips_url == &url
             });
 
-        if !url_already_found {
+        if let (false, Some(shared)) = (url_already_found, shared) {
+            self.surveys.insert(url.clone(), HiPS::sharing_textures_of(shared));
+        } else if !url_already_found {
             // The url is not processed yet
-            let cfg = HiPSConfig::new(&properties, meta.img_format)?;
+            let mut cfg = HiPSConfig::new(&properties, meta.img_format)?;
+            cfg.set_mirrors(mirrors);
+            cfg.fit_textures_in(textures_num_bytes);
 
             /*if let Some(initial_ra) = properties.get_initial_ra() {
                 if let Some(initial_dec) = properties.get_initial_dec() {
@@ -495,8 +722,7 @@ impl Layers {
             });
 
         let idx = if layer_already_found {
-            let idx = self.remove_layer(&layer, camera, projection)?;
-            idx
+            self.take_layer(&layer, camera, projection)?
         } else {
             self.layers.len()
         };
@@ -544,6 +770,131 @@ impl Layers {
         Ok(fits)
     }
 
+    pub fn add_rgb_composite(
+        &mut self,
+        cfg: RGBCompositeCfg,
+        camera: &mut CameraViewPort,
+        projection: &ProjectionType
+    ) -> Result<(), JsValue> {
+        let RGBCompositeCfg {
+            layer,
+            red,
+            green,
+            blue,
+            lupton,
+            meta,
+        } = cfg;
+
+        // 1. Check the channel layers
+        let channels = [red, green, blue];
+        for channel in channels.iter() {
+            if channel == &layer {
+                return Err(JsValue::from_str(&format!("Composite layer {:?} cannot be one of its channels.", layer)));
+            }
+
+            if self.composites.contains_key(channel) {
+                return Err(JsValue::from_str(&format!("Channel layer {:?} is a composite layer.", channel)));
+            }
+            if !self.urls.contains_key(channel) {
+                return Err(JsValue::from_str(&format!("Channel layer {:?} not found.", channel)));
+            }
+        }
+
+        // 2. Add the layer name
+        let layer_already_found = self.layers.iter()
+            .any(|l| {
+                l == &layer
+            });
+
+        let idx = if layer_already_found {
+            self.take_layer(&layer, camera, projection)?
+        } else {
+            self.layers.len()
+        };
+
+        self.layers.insert(idx, layer.to_string());
+
+        // 3. Add the composite. It is not shared so it is indexed by its layer name
+        let composite = Composite::new_rgb(&self.gl, channels, lupton, camera)?;
+        self.composites.insert(layer.clone(), composite);
+
+        // 4. Add the meta information of the layer
+        self.meta.insert(layer, meta);
+
+        Ok(())
+    }
+
+    pub fn add_layer_arithmetic(
+        &mut self,
+        cfg: LayerArithmeticCfg,
+        camera: &mut CameraViewPort,
+        projection: &ProjectionType
+    ) -> Result<(), JsValue> {
+        let LayerArithmeticCfg {
+            layer,
+            a,
+            b,
+            op,
+            meta,
+        } = cfg;
+
+        // 1. Check the operand layers
+        let channels = [a, b];
+        // The HiPS operands must be given in the same frame
+        let mut hips_frame = None;
+        for channel in channels.iter() {
+            if channel == &layer {
+                return Err(JsValue::from_str(&format!("Layer {:?} cannot be one of its operands.", layer)));
+            }
+
+            if self.composites.contains_key(channel) {
+                return Err(JsValue::from_str(&format!("Operand layer {:?} is a composite layer.", channel)));
+            }
+            let url = self.urls.get(channel)
+                .ok_or_else(|| JsValue::from_str(&format!("Operand layer {:?} not found.", channel)))?;
+
+            let fits_valued = if let Some(survey) = self.surveys.get(url) {
+                let frame = survey.get_config().get_frame();
+                if *hips_frame.get_or_insert(frame) != frame {
+                    return Err(JsValue::from_str(&format!("Operand layer {:?} is not given in the frame of the other operand.", channel)));
+                }
+
+                survey.get_config().tex_storing_fits
+            } else if let Some(image) = self.images.get(url) {
+                image.get_channel() != ChannelType::RGBA8U
+            } else {
+                false
+            };
+
+            if !fits_valued {
+                return Err(JsValue::from_str(&format!("Operand layer {:?} does not store FITS values.", channel)));
+            }
+        }
+
+        // 2. Add the layer name
+        let layer_already_found = self.layers.iter()
+            .any(|l| {
+                l == &layer
+            });
+
+        let idx = if layer_already_found {
+            self.take_layer(&layer, camera, projection)?
+        } else {
+            self.layers.len()
+        };
+
+        self.layers.insert(idx, layer.to_string());
+
+        // 3. Add the composite. It is not shared so it is indexed by its layer name
+        let composite = Composite::new_arithmetic(&self.gl, channels, op, camera)?;
+        self.composites.insert(layer.clone(), composite);
+
+        // 4. Add the meta information of the layer
+        self.meta.insert(layer, meta);
+
+        Ok(())
+    }
+
     pub fn get_layer_cfg(&self, layer: &str) -> Result<ImageMetadata, JsValue> {
         self.meta
             .get(layer)
@@ -611,6 +962,19 @@ impl Layers {
         }
     }
 
+    /// Recompute the vertices of all the HiPS and images, e.g. when the size of the render changes
+    pub fn recompute_vertices(&mut self, camera: &CameraViewPort, projection: &ProjectionType) -> Result<(), JsValue> {
+        for survey in self.surveys.values_mut() {
+            survey.recompute_vertices(camera, projection);
+        }
+
+        for image in self.images.values_mut() {
+            image.recompute_vertices(camera, projection)?;
+        }
+
+        Ok(())
+    }
+
     // Accessors
     // HiPSes getters
     pub fn get_hips_from_layer(&self, layer: &str) -> Option<&HiPS> {
@@ -631,10 +995,61 @@ impl Layers {
         self.surveys.get_mut(root_url)
     }
 
-    pub fn get_hips_from_url(&mut self, root_url: &str) -> Option<&HiPS> {
+    pub fn get_hips_from_url(&self, root_url: &str) -> Option<&HiPS> {
         self.surveys.get(root_url)
     }
 
+    /// The layers from the bottom to the top one
+    pub fn get_layers(&self) -> impl Iterator<Item = &LayerId> {
+        self.layers.iter()
+    }
+
+    /// Tell whether a layer is computed from other layers, i.e. an RGB composite or an arithmetic layer
+    pub fn is_composite(&self, layer: &str) -> bool {
+        self.composites.contains_key(layer)
+    }
+
+    /// The configs of the RGB composites and of the arithmetic layers, from the bottom to the top one
+    pub fn get_composite_cfgs(&self) -> (Vec<RGBCompositeCfg>, Vec<LayerArithmeticCfg>) {
+        let mut rgb_cfgs = vec![];
+        let mut arithmetic_cfgs = vec![];
+        for layer in self.layers.iter() {
+            if let (Some(composite), Some(meta)) = (self.composites.get(layer), self.meta.get(layer)) {
+                let channels = composite.get_channels();
+                match composite.get_kind() {
+                    CompositeKind::RGB { lupton } => rgb_cfgs.push(RGBCompositeCfg {
+                        layer: layer.clone(),
+                        red: channels[0].clone(),
+                        green: channels[1].clone(),
+                        blue: channels[2].clone(),
+                        lupton: *lupton,
+                        meta: meta.clone(),
+                    }),
+                    CompositeKind::Arithmetic { op } => arithmetic_cfgs.push(LayerArithmeticCfg {
+                        layer: layer.clone(),
+                        a: channels[0].clone(),
+                        b: channels[1].clone(),
+                        op: *op,
+                        meta: meta.clone(),
+                    }),
+                }
+            }
+        }
+
+        (rgb_cfgs, arithmetic_cfgs)
+    }
+
+    /// Tell whether a HiPS is displayed by at least one layer
+    pub fn is_hips_visible(&self, root_url: &str) -> bool {
+        self.urls.iter()
+            .filter(|(_, url)| url.as_str() == root_url)
+            .any(|(layer, _)| {
+                self.meta.get(layer)
+                    .map(|meta| meta.visible())
+                    .unwrap_or(false)
+            })
+    }
+
     pub fn values_hips(&self) -> impl Iterator<Item = &HiPS> {
         self.surveys.values()
     }
@@ -0,0 +1,61 @@
+use crate::time::Time;
+use crate::Abort;
+
+/// Number of texture slices to release for each HiPS so that the textures fit in `budget`
+///
+/// `slices_last_use` gives for every HiPS the number of bytes of one of its slices
+/// and the time its releasable slices have been used for the last time, the last allocated first.
+/// A slice can only be released from the end of a texture array so the least recently
+/// used among the last slices of all the HiPS is released first.
+pub fn num_slices_to_release(
+    slices_last_use: &[(usize, Vec<Time>)],
+    mut num_bytes: usize,
+    budget: usize,
+) -> Vec<usize> {
+    let mut num_slices = vec![0; slices_last_use.len()];
+
+    while num_bytes > budget {
+        let oldest = slices_last_use
+            .iter()
+            .zip(num_slices.iter())
+            .enumerate()
+            .filter_map(|(idx, ((num_bytes_by_slice, last_use), num_released))| {
+                last_use
+                    .get(*num_released)
+                    .map(|time| (idx, *time, *num_bytes_by_slice))
+            })
+            .min_by(|(_, t1, _), (_, t2, _)| t1.partial_cmp(t2).unwrap_abort());
+
+        if let Some((idx, _, num_bytes_by_slice)) = oldest {
+            num_slices[idx] += 1;
+            num_bytes = num_bytes.saturating_sub(num_bytes_by_slice);
+        } else {
+            // Only the first slices remain
+            break;
+        }
+    }
+
+    num_slices
+}
+
+#[cfg(test)]
+mod tests {
+    use super::num_slices_to_release;
+    use crate::time::Time;
+
+    #[test]
+    fn test_release_slices_of_two_layers() {
+        // Two HiPS with 3 slices of 10 bytes each, the first slices are not releasable
+        let slices_last_use = vec![
+            (10, vec![Time(5.0), Time(9.0)]),
+            (10, vec![Time(3.0), Time(7.0)]),
+        ];
+
+        // Within the budget
+        assert_eq!(num_slices_to_release(&slices_last_use, 60, 60), vec![0, 0]);
+        // The least recently used slices of both HiPS are released first
+        assert_eq!(num_slices_to_release(&slices_last_use, 60, 35), vec![1, 2]);
+        // The first slices are kept whatever the budget
+        assert_eq!(num_slices_to_release(&slices_last_use, 60, 0), vec![2, 2]);
+    }
+}
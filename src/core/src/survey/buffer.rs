@@ -2,6 +2,7 @@ use std::cmp::Ordering;
 use std::rc::Rc;
 use std::collections::BinaryHeap;
 use std::collections::HashMap;
+use std::collections::VecDeque;
 
 use al_core::image::format::ChannelType;
 use cgmath::Vector3;
@@ -126,6 +127,41 @@ impl HEALPixCellHeap {
     }
 }
 
+// Maximum number of HiPS cube frames whose textures are kept
+// in addition to the frame being displayed
+const MAX_NUM_STORED_CUBE_FRAMES: usize = 2;
+
+// The textures of a HiPS cube frame that is not displayed
+struct FrameTextures {
+    heap: HEALPixCellHeap,
+    num_root_textures_available: usize,
+
+    textures: HashMap<HEALPixCell, Texture>,
+    base_textures: [Texture; NUM_HPX_TILES_DEPTH_ZERO],
+
+    texture_2d_array: Rc<Texture2DArray>,
+
+    ready: bool,
+    start_time: Option<Time>,
+}
+
+impl FrameTextures {
+    fn new(texture_2d_array: Rc<Texture2DArray>, size: usize) -> Self {
+        Self {
+            heap: HEALPixCellHeap::with_capacity(size - NUM_HPX_TILES_DEPTH_ZERO),
+            num_root_textures_available: 0,
+
+            textures: HashMap::with_capacity(size),
+            base_textures: create_base_textures(Time::now()),
+
+            texture_2d_array,
+
+            ready: false,
+            start_time: None,
+        }
+    }
+}
+
 // Fixed sized binary heap
 pub struct ImageSurveyTextures {
     // Some information about the HiPS
@@ -148,23 +184,75 @@ pub struct ImageSurveyTextures {
     ready: bool,
     pub start_time: Option<Time>,
 
-    available_tiles_during_frame: bool,
+    // Changed each time tiles become available or are removed,
+    // telling the HiPS sharing the textures to recompute their vertices
+    version: u32,
     //exec: Rc<RefCell<TaskExecutor>>,
+
+    // Textures of the HiPS cube frames previously displayed,
+    // the most recently displayed being the last one
+    stored_frames: VecDeque<(u32, FrameTextures)>,
+
+    gl: WebGlContext,
+}
+
+fn create_base_textures(time: Time) -> [Texture; NUM_HPX_TILES_DEPTH_ZERO] {
+    [
+        Texture::new(&HEALPixCell(0, 0), 0, time),
+        Texture::new(&HEALPixCell(0, 1), 1, time),
+        Texture::new(&HEALPixCell(0, 2), 2, time),
+        Texture::new(&HEALPixCell(0, 3), 3, time),
+        Texture::new(&HEALPixCell(0, 4), 4, time),
+        Texture::new(&HEALPixCell(0, 5), 5, time),
+        Texture::new(&HEALPixCell(0, 6), 6, time),
+        Texture::new(&HEALPixCell(0, 7), 7, time),
+        Texture::new(&HEALPixCell(0, 8), 8, time),
+        Texture::new(&HEALPixCell(0, 9), 9, time),
+        Texture::new(&HEALPixCell(0, 10), 10, time),
+        Texture::new(&HEALPixCell(0, 11), 11, time),
+    ]
+}
+
+// Define the texture array storing the tiles of a HiPS
+//
+// Only its first slice is allocated, the next ones are added when tiles are stored in them
+fn create_texture_array_for_config(
+    gl: &WebGlContext,
+    config: &HiPSConfig,
+) -> Result<Texture2DArray, JsValue> {
+    let channel = config.get_format().get_channel();
+
+    let texture_2d_array = match channel {
+        ChannelType::RGBA32F => unimplemented!(),
+        ChannelType::RGB32F => unimplemented!(),
+        ChannelType::RGBA8U => create_texture_array::<RGBA8U>(gl, config)?,
+        ChannelType::RGB8U => create_texture_array::<RGB8U>(gl, config)?,
+        ChannelType::R32F => create_texture_array::<R32F>(gl, config)?,
+        #[cfg(feature = "webgl2")]
+        ChannelType::R8UI => create_texture_array::<R8UI>(gl, config)?,
+        #[cfg(feature = "webgl2")]
+        ChannelType::R16I => create_texture_array::<R16I>(gl, config)?,
+        #[cfg(feature = "webgl2")]
+        ChannelType::R32I => create_texture_array::<R32I>(gl, config)?,
+        #[cfg(feature = "webgl2")]
+        ChannelType::R64F => create_texture_array::<R64F>(gl, config)?,
+    };
+
+    Ok(texture_2d_array)
 }
 
-// Define a set of textures compatible with the HEALPix tile format and size
+// Define a slice of textures compatible with the HEALPix tile format and size
 fn create_texture_array<F: ImageFormat>(
     gl: &WebGlContext,
     config: &HiPSConfig,
 ) -> Result<Texture2DArray, JsValue> {
     let texture_size = config.get_texture_size();
     let num_textures_by_side_slice = config.num_textures_by_side_slice();
-    let num_slices = config.num_slices();
     Texture2DArray::create_empty::<F>(
         gl,
         texture_size * num_textures_by_side_slice,
         texture_size * num_textures_by_side_slice,
-        num_slices,
+        1,
         TEX_PARAMS,
     )
 }
@@ -182,51 +270,17 @@ impl ImageSurveyTextures {
         let heap = HEALPixCellHeap::with_capacity(size - NUM_HPX_TILES_DEPTH_ZERO);
         let textures = HashMap::with_capacity(size);
 
-        let now = Time::now();
-        let base_textures = [
-            Texture::new(&HEALPixCell(0, 0), 0, now),
-            Texture::new(&HEALPixCell(0, 1), 1, now),
-            Texture::new(&HEALPixCell(0, 2), 2, now),
-            Texture::new(&HEALPixCell(0, 3), 3, now),
-            Texture::new(&HEALPixCell(0, 4), 4, now),
-            Texture::new(&HEALPixCell(0, 5), 5, now),
-            Texture::new(&HEALPixCell(0, 6), 6, now),
-            Texture::new(&HEALPixCell(0, 7), 7, now),
-            Texture::new(&HEALPixCell(0, 8), 8, now),
-            Texture::new(&HEALPixCell(0, 9), 9, now),
-            Texture::new(&HEALPixCell(0, 10), 10, now),
-            Texture::new(&HEALPixCell(0, 11), 11, now)
-        ];
-        let channel = config.get_format().get_channel();
-
-        #[cfg(feature = "webgl2")]
-        let texture_2d_array = match channel {
-            ChannelType::RGBA32F => unimplemented!(),
-            ChannelType::RGB32F => unimplemented!(),
-            ChannelType::RGBA8U => Rc::new(create_texture_array::<RGBA8U>(gl, &config)?),
-            ChannelType::RGB8U => Rc::new(create_texture_array::<RGB8U>(gl, &config)?),
-            ChannelType::R8UI => Rc::new(create_texture_array::<R8UI>(gl, &config)?),
-            ChannelType::R16I => Rc::new(create_texture_array::<R16I>(gl, &config)?),
-            ChannelType::R32I => Rc::new(create_texture_array::<R32I>(gl, &config)?),
-            ChannelType::R32F => Rc::new(create_texture_array::<R32F>(gl, &config)?),
-            ChannelType::R64F => Rc::new(create_texture_array::<R64F>(gl, &config)?),
-        };
-        #[cfg(feature = "webgl1")]
-        let texture_2d_array = match config.get_format() {
-            ChannelType::RGBA32F => unimplemented!(),
-            ChannelType::RGB32F => unimplemented!(),
-            ChannelType::RGBA8U => Rc::new(create_texture_array::<RGBA8U>(gl, &config)?),
-            ChannelType::RGB8U => Rc::new(create_texture_array::<RGB8U>(gl, &config)?),
-            ChannelType::R32F => Rc::new(create_texture_array::<R32F>(gl, &config)?),
-        };
+        let base_textures = create_base_textures(Time::now());
+        let texture_2d_array = Rc::new(create_texture_array_for_config(gl, &config)?);
         // The root textures have not been loaded
         let ready = false;
         let num_root_textures_available = 0;
-        let available_tiles_during_frame = false;
+        let version = 0;
         let start_time = None;
         Ok(ImageSurveyTextures {
             config,
             heap,
+            gl: gl.clone(),
 
             size,
             num_root_textures_available,
@@ -235,60 +289,191 @@ impl ImageSurveyTextures {
             base_textures,
 
             texture_2d_array,
-            available_tiles_during_frame,
+            version,
 
             ready,
             start_time,
+
+            stored_frames: VecDeque::new(),
         })
     }
 
     pub fn set_format(&mut self, gl: &WebGlContext, ext: ImageExt) -> Result<(), JsValue> {
         self.config.set_image_fmt(ext)?;
 
-        let channel = self.config.get_format().get_channel();
-
-        self.texture_2d_array = match channel {
-            ChannelType::RGBA32F => unimplemented!(),
-            ChannelType::RGB32F => unimplemented!(),
-            ChannelType::RGBA8U => Rc::new(create_texture_array::<RGBA8U>(gl, &self.config)?),
-            ChannelType::RGB8U => Rc::new(create_texture_array::<RGB8U>(gl, &self.config)?),
-            ChannelType::R32F => Rc::new(create_texture_array::<R32F>(gl, &self.config)?),
-            #[cfg(feature = "webgl2")]
-            ChannelType::R8UI => Rc::new(create_texture_array::<R8UI>(gl, &self.config)?),
-            #[cfg(feature = "webgl2")]
-            ChannelType::R16I => Rc::new(create_texture_array::<R16I>(gl, &self.config)?),
-            #[cfg(feature = "webgl2")]
-            ChannelType::R32I => Rc::new(create_texture_array::<R32I>(gl, &self.config)?),
-            #[cfg(feature = "webgl2")]
-            ChannelType::R64F => Rc::new(create_texture_array::<R64F>(gl, &self.config)?),
-        };
-
-        let now = Time::now();
-        self.base_textures = [
-            Texture::new(&HEALPixCell(0, 0), 0, now),
-            Texture::new(&HEALPixCell(0, 1), 1, now),
-            Texture::new(&HEALPixCell(0, 2), 2, now),
-            Texture::new(&HEALPixCell(0, 3), 3, now),
-            Texture::new(&HEALPixCell(0, 4), 4, now),
-            Texture::new(&HEALPixCell(0, 5), 5, now),
-            Texture::new(&HEALPixCell(0, 6), 6, now),
-            Texture::new(&HEALPixCell(0, 7), 7, now),
-            Texture::new(&HEALPixCell(0, 8), 8, now),
-            Texture::new(&HEALPixCell(0, 9), 9, now),
-            Texture::new(&HEALPixCell(0, 10), 10, now),
-            Texture::new(&HEALPixCell(0, 11), 11, now),
-        ];
+        self.texture_2d_array = Rc::new(create_texture_array_for_config(gl, &self.config)?);
+        self.base_textures = create_base_textures(Time::now());
 
         self.heap.clear();
         self.textures.clear();
         self.ready = false;
         self.num_root_textures_available = 0;
-        self.available_tiles_during_frame = false;
         self.start_time = None;
+        self.mark_changed();
+
+        // The textures of the other cube frames are in the previous format
+        self.stored_frames.clear();
+
+        Ok(())
+    }
+
+    /// Display another frame of a HiPS cube
+    ///
+    /// The textures of the current frame are kept so that going back to it
+    /// does not require to download its tiles again.
+    pub fn set_cube_frame(&mut self, gl: &WebGlContext, frame: u32) -> Result<(), JsValue> {
+        let prev_frame = self.config.get_cube_frame()
+            .ok_or_else(|| JsValue::from_str("The HiPS is not a cube"))?;
+        if prev_frame == frame {
+            return Ok(());
+        }
+        self.config.set_cube_frame(frame)?;
+
+        let frame_textures = if let Some(idx) = self.stored_frames.iter().position(|(f, _)| *f == frame) {
+            self.stored_frames.remove(idx).unwrap_abort().1
+        } else if self.stored_frames.len() >= MAX_NUM_STORED_CUBE_FRAMES {
+            // Reuse the texture array of the least recently displayed frame
+            let (_, oldest) = self.stored_frames.pop_front().unwrap_abort();
+            FrameTextures::new(oldest.texture_2d_array, self.size)
+        } else {
+            FrameTextures::new(Rc::new(create_texture_array_for_config(gl, &self.config)?), self.size)
+        };
+
+        let prev_frame_textures = self.swap_frame_textures(frame_textures);
+        self.stored_frames.push_back((prev_frame, prev_frame_textures));
+
+        // The vertices have to be recomputed with the textures of the new frame
+        self.mark_changed();
+
+        Ok(())
+    }
+
+    /// Memory taken by the texture arrays in bytes, those of the stored cube frames included
+    pub fn num_bytes(&self) -> usize {
+        let num_slices = self.texture_2d_array.textures.len()
+            + self.stored_frames.iter()
+                .map(|(_, frame)| frame.texture_2d_array.textures.len())
+                .sum::<usize>();
+
+        self.config.num_bytes_by_slice() * num_slices
+    }
+
+    /// Release the textures of the stored cube frames and return the number of bytes freed
+    pub fn release_stored_frames(&mut self) -> usize {
+        let num_slices = self.stored_frames.iter()
+            .map(|(_, frame)| frame.texture_2d_array.textures.len())
+            .sum::<usize>();
+        self.stored_frames.clear();
+
+        self.config.num_bytes_by_slice() * num_slices
+    }
+
+    /// Time the allocated slices of the texture array have been used for the last time,
+    /// from the last slice to the second one
+    ///
+    /// The first slice is not given as it holds the root textures that are never released.
+    pub fn get_releasable_slices_last_use(&self) -> Vec<Time> {
+        let num_textures_by_slice = self.config.num_textures_by_slice();
+        // A slice with no texture has never been used
+        let mut last_use = vec![Time(0.0); self.texture_2d_array.textures.len()];
+        for item in self.heap.0.iter() {
+            if let Some(texture) = self.textures.get(&item.cell) {
+                let idx_slice = (texture.idx() / num_textures_by_slice) as usize;
+                if last_use[idx_slice] < item.time_request {
+                    last_use[idx_slice] = item.time_request;
+                }
+            }
+        }
+
+        last_use.into_iter()
+            .skip(1)
+            .rev()
+            .collect()
+    }
+
+    /// Release the last slice of the texture array and return the number of bytes freed
+    ///
+    /// The textures it contains are removed from the buffer and the HiPS cannot allocate it again
+    /// until its maximum number of slices is raised. The stored cube frames are released too
+    /// as their textures may lie in the slice.
+    pub fn release_last_slice(&mut self) -> usize {
+        let num_slices = self.texture_2d_array.textures.len();
+        if num_slices <= 1 {
+            return 0;
+        }
+
+        let texture_array = if let Some(texture_array) = Rc::get_mut(&mut self.texture_2d_array) {
+            texture_array
+        } else {
+            return 0;
+        };
+        texture_array.textures.pop();
+
+        let num_bytes_by_slice = self.config.num_bytes_by_slice();
+        self.config.fit_textures_in((num_slices - 1) * num_bytes_by_slice);
+        self.size = self.config.num_textures();
+
+        // The indices of the textures are given in order so the remaining ones
+        // keep filling the first slices
+        let size = self.size as i32;
+        self.textures.retain(|_, texture| texture.idx() < size);
+        let textures = &self.textures;
+        self.heap.0 = self.heap.0
+            .drain()
+            .filter(|item| textures.contains_key(&item.cell))
+            .collect();
+
+        // The vertices pointing to the removed textures have to be recomputed
+        self.mark_changed();
+
+        num_bytes_by_slice + self.release_stored_frames()
+    }
+
+    /// Set the maximum memory the texture array can take
+    ///
+    /// The slices already allocated are kept, see `release_last_slice` to free them.
+    pub fn fit_textures_in(&mut self, num_bytes: usize) {
+        let num_bytes_allocated = self.texture_2d_array.textures.len() * self.config.num_bytes_by_slice();
+        self.config.fit_textures_in(num_bytes.max(num_bytes_allocated));
+        self.size = self.config.num_textures();
+    }
+
+    // Allocate the slices of the texture array up to the one of a texture
+    fn allocate_slices_for(&mut self, texture_idx: i32) -> Result<(), JsValue> {
+        let idx_slice = (texture_idx / self.config.num_textures_by_slice()) as usize;
+        while self.texture_2d_array.textures.len() <= idx_slice {
+            let slice = create_texture_array_for_config(&self.gl, &self.config)?;
+            Rc::get_mut(&mut self.texture_2d_array)
+                .ok_or_else(|| JsValue::from_str("The texture array is in use"))?
+                .textures
+                .extend(slice.textures);
+        }
 
         Ok(())
     }
 
+    fn swap_frame_textures(&mut self, frame_textures: FrameTextures) -> FrameTextures {
+        let FrameTextures {
+            heap,
+            num_root_textures_available,
+            textures,
+            base_textures,
+            texture_2d_array,
+            ready,
+            start_time,
+        } = frame_textures;
+
+        FrameTextures {
+            heap: std::mem::replace(&mut self.heap, heap),
+            num_root_textures_available: std::mem::replace(&mut self.num_root_textures_available, num_root_textures_available),
+            textures: std::mem::replace(&mut self.textures, textures),
+            base_textures: std::mem::replace(&mut self.base_textures, base_textures),
+            texture_2d_array: std::mem::replace(&mut self.texture_2d_array, texture_2d_array),
+            ready: std::mem::replace(&mut self.ready, ready),
+            start_time: std::mem::replace(&mut self.start_time, start_time),
+        }
+    }
+
     pub fn push_allsky(
         &mut self,
         allsky: Allsky,
@@ -309,7 +494,10 @@ impl ImageSurveyTextures {
             }
         }
 
-        self.set_ready();
+        // The textures may already be ready if they are shared with other views
+        if !self.ready {
+            self.set_ready();
+        }
 
         Ok(())
     }
@@ -378,7 +566,10 @@ impl ImageSurveyTextures {
             // is in the buffer
             // and the tile is not already in any textures of the buffer
             // We can safely push it
-            // First get the texture
+            // First ensure the slice of the texture is allocated
+            let texture_idx = self.textures[&tex_cell].idx();
+            self.allocate_slices_for(texture_idx)?;
+            // Then get the texture
             let texture = self
                 .textures
                 .get_mut(&tex_cell)
@@ -414,7 +605,6 @@ impl ImageSurveyTextures {
                 );
             };
 
-            self.available_tiles_during_frame = true;
             if tex_cell.is_root() && texture.is_available() {
                 self.num_root_textures_available += 1;
                 debug_assert!(self.num_root_textures_available <= NUM_HPX_TILES_DEPTH_ZERO);
@@ -425,17 +615,57 @@ impl ImageSurveyTextures {
                     self.start_time = Some(Time::now());
                 }
             }
+
+            self.mark_changed();
         }
 
         Ok(())
     }
 
-    // Return if tiles did become available
-    pub fn reset_available_tiles(&mut self) -> bool {
-        let available_tiles_during_frame = self.available_tiles_during_frame;
-        self.available_tiles_during_frame = false;
+    // Mark the texture containing a tile that could not be downloaded
+    pub fn mark_failed(&mut self, cell: &HEALPixCell) {
+        let tex_cell = cell.get_texture_cell(&self.config);
+        if let Some(texture) = self.textures.get_mut(&tex_cell) {
+            texture.set_failed();
+        }
+    }
+
+    // Empty the textures containing tiles that could not be downloaded
+    // so that their tiles are requested again.
+    // The textures keep their place in the buffer.
+    // Return true if root textures have been emptied
+    pub fn reset_failed_tiles(&mut self) -> bool {
+        let mut root_reset = false;
+        let mut reset = false;
+        let now = Time::now();
+        for (cell, texture) in self.textures.iter_mut() {
+            if texture.has_failed() {
+                if cell.is_root() {
+                    if texture.is_available() {
+                        self.num_root_textures_available -= 1;
+                    }
+                    root_reset = true;
+                }
+
+                texture.replace(cell, now);
+                reset = true;
+            }
+        }
+
+        if reset {
+            self.mark_changed();
+        }
+
+        root_reset
+    }
+
+    /// Changes each time tiles become available or are removed
+    pub fn version(&self) -> u32 {
+        self.version
+    }
 
-        available_tiles_during_frame
+    fn mark_changed(&mut self) {
+        self.version = self.version.wrapping_add(1);
     }
 
     fn is_heap_full(&self) -> bool {
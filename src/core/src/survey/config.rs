@@ -132,6 +132,9 @@ fn create_black_tile(format: FormatImageType, width: i32, value: f32) -> TileArr
     }
 }*/
 
+// Maximum number of slices of the texture array of a HiPS
+const MAX_NUM_SLICES: i32 = 2;
+
 #[derive(Debug)]
 pub struct HiPSConfig {
     pub root_url: String,
@@ -164,6 +167,12 @@ pub struct HiPSConfig {
     pub scale: f32,
     pub offset: f32,
     pub blank: f32,
+    // Physical unit of the values
+    pub bunit: Option<String>,
+    // Cuts used when the user has not given any
+    default_cuts: Option<[f32; 2]>,
+    // Range of the physical values
+    data_range: Option<[f32; 2]>,
 
     pub tex_storing_integers: bool,
     pub tex_storing_fits: bool,
@@ -175,12 +184,46 @@ pub struct HiPSConfig {
     format: ImageFormatType,
     dataproduct_subtype: Option<Vec<String>>,
     colored: bool,
+
+    // HiPS cube: the range of frames and the current one
+    cube: Option<CubeFrames>,
+    // Base urls of the mirrors serving the HiPS, the root url included
+    mirrors: Vec<String>,
+    // Where the tiles are read from
+    tile_source: Rc<dyn TileSource>,
+}
+
+/// The frames of a HiPS cube
+#[derive(Debug, Clone)]
+pub struct CubeFrames {
+    // Number of frames
+    pub depth: u32,
+    // Index of the first frame
+    pub first: u32,
+    // Index of the frame being displayed
+    pub current: u32,
+    // Spectral WCS of the frames, i.e. (CRPIX3, CRVAL3, CDELT3)
+    pub spectral_wcs: Option<(f64, f64, f64)>,
+    pub spectral_unit: Option<String>,
+}
+
+impl CubeFrames {
+    /// Spectral coordinate of a frame
+    pub fn get_spectral_value(&self, frame: u32) -> Option<f64> {
+        self.spectral_wcs.map(|(crpix3, crval3, cdelt3)| {
+            // FITS pixel indices start from 1
+            crval3 + ((frame - self.first) as f64 + 1.0 - crpix3) * cdelt3
+        })
+    }
 }
 
 use crate::math;
 use crate::HiPSProperties;
 use al_api::coo_system::CooSystem;
 use wasm_bindgen::JsValue;
+use crate::downloader::request::allsky::AllskyStats;
+use crate::downloader::source::{HttpSource, TileSource};
+use std::rc::Rc;
 
 impl HiPSConfig {
     /// Define a HiPS configuration
@@ -198,7 +241,7 @@ impl HiPSConfig {
         // characterics of the client
         let num_textures_by_side_slice = 8;
         let num_textures_by_slice = num_textures_by_side_slice * num_textures_by_side_slice;
-        let num_slices = 2;
+        let num_slices = MAX_NUM_SLICES;
         let num_textures = (num_textures_by_slice * num_slices) as usize;
 
         let max_depth_tile = properties.get_max_order();
@@ -299,6 +342,30 @@ impl HiPSConfig {
 
         let is_allsky = sky_fraction >= 1.0;
 
+        let cube_depth = properties.get_cube_depth().filter(|depth| *depth > 1);
+        // The tiles of the cubes packing several frames (hips_tile_depth > 1) are not handled:
+        // the tile of a frame would be the one starting its packet
+        if let (Some(_), Some(tile_depth)) = (cube_depth, properties.get_tile_depth()) {
+            if tile_depth > 1 {
+                return Err(JsValue::from_str(&format!(
+                    "HiPS cubes whose tiles pack several frames (hips_tile_depth = {}) are not supported",
+                    tile_depth
+                )));
+            }
+        }
+
+        let cube = cube_depth
+            .map(|depth| {
+                let first = properties.get_cube_firstframe().unwrap_or(0);
+                CubeFrames {
+                    depth,
+                    first,
+                    current: first,
+                    spectral_wcs: properties.get_cube_spectral_wcs(),
+                    spectral_unit: properties.get_cube_bunit3().cloned(),
+                }
+            });
+
         let min_depth_texture = properties.get_min_order();
         let min_depth_tile = min_depth_texture.unwrap_or(0);
         let hips_config = HiPSConfig {
@@ -327,6 +394,10 @@ impl HiPSConfig {
             scale: 1.0,
             offset: 0.0,
             blank: -1.0, // by default, set it to -1
+            bunit: None,
+            // The standard keywords first, the allsky statistics otherwise
+            default_cuts: properties.get_cutouts().or_else(|| properties.get_data_range()),
+            data_range: properties.get_data_range(),
 
             tex_storing_fits,
             tex_storing_integers,
@@ -338,7 +409,10 @@ impl HiPSConfig {
             format,
             tile_size,
             dataproduct_subtype,
-            colored
+            colored,
+            cube,
+            mirrors: vec![root_url.to_string()],
+            tile_source: Rc::new(HttpSource),
         };
 
         Ok(hips_config)
@@ -443,6 +517,48 @@ impl HiPSConfig {
         self.root_url = root_url;
     }
 
+    /// Set the mirrors of the HiPS service, the root url is always part of them
+    pub fn set_mirrors(&mut self, mirrors: Vec<String>) {
+        self.mirrors = std::iter::once(self.root_url.clone())
+            .chain(mirrors)
+            .fold(vec![], |mut urls, url| {
+                if !urls.contains(&url) {
+                    urls.push(url);
+                }
+                urls
+            });
+    }
+
+    #[inline]
+    pub fn get_mirrors(&self) -> &[String] {
+        &self.mirrors
+    }
+
+    #[inline]
+    pub fn get_tile_source(&self) -> &Rc<dyn TileSource> {
+        &self.tile_source
+    }
+
+    pub fn set_tile_source(&mut self, tile_source: Rc<dyn TileSource>) {
+        self.tile_source = tile_source;
+    }
+
+    /// Complete the cuts and the data range not given by the properties
+    pub fn set_allsky_stats(&mut self, stats: &AllskyStats) {
+        self.default_cuts.get_or_insert(stats.cuts);
+        self.data_range.get_or_insert(stats.range);
+    }
+
+    #[inline]
+    pub fn get_default_cuts(&self) -> Option<[f32; 2]> {
+        self.default_cuts
+    }
+
+    #[inline]
+    pub fn get_data_range(&self) -> Option<[f32; 2]> {
+        self.data_range
+    }
+
     #[inline]
     pub fn set_fits_metadata(&mut self, bscale: f32, bzero: f32, blank: f32) {
         self.scale = bscale;
@@ -510,6 +626,23 @@ impl HiPSConfig {
         self.num_textures_by_slice
     }
 
+    /// Memory taken by one slice of the texture array in bytes
+    pub fn num_bytes_by_slice(&self) -> usize {
+        let texture_size = self.texture_size as usize;
+
+        texture_size * texture_size * (self.num_textures_by_slice as usize) * self.format.get_channel().num_bytes_per_pixel()
+    }
+
+    /// Limit the number of slices of the texture array so that it fits in `num_bytes`
+    ///
+    /// At least one slice is allowed so that the HiPS can still be displayed
+    pub fn fit_textures_in(&mut self, num_bytes: usize) {
+        let num_slices = (num_bytes / self.num_bytes_by_slice()) as i32;
+
+        self.num_slices = num_slices.clamp(1, MAX_NUM_SLICES);
+        self.num_textures = (self.num_textures_by_slice * self.num_slices) as usize;
+    }
+
     #[inline]
     pub fn num_slices(&self) -> i32 {
         self.num_slices
@@ -529,6 +662,34 @@ impl HiPSConfig {
     pub fn get_default_image(&self) -> &EmptyTileImage {
         &self.empty_image
     }
+
+    #[inline]
+    pub fn get_cube(&self) -> Option<&CubeFrames> {
+        self.cube.as_ref()
+    }
+
+    /// The frame currently displayed if the HiPS is a cube
+    #[inline]
+    pub fn get_cube_frame(&self) -> Option<u32> {
+        self.cube.as_ref().map(|cube| cube.current)
+    }
+
+    pub fn set_cube_frame(&mut self, frame: u32) -> Result<(), JsValue> {
+        let cube = self.cube.as_mut()
+            .ok_or_else(|| JsValue::from_str("The HiPS is not a cube"))?;
+
+        if frame < cube.first || frame >= cube.first + cube.depth {
+            return Err(JsValue::from_str(&format!(
+                "Frame {} out of the cube frames [{}, {}]",
+                frame,
+                cube.first,
+                cube.first + cube.depth - 1
+            )));
+        }
+        cube.current = frame;
+
+        Ok(())
+    }
 }
 
 use al_core::shader::{SendUniforms, ShaderBound};
@@ -1,3 +1,4 @@
+pub mod budget;
 pub mod buffer;
 pub mod config;
 pub mod texture;
@@ -32,6 +32,8 @@ pub struct Texture {
     // for drawing
     is_available: bool,*/
     missing: bool,
+    // Some of its tiles could not be downloaded because of transient errors
+    failed: bool,
 }
 
 use super::config::HiPSConfig;
@@ -62,6 +64,7 @@ impl Texture {
             //is_available,
             num_tiles_written,
             missing,
+            failed: false,
         }
     }
 
@@ -138,6 +141,14 @@ impl Texture {
         self.missing
     }
 
+    pub fn has_failed(&self) -> bool {
+        self.failed
+    }
+
+    pub fn set_failed(&mut self) {
+        self.failed = true;
+    }
+
     // Setter
     pub fn replace(
         &mut self,
@@ -156,6 +167,7 @@ impl Texture {
         self.tiles.clear();
         //self.is_available = false;
         self.missing = true;
+        self.failed = false;
         self.num_tiles_written = 0;
     }
 
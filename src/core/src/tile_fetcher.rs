@@ -1,96 +1,326 @@
 use crate::downloader::{query, Downloader};
-use crate::renderable::HiPS;
+use crate::healpix::cell::HEALPixCell;
+use crate::renderable::{HiPS, Layers, Url};
 use crate::Abort;
 
-use std::collections::VecDeque;
+use std::cmp::Ordering;
+use std::collections::{HashMap, HashSet};
 
-const MAX_NUM_TILE_FETCHING: isize = 8;
+const DEFAULT_MAX_NUM_TILE_FETCHING: usize = 8;
 const MAX_QUERY_QUEUE_LENGTH: usize = 100;
+const MAX_PREFETCH_QUEUE_LENGTH: usize = 200;
+
+// Priority of a tile query, coarse tiles first, then the ones closest to the center of the view
+#[derive(Clone, Copy, PartialEq)]
+pub struct TilePriority {
+    depth: u8,
+    // Angular distance in radians between the cell center and the view center
+    distance: f64,
+}
+
+impl TilePriority {
+    pub fn new(cell: &HEALPixCell, view_center: &cgmath::Vector4<f64>) -> Self {
+        let (lon, lat) = cell.center();
+        let cell_center = crate::math::lonlat::radec_to_xyzw(
+            crate::math::angle::Angle(lon),
+            crate::math::angle::Angle(lat),
+        );
+
+        let cos_dist = cgmath::InnerSpace::dot(cell_center.truncate(), view_center.truncate());
+        Self {
+            depth: cell.depth(),
+            distance: cos_dist.clamp(-1.0, 1.0).acos(),
+        }
+    }
+
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.depth
+            .cmp(&other.depth)
+            .then(self.distance.partial_cmp(&other.distance).unwrap_or(Ordering::Equal))
+    }
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum QueueKind {
+    View,
+    Target,
+    Prefetch,
+}
+
+// Sort the queries by decreasing priority and keep the `max_len` most prioritary ones
+fn sort_queries(queries: &mut Vec<(TilePriority, query::Tile)>, max_len: usize) {
+    queries.sort_by(|(p1, _), (p2, _)| p2.cmp(p1));
+    let num_discarded = queries.len().saturating_sub(max_len);
+    queries.drain(..num_discarded);
+}
 
 pub struct TileFetcherQueue {
-    num_tiles_fetched: isize,
-    // A stack of queries to fetch
-    queries: VecDeque<query::Tile>,
+    // Maximum number of tiles being fetched at the same time, all the HiPS included
+    max_num_fetching: usize,
+    // Maximum number of tiles being fetched at the same time for a specific HiPS
+    max_num_fetching_by_hips: HashMap<Url, usize>,
+    // Urls of the tiles being fetched for each HiPS
+    fetching_by_hips: HashMap<Url, HashSet<Url>>,
+    // The queries to fetch, the most prioritary ones being at the end
+    queries: Vec<(TilePriority, query::Tile)>,
+    sorted: bool,
     base_tile_queries: Vec<query::Tile>,
+    // Tiles of the target of a camera animation, fetched once the view ones are
+    target_queries: Vec<(TilePriority, query::Tile)>,
+    // Tiles around the view and of the next deeper order, fetched only when the network is idle
+    prefetch_queries: Vec<(TilePriority, query::Tile)>,
 }
 
 impl TileFetcherQueue {
     pub fn new() -> Self {
-        let queries = VecDeque::new();
+        let queries = Vec::new();
         let base_tile_queries = Vec::new();
         Self {
-            num_tiles_fetched: 0,
+            max_num_fetching: DEFAULT_MAX_NUM_TILE_FETCHING,
+            max_num_fetching_by_hips: HashMap::new(),
+            fetching_by_hips: HashMap::new(),
             queries,
+            sorted: true,
             base_tile_queries,
+            target_queries: Vec::new(),
+            prefetch_queries: Vec::new(),
         }
     }
 
     pub fn clear(&mut self) {
         self.queries.clear();
+        self.target_queries.clear();
+        self.prefetch_queries.clear();
     }
 
-    pub fn append(&mut self, query: query::Tile, downloader: &mut Downloader) {
-        // discard too old tile queries
-        // this may not be the best thing to do but
-        if self.queries.len() > MAX_QUERY_QUEUE_LENGTH {
-            self.queries.pop_front();   
+    /// Queue a tile to fetch ahead of time with a low priority
+    ///
+    /// The tiles already cached by the downloader are not queued again
+    pub fn append_prefetch(&mut self, query: query::Tile, priority: TilePriority, downloader: &Downloader) {
+        if downloader.is_cached(&query.url) {
+            return;
         }
-        self.queries.push_back(query);
+
+        self.prefetch_queries.push((priority, query));
+        self.sorted = false;
+    }
+
+    /// Replace the tiles fetched for the target of a camera animation
+    pub fn set_target(&mut self, queries: impl Iterator<Item = (TilePriority, query::Tile)>) {
+        self.target_queries = queries.collect();
+        self.sorted = false;
+    }
+
+    /// Set the maximum number of tiles fetched at the same time, all the HiPS included
+    pub fn set_max_num_fetching(&mut self, max_num_fetching: usize, downloader: &mut Downloader) {
+        self.max_num_fetching = max_num_fetching.max(1);
+        self.fetch(downloader);
+    }
+
+    /// Set the maximum number of tiles fetched at the same time for one HiPS
+    ///
+    /// Giving `None` removes the HiPS limit, the global one still applies
+    pub fn set_hips_max_num_fetching(&mut self, hips_url: &str, max_num_fetching: Option<usize>, downloader: &mut Downloader) {
+        if let Some(max_num_fetching) = max_num_fetching {
+            self.max_num_fetching_by_hips.insert(hips_url.to_string(), max_num_fetching.max(1));
+        } else {
+            self.max_num_fetching_by_hips.remove(hips_url);
+        }
+
         self.fetch(downloader);
     }
 
+    pub fn set_hips_url(&mut self, past_url: &str, new_url: &str) {
+        if let Some(max_num_fetching) = self.max_num_fetching_by_hips.remove(past_url) {
+            self.max_num_fetching_by_hips.insert(new_url.to_string(), max_num_fetching);
+        }
+    }
+
+    /// Queue a tile query, it will be fetched by order of priority
+    ///
+    /// The queries are sent when calling [`TileFetcherQueue::fetch`]
+    pub fn append(&mut self, query: query::Tile, priority: TilePriority) {
+        self.queries.push((priority, query));
+        self.sorted = false;
+    }
+
     pub fn append_base_tile(&mut self, query: query::Tile, downloader: &mut Downloader) {
         self.base_tile_queries.push(query);
         self.fetch(downloader);
     }
 
-    pub fn notify(&mut self, num_tiles_completed: usize, downloader: &mut Downloader) {
-        self.num_tiles_fetched -= num_tiles_completed as isize;
+    /// Cancel the queued queries whose cells are not in the view of their HiPS anymore
+    ///
+    /// A query is kept as long as one of the views still needs it
+    pub fn cancel_out_of_view(&mut self, layers: &[&Layers]) {
+        self.queries.retain(|(_, query)| {
+            layers.iter().any(|layers| {
+                layers
+                    .get_hips_from_url(&query.hips_url)
+                    .map(|hips| hips.get_view().get_coverage().contains(&query.cell))
+                    .unwrap_or(false)
+            })
+        });
+    }
 
+    /// Tell a tile has been received and fetch the next ones
+    pub fn notify(&mut self, hips_url: &str, tile_url: &str, downloader: &mut Downloader) {
+        self.received(hips_url, tile_url);
         self.fetch(downloader);
     }
 
-    fn fetch(&mut self, downloader: &mut Downloader) {
+    // Tiles coming from the cache of the downloader were not fetched so they are not found
+    fn received(&mut self, hips_url: &str, tile_url: &str) {
+        if let Some(fetching) = self.fetching_by_hips.get_mut(hips_url) {
+            fetching.remove(tile_url);
+        }
+    }
+
+    fn fetched(&mut self, hips_url: Url, tile_url: Url) {
+        self.fetching_by_hips.entry(hips_url).or_default().insert(tile_url);
+    }
+
+    fn num_fetching(&self) -> usize {
+        self.fetching_by_hips.values().map(|fetching| fetching.len()).sum()
+    }
+
+    fn can_fetch(&self, hips_url: &str) -> bool {
+        let num_hips_fetching = self.fetching_by_hips.get(hips_url).map(|fetching| fetching.len()).unwrap_or(0);
+        let max_num_hips_fetching = self.max_num_fetching_by_hips.get(hips_url).copied().unwrap_or(usize::MAX);
+
+        num_hips_fetching < max_num_hips_fetching
+    }
+
+    pub fn fetch(&mut self, downloader: &mut Downloader) {
+        let mut num_fetching = self.num_fetching();
+
         // Fetch the base tiles with higher priority
-        while self.num_tiles_fetched < MAX_NUM_TILE_FETCHING && !self.base_tile_queries.is_empty() {
+        while num_fetching < self.max_num_fetching && !self.base_tile_queries.is_empty() {
             let query = self.base_tile_queries.pop().unwrap_abort();
 
+            let (hips_url, tile_url) = (query.hips_url.clone(), query.url.clone());
             if downloader.fetch(query) {
                 // The fetch has succeded
-                self.num_tiles_fetched += 1;
+                self.fetched(hips_url, tile_url);
+                num_fetching += 1;
             }
         }
 
-        while self.num_tiles_fetched < MAX_NUM_TILE_FETCHING && !self.queries.is_empty() {
-            let query = self.queries.pop_back().unwrap_abort();
+        if !self.sorted {
+            // Sort by decreasing priority so that the most prioritary queries are popped first
+            // and discard the less prioritary ones
+            sort_queries(&mut self.queries, MAX_QUERY_QUEUE_LENGTH);
+            sort_queries(&mut self.target_queries, MAX_QUERY_QUEUE_LENGTH);
+            sort_queries(&mut self.prefetch_queries, MAX_PREFETCH_QUEUE_LENGTH);
+
+            self.sorted = true;
+        }
+
+        num_fetching = self.fetch_queue(QueueKind::View, num_fetching, self.max_num_fetching, downloader);
+        num_fetching = self.fetch_queue(QueueKind::Target, num_fetching, self.max_num_fetching, downloader);
+
+        // The network is idle, half of the fetches are left free for the tiles the view will need
+        let is_idle = self.queries.is_empty() && self.base_tile_queries.is_empty();
+        if is_idle {
+            let max_num_prefetching = (self.max_num_fetching / 2).max(1);
+            self.fetch_queue(QueueKind::Prefetch, num_fetching, max_num_prefetching, downloader);
+        }
+    }
+
+    fn fetch_queue(&mut self, kind: QueueKind, mut num_fetching: usize, max_num_fetching: usize, downloader: &mut Downloader) -> usize {
+        let mut queries = std::mem::take(match kind {
+            QueueKind::View => &mut self.queries,
+            QueueKind::Target => &mut self.target_queries,
+            QueueKind::Prefetch => &mut self.prefetch_queries,
+        });
+
+        // Queries of the HiPS having reached their own limit wait for the next fetch
+        let mut postponed = vec![];
+        while num_fetching < max_num_fetching && !queries.is_empty() {
+            let (priority, query) = queries.pop().unwrap_abort();
 
+            if !self.can_fetch(&query.hips_url) {
+                postponed.push((priority, query));
+                continue;
+            }
+
+            // Tiles fetched ahead of time that are already cached do not need to be fetched again
+            if kind != QueueKind::View && downloader.is_cached(&query.url) {
+                continue;
+            }
+
+            let (hips_url, tile_url) = (query.hips_url.clone(), query.url.clone());
             if downloader.fetch(query) {
                 // The fetch has succeded
-                self.num_tiles_fetched += 1;
+                self.fetched(hips_url, tile_url);
+                num_fetching += 1;
             }
         }
+
+        // Put them back keeping the order of the queue
+        queries.extend(postponed.into_iter().rev());
+        match kind {
+            QueueKind::View => self.queries = queries,
+            QueueKind::Target => self.target_queries = queries,
+            QueueKind::Prefetch => self.prefetch_queries = queries,
+        }
+
+        num_fetching
     }
 
     pub fn launch_starting_hips_requests(&mut self, hips: &HiPS, downloader: &mut Downloader) {
         let cfg = hips.get_config();
         // Request for the allsky first
         // The allsky is not mandatory present in a HiPS service but it is better to first try to search for it
-        downloader.fetch(query::PixelMetadata::new(cfg));
-        // Try to fetch the MOC
-        downloader.fetch(query::Moc::new(format!("{}/Moc.fits", cfg.get_root_url()), al_api::moc::MOC::default()));
+        downloader.fetch(query::PixelMetadata::new(&cfg));
+        // Try to fetch the MOC, only served by HiPS web servers
+        if cfg.get_tile_source().is_http() {
+            downloader.fetch(query::Moc::new(format!("{}/Moc.fits", cfg.get_root_url()), al_api::moc::MOC::default()));
+        }
 
         let tile_size = cfg.get_tile_size();
         //Request the allsky for the small tile size or if base tiles are not available
         if tile_size <= 128 || cfg.get_min_depth_tile() > 0 {
             // Request the allsky
-            downloader.fetch(query::Allsky::new(cfg));
+            downloader.fetch(query::Allsky::new(&cfg));
         } else {
             for texture_cell in crate::healpix::cell::ALLSKY_HPX_CELLS_D0 {
-                for cell in texture_cell.get_tile_cells(cfg) {
-                    let query = query::Tile::new(&cell, cfg);
+                for cell in texture_cell.get_tile_cells(&cfg) {
+                    let query = query::Tile::new(&cell, &cfg);
                     self.append_base_tile(query, downloader);
                 }
             }
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::TileFetcherQueue;
+
+    #[test]
+    fn test_cached_tiles_during_burst() {
+        let hips_url = "http://hips";
+        let tile_url = |idx: u64| format!("{}/Norder3/Dir0/Npix{}.png", hips_url, idx);
+
+        let mut queue = TileFetcherQueue::new();
+        queue.max_num_fetching_by_hips.insert(hips_url.to_string(), 2);
+
+        // A burst of two tiles fetched from the network
+        queue.fetched(hips_url.to_string(), tile_url(0));
+        queue.fetched(hips_url.to_string(), tile_url(1));
+        assert!(!queue.can_fetch(hips_url));
+
+        // Tiles served by the cache of the downloader arrive meanwhile
+        queue.received(hips_url, &tile_url(2));
+        queue.received(hips_url, &tile_url(3));
+        assert_eq!(queue.num_fetching(), 2);
+        assert!(!queue.can_fetch(hips_url));
+
+        // A tile of the burst is received, and received again after being delayed
+        queue.received(hips_url, &tile_url(0));
+        queue.received(hips_url, &tile_url(0));
+        assert_eq!(queue.num_fetching(), 1);
+        assert!(queue.can_fetch(hips_url));
+    }
+}
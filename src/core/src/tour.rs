@@ -0,0 +1,105 @@
+use al_api::tour::{Keyframe, Tour};
+
+use crate::time::Time;
+
+enum Playback {
+    // The tour time 0 corresponds to that time
+    Playing { start: Time },
+    Paused { time: f64 },
+}
+
+/// Plays a tour back, the views are applied by the app at each update
+pub struct TourPlayer {
+    tour: Tour,
+    playback: Playback,
+    // The view must be updated even if the tour is paused
+    seeked: bool,
+}
+
+impl TourPlayer {
+    pub fn new(tour: Tour) -> Self {
+        Self {
+            tour,
+            playback: Playback::Paused { time: 0.0 },
+            seeked: false,
+        }
+    }
+
+    pub fn get_tour(&self) -> &Tour {
+        &self.tour
+    }
+
+    /// Current time of the tour in seconds
+    pub fn get_time(&self) -> f64 {
+        match self.playback {
+            Playback::Playing { start } => ((Time::now() - start).as_millis() / 1000.0) as f64,
+            Playback::Paused { time } => time,
+        }
+    }
+
+    pub fn is_playing(&self) -> bool {
+        matches!(self.playback, Playback::Playing { .. })
+    }
+
+    pub fn play(&mut self) {
+        // Restart a finished tour
+        let time = self.get_time();
+        let time = if time >= self.tour.duration() { 0.0 } else { time };
+
+        self.playback = Playback::Playing {
+            start: Time(Time::now().0 - (time * 1000.0) as f32),
+        };
+    }
+
+    pub fn pause(&mut self) {
+        self.playback = Playback::Paused { time: self.get_time() };
+    }
+
+    /// Move to a time of the tour in seconds
+    pub fn seek(&mut self, time: f64) {
+        let time = time.clamp(0.0, self.tour.duration());
+        self.playback = match self.playback {
+            Playback::Playing { .. } => Playback::Playing {
+                start: Time(Time::now().0 - (time * 1000.0) as f32),
+            },
+            Playback::Paused { .. } => Playback::Paused { time },
+        };
+        self.seeked = true;
+    }
+
+    /// The keyframes to interpolate at the current time, `None` if the view does not need to be updated
+    ///
+    /// The tour is paused once its end is reached
+    pub fn update(&mut self) -> Option<(&Keyframe, &Keyframe, f64)> {
+        let seeked = std::mem::take(&mut self.seeked);
+        if !self.is_playing() && !seeked {
+            return None;
+        }
+
+        let time = self.get_time();
+        if time >= self.tour.duration() {
+            self.playback = Playback::Paused { time: self.tour.duration() };
+        }
+
+        self.tour.segment(time)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use al_api::tour::Tour;
+
+    #[test]
+    fn test_keyframe_projections() {
+        let tour: Tour = serde_json::from_str(r#"{"keyframes": [
+            {"time": 0, "ra": 0, "dec": 0, "fov": 180, "projection": "AIT"},
+            {"time": 2, "ra": 10, "dec": 0, "fov": 60, "projection": {"name": "COD", "thetaA": 30}},
+            {"time": 4, "ra": 20, "dec": 0, "fov": 10}
+        ]}"#).unwrap();
+
+        let projections = tour.keyframes().iter()
+            .map(|k| k.projection.as_ref().map(|cfg| (cfg.name.as_str(), cfg.params.theta_a)))
+            .collect::<Vec<_>>();
+        assert_eq!(projections, vec![Some(("AIT", None)), Some(("COD", Some(30.0))), None]);
+    }
+}
@@ -0,0 +1,50 @@
+#version 300 es
+precision highp float;
+precision highp sampler2D;
+precision highp isampler2D;
+precision highp usampler2D;
+precision mediump int;
+
+out vec4 out_frag_color;
+in vec2 frag_uv;
+
+// The physical values of the two operand layers rendered offscreen
+uniform sampler2D tex_a;
+uniform sampler2D tex_b;
+
+// 0: A - B, 1: A / B, 2: (A - B) / (A + B)
+uniform int op;
+
+uniform float opacity;
+
+@include "../hips/color.glsl"
+
+void main() {
+    vec4 a = texture(tex_a, frag_uv);
+    vec4 b = texture(tex_b, frag_uv);
+
+    // The value is only defined where both layers are
+    if (a.a <= 0.0 || b.a <= 0.0) {
+        discard;
+    }
+
+    // Values are blended with the alpha of the layers
+    float va = a.r / a.a;
+    float vb = b.r / b.a;
+
+    float x;
+    if (op == 0) {
+        x = va - vb;
+    } else if (op == 1) {
+        x = va / vb;
+    } else {
+        x = (va - vb) / (va + vb);
+    }
+
+    if (isnan(x) || isinf(x)) {
+        discard;
+    }
+
+    out_frag_color = apply_colormap_to_grayscale(x, 1.0);
+    out_frag_color.a = out_frag_color.a * opacity * min(a.a, b.a);
+}
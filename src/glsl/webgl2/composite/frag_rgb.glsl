@@ -0,0 +1,67 @@
+#version 300 es
+precision highp float;
+precision highp sampler2D;
+precision mediump int;
+
+out vec4 out_frag_color;
+in vec2 frag_uv;
+
+// The red, green and blue channels rendered offscreen
+uniform sampler2D tex_r;
+uniform sampler2D tex_g;
+uniform sampler2D tex_b;
+
+uniform float opacity;
+
+// Lupton et al. (2004) asinh scaling
+uniform int lupton;
+uniform float lupton_q;
+uniform float lupton_stretch;
+
+@include "../hips/tonal_corrections.glsl"
+
+// The channel layers are rendered with a grayscale colormap but
+// a color HiPS can also be given so we take its luminance
+float get_channel_value(vec4 c) {
+    const vec3 luminosity_factor = vec3(0.2126, 0.7152, 0.0722);
+    return dot(c.rgb, luminosity_factor);
+}
+
+vec3 apply_lupton(vec3 rgb) {
+    float I = (rgb.r + rgb.g + rgb.b) / 3.0;
+    if (I <= 0.0) {
+        return vec3(0.0);
+    }
+
+    float f = asinh(lupton_q * I / lupton_stretch) / lupton_q;
+    rgb = rgb * f / I;
+
+    // Saturated pixels keep their color
+    float max_rgb = max(rgb.r, max(rgb.g, rgb.b));
+    if (max_rgb > 1.0) {
+        rgb = rgb / max_rgb;
+    }
+
+    return rgb;
+}
+
+void main() {
+    vec4 r = texture(tex_r, frag_uv);
+    vec4 g = texture(tex_g, frag_uv);
+    vec4 b = texture(tex_b, frag_uv);
+
+    vec3 rgb = vec3(
+        get_channel_value(r),
+        get_channel_value(g),
+        get_channel_value(b)
+    );
+
+    if (lupton == 1) {
+        rgb = apply_lupton(rgb);
+    }
+
+    float alpha = max(r.a, max(g.a, b.a));
+
+    out_frag_color = apply_tonal(vec4(rgb, alpha));
+    out_frag_color.a = out_frag_color.a * opacity;
+}
@@ -0,0 +1,12 @@
+#version 300 es
+precision highp float;
+precision mediump int;
+
+layout (location = 0) in vec2 pos_clip_space;
+
+out vec2 frag_uv;
+
+void main() {
+    gl_Position = vec4(pos_clip_space, 0.0, 1.0);
+    frag_uv = pos_clip_space * 0.5 + 0.5;
+}
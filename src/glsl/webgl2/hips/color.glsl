@@ -41,10 +41,10 @@ vec3 reverse_uv(vec3 uv) {
 }
 
 uniform float reversed;
+// Write the physical value in the red channel instead of its color
+uniform int raw_value;
 
-vec4 get_color_from_texture(vec3 UV) {
-    vec4 color = get_pixels(UV);
-    
+vec4 apply_color_settings(vec4 color) {
     color.r = transfer_func(H, color.r, min_value, max_value);
     color.g = transfer_func(H, color.g, min_value, max_value);
     color.b = transfer_func(H, color.b, min_value, max_value);
@@ -55,8 +55,21 @@ vec4 get_color_from_texture(vec3 UV) {
     return apply_tonal(color);
 }
 
+vec4 get_color_from_texture(vec3 UV) {
+    vec4 color = get_pixels(UV);
+    return apply_color_settings(color);
+}
+
 vec4 apply_colormap_to_grayscale(float x, float a) {
     float alpha = x * scale + offset;
+    if (raw_value == 1) {
+        if (x == blank || isnan(x)) {
+            return vec4(0.0);
+        }
+
+        return vec4(alpha, 0.0, 0.0, a);
+    }
+
     alpha = transfer_func(H, alpha, min_value, max_value);
 
     // apply reversed
@@ -12,6 +12,8 @@ uniform float min_value;
 uniform float max_value;
 uniform int H;
 uniform float reversed;
+// Write the physical value in the red channel instead of its color
+uniform int raw_value;
 
 uniform float size_tile_uv;
 
@@ -45,6 +47,10 @@ vec4 get_colormap_from_grayscale_texture(vec3 UV) {
 
     float x = float(get_pixels(uv).r);
     float alpha = x * scale + offset;
+    if (raw_value == 1) {
+        return mix(vec4(alpha, 0.0, 0.0, 1.0), vec4(0.0), float(x == blank));
+    }
+
     alpha = transfer_func(H, alpha, min_value, max_value);
 
     // apply reversed
@@ -8,8 +8,11 @@ uniform vec4 u_color;
 out vec4 v_rgba;
 
 void main() {
+  // pos is given in screen pixels
+  vec2 ndc = (pos / u_screen_size) * 2.0 - 1.0;
   gl_Position = vec4(
-        pos,
+        ndc.x,
+        -ndc.y,
         0.0,
         1.0
     );
@@ -57,6 +57,7 @@ import { ALEvent } from "./events/ALEvent.js";
 import { Color } from './Color.js';
 import { ColorCfg } from './ColorCfg.js';
 import { ImageFITS } from "./ImageFITS.js";
+import { requestAnimFrame } from "./libs/RequestAnimationFrame.js";
 
 import $ from 'jquery';
 
@@ -682,12 +683,49 @@ export let Aladin = (function () {
         $(this.aladinDiv).find('.aladin-frameChoice').val(newFrame.label);
     };
 
-    Aladin.prototype.setProjection = function (projection) {
+    /**
+     * Change the projection of the view
+     * Parameters:
+     * - projection: name of the projection (e.g. 'SIN', 'AIT', 'MOL'), or an object giving the FITS WCS parameters
+     *   of the conic and cylindrical projections in degrees, e.g. {name: 'COD', theta_a: 45, eta: 25},
+     *   {name: 'CYP', mu: 1, lambda: 0.7} or {name: 'CEA', lambda: 1}
+     * - duration (optional): duration in seconds of a smooth transition from the current projection.
+     *   The projection changes at once if not given
+     *
+     * Return a Promise resolving with true once the transition is over, false if it has been interrupted,
+     * when a duration is given
+     *
+     * @API
+    */
+    Aladin.prototype.setProjection = function (projection, duration) {
         if (!projection) {
             return;
         }
-        this.view.setProjection(projection);
+        const morph = this.view.setProjection(projection, duration);
+        const name = typeof projection === 'string' ? projection : projection.name;
+        ALEvent.PROJECTION_CHANGED.dispatchedTo(this.aladinDiv, {projection: name});
+
+        return morph;
+    };
+
+    /**
+     * Display an image layer pixel per pixel
+     *
+     * The view takes the projection and the frame of the image and is centered on its reference pixel,
+     * rotated and zoomed so that one pixel of the image covers one pixel of the screen.
+     * The HiPS layers are reprojected around the image. Call it again after a resize of
+     * the view to keep this scale.
+     *
+     * Parameters:
+     * - layer: name of the FITS or PNG/JPEG image layer
+     *
+     * @API
+    */
+    Aladin.prototype.setNativeImageView = function (layer) {
+        const projection = this.view.setNativeImageView(layer);
         ALEvent.PROJECTION_CHANGED.dispatchedTo(this.aladinDiv, {projection: projection});
+        // màj select box
+        $(this.aladinDiv).find('.aladin-frameChoice').val(this.view.cooFrame.label);
     };
 
     /** point view to a given object (resolved by Sesame) or position
@@ -896,8 +934,135 @@ export let Aladin = (function () {
         doZoomAnimation(this);
     };
 
+    /*
+     * fly smoothly from the current view to the given ra, dec (in degrees) and fov (in degrees)
+     *
+     * the view zooms out during the flight if the target is far away.
+     * fov defaults to the current one, the duration (in seconds) to 2 seconds.
+     * Any user interaction with the view interrupts the flight.
+     *
+     * Returns a Promise resolving to true once the target is reached, false if the flight has been interrupted
+     *
+     * @API
+     *
+     */
+    Aladin.prototype.flyTo = function (ra, dec, fov, duration) {
+        const view = this.view;
+        if (fov === undefined || fov === null) {
+            fov = view.fov;
+        }
+        duration = duration || 2;
+
+        const flight = this.wasm.flyTo(ra, dec, fov, duration);
+
+        // Keep the state of the view in sync with the camera during the flight
+        view.syncWithCamera(() => this.wasm.isFlying());
+
+        return flight.then(function (reached) {
+            view.syncWithCamera();
+            view.requestRedraw();
+
+            return reached;
+        });
+    };
 
 
+
+    /*
+     * Load a tour, i.e. a sequence of views played one after the other.
+     * The tour is given as a JSON string or an object of the form:
+     * { keyframes: [{ time, ra, dec, fov, rotation, projection, opacities }, ...] }
+     * with time in seconds, angles in degrees and opacities mapping layer names to opacities.
+     * The projection is a name (e.g. "TAN") or an object with a name and its parameters (e.g. { name: "COD", thetaA: 30 })
+     * An error is thrown if the projection of a keyframe is not valid
+     *
+     * @API
+     */
+    Aladin.prototype.loadTour = function (tour) {
+        this.wasm.loadTour(tour);
+    };
+
+    /*
+     * Play the tour loaded from its current time. Any user interaction with the view pauses it
+     *
+     * @API
+     */
+    Aladin.prototype.playTour = function () {
+        this.wasm.playTour();
+        this.view.syncWithCamera(() => this.wasm.isTourPlaying());
+    };
+
+    // @API
+    Aladin.prototype.pauseTour = function () {
+        this.wasm.pauseTour();
+    };
+
+    /*
+     * Move to a time (in seconds) of the tour loaded
+     *
+     * @API
+     */
+    Aladin.prototype.seekTour = function (time) {
+        this.wasm.seekTour(time);
+        // The view is updated at the next frame
+        requestAnimFrame(() => this.view.syncWithCamera());
+    };
+
+    // Current time in seconds of the tour loaded
+    // @API
+    Aladin.prototype.getTourTime = function () {
+        return this.wasm.getTourTime();
+    };
+
+    // @API
+    Aladin.prototype.getTour = function () {
+        return this.wasm.getTour();
+    };
+
+    // Serialize the tour loaded to a JSON string
+    // @API
+    Aladin.prototype.exportTour = function () {
+        return this.wasm.exportTour();
+    };
+
+    /*
+     * Record a tour: call recordTourKeyframe each time the current view must be added to it,
+     * the keyframes are timed from the start of the recording.
+     * stopTourRecording returns the tour recorded, it is loaded to be played back
+     *
+     * @API
+     */
+    Aladin.prototype.startTourRecording = function () {
+        this.wasm.startTourRecording();
+    };
+
+    // @API
+    Aladin.prototype.recordTourKeyframe = function () {
+        this.wasm.recordTourKeyframe();
+    };
+
+    // @API
+    Aladin.prototype.stopTourRecording = function () {
+        return this.wasm.stopTourRecording();
+    };
+
+    /*
+     * Enable/disable the navigation gestures and set their sensitivity.
+     * cfg is an object with the optional fields:
+     * drag, pinchZoom, rotation, wheelZoom, keyboard (booleans),
+     * zoomSensitivity, panStep and rotationSensitivity (numbers)
+     *
+     * @API
+     */
+    Aladin.prototype.setInputConfig = function (cfg) {
+        this.wasm.setInputConfig(cfg);
+    };
+
+    // @API
+    Aladin.prototype.getInputConfig = function () {
+        return this.wasm.getInputConfig();
+    };
+
     /**
      *  Compute intermediate point between points (lng1, lat1) and (lng2, lat2)
      *  at distance fraction times the total distance (fraction between 0 and 1)
@@ -1052,6 +1217,8 @@ export let Aladin = (function () {
             url = Utils.getAbsoluteURL(url)
             url = new URL(url);
         }
+        // The url before proxying it, saved in the state of the view
+        const srcUrl = url.toString();
 
         // Check the protocol, for http ones, use a CORS compatible proxy
         if (Utils.requestCORSIfNotSameOrigin(url)) {
@@ -1070,7 +1237,10 @@ export let Aladin = (function () {
             cfg = Utils.clone(cfg)
         }
 
-        return new ImageFITS(cfg.url, cfg.name, this.view, cfg.options, cfg.successCallback, cfg.errorCallback);
+        const imageFITS = new ImageFITS(cfg.url, cfg.name, this.view, cfg.options, cfg.successCallback, cfg.errorCallback);
+        imageFITS.srcUrl = srcUrl;
+
+        return imageFITS;
     };
 
     Aladin.prototype.newImageSurvey = function(rootUrlOrId, options) {
@@ -1121,6 +1291,201 @@ export let Aladin = (function () {
         this.wasm.setBackgroundColor(color);
     };
 
+    // Set the maximum number of tiles downloaded at the same time, all the image surveys included.
+    // The tiles are fetched coarsest first, then by increasing distance to the center of the view. 8 by default
+    // @api
+    Aladin.prototype.setTileFetchConcurrency = function(maxNumFetching) {
+        this.wasm.setTileFetchConcurrency(maxNumFetching);
+    };
+
+    // Set the memory in bytes shared by the tiles cached and the textures of all the image surveys
+    // @api
+    Aladin.prototype.setMemoryBudget = function(numBytes) {
+        this.wasm.setMemoryBudget(numBytes);
+    };
+
+    // Get the memory used by the cache and the textures, globally and for each image layer
+    // @api
+    Aladin.prototype.getCacheStats = function() {
+        return this.wasm.getCacheStats();
+    };
+
+    // Enable or disable the fetching of the tiles around the view and of the next deeper order
+    // when the network is idle. Enabled by default
+    // @api
+    Aladin.prototype.setTilePrefetching = function(enabled) {
+        this.wasm.setTilePrefetching(enabled);
+    };
+
+    // Fetch ahead of time the tiles of a view given by its center (in degrees, ICRS) and its field of view in degrees
+    // @api
+    Aladin.prototype.prefetchView = function(ra, dec, fov) {
+        this.wasm.prefetchView(ra, dec, fov);
+    };
+
+    // Read the tiles of a HiPS from another source than its web server
+    // The HiPS is identified by the url given when creating it. The source can be:
+    // - a function called with the path of a file relative to the HiPS root (e.g. "Norder3/Dir0/Npix42.jpg")
+    //   returning its content as an Uint8Array or an ArrayBuffer, possibly through a Promise, null if not found
+    // - an ArrayBuffer or an Uint8Array storing a zip or tar archive of the HiPS. The content of its
+    //   properties file is returned
+    // - an object mapping the paths of the files to their content
+    // @api
+    Aladin.prototype.setHiPSSource = function(url, source) {
+        url = Utils.fixURLForHTTPS(url);
+
+        if (typeof source === "function") {
+            this.wasm.setTileSourceFromCallback(url, source);
+        } else if (source instanceof ArrayBuffer || source instanceof Uint8Array) {
+            const bytes = source instanceof ArrayBuffer ? new Uint8Array(source) : source;
+            return this.wasm.setTileSourceFromArchive(url, bytes);
+        } else {
+            this.wasm.setTileSourceFromFiles(url, source);
+        }
+    };
+
+    // Add a HiPS layer from the root url of its service, its properties file being fetched to configure it
+    // The options are the ones of the color config of an image survey (colormap, stretch, opacity, ...) plus:
+    // - imgFormat: the format of the tiles to display ("jpeg" by default)
+    // - longitudeReversed: whether the longitudes increase to the right
+    // The Promise returned resolves to the keywords of the properties file once the HiPS has been added.
+    // It is rejected if the properties cannot be retrieved or are not valid, or if the HiPS cannot be added
+    // @api
+    Aladin.prototype.addHiPSFromURL = function(url, options, layer = "overlay") {
+        url = Utils.fixURLForHTTPS(url);
+        options = options || {};
+
+        const colorCfg = new ColorCfg(options);
+        return this.wasm.addImageSurveyFromURL(layer, url, {
+            ...colorCfg.get(),
+            longitudeReversed: options.longitudeReversed === true,
+            imgFormat: options.imgFormat || "jpeg",
+        });
+    };
+
+    // Add a PNG/JPEG image (e.g. a press release picture) positioned on the sky from its WCS
+    // - image: an HTMLImageElement already loaded, or the bytes of the PNG/JPEG file as an Uint8Array or an ArrayBuffer
+    // - wcs: the WCS keywords as an object (e.g. {CTYPE1: "RA---TAN", CRVAL1: 83.63, ...}) or as a FITS header string.
+    //   AVM tags have to be converted to their WCS keywords first
+    // - options: the color config of the layer (opacity, blending, ...) plus the name of the image (name)
+    // The Promise returned resolves to the parameters of the image once it has been added
+    // @api
+    Aladin.prototype.addImageWithWCS = function(image, wcs, options, layer = "overlay") {
+        options = options || {};
+
+        const colorCfg = new ColorCfg(options);
+        return this.wasm.addImageWithWCS({
+            layer: layer,
+            url: options.name || layer,
+            wcs: typeof wcs === "string" ? wcs : JSON.stringify(wcs),
+            meta: {
+                ...colorCfg.get(),
+                longitudeReversed: false,
+                imgFormat: "png",
+            }
+        }, image instanceof ArrayBuffer ? new Uint8Array(image) : image);
+    };
+
+    // Add a color layer combining three image layers already added, giving its red, green and blue channels
+    // Each channel layer is rendered in grayscale with its own cuts and transfer function. While the composite
+    // is visible, its channel layers are only drawn through it. They cannot be removed before the composite.
+    // The options are the color config of the layer (opacity, blending, ...) plus an optional
+    // Lupton et al. (2004) asinh scaling, e.g. lupton: {q: 8, stretch: 0.5}
+    // @api
+    Aladin.prototype.addRGBComposite = function(red, green, blue, options, layer = "composite") {
+        options = options || {};
+
+        const colorCfg = new ColorCfg(options);
+        this.wasm.addRGBComposite({
+            layer: layer,
+            red: red,
+            green: green,
+            blue: blue,
+            lupton: options.lupton,
+            meta: {
+                ...colorCfg.get(),
+                longitudeReversed: false,
+                imgFormat: "png",
+            }
+        });
+    };
+
+    // Add a layer computing the difference (a - b), the ratio (a / b) or the normalized difference
+    // ((a - b) / (a + b)) of two image layers already added, storing FITS values (FITS HiPS or FITS images)
+    // - op: "difference", "ratio" or "normalizedDifference"
+    // - options: the color config of the layer (colormap, cuts, stretch, opacity, ...) applied to the computed values
+    // The operand layers cannot be removed before it
+    // @api
+    Aladin.prototype.addLayerArithmetic = function(a, b, op, options, layer = "arithmetic") {
+        options = options || {};
+
+        const colorCfg = new ColorCfg(options);
+        this.wasm.addLayerArithmetic({
+            layer: layer,
+            a: a,
+            b: b,
+            op: op,
+            meta: {
+                ...colorCfg.get(),
+                longitudeReversed: false,
+                imgFormat: "fits",
+            }
+        });
+    };
+
+    // Add contours computed from the values of a FITS image or FITS HiPS layer. The contours of a HiPS
+    // are completed as its tiles are received
+    // - levels: the levels of the contours in physical values
+    // - options:
+    //   - id: identifier of the contours used to remove them, the name of the layer by default
+    //   - color: color of the lines as an hexadecimal string ("#00ff00" by default)
+    //   - opacity: opacity of the lines between 0 and 1
+    //   - thickness: thickness of the lines in pixels
+    // Returns the identifier of the contours
+    // @api
+    Aladin.prototype.addContours = function(layer, levels, options) {
+        options = options || {};
+
+        const id = options.id || layer;
+        const rgb = Color.hexToRgb(options.color || "#00ff00") || {r: 0, g: 255, b: 0};
+        this.wasm.addContours({
+            id: id,
+            layer: layer,
+            levels: levels,
+            color: {
+                r: rgb.r / 255,
+                g: rgb.g / 255,
+                b: rgb.b / 255,
+                a: options.opacity === undefined ? 1.0 : options.opacity,
+            },
+            thickness: options.thickness,
+        });
+
+        return id;
+    };
+
+    // Remove the contours of identifier id
+    // @api
+    Aladin.prototype.removeContours = function(id) {
+        this.wasm.removeContours(id);
+    };
+
+    // Extract the values of a FITS image or FITS HiPS layer drawn in a rectangle of the view as a FITS file
+    // - rect (optional): {x, y, width, height} in pixels from the top-left corner of the view,
+    //   the whole view being extracted if not given
+    // Returns the bytes of the FITS file as an Uint8Array
+    // @api
+    Aladin.prototype.getCutout = function(layer, rect) {
+        return this.wasm.getCutout({layer: layer, rect: rect});
+    };
+
+    // Request again the tiles of the HiPS layers that could not be downloaded,
+    // e.g. after a network outage
+    // @api
+    Aladin.prototype.resetFailedTiles = function() {
+        this.wasm.resetFailedTiles();
+    };
+
     // @api
     Aladin.prototype.removeImageLayer = function(layer) {
         this.view.removeImageLayer(layer);
@@ -1360,6 +1725,141 @@ export let Aladin = (function () {
         }
     }
 
+    /**
+     * Export the image layers of the current view with the WCS of its projection
+     * Parameters:
+     * - options (optional): object with attributs
+     *     * format (optional): 'fits' (a RGB cube, default) or 'png'
+     *     * width: width in pixels of the image to output
+     *     * height: height in pixels of the image to output
+     *
+     * Return an object {image, wcs} of Uint8Array. image is the content of the file,
+     * wcs is a FITS header giving the WCS of a PNG image
+     *
+     * @API
+    */
+    Aladin.prototype.exportView = function (options) {
+        return this.wasm.exportView(options || {});
+    }
+
+    /**
+     * Render the current view into a PNG at a resolution higher than the screen one
+     * The view is frozen until the tiles needed at that resolution are received and the image rendered
+     * Parameters:
+     * - options (optional): object with attributs
+     *     * width: width in pixels of the image (e.g. 8000)
+     *     * height: height in pixels of the image. The aspect ratio of the view is kept if not given
+     *     * timeout: time in seconds waited for the tiles of each part of the image (30 by default)
+     *
+     * The sources of the catalogs shown are drawn over the image layers, MOCs and coordinate grid.
+     *
+     * Return a Promise resolving with a Uint8Array storing the PNG
+     *
+     * @API
+    */
+    Aladin.prototype.getSnapshot = function (options) {
+        options = options || {};
+
+        // Resolve any CSS color into its hexadecimal form
+        var ctx = document.createElement('canvas').getContext('2d');
+        var catalogs = [];
+        for (var cat of this.view.catalogs) {
+            if (!cat.isShowing || !this.view.displayCatalog) {
+                continue;
+            }
+
+            ctx.fillStyle = cat.color;
+            var rgb = Color.hexToRgb(ctx.fillStyle) || {r: 255, g: 255, b: 255};
+            catalogs.push({
+                sources: cat.getSources()
+                    .filter(s => s.isShowing !== false)
+                    .map(s => [s.ra, s.dec]),
+                color: {r: rgb.r / 255, g: rgb.g / 255, b: rgb.b / 255, a: 1.0},
+                size: cat.sourceSize,
+                // Custom shapes are drawn as squares
+                shape: typeof cat.shape === 'string' ? cat.shape : 'square',
+            });
+        }
+
+        var cfg = {catalogs: catalogs};
+        for (var key of ['width', 'height', 'timeout']) {
+            if (options[key] !== undefined) {
+                cfg[key] = options[key];
+            }
+        }
+
+        return this.wasm.snapshot(cfg);
+    }
+
+    /**
+     * Add a view of the sky sharing the canvas with the current ones
+     * The new view starts at the position of the active view and has no image layer
+     * Parameters:
+     * - rect: object {x, y, width, height} giving the area of the canvas the view covers,
+     *   in fractions of the canvas size from its top-left corner (e.g. {x: 0.5, y: 0, width: 0.5, height: 1})
+     *
+     * The image layers, the projection and the moves apply to the active view. Pressing
+     * the mouse on a view makes it the active one.
+     * The views displaying the same HiPS in the same image format share its tiles, changing
+     * its image format or its cube frame in one of them changes it in the others.
+     *
+     * Return the index of the new view
+     *
+     * @API
+    */
+    Aladin.prototype.addView = function (rect) {
+        return this.view.addView(rect);
+    }
+
+    // @API
+    Aladin.prototype.removeView = function (idx) {
+        this.view.removeView(idx);
+    }
+
+    // @API
+    Aladin.prototype.setActiveView = function (idx) {
+        this.wasm.setActiveView(idx);
+        this.view.syncActiveView();
+    }
+
+    // @API
+    Aladin.prototype.getActiveView = function () {
+        return this.wasm.getActiveView();
+    }
+
+    /**
+     * Set the area of the canvas covered by the active view
+     * Parameters:
+     * - rect: object {x, y, width, height} in fractions of the canvas size from its top-left corner
+     *
+     * @API
+    */
+    Aladin.prototype.setViewRect = function (rect) {
+        this.view.setViewRect(rect);
+    }
+
+    /**
+     * Make the other views follow the moves of the active one
+     * Parameters:
+     * - options: object with the boolean attributs center, fov and rotation telling what the views share
+     *   (e.g. {center: true, fov: true}). Calling it without options unlocks the views.
+     *
+     * @API
+    */
+    Aladin.prototype.lockViews = function (options) {
+        options = options || {};
+
+        var lock = {};
+        for (var key of ['center', 'fov', 'rotation']) {
+            if (options[key] !== undefined) {
+                lock[key] = options[key];
+            }
+        }
+
+        this.wasm.lockViews(lock);
+        this.view.requestRedraw();
+    }
+
     /** restrict FOV range
      * @API
      * @param minFOV in degrees when zoom in at max
@@ -1449,13 +1949,18 @@ export let Aladin = (function () {
             nbSteps = 1;
         }
 
+        // Corners of the area of the canvas covered by the active view
+        var rect = this.view.viewRect;
+        var left = rect.x * this.view.width, top = rect.y * this.view.height;
+        var right = left + rect.width * this.view.width - 1, bottom = top + rect.height * this.view.height - 1;
+
         var points = [];
         var x1, y1, x2, y2;
         for (var k = 0; k < 4; k++) {
-            x1 = (k == 0 || k == 3) ? 0 : this.view.width - 1;
-            y1 = (k < 2) ? 0 : this.view.height - 1;
-            x2 = (k < 2) ? this.view.width - 1 : 0;
-            y2 = (k == 1 || k == 2) ? this.view.height - 1 : 0;
+            x1 = (k == 0 || k == 3) ? left : right;
+            y1 = (k < 2) ? top : bottom;
+            x2 = (k < 2) ? right : left;
+            y2 = (k == 1 || k == 2) ? bottom : top;
 
             for (var step = 0; step < nbSteps; step++) {
                 let radec = this.wasm.screenToWorld(x1 + step / nbSteps * (x2 - x1), y1 + step / nbSteps * (y2 - y1));
@@ -1650,6 +2155,148 @@ Aladin.prototype.getShareURL = function () {
         '&fov=' + this.getFov()[0].toFixed(2) + '&survey=' + encodeURIComponent(this.getBaseImageLayer().id || this.getBaseImageLayer().rootUrl);
 };
 
+Aladin.STATE_VERSION = 1;
+
+// @API
+/*
+ * return the state of the viewer as an object that can be serialized in JSON:
+ * the view (center, fov, rotation, projection, frame, grid, custom colormaps,
+ * RGB composites, arithmetic layers and contours), the image layers, the catalogs and the MOCs.
+ * The remote resources are saved by their URL, the catalogs and MOCs created
+ * in the page are saved with their data.
+ * The HiPS read from a tile source cannot be saved: it throws if the view has some
+ * With several views (see addView), only the active view is saved: the other views,
+ * the areas of the canvas they cover and their locking are not part of the state
+ */
+Aladin.prototype.getState = function () {
+    const layers = [];
+    this.view.overlayLayers.forEach((layer) => {
+        const imageLayer = this.view.imageLayers.get(layer);
+        if (!imageLayer) {
+            return;
+        }
+
+        const options = imageLayer.colorCfg.getOptions();
+        if (imageLayer.subtype === "fits") {
+            layers.push({layer, type: "fits", url: imageLayer.srcUrl || imageLayer.url, name: imageLayer.name, options});
+        } else {
+            options.imgFormat = imageLayer.imgFormat;
+            layers.push({layer, type: "hips", id: imageLayer.id, name: imageLayer.name, options});
+        }
+    });
+
+    const catalogs = this.view.catalogs.map((catalog) => {
+        const options = {name: catalog.name, color: catalog.color, sourceSize: catalog.sourceSize};
+        if (typeof catalog.shape === "string") {
+            options.shape = catalog.shape;
+        }
+
+        if (catalog.type === "progressivecat") {
+            return {type: "hips", url: catalog.rootUrl, options};
+        } else if (catalog.url) {
+            return {type: "url", url: catalog.url, useProxy: catalog.useProxy, options};
+        } else {
+            const sources = catalog.getSources().map((s) => ({ra: s.ra, dec: s.dec, data: s.data}));
+            return {type: "sources", sources, options};
+        }
+    });
+
+    const mocs = this.view.mocs.map((moc) => {
+        const options = {name: moc.name, color: moc.color, opacity: moc.opacity, lineWidth: moc.lineWidth, adaptativeDisplay: moc.adaptativeDisplay};
+        if (moc.dataURL) {
+            return {url: moc.dataURL, options};
+        } else {
+            return {json: moc.dataJSON, options};
+        }
+    });
+
+    return {
+        version: Aladin.STATE_VERSION,
+        view: this.wasm.getViewState(),
+        layers,
+        catalogs,
+        mocs,
+    };
+};
+
+// @API
+/*
+ * restore a state given by getState, as an object or a JSON string.
+ * The image layers, catalogs and MOCs are fetched again from their URL
+ * With several views, the state is restored in the active view, the other views are kept as they are
+ */
+Aladin.prototype.setState = function (state) {
+    if (typeof state === "string") {
+        state = JSON.parse(state);
+    }
+
+    if (!state.version || state.version > Aladin.STATE_VERSION) {
+        throw 'State version ' + state.version + ' is not supported';
+    }
+
+    // The view goes first, the layers may use its custom colormaps
+    this.wasm.setViewState(state.view);
+
+    // The core has applied the projection, the frame and the grid, only the UI follows them
+    this.view.updateProjection(state.view.projection);
+    ALEvent.PROJECTION_CHANGED.dispatchedTo(this.aladinDiv, {projection: state.view.projection});
+    this.view.cooFrame = CooFrameEnum.fromString(state.view.frame, CooFrameEnum.J2000);
+    $(this.aladinDiv).find('.aladin-frameChoice').val(this.view.cooFrame.label);
+    this.view.dispatchGridConfig(state.view.grid);
+
+    // Image layers
+    const layerNames = state.layers.map((l) => l.layer);
+    [...this.view.overlayLayers]
+        .filter((layer) => !layerNames.includes(layer))
+        .forEach((layer) => this.removeImageLayer(layer));
+
+    const added = state.layers.map((l) => {
+        let imageLayer;
+        if (l.type === "fits") {
+            imageLayer = this.createImageFITS(l.url, l.name, l.options);
+        } else {
+            imageLayer = new ImageSurvey(l.id, l.name, undefined, this.view, l.options);
+        }
+
+        this.view.imageLayersBeingQueried.set(l.layer, imageLayer);
+        return this.view.addImageLayer(imageLayer, l.layer);
+    });
+
+    // The composites, arithmetic layers and contours are computed from the image layers
+    Promise.allSettled(added).then(() => {
+        (state.view.composites || []).forEach((cfg) => this.wasm.addRGBComposite(cfg));
+        (state.view.arithmeticLayers || []).forEach((cfg) => this.wasm.addLayerArithmetic(cfg));
+        (state.view.contours || []).forEach((cfg) => this.wasm.addContours(cfg));
+        this.view.requestRedraw();
+    });
+
+    // Catalogs and MOCs
+    [...this.view.allOverlayLayers]
+        .filter((layer) => layer.type !== "overlay")
+        .forEach((layer) => this.removeLayer(layer));
+
+    state.catalogs.forEach((c) => {
+        let catalog;
+        if (c.type === "hips") {
+            catalog = A.catalogHiPS(c.url, c.options);
+        } else if (c.type === "url") {
+            catalog = A.catalogFromURL(c.url, c.options, undefined, c.useProxy);
+        } else {
+            catalog = A.catalog(c.options);
+            catalog.addSources(c.sources.map((s) => A.source(s.ra, s.dec, s.data)));
+        }
+
+        this.addCatalog(catalog);
+    });
+
+    state.mocs.forEach((m) => {
+        const moc = m.url ? A.MOCFromURL(m.url, m.options) : A.MOCFromJSON(m.json, m.options);
+        this.addMOC(moc);
+    });
+
+    this.view.syncWithCamera();
+};
+
 // @API
 /*
  * return, as a string, the HTML embed code
@@ -1792,6 +2439,9 @@ A.MOCFromJSON = function (jsonMOC, options) {
 // API
 A.catalogFromURL = function (url, options, successCallback, useProxy) {
     var catalog = A.catalog(options);
+    // Kept to save the catalog in the state of the view
+    catalog.url = url;
+    catalog.useProxy = useProxy;
     // TODO: should be self-contained in Catalog class
     Catalog.parseVOTable(url, function (sources) {
         catalog.addSources(sources);
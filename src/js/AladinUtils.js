@@ -92,8 +92,9 @@ export let AladinUtils = (function() {
 
             return AladinUtils.xyToView(xy.X, xy.Y, width, height, largestDim, zoomFactor, false);
         },*/
+        // The position is projected through the view the overlays are being drawn on, the active one by default
         radecToViewXy: function(ra, dec, view) {
-            let xy = view.wasm.worldToScreen(ra, dec);
+            let xy = view.wasm.worldToScreen(ra, dec, view.drawnView);
             return xy;
         },
     	
@@ -46,8 +46,9 @@
             this.reversed = true;
         }
 
-        this.minCut = (options && options.minCut) || 0.0;
-        this.maxCut = (options && options.maxCut) || 1.0;
+        // Left undefined, the cuts of the HiPS properties or its allsky statistics are used
+        this.minCut = options && options.minCut;
+        this.maxCut = options && options.maxCut;
 
         this.additiveBlending = options && options.additive;
         if (this.additiveBlending === undefined)  {
@@ -96,6 +97,23 @@
         };
     }
 
+    // The options of the constructor giving back this config
+    ColorCfg.prototype.getOptions = function() {
+        return {
+            opacity: this.opacity,
+            colormap: this.colormap,
+            stretch: this.stretch,
+            reversed: this.reversed,
+            minCut: this.minCut,
+            maxCut: this.maxCut,
+            additive: this.additiveBlending,
+            gamma: this.kGamma,
+            saturation: this.kSaturation,
+            brightness: this.kBrightness,
+            contrast: this.kContrast,
+        };
+    };
+
     // @api
     ColorCfg.prototype.setBrightness = function(kBrightness) {
         kBrightness = +kBrightness || 0.0; // coerce to number
@@ -114,6 +114,24 @@ HiPSProperties.fetch = async function(urlOrId) {
     return result;
 }
 
+// Get all the base urls serving the tiles of a HiPS, i.e. hips_service_url, hips_service_url_1, ...
+HiPSProperties.getMirrorUrls = function (metadata) {
+    let urls = [];
+    if (metadata.hips_service_url) {
+        urls.push(Utils.fixURLForHTTPS(metadata.hips_service_url));
+    }
+
+    let numHiPSServiceURL = 1;
+    while (metadata.hasOwnProperty("hips_service_url_" + numHiPSServiceURL.toString())) {
+        const key = "hips_service_url_" + numHiPSServiceURL.toString();
+
+        urls.push(Utils.fixURLForHTTPS(metadata[key]));
+        numHiPSServiceURL += 1;
+    }
+
+    return urls;
+};
+
 HiPSProperties.getFasterMirrorUrl = function (metadata) {
     const pingHiPSServiceUrl = (hipsServiceUrl) => {
         hipsServiceUrl = Utils.fixURLForHTTPS(hipsServiceUrl);
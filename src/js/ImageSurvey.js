@@ -112,6 +112,15 @@ PropertyParser.cutouts = function(options, properties = {}) {
     return [minCutout, maxCutout]
 }
 
+PropertyParser.dataRange = function(options, properties = {}) {
+    let range = properties.hips_data_range && properties.hips_data_range.split(" ");
+
+    const minDataRange = range && parseFloat(range[0]);
+    const maxDataRange = range && parseFloat(range[1]);
+
+    return [minDataRange, maxDataRange]
+}
+
 PropertyParser.bitpix = function(options, properties = {}) {
     const bitpix = properties.hips_pixel_bitpix && (+properties.hips_pixel_bitpix);
     return bitpix;
@@ -128,6 +137,21 @@ PropertyParser.isPlanetaryBody = function(options, properties = {}) {
     return properties.hips_body !== undefined;
 }
 
+PropertyParser.cube = function(options, properties = {}) {
+    const parseNumber = (value) => value !== undefined ? +value : undefined;
+
+    return {
+        hipsCubeDepth: parseNumber(properties.hips_cube_depth),
+        hipsCubeFirstframe: parseNumber(properties.hips_cube_firstframe),
+        hipsCubeCrpix3: parseNumber(properties.hips_cube_crpix3),
+        hipsCubeCrval3: parseNumber(properties.hips_cube_crval3),
+        hipsCubeCdelt3: parseNumber(properties.hips_cube_cdelt3),
+        hipsCubeBunit3: properties.hips_cube_bunit3,
+        // Number of frames packed in a tile
+        hipsTileDepth: parseNumber(properties.hips_tile_depth),
+    };
+}
+
 export let ImageSurvey = (function () {
     /** Constructor
      * cooFrame and maxOrder can be set to null
@@ -147,10 +171,12 @@ export let ImageSurvey = (function () {
         this.colorCfg = new ColorCfg(options);
 
         this.properties = {};
+        // Base urls of the mirrors the tiles can be fetched from
+        this.mirrors = (options && options.mirrors) ? options.mirrors.map((url) => Utils.fixURLForHTTPS(url)) : [];
 
         let self = this;
         self.query = (async () => {
-            let maxOrder, frame, tileSize, formats, minCutout, maxCutout, bitpix, skyFraction, minOrder, initialFov, initialRa, initialDec, hipsBody, isPlanetaryBody, dataproductSubtype;
+            let maxOrder, frame, tileSize, formats, minCutout, maxCutout, minDataRange, maxDataRange, bitpix, skyFraction, minOrder, initialFov, initialRa, initialDec, hipsBody, isPlanetaryBody, dataproductSubtype, cube = {};
 
             try {
                 const properties = await HiPSProperties.fetch(url || id);
@@ -162,6 +188,7 @@ export let ImageSurvey = (function () {
                     throw 'no valid service URL for retrieving the tiles'
                 }
                 url = Utils.fixURLForHTTPS(properties.hips_service_url);
+                self.mirrors = [...new Set([...HiPSProperties.getMirrorUrls(properties), ...self.mirrors])];
 
                 // Request all the properties to see which mirror is the fastest
                 HiPSProperties.getFasterMirrorUrl(properties)
@@ -194,6 +221,7 @@ export let ImageSurvey = (function () {
 
                 // Cutouts
                 [minCutout, maxCutout] = PropertyParser.cutouts(options, properties);
+                [minDataRange, maxDataRange] = PropertyParser.dataRange(options, properties);
 
                 // Bitpix
                 bitpix = PropertyParser.bitpix(options, properties);
@@ -201,6 +229,9 @@ export let ImageSurvey = (function () {
                 // Dataproduct subtype
                 dataproductSubtype = PropertyParser.dataproductSubtype(options, properties);
 
+                // HiPS cube
+                cube = PropertyParser.cube(options, properties);
+
                 // HiPS body
                 isPlanetaryBody = PropertyParser.isPlanetaryBody(options, properties);
                 if (properties.hips_body) {
@@ -260,6 +291,8 @@ export let ImageSurvey = (function () {
                 formats: formats,
                 minCutout: minCutout,
                 maxCutout: maxCutout,
+                minDataRange: minDataRange,
+                maxDataRange: maxDataRange,
                 bitpix: bitpix,
                 skyFraction: skyFraction,
                 minOrder: minOrder,
@@ -268,7 +301,8 @@ export let ImageSurvey = (function () {
                 hipsInitialDec: initialDec,
                 dataproductSubtype: dataproductSubtype,
                 isPlanetaryBody: isPlanetaryBody,
-                hipsBody: hipsBody
+                hipsBody: hipsBody,
+                ...cube
             };
 
             // Use the property to define and check some user given infos
@@ -343,18 +377,53 @@ export let ImageSurvey = (function () {
     }
 
     ImageSurvey.prototype.setUrl = function (url) {
-        if (this.properties.url !== url) {
-            console.info("Change url of ", this.id, " from ", this.properties.url, " to ", url)
+        // The backend may have switched to another mirror
+        const curUrl = this.getUrl();
+        if (curUrl !== url) {
+            console.info("Change url of ", this.id, " from ", curUrl, " to ", url)
 
             // If added to the backend, then we need to tell it the url has changed
             if (this.added) {
-                this.wasm.setHiPSUrl(this.properties.url, url);
+                this.wasm.setHiPSUrl(curUrl, url);
             }
 
             this.properties.url = url;
         }
     }
 
+    // @api
+    // Get the base url the tiles are currently fetched from, i.e. the mirror in use
+    ImageSurvey.prototype.getUrl = function () {
+        if (this.added) {
+            this.properties.url = this.wasm.getHiPSUrl(this.layer);
+        }
+
+        return this.properties.url;
+    }
+
+    // @api
+    // Get the [min, max] range of the pixel values of a FITS survey, e.g. to bound the cut sliders
+    // Given by the hips_data_range property or computed from the allsky, undefined if not known yet
+    ImageSurvey.prototype.getDataRange = function () {
+        if (!this.added) {
+            return undefined;
+        }
+
+        const range = this.wasm.getHiPSDataRange(this.layer);
+        return range && [range[0], range[1]];
+    }
+
+    // @api
+    // Get the cuts used when none have been given, from the hips_pixel_cut property or the allsky
+    ImageSurvey.prototype.getDefaultCuts = function () {
+        if (!this.added) {
+            return undefined;
+        }
+
+        const cuts = this.wasm.getHiPSDefaultCuts(this.layer);
+        return cuts && [cuts[0], cuts[1]];
+    }
+
     // @api
     // TODO: include imgFormat inside the ImageSurvey's meta attribute
     ImageSurvey.prototype.setImageFormat = function (format) {
@@ -494,6 +563,7 @@ export let ImageSurvey = (function () {
             layer: this.layer,
             properties: this.properties,
             meta: this.metadata(),
+            mirrors: this.mirrors,
         });
 
         this.added = true;
@@ -536,6 +606,41 @@ export let ImageSurvey = (function () {
         return this.wasm.readPixel(x, y, this.layer);
     };
 
+    // @api
+    // Number of tiles found, missing, failed and retried
+    ImageSurvey.prototype.getTileStats = function () {
+        return this.wasm.getHiPSTileStats(this.layer);
+    };
+
+    // @api
+    // Request again the tiles that could not be downloaded, e.g. after a network outage.
+    // This applies to all the HiPS layers
+    ImageSurvey.prototype.resetFailedTiles = function () {
+        this.wasm.resetFailedTiles();
+    };
+
+    // @api
+    // Extract the values of the HiPS drawn in a rectangle of the view {x, y, width, height}, given in pixels
+    // from its top-left corner, as a FITS file. The whole view is extracted if no rectangle is given.
+    // The HiPS must store FITS values
+    ImageSurvey.prototype.getCutout = function (rect) {
+        return this.wasm.getCutout({layer: this.layer, rect: rect});
+    };
+
+    // @api
+    // Display a frame of a HiPS cube and return its spectral coordinate
+    ImageSurvey.prototype.setCubeFrame = function (frame) {
+        this.wasm.setHiPSCubeFrame(this.layer, frame);
+        return this.wasm.getHiPSCubeFrameValue(this.layer);
+    };
+
+    // @api
+    // Limit the number of tiles of the survey downloaded at the same time
+    // Giving undefined removes the limit, the global one of the view still applies
+    ImageSurvey.prototype.setMaxConcurrentTileFetches = function (maxNumFetching) {
+        this.wasm.setHiPSTileFetchConcurrency(this.layer, maxNumFetching);
+    };
+
     ImageSurvey.DEFAULT_SURVEY_ID = "P/DSS2/color";
 
     return ImageSurvey;
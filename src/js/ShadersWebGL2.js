@@ -44,6 +44,16 @@ import FitsVS from '../glsl/webgl2/fits/vert.glsl'
 import FitsFS from '../glsl/webgl2/fits/frag_sampler.glsl'
 import FitsFSUnsigned from '../glsl/webgl2/fits/frag_usampler.glsl'
 import FitsFSInteger from '../glsl/webgl2/fits/frag_isampler.glsl'
+import FitsFSColor from '../glsl/webgl2/fits/frag_color.glsl'
+
+// Composite layers
+import CompositeVS from '../glsl/webgl2/composite/vert.glsl'
+import CompositeRGBFS from '../glsl/webgl2/composite/frag_rgb.glsl'
+import CompositeArithmeticFS from '../glsl/webgl2/composite/frag_arithmetic.glsl'
+
+// Lines
+import LineVS from '../glsl/webgl2/line/line_vertex.glsl'
+import LineFS from '../glsl/webgl2/line/line_frag.glsl'
 
 let shaders = [
     // Catalog shaders
@@ -178,6 +188,32 @@ let shaders = [
         id: "FitsFSInteger",
         content: FitsFSInteger,
     },
+    {
+        id: "FitsFSColor",
+        content: FitsFSColor,
+    },
+    // Composite
+    {
+        id: "CompositeVS",
+        content: CompositeVS,
+    },
+    {
+        id: "CompositeRGBFS",
+        content: CompositeRGBFS,
+    },
+    {
+        id: "CompositeArithmeticFS",
+        content: CompositeArithmeticFS,
+    },
+    // Lines
+    {
+        id: "LineVS",
+        content: LineVS,
+    },
+    {
+        id: "LineFS",
+        content: LineFS,
+    },
 ];
 
 export function loadShadersWebGL2() {
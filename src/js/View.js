@@ -165,8 +165,6 @@ export let View = (function () {
         let initialFov = zoom || 180.0;
         this.pinchZoomParameters = {
             isPinching: false, // true if a pinch zoom is ongoing
-            initialFov: undefined,
-            initialDistance: undefined,
             initialAccDelta: Math.pow(si / initialFov, 1.0 / alpha)
         };
         this.setZoom(initialFov);
@@ -174,6 +172,13 @@ export let View = (function () {
         this.imageLayers = new Map();
 
         this.overlayLayers = [];
+        // The views sharing the canvas. The image layers and the projection of the view
+        // are the ones of the active view, the slots of the others keeping theirs
+        this.activeView = 0;
+        this.viewRect = {x: 0, y: 0, width: 1, height: 1};
+        this.otherViews = [undefined];
+        // The view the overlays are being drawn on, the active one if undefined
+        this.drawnView = undefined;
         // current catalogs displayed
         this.catalogs = [];
         // a dedicated catalog for the popup
@@ -223,13 +228,6 @@ export let View = (function () {
 
         this.needRedraw = true;
 
-        // two-fingers rotation
-        this.fingersRotationParameters = {
-            initialViewAngleFromCenter: undefined,
-            initialFingerAngle: undefined,
-            rotationInitiated: false
-        }
-
         this.fadingLatestUpdate = null;
         this.dateRequestRedraw = null;
 
@@ -456,6 +454,15 @@ export let View = (function () {
         let cutMinInit = null
         let cutMaxInit = null;
 
+        // The gestures are interpreted by the core, which receives the raw pointer positions
+        const MOUSE_POINTER_ID = -1;
+        const forEachTouch = function (touches, f) {
+            const rect = view.catalogCanvas.getBoundingClientRect();
+            for (let i = 0; i < touches.length; i++) {
+                f(touches[i].identifier, touches[i].clientX - rect.left, touches[i].clientY - rect.top);
+            }
+        };
+
         $(view.catalogCanvas).bind("mousedown touchstart", function (e) {
             e.preventDefault();
             e.stopPropagation();
@@ -484,22 +491,24 @@ export let View = (function () {
                 return;
             }
 
-            // zoom pinching
-            if (e.type === 'touchstart' && e.originalEvent && e.originalEvent.targetTouches && e.originalEvent.targetTouches.length == 2) {
-                view.dragging = false;
+            // Give the focus to the view for the keyboard navigation
+            view.aladinDiv.focus({ preventScroll: true });
 
-                view.pinchZoomParameters.isPinching = true;
-                //var fov = view.aladin.getFov();
-                //view.pinchZoomParameters.initialFov = Math.max(fov[0], fov[1]);
-                var fov = view.wasm.getFieldOfView();
-                view.pinchZoomParameters.initialFov = fov;
-                view.pinchZoomParameters.initialDistance = Math.sqrt(Math.pow(e.originalEvent.targetTouches[0].clientX - e.originalEvent.targetTouches[1].clientX, 2) + Math.pow(e.originalEvent.targetTouches[0].clientY - e.originalEvent.targetTouches[1].clientY, 2));
+            if (e.type === 'touchstart' && e.originalEvent && e.originalEvent.changedTouches) {
+                forEachTouch(e.originalEvent.changedTouches, (id, x, y) => view.wasm.pointerDown(id, x, y));
 
-                view.fingersRotationParameters.initialViewAngleFromCenter = view.wasm.getRotationAroundCenter();
-                view.fingersRotationParameters.initialFingerAngle = Math.atan2(e.originalEvent.targetTouches[1].clientY - e.originalEvent.targetTouches[0].clientY, e.originalEvent.targetTouches[1].clientX - e.originalEvent.targetTouches[0].clientX) * 180.0 / Math.PI;
+                // zoom pinching
+                if (e.originalEvent.touches.length >= 2) {
+                    view.dragging = false;
+                    view.pinchZoomParameters.isPinching = true;
 
-                return;
+                    return;
+                }
+            } else {
+                view.wasm.pointerDown(MOUSE_POINTER_ID, xymouse.x, xymouse.y);
             }
+            // The view pressed becomes the active one
+            view.syncActiveView();
 
             view.dragx = xymouse.x;
             view.dragy = xymouse.y;
@@ -512,7 +521,6 @@ export let View = (function () {
                 view.selectStartCoo = { x: view.dragx, y: view.dragy };
             }
 
-            view.wasm.pressLeftMouseButton(view.dragx, view.dragy);
             return false; // to disable text selection
         });
 
@@ -527,16 +535,20 @@ export let View = (function () {
         });
 
         $(view.catalogCanvas).bind("click mouseout touchend", function (e) { // reacting on 'click' rather on 'mouseup' is more reliable when panning the view
-            if (e.type === 'touchend' && view.pinchZoomParameters.isPinching) {
-                view.pinchZoomParameters.isPinching = false;
-                view.pinchZoomParameters.initialFov = view.pinchZoomParameters.initialDistance = undefined;
-
-                return;
+            // Release the pointers, the view may keep moving by inertia
+            if (e.type === 'touchend') {
+                if (e.originalEvent && e.originalEvent.changedTouches) {
+                    forEachTouch(e.originalEvent.changedTouches, (id) => view.wasm.pointerUp(id));
+                }
+            } else {
+                view.wasm.pointerUp(MOUSE_POINTER_ID);
             }
-            if (e.type === 'touchend' && view.fingersRotationParameters.rotationInitiated) {
-                view.fingersRotationParameters.initialViewAngleFromCenter = undefined;
-                view.fingersRotationParameters.initialFingerAngle = undefined;
-                view.fingersRotationParameters.rotationInitiated = false;
+            view.syncWithInputs();
+
+            if (e.type === 'touchend' && view.pinchZoomParameters.isPinching) {
+                if (e.originalEvent.touches.length < 2) {
+                    view.pinchZoomParameters.isPinching = false;
+                }
 
                 return;
             }
@@ -674,9 +686,6 @@ export let View = (function () {
             // TODO : remplacer par mecanisme de listeners
             // on avertit les catalogues progressifs
             view.refreshProgressiveCats();
-
-            //view.requestRedraw();
-            view.wasm.releaseLeftButtonMouse();
         });
         var lastHoveredObject; // save last object hovered by mouse
         var lastMouseMovePos = null;
@@ -705,33 +714,10 @@ export let View = (function () {
                 return;
             }
 
-            if (e.type === 'touchmove' && view.pinchZoomParameters.isPinching && e.originalEvent && e.originalEvent.touches && e.originalEvent.touches.length == 2) {
-                // rotation
-                var currentFingerAngle = Math.atan2(e.originalEvent.targetTouches[1].clientY - e.originalEvent.targetTouches[0].clientY, e.originalEvent.targetTouches[1].clientX - e.originalEvent.targetTouches[0].clientX) * 180.0 / Math.PI;
-                var fingerAngleDiff = view.fingersRotationParameters.initialFingerAngle - currentFingerAngle;
-                // rotation is initiated when angle is equal or greater than 7 degrees
-                if (!view.fingersRotationParameters.rotationInitiated && Math.abs(fingerAngleDiff) >= 7) {
-                    view.fingersRotationParameters.rotationInitiated = true;
-                    view.fingersRotationParameters.initialFingerAngle = currentFingerAngle;
-                    fingerAngleDiff = 0;
-                }
-
-                if (view.fingersRotationParameters.rotationInitiated) {
-                    let rotation = view.fingersRotationParameters.initialViewAngleFromCenter;
-                    if (!view.wasm.getLongitudeReversed()) {
-                        // spatial survey case
-                        rotation += fingerAngleDiff;
-                    } else {
-                        // planetary survey case
-                        rotation -= fingerAngleDiff;
-                    }
-                    view.wasm.setRotationAroundCenter(rotation);
-                }
-
-                // zoom
-                const dist = Math.sqrt(Math.pow(e.originalEvent.touches[0].clientX - e.originalEvent.touches[1].clientX, 2) + Math.pow(e.originalEvent.touches[0].clientY - e.originalEvent.touches[1].clientY, 2));
-                const fov = Math.min(Math.max(view.pinchZoomParameters.initialFov * view.pinchZoomParameters.initialDistance / dist, 0.00002777777), view.fovLimit);
-                view.setZoom(fov);
+            if (e.type === 'touchmove' && view.pinchZoomParameters.isPinching && e.originalEvent && e.originalEvent.changedTouches) {
+                // zoom and rotation
+                forEachTouch(e.originalEvent.changedTouches, (id, x, y) => view.wasm.pointerMove(id, x, y));
+                view.updateZoomState();
 
                 return;
             }
@@ -803,7 +789,11 @@ export let View = (function () {
 
             view.realDragging = true;
 
-            view.wasm.goFromTo(s1.x, s1.y, s2.x, s2.y);
+            if (e.type === 'touchmove') {
+                forEachTouch(e.originalEvent.changedTouches, (id, x, y) => view.wasm.pointerMove(id, x, y));
+            } else {
+                view.wasm.pointerMove(MOUSE_POINTER_ID, s2.x, s2.y);
+            }
 
             const [ra, dec] = view.wasm.getCenter();
             view.viewCenter.lon = ra;
@@ -859,11 +849,8 @@ export let View = (function () {
             // The value of the field of view is determined
             // inside the backend
             const triggerZoom = (amount) => {
-                if (delta > 0.0) {
-                    view.increaseZoom(amount);
-                } else {
-                    view.decreaseZoom(amount);
-                }
+                view.wasm.wheel(delta > 0.0 ? amount : -amount);
+                view.syncWithInputs();
             };
 
             if (isTouchPadDefined) {
@@ -872,12 +859,12 @@ export let View = (function () {
                     newTime = new Date().getTime();
 
                     //if ( newTime - oldTime > 20 ) {
-                        triggerZoom(0.002);
+                        triggerZoom(0.3);
                         oldTime = new Date().getTime();
                     //}
                 } else {
                     // mouse
-                    triggerZoom(0.007);
+                    triggerZoom(1.0);
                 }
             }
 
@@ -892,6 +879,22 @@ export let View = (function () {
 
             return false;
         });
+
+        // Keyboard navigation: arrows move the view, + and - zoom
+        if (!view.aladinDiv.hasAttribute('tabindex')) {
+            view.aladinDiv.setAttribute('tabindex', 0);
+        }
+        $(view.aladinDiv).on('keydown', function (e) {
+            // Let the inputs of the widgets get their keys
+            if (e.target !== view.aladinDiv) {
+                return;
+            }
+
+            if (view.wasm.keyDown(e.key)) {
+                e.preventDefault();
+                view.syncWithInputs();
+            }
+        });
     };
 
     var init = function (view) {
@@ -956,7 +959,9 @@ export let View = (function () {
             //const [ra, dec] = this.wasm.ICRSJ2000ToViewCooSys(this.viewCenter.lon, this.viewCenter.lat);
             this.location.update(this.viewCenter.lon, this.viewCenter.lat, this.cooFrame, true);
         } else {
-            let radec = this.wasm.screenToWorld(mouseX, mouseY); // This is given in the frame of the view
+            // The position is read in the view under the mouse
+            const view = this.wasm.getViewAt(mouseX, mouseY);
+            let radec = this.wasm.screenToWorld(mouseX, mouseY, view); // This is given in the frame of the view
             if (radec) {
                 if (radec[0] < 0) {
                     radec = [radec[0] + 360.0, radec[1]];
@@ -1031,6 +1036,109 @@ export let View = (function () {
         }
     };
 
+    // Call f for each view with the context clipped to its area, the active view being drawn last
+    // so that the positions of the sources kept for the objects lookup are the ones of the active view
+    View.prototype.forEachDrawnView = function (ctx, f) {
+        const drawView = (rect) => {
+            ctx.save();
+            ctx.beginPath();
+            ctx.rect(rect.x * this.width, rect.y * this.height, rect.width * this.width, rect.height * this.height);
+            ctx.clip();
+
+            f();
+
+            ctx.restore();
+        };
+
+        this.otherViews.forEach((other, idx) => {
+            if (other) {
+                this.drawnView = idx;
+                drawView(other.viewRect);
+            }
+        });
+
+        this.drawnView = undefined;
+        drawView(this.viewRect);
+    };
+
+    // Keep the image layers and the projection of each view, the ones of the active view
+    // being the attributes of the view. Must be called once the backend changed its active view
+    View.prototype.syncActiveView = function () {
+        const activeView = this.wasm.getActiveView();
+        if (activeView === this.activeView) {
+            return;
+        }
+
+        // The layers of the previous view are hidden from the layer stack
+        for (const layer of this.imageLayers.keys()) {
+            ALEvent.HIPS_LAYER_REMOVED.dispatchedTo(this.aladinDiv, { layer: layer });
+        }
+
+        this.otherViews[this.activeView] = {
+            imageLayers: this.imageLayers,
+            overlayLayers: this.overlayLayers,
+            imageLayersBeingQueried: this.imageLayersBeingQueried,
+            selectedLayer: this.selectedLayer,
+            empty: this.empty,
+            projection: this.projection,
+            fovLimit: this.fovLimit,
+            viewRect: this.viewRect,
+        };
+
+        Object.assign(this, this.otherViews[activeView]);
+        this.otherViews[activeView] = undefined;
+        this.activeView = activeView;
+
+        for (const imageLayer of this.imageLayers.values()) {
+            ALEvent.HIPS_LAYER_ADDED.dispatchedTo(this.aladinDiv, { layer: imageLayer });
+        }
+        const projectionName = Object.keys(ProjectionEnum).find((name) => ProjectionEnum[name] === this.projection);
+        ALEvent.PROJECTION_CHANGED.dispatchedTo(this.aladinDiv, { projection: projectionName });
+
+        this.updateZoomState();
+        this.requestRedraw();
+    };
+
+    // Add a view with no image layer, it starts in the projection of the active view
+    View.prototype.addView = function (rect) {
+        const idx = this.wasm.addView(rect);
+
+        this.otherViews[idx] = {
+            imageLayers: new Map(),
+            overlayLayers: [],
+            imageLayersBeingQueried: new Map(),
+            selectedLayer: 'base',
+            empty: true,
+            projection: this.projection,
+            fovLimit: this.fovLimit,
+            viewRect: rect,
+        };
+        this.requestRedraw();
+
+        return idx;
+    };
+
+    View.prototype.removeView = function (idx) {
+        // Another view is activated before removing the active one
+        if (idx === this.activeView && this.otherViews.length > 1) {
+            this.wasm.setActiveView(idx === 0 ? 1 : idx - 1);
+            this.syncActiveView();
+        }
+
+        this.wasm.removeView(idx);
+        this.otherViews.splice(idx, 1);
+        this.activeView = this.wasm.getActiveView();
+
+        this.requestRedraw();
+    };
+
+    View.prototype.setViewRect = function (rect) {
+        this.wasm.setViewRect(rect);
+        this.viewRect = rect;
+
+        this.requestRedraw();
+    };
+
     View.prototype.drawAllOverlays = function () {
         var catalogCtx = this.catalogCtx;
         var catalogCanvasCleared = false;
@@ -1048,10 +1156,12 @@ export let View = (function () {
                 catalogCanvasCleared = true;
             }
 
-            for (var i = 0; i < this.catalogs.length; i++) {
-                var cat = this.catalogs[i];
-                cat.draw(catalogCtx, this.cooFrame, this.width, this.height, this.largestDim, this.zoomFactor);
-            }
+            this.forEachDrawnView(catalogCtx, () => {
+                for (var i = 0; i < this.catalogs.length; i++) {
+                    var cat = this.catalogs[i];
+                    cat.draw(catalogCtx, this.cooFrame, this.width, this.height, this.largestDim, this.zoomFactor);
+                }
+            });
         }
         // draw popup catalog
         if (this.catalogForPopup.isShowing && this.catalogForPopup.sources.length > 0) {
@@ -1060,12 +1170,14 @@ export let View = (function () {
                 catalogCanvasCleared = true;
             }
 
-            this.catalogForPopup.draw(catalogCtx, this.cooFrame, this.width, this.height, this.largestDim, this.zoomFactor);
+            this.forEachDrawnView(catalogCtx, () => {
+                this.catalogForPopup.draw(catalogCtx, this.cooFrame, this.width, this.height, this.largestDim, this.zoomFactor);
 
-            // draw popup overlay layer
-            if (this.overlayForPopup.isShowing) {
-                this.overlayForPopup.draw(catalogCtx, this.cooFrame, this.width, this.height, this.largestDim, this.zoomFactor);
-            }
+                // draw popup overlay layer
+                if (this.overlayForPopup.isShowing) {
+                    this.overlayForPopup.draw(catalogCtx, this.cooFrame, this.width, this.height, this.largestDim, this.zoomFactor);
+                }
+            });
         }
 
         ////// 3. Draw overlays////////
@@ -1076,9 +1188,11 @@ export let View = (function () {
                 catalogCanvasCleared = true;
             }
 
-            for (var i = 0; i < this.overlays.length; i++) {
-                this.overlays[i].draw(overlayCtx, this.cooFrame, this.width, this.height, this.largestDim, this.zoomFactor);
-            }
+            this.forEachDrawnView(overlayCtx, () => {
+                for (var i = 0; i < this.overlays.length; i++) {
+                    this.overlays[i].draw(overlayCtx, this.cooFrame, this.width, this.height, this.largestDim, this.zoomFactor);
+                }
+            });
         }
 
         // Redraw HEALPix grid
@@ -1193,8 +1307,8 @@ export let View = (function () {
 
     View.prototype.getVisiblePixList = function (norder) {
         var pixList = [];
-        let centerWorldPosition = this.wasm.screenToWorld(this.cx, this.cy);
-        const [lon, lat] = this.wasm.viewToICRSJ2000CooSys(centerWorldPosition[0], centerWorldPosition[1]);
+        // The center of the active view in ICRS
+        const [lon, lat] = this.wasm.getCenter();
 
         var radius = this.fov * 0.5 * this.ratio;
         this.wasm.queryDisc(norder, lon, lat, radius).forEach(x => pixList.push(Number(x)));
@@ -1345,8 +1459,13 @@ export let View = (function () {
 
     View.prototype.setGridConfig = function (gridCfg) {
         this.wasm.setGridConfig(gridCfg);
+        this.dispatchGridConfig(gridCfg);
+
+        this.requestRedraw();
+    };
 
-        // send events
+    // Send the events of a grid config applied to the core
+    View.prototype.dispatchGridConfig = function (gridCfg) {
         if (gridCfg) {
             if (gridCfg.hasOwnProperty('enabled')) {
                 if (gridCfg.enabled === true) {
@@ -1360,8 +1479,33 @@ export let View = (function () {
                 ALEvent.COO_GRID_UPDATED.dispatchedTo(this.aladinDiv, { color: gridCfg.color, opacity: gridCfg.opacity });
             }
         }
+    };
 
-        this.requestRedraw();
+    // Update the center and the zoom state from the camera moved by the core, e.g. during a flight or a tour
+    // The update goes on at every frame as long as isAnimating returns true
+    View.prototype.syncWithCamera = function (isAnimating) {
+        const [ra, dec] = this.aladin.getRaDec();
+        this.viewCenter.lon = ra;
+        this.viewCenter.lat = dec;
+        this.location.update(ra, dec, this.cooFrame, true);
+        this.updateZoomState();
+
+        if (isAnimating && isAnimating()) {
+            requestAnimFrame(() => this.syncWithCamera(isAnimating));
+        }
+    };
+
+    // Update the view while it keeps moving after the inputs (inertia, smooth zoom)
+    View.prototype.syncWithInputs = function () {
+        if (this.syncingInputs) {
+            return;
+        }
+
+        this.syncingInputs = true;
+        this.syncWithCamera(() => {
+            this.syncingInputs = this.wasm.isInputAnimating();
+            return this.syncingInputs;
+        });
     };
 
     View.prototype.updateZoomState = function () {
@@ -1470,7 +1614,8 @@ export let View = (function () {
         this.promises.push(imageLayerPromise);
 
         // All image layer promises must be completed (fullfilled or rejected)
-        Promise.allSettled(this.promises)
+        // The promise returned is settled once the layer is added to the backend
+        return Promise.allSettled(this.promises)
             .then(() => imageLayerPromise)
             // The promise is resolved and we now have access
             // to the image layer objet (whether it is an ImageSurvey or an ImageFITS)
@@ -1572,6 +1717,12 @@ export let View = (function () {
     View.prototype.removeImageLayer = function (layer) {
         // Get the survey to remove to dissociate it from the view
         let imageLayer = this.imageLayers.get(layer);
+        if (!imageLayer) {
+            // The layer has only been added to the backend, e.g. an image positioned from its WCS
+            this.wasm.removeLayer(layer);
+            this.requestRedraw();
+            return;
+        }
         // Update the backend
         if (imageLayer.added) {
             this.wasm.removeLayer(layer);
@@ -1635,7 +1786,8 @@ export let View = (function () {
         this.needRedraw = true;
     };
 
-    View.prototype.setProjection = function (projectionName) {
+    // Update the projection known by the view and its fov limit
+    View.prototype.updateProjection = function (projectionName) {
         this.fovLimit = 1000.0;
         /*
             TAN: {id: 1, fov: 180},
@@ -1731,13 +1883,51 @@ export let View = (function () {
             default:
                 break;
         }
+    };
+
+    View.prototype.setProjection = function (projection, duration) {
+        // The projection is given by its name or by an object with its name and parameters
+        const projectionName = typeof projection === 'string' ? projection : projection.name;
+        this.updateProjection(projectionName);
+
         // Change the projection here
-        this.wasm.setProjection(projectionName);
+        if (duration) {
+            // The sky is interpolated from the current projection to the new one
+            const morph = this.wasm.morphProjection(projection, duration);
+            this.syncWithCamera(() => this.wasm.isMorphingProjection());
+            this.requestRedraw();
+
+            return morph.then((finished) => {
+                this.updateZoomState();
+                this.requestRedraw();
+
+                return finished;
+            });
+        }
+
+        this.wasm.setProjection(projection);
         this.updateZoomState();
 
         this.requestRedraw();
     };
 
+    // Set the view in the projection of an image layer, the image being displayed pixel per pixel
+    View.prototype.setNativeImageView = function (layer) {
+        const projectionName = this.wasm.setNativeImageView(layer);
+        this.updateProjection(projectionName);
+
+        // The view takes the frame of the image
+        const system = this.wasm.getCooSystem() === Aladin.wasmLibs.core.CooSystem.GAL ? CooFrameEnum.SYSTEMS.GAL : CooFrameEnum.SYSTEMS.J2000;
+        if (this.cooFrame.system !== system) {
+            this.changeFrame(system === CooFrameEnum.SYSTEMS.GAL ? CooFrameEnum.GAL : CooFrameEnum.J2000);
+        }
+        this.syncWithCamera();
+
+        this.requestRedraw();
+
+        return projectionName;
+    };
+
     View.prototype.changeFrame = function (cooFrame) {
         this.cooFrame = cooFrame;
 
@@ -418,13 +418,15 @@ export class HiPSLayer {
         reverseTr[0].style.display = "flex";
         stretchTr[0].style.display = "flex";
 
+        // Cuts not given are taken from the survey properties or its allsky
+        const defaultCuts = (this.layer.getDefaultCuts && this.layer.getDefaultCuts()) || [0.0, 1.0];
         if (colorCfg.minCut) {
             if (parseFloat(minCut.val()) != colorCfg.minCut) {
                 minCut.val(parseFloat(colorCfg.minCut.toFixed(5)));
             }
         }
         else {
-            minCut.val(0.0);
+            minCut.val(parseFloat(defaultCuts[0].toFixed(5)));
         }
 
         minCutTr[0].style.display = "flex";
@@ -435,7 +437,7 @@ export class HiPSLayer {
             }
         }
         else {
-            maxCut.val(0.0);
+            maxCut.val(parseFloat(defaultCuts[1].toFixed(5)));
         }
         maxCutTr[0].style.display = "flex";
         // save opacity